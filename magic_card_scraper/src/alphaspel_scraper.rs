@@ -1,23 +1,33 @@
-use std::{collections::HashMap, error::Error};
+use std::collections::HashMap;
 
+use async_trait::async_trait;
 use futures::{stream, StreamExt};
-use log::{debug, error, info, warn};
+use log::{debug, error, info};
 use regex::Regex;
+use rust_decimal::Decimal;
 use scraper::{Html, Selector};
 
 use crate::cards::{
-    cardname::CardName, currency::Currency, price::Price, setname::SetName, vendor::Vendor,
-    vendorcard::VendorCard,
+    card_parser::condition_and_language, cardname::CardName, currency::Currency, price::Price,
+    setname::SetName, vendor::Vendor, vendorcard::VendorCard,
 };
+use crate::error::ScrapeError;
+use crate::http_client::HttpClient;
+use crate::scrape_report::{ScrapeReport, SkipReason, SkippedProduct};
+use crate::utilities::constants::{ALPHASPEL_CARDS_FOLDER, ALPHASPEL_CARDS_PREFIX};
+use crate::vendor_scraper::VendorScraper;
 
 #[derive(Debug)]
 pub struct AlphaspelScraper {
     promo_patterns: Vec<Regex>,
     base_url: String,
+    client: HttpClient,
+    /// Max number of pages fetched at the same time
+    concurrency: usize,
 }
 
 impl AlphaspelScraper {
-    pub fn new(base_url: &str) -> Self {
+    pub fn new(base_url: &str, client: HttpClient, concurrency: usize) -> Self {
         let patterns = [
             r"(?i)\(Promo\)",
             r"(?i)\(promo\)",
@@ -27,33 +37,29 @@ impl AlphaspelScraper {
 
         let promo_patterns = patterns
             .iter()
-            .map(|&p| Regex::new(p).map_err(|e| Box::new(e) as Box<dyn Error>))
-            .collect::<Result<Vec<Regex>, Box<dyn Error>>>()
-            .unwrap();
+            .map(|&p| Regex::new(p).unwrap())
+            .collect::<Vec<Regex>>();
 
         Self {
             promo_patterns,
             base_url: base_url.to_string(),
+            client,
+            concurrency,
         }
     }
 
-    async fn get_all_card_pages(&self) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    async fn get_all_card_pages(&self) -> Result<Vec<(String, String)>, ScrapeError> {
         let url = format!("{}/1978-mtg-loskort/", self.base_url);
         info!("Fetching all card pages from: {}", url);
-        let client = reqwest::Client::new();
-        let mut header_map = reqwest::header::HeaderMap::new();
-        header_map.insert(reqwest::header::ACCEPT, "*/*".parse().unwrap());
-        header_map.insert(
-            reqwest::header::USER_AGENT,
-            "application/json".parse().unwrap(),
-        );
-        let sets_page = client
-            .get(url)
-            .headers(header_map.clone())
-            .send()
-            .await?
+        let sets_page = self
+            .client
+            .get(&url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| ScrapeError::request(&url, e))?
             .text()
-            .await?;
+            .await
+            .map_err(|e| ScrapeError::request(&url, e))?;
 
         let document = Html::parse_document(&sets_page);
         // let selector = Selector::parse(".nav.nav-list a").unwrap();
@@ -68,6 +74,9 @@ impl AlphaspelScraper {
             })
             // .map(|(href, content)| href.to_string(),)
             .collect();
+        if sets_links.is_empty() {
+            return Err(ScrapeError::page(&url, "found no set pages"));
+        }
 
         Ok(sets_links)
     }
@@ -76,23 +85,44 @@ impl AlphaspelScraper {
         &self,
         card_elements: scraper::ElementRef,
         list_of_sets: Vec<String>,
-    ) -> Result<VendorCard, Box<dyn Error>> {
+    ) -> Result<VendorCard, SkippedProduct> {
+        let product_name = card_elements
+            .select(&Selector::parse(".product-name").unwrap())
+            .next()
+            .ok_or_else(|| SkippedProduct::new(SkipReason::BadName, "", "No product name found"))?
+            .text()
+            .collect::<String>();
+
+        let product_name = product_name
+            .replace("\n", "")
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let skipped = |reason, message: String| SkippedProduct::new(reason, &product_name, message);
+
+        if product_name.contains("Token") {
+            return Err(skipped(SkipReason::Token, "Card is a token".to_string()));
+        }
+
         let in_stock = card_elements
             .select(&Selector::parse(".stock").unwrap())
             .next()
-            .ok_or("No stock information found")?
+            .ok_or_else(|| skipped(SkipReason::Other, "No stock information found".to_string()))?
             .text()
             .collect::<String>()
             .trim()
             .to_string();
 
         let stock = if in_stock == "Slutsåld" {
-            return Err("Card is Slutsåld".into());
+            return Err(skipped(SkipReason::SoldOut, "Card is Slutsåld".to_string()));
         } else {
             let cleaned_stock = in_stock.replace("i butiken", "").trim().to_string();
-            cleaned_stock
-                .parse::<i8>()
-                .map_err(|e| format!("Failed to parse stock '{}': {}", cleaned_stock, e))?
+            cleaned_stock.parse::<i8>().map_err(|e| {
+                skipped(
+                    SkipReason::Other,
+                    format!("Failed to parse stock '{}': {}", cleaned_stock, e),
+                )
+            })?
         };
 
         let image_url: String = card_elements
@@ -103,19 +133,6 @@ impl AlphaspelScraper {
 
         let image_url = format!("{}{}", self.base_url, image_url.replace("\n", "").trim());
 
-        let product_name = card_elements
-            .select(&Selector::parse(".product-name").unwrap())
-            .next()
-            .ok_or("No product name found")?
-            .text()
-            .collect::<String>();
-
-        let product_name = product_name
-            .replace("\n", "")
-            .split_whitespace()
-            .collect::<Vec<&str>>()
-            .join(" ");
-
         // let promo_patterns = create_regex_patterns(&PROMO_PATTERNS)?;
         let prerelease = self
             .promo_patterns
@@ -124,63 +141,67 @@ impl AlphaspelScraper {
 
         let alternative_art = product_name.contains("(alternative art)");
 
-        if product_name.to_lowercase().contains("(italiensk)")
-            || product_name.to_lowercase().contains("(tysk)")
-            || product_name.to_lowercase().contains("(rysk)")
-        {
-            return Err("Card is not english".into());
-        }
-
-        if Regex::new(r"Token")?.is_match(&product_name) {
-            return Err("Card is a token".into());
-        }
+        let (condition, language) = condition_and_language(&product_name);
 
         let set = list_of_sets
             .into_iter()
             .find(|set| product_name.to_lowercase().contains(&set.to_lowercase()));
 
-        let set = if set.is_some() {
-            set.unwrap()
-        } else {
-            return Err(format!("Unable to find what set {} belongs to", &product_name).into());
+        let Some(set) = set else {
+            return Err(skipped(
+                SkipReason::UnknownSet,
+                format!("Unable to find what set {} belongs to", &product_name),
+            ));
         };
 
         let raw_name = &product_name
             .split_once(&set)
             .map(|(_, after)| after)
-            .unwrap_or("Error retrieving the name".into())
+            .unwrap_or("Error retrieving the name")
             .replace(":", "");
 
         let price = card_elements
             .select(&Selector::parse(".price.text-success").unwrap())
             .next()
-            .ok_or("No price found")?
+            .ok_or_else(|| skipped(SkipReason::BadPrice, "No price found".to_string()))?
             .text()
             .collect::<String>();
 
-        let price: f64 = Regex::new(r"\d+")?
-            .find(&price)
-            .ok_or_else(|| format!("No numeric value found in price string: '{}'", price))?
-            .as_str()
-            .parse()
-            .map_err(|e| format!("Failed to parse price '{}': {}", price, e))?;
+        let digits: String = price
+            .chars()
+            .skip_while(|c| !c.is_ascii_digit())
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        if digits.is_empty() {
+            return Err(skipped(
+                SkipReason::BadPrice,
+                format!("No numeric value found in price string: '{}'", price),
+            ));
+        }
+        let price: Decimal = digits.parse().map_err(|e| {
+            skipped(
+                SkipReason::BadPrice,
+                format!("Failed to parse price '{}': {}", price, e),
+            )
+        })?;
 
         let price = Price::new(price, Currency::SEK);
 
-        let foil = Regex::new(r"\(Foil\)")?.is_match(raw_name)
-            || Regex::new(r"\(Etched Foil\)")?.is_match(raw_name)
-            || Regex::new(r"\(Foil Etched\)")?.is_match(raw_name);
+        let foil = raw_name.contains("(Foil)")
+            || raw_name.contains("(Etched Foil)")
+            || raw_name.contains("(Foil Etched)");
 
-        let name = CardName::new(raw_name.to_owned())?;
-        let set = SetName::new(set)?;
+        let name = CardName::new(raw_name.to_owned())
+            .map_err(|e| skipped(SkipReason::BadName, e.to_string()))?;
+        let set = SetName::new(set).map_err(|e| skipped(SkipReason::UnknownSet, e.to_string()))?;
 
         Ok(VendorCard {
             name,
             vendor: Vendor::Alphaspel,
             foil,
-            image_url: image_url,
+            image_url,
             extended_art: alternative_art,
-            prerelease: prerelease,
+            prerelease,
             showcase: false,
             set,
             price,
@@ -188,17 +209,22 @@ impl AlphaspelScraper {
             current_stock: stock,
             max_stock: 3,
             collector_number: None,
+            condition,
+            language,
         })
     }
 
-    pub async fn scrape_cards(&self) -> Result<HashMap<CardName, Vec<VendorCard>>, Box<dyn Error>> {
+    pub async fn scrape_cards(
+        &self,
+    ) -> Result<(HashMap<CardName, Vec<VendorCard>>, ScrapeReport), ScrapeError> {
         let pages_and_set_names = self.get_all_card_pages().await?;
         let (pages, set_names): (Vec<_>, Vec<_>) = pages_and_set_names.into_iter().unzip();
         info!("Found {} alphaspel set pages", pages.len());
 
         //Get all pages to call
-        let links_to_call = self.get_app_pages_to_call(pages).await;
-        let cards = self.extract_cards(set_names, links_to_call).await;
+        let (links_to_call, pages_report) = self.get_app_pages_to_call(pages).await;
+        let (cards, mut report) = self.extract_cards(set_names, links_to_call).await;
+        report.merge(pages_report);
 
         let mut grouped_cards = HashMap::new();
         for card in &cards {
@@ -207,26 +233,24 @@ impl AlphaspelScraper {
                 .or_insert_with(Vec::new)
                 .push(card.clone())
         }
-        Ok(grouped_cards)
+        Ok((grouped_cards, report))
     }
 
     
-    pub async fn extract_cards(&self, set_names: Vec<String>, links_to_call: Vec<(String, u32)>) -> Vec<VendorCard> {
-
-        let client = reqwest::Client::new();
-        let mut header_map = reqwest::header::HeaderMap::new();
-        header_map.insert(reqwest::header::ACCEPT, "*/*".parse().unwrap());
-        header_map.insert(
-            reqwest::header::USER_AGENT,
-            "application/json".parse().unwrap(),
-        );
-        let cards: Vec<VendorCard> = stream::iter(links_to_call)
+    /// Fetches every page of the sets and parses the cards on them, with a report of the pages
+    /// and the products that were skipped
+    pub async fn extract_cards(
+        &self,
+        set_names: Vec<String>,
+        links_to_call: Vec<(String, u32)>,
+    ) -> (Vec<VendorCard>, ScrapeReport) {
+        let mut report = ScrapeReport::new(Vendor::Alphaspel);
+        let set_pages = stream::iter(links_to_call)
             .map(|(set_href, max_page_count)| {
                 let set_names_clone = set_names.clone();
-                let client2 = client.clone();
-                let headers_map2 = header_map.clone();    
                 async move {
                     let mut cards = Vec::new();
+                    let mut report = ScrapeReport::new(Vendor::Alphaspel);
                     // let value = set_names.clone();
     
                     for page_count in 1..=max_page_count as i32 {
@@ -235,9 +259,15 @@ impl AlphaspelScraper {
                             self.base_url, set_href, page_count
                         );
                         info!("Fetching cards from {}", &link);
-                        match client2.get(&link).headers(headers_map2.clone()).send().await {
+                        match self
+                            .client
+                            .get(&link)
+                            .await
+                            .and_then(|response| response.error_for_status())
+                        {
                             Ok(response) => match response.text().await {
                                 Ok(set_page) => {
+                                    report.page_fetched();
                                     let document = Html::parse_document(&set_page);
                                     let product_selector =
                                         &Selector::parse(".products.row .product").unwrap();
@@ -246,95 +276,139 @@ impl AlphaspelScraper {
                                         match self
                                             .get_card_from_html(product, set_names_clone.clone())
                                         {
-                                            Ok(card) => cards.push(card),
-                                            Err(e) => warn!("Error parsing card: {}", e),
+                                            Ok(card) => {
+                                                report.product_parsed();
+                                                cards.push(card)
+                                            }
+                                            Err(skipped) => {
+                                                debug!("Skipping product: {}", skipped);
+                                                report.product_skipped(skipped)
+                                            }
                                         }
                                     }
                                 }
                                 Err(e) => {
-                                    error!("Error reading response text for {}: {}", link, e)
+                                    error!("Error reading response text for {}: {}", link, e);
+                                    report.page_failed(&link, e);
                                 }
                             },
-                            Err(e) => error!("Error fetching page {}: {}", link, e),
+                            Err(e) => {
+                                error!("Error fetching page {}: {}", link, e);
+                                report.page_failed(&link, e);
+                            }
                         }
                     }
-                    cards
+                    (cards, report)
                 }
             })
-            .buffered(40)
+            .buffered(self.concurrency)
             .collect::<Vec<_>>()
-            .await
-            .into_iter()
-            .flatten()
-            .collect();
-        cards
+            .await;
+
+        let mut cards = Vec::new();
+        for (mut set_cards, set_report) in set_pages {
+            cards.append(&mut set_cards);
+            report.merge(set_report);
+        }
+        report.finish();
+        (cards, report)
     }
     
-    pub async fn get_app_pages_to_call(&self, pages: Vec<String>) -> Vec<(String, u32)> {
-
-    let client = reqwest::Client::new();
-    let mut header_map = reqwest::header::HeaderMap::new();
-    header_map.insert(reqwest::header::ACCEPT, "*/*".parse().unwrap());
-    header_map.insert(
-            reqwest::header::USER_AGENT,
-            "application/json".parse().unwrap(),
-    );
-
-    let links_to_call = stream::iter(pages)
-        .map(|set_href| {
-            let link = format!(
-                "{}{}?order_by=stock_a&ordering=desc&page=1",
-                self.base_url, set_href
-            );
-            debug!("Processing link {}", &link);
-            let value = client.clone();
-            let header_map2 = header_map.clone();
-            async move {
-                match value.get(&link).headers(header_map2).send().await {
-                    Ok(response) => match response.text().await {
-                        Ok(set_initial_page) => {
-                            let document = Html::parse_document(&set_initial_page);
-                            let selector = Selector::parse("ul.pagination li").unwrap();
-
-                            let mut max_page = 1;
-                            for element in document.select(&selector) {
-                                if let Ok(num) =
-                                    element.text().collect::<String>().trim().parse::<u32>()
-                                {
-                                    if num > max_page {
-                                        max_page = num;
-                                    }
-                                }
-                            }
-                            (set_href, max_page)
-                        }
-                        Err(e) => {
-                            error!("Error reading response text for {}: {}", link, e);
-                            (set_href, 1)
-                        }
-                    },
-                    Err(e) => {
-                        error!("Error fetching page {}: {}", link, e);
-                        (set_href, 1)
+    /// The number of pages of every set, read from the pagination on its first page. Sets whose
+    /// first page can't be fetched are left out and counted as failed pages in the report
+    pub async fn get_app_pages_to_call(
+        &self,
+        pages: Vec<String>,
+    ) -> (Vec<(String, u32)>, ScrapeReport) {
+        let first_pages = stream::iter(pages)
+            .map(|set_href| {
+                let link = format!(
+                    "{}{}?order_by=stock_a&ordering=desc&page=1",
+                    self.base_url, set_href
+                );
+                debug!("Processing link {}", &link);
+                async move {
+                    let set_initial_page = match self
+                        .client
+                        .get(&link)
+                        .await
+                        .and_then(|response| response.error_for_status())
+                    {
+                        Ok(response) => response.text().await,
+                        Err(e) => Err(e),
+                    };
+                    (set_href, link, set_initial_page)
+                }
+            })
+            .buffered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut report = ScrapeReport::new(Vendor::Alphaspel);
+        let mut links_to_call = Vec::new();
+        for (set_href, link, set_initial_page) in first_pages {
+            let set_initial_page = match set_initial_page {
+                Ok(set_initial_page) => set_initial_page,
+                Err(e) => {
+                    error!("Error fetching page {}: {}", link, e);
+                    report.page_failed(&link, e);
+                    continue;
+                }
+            };
+            let document = Html::parse_document(&set_initial_page);
+            let selector = Selector::parse("ul.pagination li").unwrap();
+
+            let mut max_page = 1;
+            for element in document.select(&selector) {
+                if let Ok(num) = element.text().collect::<String>().trim().parse::<u32>() {
+                    if num > max_page {
+                        max_page = num;
                     }
                 }
             }
-        })
-        .buffered(40)
-        .collect::<Vec<_>>()
-        .await;
-    links_to_call
-}
+            links_to_call.push((set_href, max_page));
+        }
+        (links_to_call, report)
+    }
 }
 
 
 
+#[async_trait]
+impl VendorScraper for AlphaspelScraper {
+    fn name(&self) -> &'static str {
+        "alphaspel"
+    }
+
+    fn vendor(&self) -> Vendor {
+        Vendor::Alphaspel
+    }
+
+    fn snapshot_folder(&self) -> &'static str {
+        ALPHASPEL_CARDS_FOLDER
+    }
+
+    fn snapshot_prefix(&self) -> &'static str {
+        ALPHASPEL_CARDS_PREFIX
+    }
+
+    async fn scrape(
+        &self,
+    ) -> Result<(HashMap<CardName, Vec<VendorCard>>, ScrapeReport), ScrapeError> {
+        self.scrape_cards().await
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{cards::vendorcard::VendorCard, test::alphaspel::alphaspel_page_set_endings, utilities::constants::ALPHASPEL_URL};
+    use rust_decimal_macros::dec;
+
+    use crate::{
+        cards::{condition::Condition, language::Language, vendorcard::VendorCard},
+        test::{alphaspel::alphaspel_page_set_endings, helpers::test_http_client},
+    };
 
     use super::*;
-    use tokio;
 
     fn init() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -346,7 +420,7 @@ mod tests {
 
     //     let url = "https://alphaspel.se";
 
-    //     let scraper = AlphaspelScraper::new(&url);
+    //     let scraper = AlphaspelScraper::new(&url, 40);
     //     let result = scraper.get_all_card_pages().await.unwrap();
     //     let (pages, set_names): (Vec<_>, Vec<_>) = result.into_iter().unzip();
     //     info!("Found {} alphaspel set pages", pages.len());
@@ -371,7 +445,7 @@ mod tests {
             .with_body(html_content)
             .create();
 
-        let scraper = AlphaspelScraper::new(&url);
+        let scraper = AlphaspelScraper::new(&url, test_http_client(), 40);
         let result = scraper.get_all_card_pages().await.unwrap();
         mock.assert();
         assert_eq!(result, alphaspel_page_set_endings())
@@ -383,22 +457,21 @@ mod tests {
 
         let card_html = include_str!("test/alphaspel_cards_page.html");
 
-        let document = Html::parse_document(&card_html);
+        let document = Html::parse_document(card_html);
         let selector = Selector::parse(".products.row div.product").unwrap();
         let products = document.select(&selector);
 
-        let alpha_scraper = AlphaspelScraper::new("fake");
+        let alpha_scraper = AlphaspelScraper::new("fake", test_http_client(), 40);
 
         let mut cards: Vec<VendorCard> = Vec::new();
+        let mut skipped = Vec::new();
         for product in products {
             match alpha_scraper.get_card_from_html(
                 product,
                 ["Bloomburrow".to_string(), "10th Edition".to_string()].to_vec(),
             ) {
                 Ok(card) => cards.push(card),
-                Err(e) => {
-                    error!("Error parsing card: {}", e);
-                }
+                Err(e) => skipped.push(e),
             }
         }
         let first_card = VendorCard {
@@ -410,22 +483,29 @@ mod tests {
             prerelease: false,
             showcase: false,
             set: SetName::new("10th Edition".to_owned()).unwrap(),
-            price: Price::new(5.0, Currency::SEK),
+            price: Price::new(dec!(5.0), Currency::SEK),
             trade_in_price: 0,
             current_stock: 9,
             max_stock: 3,
             collector_number: None,
+            condition: Condition::NearMint,
+            language: Language::English,
         };
 
         assert_eq!(cards.len(), 51);
         assert_eq!(cards[0], first_card);
-        assert_eq!(cards[49].extended_art, true);
-        assert_eq!(cards.last().unwrap().prerelease, true);
-        assert_eq!(cards.last().unwrap().foil, true);
+        assert!(cards[49].extended_art);
+        assert!(cards.last().unwrap().prerelease);
+        assert!(cards.last().unwrap().foil);
         assert_eq!(
             cards.last().unwrap().name.almost_raw,
             "Whiskervale Forerunner"
         );
+        let skipped: Vec<SkipReason> = skipped.iter().map(|skipped| skipped.reason).collect();
+        assert_eq!(
+            skipped,
+            vec![SkipReason::SoldOut, SkipReason::Token, SkipReason::BadName]
+        );
     }
 
     #[tokio::test]
@@ -472,14 +552,18 @@ mod tests {
             .create();
 
         //When scrape_cards is called
-        let scraper = AlphaspelScraper::new(&url);
+        let scraper = AlphaspelScraper::new(&url, test_http_client(), 40);
 
         //Then we should have a Hashmap of 54 cards
-        let result = scraper.scrape_cards().await.unwrap();
+        let (result, report) = scraper.scrape_cards().await.unwrap();
         mock.assert();
         mock2.assert();
         mock3.assert();
         assert_eq!(result.len(), 51);
+        // Bloomburrow isn't mocked, its first page fails and the set is left out
+        assert_eq!(report.pages_fetched, 1);
+        assert_eq!(report.pages_failed, 1);
+        assert!(report.failed_pages[0].url.ends_with("page=1"));
     }
 
 // #[tokio::test]
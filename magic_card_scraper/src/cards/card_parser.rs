@@ -1,23 +1,19 @@
-use log::{debug, error, warn};
+use log::{debug, warn};
 use regex::Regex;
-use reqwest;
 use scraper::{Html, Selector};
-use std::error::Error;
 use tokio::time::Instant;
 
 use crate::cards::{
-    cardname::CardName, collector_number::CollectorNumber, currency::Currency, price::Price,
-    setname::SetName, vendor::Vendor,
+    cardname::CardName, collector_number::CollectorNumber, condition::Condition,
+    currency::Currency, language::Language, price::Price, setname::SetName, vendor::Vendor,
 };
+use crate::error::{ParseError, ScrapeError};
+use crate::http_client::HttpClient;
+use crate::scrape_report::{ScrapeReport, SkipReason, SkippedProduct};
 
 use super::vendorcard::VendorCard; // Add this line to import VendorCard
 
-const UNWANTED_PATTERNS: [&str; 4] = [
-    r"(?i)\(skadad\)",
-    r"(?i)\( Skadad \)",
-    r"(?i)\(Spelad\)",
-    r"(?i)\[Token\]",
-];
+const UNWANTED_PATTERNS: [&str; 1] = [r"(?i)\[Token\]"];
 
 const FOIL_PATTERNS: [&str; 3] = [
     r"(?i)\(Foil\)",
@@ -25,86 +21,109 @@ const FOIL_PATTERNS: [&str; 3] = [
     r"(?i)\(Foil Etched\)",
 ];
 
-fn create_regex_patterns(patterns: &[&str]) -> Result<Vec<Regex>, Box<dyn Error>> {
-    patterns
-        .iter()
-        .map(|&p| Regex::new(p).map_err(|e| Box::new(e) as Box<dyn Error>)) // Convert regex::Error to Box<dyn Error>
-        .collect()
+fn create_regex_patterns(patterns: &[&str]) -> Vec<Regex> {
+    patterns.iter().map(|&p| Regex::new(p).unwrap()).collect()
 }
 
-#[derive(Debug)]
-struct NoPriceAvailableError;
-
-impl std::error::Error for NoPriceAvailableError {}
-
-impl std::fmt::Display for NoPriceAvailableError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "No price available")
+/// The condition and language in the tags of a product name, e.g. `(Spelad)` or `(tysk)`.
+/// Cards without tags are near mint and english
+pub(crate) fn condition_and_language(name: &str) -> (Condition, Language) {
+    let mut condition = Condition::default();
+    let mut language = Language::default();
+    let tags = name
+        .split('(')
+        .skip(1)
+        .filter_map(|part| part.split_once(')'))
+        .map(|(tag, _)| tag);
+    for tag in tags {
+        if let Some(tag_condition) = Condition::from_swedish(tag) {
+            condition = tag_condition;
+        }
+        if let Some(tag_language) = Language::from_swedish(tag) {
+            language = tag_language;
+        }
     }
+    (condition, language)
 }
 
-fn parse_price(price_str: &str) -> Result<i32, Box<dyn Error>> {
+/// The price in a price cell. A `-` means there is no price
+fn parse_price(price_str: &str) -> Result<i32, ParseError> {
     if price_str.trim() == "-" {
-        return Err(Box::new(NoPriceAvailableError));
+        return Err(ParseError::field(
+            "price",
+            price_str.trim(),
+            "No price available",
+        ));
     }
-    price_str
+    let amount = price_str
         .replace("Slut, ", "")
         .replace("Fullt, ", "")
-        .replace("kr", "")
+        .replace("kr", "");
+    amount
         .trim()
         .parse::<i32>()
-        .map_err(|e| Box::new(e) as Box<dyn Error>)
+        .map_err(|e| ParseError::field("price", price_str.trim(), e))
 }
 
-fn get_price(tr_elements: scraper::ElementRef) -> Result<i32, Box<dyn Error>> {
+fn get_price(tr_elements: scraper::ElementRef) -> Result<i32, ParseError> {
     let price_if_item_is_in_store = tr_elements
-        .select(&Selector::parse("td.align-right span.format-bold")?)
+        .select(&Selector::parse("td.align-right span.format-bold").unwrap())
         .next()
         .map(|element| parse_price(element.text().collect::<String>().as_str()));
 
     let price_if_item_is_not_in_store = tr_elements
-        .select(&Selector::parse("td.align-right span.format-subtle")?)
+        .select(&Selector::parse("td.align-right span.format-subtle").unwrap())
         .next()
         .map(|element| parse_price(element.text().collect::<String>().as_str()));
 
-    match price_if_item_is_in_store.or(price_if_item_is_not_in_store) {
-        Some(Ok(price)) => Ok(price),
-        Some(Err(e)) => {
-            if e.is::<NoPriceAvailableError>() {
-                Err(e)
-            } else {
-                Ok(0)
-            }
-        }
-        None => Ok(0),
-    }
+    price_if_item_is_in_store
+        .or(price_if_item_is_not_in_store)
+        .unwrap_or(Ok(0))
 }
 
-fn get_buyin_value(tr_elements: scraper::ElementRef) -> Result<i32, Box<dyn std::error::Error>> {
-    if let Some(buyin_str) = tr_elements.value().attr("data-buyin") {
-        return Ok(buyin_str.parse()?);
-    }
-
-    Err("Could not find data-buyin attribute".into())
+fn get_buyin_value(tr_elements: scraper::ElementRef) -> Result<i32, ParseError> {
+    let buyin_str = tr_elements.value().attr("data-buyin").ok_or_else(|| {
+        ParseError::field("trade-in price", "", "Could not find data-buyin attribute")
+    })?;
+    buyin_str
+        .parse()
+        .map_err(|e| ParseError::field("trade-in price", buyin_str, e))
 }
 
-pub async fn fetch_and_parse(url: &str) -> Result<Vec<VendorCard>, Box<dyn Error>> {
+/// Fetches a Dragonslair product list page and parses the cards on it, with a report of the
+/// page and the products that were skipped
+pub async fn fetch_and_parse(
+    client: &HttpClient,
+    url: &str,
+) -> Result<(Vec<VendorCard>, ScrapeReport), ScrapeError> {
     let start = Instant::now();
-    let response = reqwest::get(url).await?;
+    let response = client
+        .get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| ScrapeError::request(url, e))?;
     debug!("fetching {} took {:?} sec", url, start.elapsed().as_secs());
-    let html_content = response.text().await?;
+    let html_content = response
+        .text()
+        .await
+        .map_err(|e| ScrapeError::request(url, e))?;
+    let mut report = ScrapeReport::new(Vendor::Dragonslair);
+    report.page_fetched();
     let parse_document = Html::parse_document(&html_content);
-    let table_selector = Selector::parse("tr[id*='product-row-']")?;
+    let table_selector = Selector::parse("tr[id*='product-row-']").unwrap();
     let selected_elements = parse_document.select(&table_selector);
 
-    let unwanted_patterns = create_regex_patterns(&UNWANTED_PATTERNS)?;
-    let foil_patterns = create_regex_patterns(&FOIL_PATTERNS)?;
+    let unwanted_patterns = create_regex_patterns(&UNWANTED_PATTERNS);
+    let foil_patterns = create_regex_patterns(&FOIL_PATTERNS);
+    let prerelease_pattern = Regex::new(r"(?i)\(Prerelease\)").unwrap();
+    let showcase_pattern = Regex::new(r"(?i)\(Showcase\)").unwrap();
+    let extended_art_pattern = Regex::new(r"(?i)\(Extended Art\)").unwrap();
 
     let mut cards_on_the_page = Vec::new();
 
     for tr_elements in selected_elements {
         let name = tr_elements
-            .select(&Selector::parse("a.fancybox")?)
+            .select(&Selector::parse("a.fancybox").unwrap())
             .next()
             .map(|el| {
                 el.text()
@@ -116,51 +135,64 @@ pub async fn fetch_and_parse(url: &str) -> Result<Vec<VendorCard>, Box<dyn Error
                     .trim()
                     .to_string()
             })
-            .unwrap_or(
+            .or_else(|| {
                 // if it doesn't have a name, with a link to image such as: https://astraeus.dragonslair.se/product/card-singles/magic/store:kungsholmstorg/sort:recent/magic-warhammer-40-000
                 tr_elements
-                    .select(&Selector::parse("td.wrap")?)
+                    .select(&Selector::parse("td.wrap").unwrap())
                     .next()
                     .map(|el| {
                         el.text()
-                        .collect::<String>()
-                        .replace(
-                            "\n                                                                ",
-                            " ",
-                        )
-                        .trim()
-                        .to_string()
+                            .collect::<String>()
+                            .replace(
+                                "\n                                                                ",
+                                " ",
+                            )
+                            .trim()
+                            .to_string()
                     })
-                    .unwrap(),
-            );
+            });
+        let Some(name) = name else {
+            report.product_skipped(SkippedProduct::new(
+                SkipReason::BadName,
+                "",
+                "product row has no name",
+            ));
+            continue;
+        };
 
         if let Some(pattern) = unwanted_patterns
             .iter()
             .find(|pattern| pattern.is_match(&name))
         {
-            warn!(
-                "Skipping unwanted card: {}. Matched pattern: {}",
-                name,
-                pattern.as_str()
-            );
+            report.product_skipped(SkippedProduct::new(
+                SkipReason::Token,
+                &name,
+                format!("matched pattern {}", pattern.as_str()),
+            ));
             continue; // Skip unwanted cards
         }
 
         let foil = foil_patterns.iter().any(|pattern| pattern.is_match(&name));
-        let prerelease = Regex::new(r"(?i)\(Prerelease\)")?.is_match(&name);
-        let showcase = Regex::new(r"(?i)\(Showcase\)")?.is_match(&name);
-        let extended_art = Regex::new(r"(?i)\(Extended Art\)")?.is_match(&name);
+        let prerelease = prerelease_pattern.is_match(&name);
+        let showcase = showcase_pattern.is_match(&name);
+        let extended_art = extended_art_pattern.is_match(&name);
+        let (condition, language) = condition_and_language(&name);
 
         let card_name = match CardName::new(name.clone()) {
             Ok(card_name) => card_name,
             Err(e) => {
                 debug!("Error parsing card name: '{}', with error: {}", name, e);
+                report.product_skipped(SkippedProduct::new(
+                    SkipReason::BadName,
+                    &name,
+                    e.to_string(),
+                ));
                 continue;
             }
         };
 
         let image_url = tr_elements
-            .select(&Selector::parse("a.fancybox")?)
+            .select(&Selector::parse("a.fancybox").unwrap())
             .next()
             .and_then(|el| el.value().attr("href"))
             .map(|href| format!("https://astraeus.dragonslair.se{}", href))
@@ -170,7 +202,7 @@ pub async fn fetch_and_parse(url: &str) -> Result<Vec<VendorCard>, Box<dyn Error
             );
 
         let mut set = tr_elements
-            .select(&Selector::parse("img[title]")?)
+            .select(&Selector::parse("img[title]").unwrap())
             .next()
             .and_then(|attributes| attributes.value().attr("title").map(String::from))
             .unwrap_or("UNKNOWN".to_string())
@@ -181,7 +213,7 @@ pub async fn fetch_and_parse(url: &str) -> Result<Vec<VendorCard>, Box<dyn Error
             .to_owned();
 
         let other_set = tr_elements
-            .select(&Selector::parse("td.align-right a")?)
+            .select(&Selector::parse("td.align-right a").unwrap())
             .next()
             .map(|el| el.text().collect::<String>().trim().to_string())
             .unwrap_or("UNKNOWN".to_string())
@@ -196,7 +228,7 @@ pub async fn fetch_and_parse(url: &str) -> Result<Vec<VendorCard>, Box<dyn Error
         }
 
         let collector_number = tr_elements
-            .select(&Selector::parse("td")?)
+            .select(&Selector::parse("td").unwrap())
             .nth(2) // Get the third td element (0-based index)
             .map(|element| element.text().collect::<String>().trim().to_string())
             .and_then(|text| {
@@ -219,7 +251,11 @@ pub async fn fetch_and_parse(url: &str) -> Result<Vec<VendorCard>, Box<dyn Error
         let set_name = match SetName::new(set) {
             Ok(set_name) => set_name,
             Err(e) => {
-                error!("Error parsing set name: {}", e);
+                report.product_skipped(SkippedProduct::new(
+                    SkipReason::UnknownSet,
+                    &card_name.raw,
+                    e.to_string(),
+                ));
                 continue;
             }
         };
@@ -227,10 +263,11 @@ pub async fn fetch_and_parse(url: &str) -> Result<Vec<VendorCard>, Box<dyn Error
         let price = match get_price(tr_elements) {
             Ok(price) => Price::new(price.into(), Currency::SEK),
             Err(e) => {
-                error!(
-                    "Error parsing price. Error {}, for card: {} in set {}",
-                    e, card_name.almost_raw, set_name.raw
-                );
+                report.product_skipped(SkippedProduct::new(
+                    SkipReason::BadPrice,
+                    &card_name.raw,
+                    format!("{} in set {}", e, set_name.raw),
+                ));
                 continue;
             }
         };
@@ -238,27 +275,27 @@ pub async fn fetch_and_parse(url: &str) -> Result<Vec<VendorCard>, Box<dyn Error
         let trade_in_price = match get_buyin_value(tr_elements) {
             Ok(price) => price,
             Err(e) => {
-                error!(
-                    "Error parsing trade-in price: {}, for card: {}",
-                    e, card_name.almost_raw
-                );
+                report.product_skipped(SkippedProduct::new(
+                    SkipReason::BadPrice,
+                    &card_name.raw,
+                    e.to_string(),
+                ));
                 continue;
             }
         };
 
         let stock = tr_elements
-            .select(&Selector::parse("td.align-right")?)
+            .select(&Selector::parse("td.align-right").unwrap())
             .nth(3)
-            .and_then(|element| {
+            .map(|element| {
                 let stock_str = element.text().collect::<String>();
                 let stock_numbers: Vec<i8> = stock_str
                     .replace("/", "")
                     .replace("st", "")
-                    .trim()
                     .split_whitespace()
                     .filter_map(|s| s.parse::<i8>().ok())
                     .collect();
-                Some(stock_numbers)
+                stock_numbers
             })
             .unwrap_or_else(|| vec![0, 0]); // Default to a vector of two zeros if parsing fails
 
@@ -266,28 +303,31 @@ pub async fn fetch_and_parse(url: &str) -> Result<Vec<VendorCard>, Box<dyn Error
             vendor: Vendor::Dragonslair,
             name: card_name,
             foil,
-            image_url: image_url,
+            image_url,
             extended_art,
             prerelease,
             showcase,
             set: set_name,
             price,
-            trade_in_price: trade_in_price,
+            trade_in_price,
             current_stock: stock.first().unwrap_or(&0).to_owned(),
             max_stock: stock.last().unwrap_or(&0).to_owned(),
-            collector_number: collector_number,
+            collector_number,
+            condition,
+            language,
         };
 
         cards_on_the_page.push(card);
+        report.product_parsed();
     }
 
-    Ok(cards_on_the_page)
+    Ok((cards_on_the_page, report))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test::helpers::reaper_king_vendor_card_expensive;
+    use crate::test::helpers::{reaper_king_vendor_card_expensive, test_http_client};
     use env_logger;
     use log::info;
     use tokio;
@@ -312,24 +352,35 @@ mod tests {
             .with_body(html_content.clone())
             .create();
 
-        let result = fetch_and_parse(&format!(
-            "{}/product/card-singles/magic?name=reaper+king",
-            url
-        ))
+        let (result, report) = fetch_and_parse(
+            &test_http_client(),
+            &format!("{}/product/card-singles/magic?name=reaper+king", url),
+        )
         .await
         .unwrap();
 
         mock.assert();
+        assert_eq!(report.pages_fetched, 1);
+        assert_eq!(report.parsed, 13);
+
+        // The damaged and played copies are listed first
+        let conditions: Vec<Condition> = result[..3].iter().map(|card| card.condition).collect();
+        assert_eq!(
+            conditions,
+            vec![Condition::Damaged, Condition::Damaged, Condition::Played]
+        );
+        assert_eq!(result[2].name.almost_raw, "Reaper King");
+        let result = &result[3..];
 
         let reaper_king_vendor_card = reaper_king_vendor_card_expensive();
 
         assert_eq!(result.first().unwrap(), &reaper_king_vendor_card);
-        assert_eq!(result.get(1).unwrap().foil, true);
-        assert_eq!(result.get(2).unwrap().foil, true);
-        assert_eq!(result.get(3).unwrap().foil, true);
-        assert_eq!(result.get(4).unwrap().prerelease, true);
-        assert_eq!(result.get(5).unwrap().showcase, true);
-        assert_eq!(result.get(6).unwrap().extended_art, true);
+        assert!(result.get(1).unwrap().foil);
+        assert!(result.get(2).unwrap().foil);
+        assert!(result.get(3).unwrap().foil);
+        assert!(result.get(4).unwrap().prerelease);
+        assert!(result.get(5).unwrap().showcase);
+        assert!(result.get(6).unwrap().extended_art);
         assert_eq!(
             result.get(7).unwrap().set.cleaned,
             "mystery booster retail edition foils"
@@ -344,12 +395,19 @@ mod tests {
         assert_eq!(result.len(), 10);
     }
 
+    #[test]
+    fn test_parse_price() {
+        assert_eq!(parse_price("Slut, 45 kr").unwrap(), 45);
+        assert!(parse_price(" - ").is_err());
+        assert!(parse_price("Ring oss").is_err());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_live_fetch_and_parse() {
         init();
         let url = "https://astraeus.dragonslair.se/product/card-singles/magic/store:kungsholmstorg?name=lightning+bolt";
-        let result = fetch_and_parse(url).await.unwrap();
+        let (result, _) = fetch_and_parse(&HttpClient::default(), url).await.unwrap();
         info!("{:?}", result);
         assert_eq!(result[0].name.cleaned, "lightning bolt");
         assert_eq!(result.len(), 4);
@@ -4,7 +4,9 @@ use std::{
 };
 
 use regex::Regex;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::ParseError;
 
 #[derive(Debug, Clone)]
 pub struct CardName {
@@ -15,17 +17,23 @@ pub struct CardName {
 }
 
 impl CardName {
-    pub fn new(raw: String) -> Result<Self, String> {
+    pub fn new(raw: String) -> Result<Self, ParseError> {
         let name_without_disclaimers = Self::remove_things_in_parenthesies_after_name(&raw);
         let double_faced = name_without_disclaimers.clone().contains("//");
         let cleaned_name = Self::clean_name(&name_without_disclaimers);
 
         if name_without_disclaimers.is_empty() || cleaned_name.is_empty() {
-            return Err("Raw and cleaned names cannot be empty".to_string());
+            return Err(ParseError::CardName {
+                raw,
+                reason: "Raw and cleaned names cannot be empty",
+            });
         }
 
         if Self::is_basic_land(&cleaned_name) {
-            return Err("Card cannot be a basic land".to_string());
+            return Err(ParseError::CardName {
+                raw,
+                reason: "Card cannot be a basic land",
+            });
         }
 
         Ok(CardName {
@@ -40,7 +48,7 @@ impl CardName {
     fn remove_things_in_parenthesies_after_name(str_raw: &str) -> String {
         let mut name = Regex::new(r"\([^()]*\)")
             .unwrap()
-            .replace_all(&str_raw, "")
+            .replace_all(str_raw, "")
             .to_string();
 
         name = name
@@ -100,7 +108,7 @@ impl Eq for CardName {}
 
 impl PartialOrd for CardName {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cleaned.cmp(&other.cleaned))
+        Some(self.cmp(other))
     }
 }
 
@@ -130,6 +138,20 @@ impl<'de> Deserialize<'de> for CardName {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        Ok(CardName::new(s).unwrap())
+        CardName::new(s).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_basic_land_is_an_error() {
+        let card_name: CardName = serde_json::from_str("\"Reaper King\"").unwrap();
+        assert_eq!(card_name.raw, "Reaper King");
+
+        let error = serde_json::from_str::<CardName>("\"Island\"").unwrap_err();
+        assert!(error.to_string().starts_with("Invalid card name 'Island'"));
     }
 }
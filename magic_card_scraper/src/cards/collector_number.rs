@@ -1,6 +1,8 @@
 use log::warn;
 use serde::{Deserialize, Serialize};
 
+use crate::error::ParseError;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CollectorNumber {
     raw_value: String,
@@ -8,7 +10,7 @@ pub struct CollectorNumber {
 }
 
 impl CollectorNumber {
-    pub fn new(collector_number: &str) -> Result<Self, String> {
+    pub fn new(collector_number: &str) -> Result<Self, ParseError> {
         let c_num = collector_number.trim();
 
         if Self::is_only_digits(c_num)
@@ -21,10 +23,17 @@ impl CollectorNumber {
             })
         } else {
             warn!("{} is not a valid collector number", collector_number);
-            Err("Collector number must be 2 to 8 digits long, or 12 characters long with a dash between the first and last character".to_string())
+            Err(ParseError::CollectorNumber {
+                raw: collector_number.to_string(),
+            })
         }
     }
 
+    /// The collector number as printed, e.g. `SHM-260`
+    pub fn raw(&self) -> &str {
+        &self.raw_value
+    }
+
     fn is_underscore_and_dash_separated_string(num: &str) -> bool {
         num.len() >= 4
             && num.len() <= 12
@@ -35,7 +44,7 @@ impl CollectorNumber {
     }
 
     fn is_only_digits(num: &str) -> bool {
-        num.len() >= 2 && num.len() <= 8 && num.chars().all(|c| c.is_digit(10))
+        num.len() >= 2 && num.len() <= 8 && num.chars().all(|c| c.is_ascii_digit())
     }
 
     fn is_dash_separated_string(num: &str) -> bool {
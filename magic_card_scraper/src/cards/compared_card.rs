@@ -1,25 +1,70 @@
+use std::fmt;
+
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-use super::{scryfallcard::ScryfallCard, vendorcard::VendorCard};
+use super::{price::Price, scryfallcard::ScryfallCard, vendorcard::VendorCard};
+use crate::price_source::PriceQuote;
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct ComparedCard {
     pub vendor_card: VendorCard,
     pub scryfall_card: ScryfallCard,
-    // Positive here is a cheaper vendor card then the MCM. The amount is the difference in SEK
-    pub price_difference_to_cheapest_vendor_card: i32,
+    // Vendor price minus the reference price in SEK, to the öre. Negative when the vendor card
+    // is cheaper, 0 when there is no reference price
+    pub price_difference_to_cheapest_vendor_card: Decimal,
+    /// The MCM trend price the vendor card was compared to, less the discount for its condition
+    /// and language. None if no source had a price
+    #[serde(default)]
+    pub reference_price: Option<PriceQuote>,
+    #[serde(default)]
+    pub match_quality: MatchQuality,
+}
+
+impl ComparedCard {
+    /// The reference price, taken from the Scryfall card for snapshots saved before the
+    /// reference price was recorded
+    pub fn reference_price(&self) -> Option<Price> {
+        match (&self.reference_price, self.match_quality) {
+            (Some(quote), _) => Some(quote.price),
+            (None, MatchQuality::Unknown) if self.vendor_card.foil => {
+                self.scryfall_card.prices.eur_foil
+            }
+            (None, MatchQuality::Unknown) => self.scryfall_card.prices.eur,
+            (None, _) => None,
+        }
+    }
+
+    /// Whether the price difference can be trusted
+    pub fn has_reference_price(&self) -> bool {
+        self.match_quality != MatchQuality::NoPrice
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct CurrencyRate {
-    amount: f64,
-    base: String,
-    date: String,
-    rates: Rates,
+/// How well the reference price fits the vendor card, best first
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum MatchQuality {
+    /// Same collector number, priced for the same finish
+    Exact,
+    /// Only the set name matched, so it may be another printing in the set
+    SameSet,
+    /// The price is for the other finish, e.g. a non-foil price for a foil card
+    OtherFinish,
+    /// No price source had a price
+    NoPrice,
+    /// Compared before the match quality was recorded
+    #[default]
+    Unknown,
 }
 
-#[allow(non_snake_case)]
-#[derive(Debug, Deserialize, Serialize)]
-struct Rates {
-    SEK: f64,
+impl fmt::Display for MatchQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchQuality::Exact => write!(f, "exact"),
+            MatchQuality::SameSet => write!(f, "same set"),
+            MatchQuality::OtherFinish => write!(f, "other finish"),
+            MatchQuality::NoPrice => write!(f, "no price"),
+            MatchQuality::Unknown => write!(f, "unknown"),
+        }
+    }
 }
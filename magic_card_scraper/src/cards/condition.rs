@@ -0,0 +1,134 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Condition of a card, best first
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum Condition {
+    #[default]
+    NearMint,
+    Excellent,
+    Good,
+    Played,
+    Damaged,
+}
+
+impl Condition {
+    /// Reads the Swedish condition tags of the stores, e.g. `Spelad` in `Reaper King (Spelad)`
+    pub fn from_swedish(tag: &str) -> Option<Self> {
+        match tag.trim().to_lowercase().as_str() {
+            "spelad" => Some(Condition::Played),
+            "skadad" => Some(Condition::Damaged),
+            _ => None,
+        }
+    }
+
+    /// Whether this is the given condition or a better one
+    pub fn is_at_least(&self, condition: Condition) -> bool {
+        self.rank() <= condition.rank()
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Condition::NearMint => 0,
+            Condition::Excellent => 1,
+            Condition::Good => 2,
+            Condition::Played => 3,
+            Condition::Damaged => 4,
+        }
+    }
+
+    /// The condition as Cardmarket writes it in stock uploads
+    pub fn cardmarket_code(&self) -> &'static str {
+        match self {
+            Condition::NearMint => "NM",
+            Condition::Excellent => "EX",
+            Condition::Good => "GD",
+            Condition::Played => "PL",
+            Condition::Damaged => "PO",
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::NearMint => write!(f, "NM"),
+            Condition::Excellent => write!(f, "EX"),
+            Condition::Good => write!(f, "GD"),
+            Condition::Played => write!(f, "PL"),
+            Condition::Damaged => write!(f, "DMG"),
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    /// Takes the short and long names of the collection apps, e.g. `NM`, `Near Mint`,
+    /// `near_mint` or `NearMint`. `Lightly Played` is the US grading and counts as excellent,
+    /// Cardmarket's `Light Played` is a step below good and counts as played
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let condition: String = input
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect();
+        match condition.as_str() {
+            "m" | "mt" | "mint" | "nm" | "nearmint" => Ok(Condition::NearMint),
+            "ex" | "excellent" | "sp" | "slightlyplayed" | "lightlyplayed" => {
+                Ok(Condition::Excellent)
+            }
+            "gd" | "good" | "goodlightlyplayed" | "mp" | "moderatelyplayed" => Ok(Condition::Good),
+            "lp" | "lightplayed" | "pl" | "played" | "hp" | "heavilyplayed" => {
+                Ok(Condition::Played)
+            }
+            "dmg" | "damaged" | "po" | "poor" => Ok(Condition::Damaged),
+            _ => Err(format!("unknown condition '{}'", input)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_condition() {
+        let parsed: Vec<Result<Condition, String>> = [
+            "NM",
+            "near_mint",
+            "Lightly Played",
+            "Good (Lightly Played)",
+            "Light Played",
+            "PL",
+            "Poor",
+            "worn",
+        ]
+        .iter()
+        .map(|condition| condition.parse())
+        .collect();
+
+        assert_eq!(
+            parsed,
+            vec![
+                Ok(Condition::NearMint),
+                Ok(Condition::NearMint),
+                Ok(Condition::Excellent),
+                Ok(Condition::Good),
+                Ok(Condition::Played),
+                Ok(Condition::Played),
+                Ok(Condition::Damaged),
+                Err("unknown condition 'worn'".to_string()),
+            ]
+        );
+        assert_eq!(
+            Condition::from_swedish(" Skadad "),
+            Some(Condition::Damaged)
+        );
+        assert_eq!(Condition::from_swedish("foil"), None);
+        assert!(Condition::NearMint.is_at_least(Condition::Excellent));
+        assert!(Condition::Excellent.is_at_least(Condition::Excellent));
+        assert!(!Condition::Good.is_at_least(Condition::Excellent));
+    }
+}
@@ -1,38 +1,233 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
-use std::{fmt, str::FromStr};
+use std::{collections::HashMap, fmt, str::FromStr};
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Currency {
     EUR,
     SEK,
+    USD,
+    NOK,
+    DKK,
+    GBP,
 }
 
 impl Currency {
-    /// Get the exchange rate based on currency
-    /// Example: 1 SEK = 0.87 EUR (1 EUR = 11.50 SEK)
-    pub fn exchange_rate(&self) -> f64 {
+    pub const ALL: [Currency; 6] = [
+        Currency::EUR,
+        Currency::SEK,
+        Currency::USD,
+        Currency::NOK,
+        Currency::DKK,
+        Currency::GBP,
+    ];
+
+    /// ISO 4217 code, e.g. `SEK`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::EUR => "EUR",
+            Currency::SEK => "SEK",
+            Currency::USD => "USD",
+            Currency::NOK => "NOK",
+            Currency::DKK => "DKK",
+            Currency::GBP => "GBP",
+        }
+    }
+
+    /// Number of decimals an amount in this currency is rounded to
+    pub fn decimal_places(&self) -> u32 {
+        match self {
+            // Öre and øre still exist in prices even if not in cash
+            Currency::EUR
+            | Currency::SEK
+            | Currency::USD
+            | Currency::NOK
+            | Currency::DKK
+            | Currency::GBP => 2,
+        }
+    }
+
+    /// Rounds an amount to the smallest unit of the currency, halves away from zero
+    pub fn round(&self, amount: Decimal) -> Decimal {
+        amount.round_dp_with_strategy(
+            self.decimal_places(),
+            RoundingStrategy::MidpointAwayFromZero,
+        )
+    }
+
+    /// Formats an amount the way prices in this currency are usually written
+    pub fn format_amount(&self, amount: Decimal) -> String {
+        let amount = self.round(amount);
+        let dp = self.decimal_places() as usize;
         match self {
-            Currency::EUR => 11.0304,
-            Currency::SEK => 0.090658791,
+            Currency::EUR => format!("€{:.dp$}", amount),
+            Currency::USD => format!("${:.dp$}", amount),
+            Currency::GBP => format!("£{:.dp$}", amount),
+            // kr is ambiguous between the nordic currencies, so they keep their code
+            Currency::SEK | Currency::NOK | Currency::DKK => {
+                format!("{:.dp$} {}", amount, self.code())
+            }
+        }
+    }
+}
+
+/// How much of each currency one EUR buys
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeRates {
+    per_eur: HashMap<Currency, Decimal>,
+}
+
+impl ExchangeRates {
+    /// The built in rates with the given SEK per EUR
+    pub fn from_eur_to_sek(eur_to_sek: Decimal) -> Self {
+        Self::default().with_rate(Currency::SEK, eur_to_sek)
+    }
+
+    /// Sets how much of the currency one EUR buys
+    pub fn with_rate(mut self, currency: Currency, per_eur: Decimal) -> Self {
+        if currency != Currency::EUR {
+            self.per_eur.insert(currency, per_eur);
+        }
+        self
+    }
+
+    /// How much of the currency one EUR buys, None if there is no rate for it
+    pub fn per_eur(&self, currency: Currency) -> Option<Decimal> {
+        match currency {
+            Currency::EUR => Some(Decimal::ONE),
+            _ => self.per_eur.get(&currency).copied(),
+        }
+    }
+
+    pub fn eur_to_sek(&self) -> Decimal {
+        self.rate(Currency::SEK)
+    }
+
+    /// Converts an amount between two currencies, without rounding it
+    pub fn convert(&self, amount: Decimal, from: Currency, to: Currency) -> Decimal {
+        if from == to {
+            amount
+        } else {
+            amount * self.rate(to) / self.rate(from)
+        }
+    }
+
+    /// The rate of the currency, the built in one if there is none
+    fn rate(&self, currency: Currency) -> Decimal {
+        self.per_eur(currency)
+            .unwrap_or_else(|| built_in_per_eur(currency))
+    }
+}
+
+/// Rough rates used when no live or configured rate is available
+fn built_in_per_eur(currency: Currency) -> Decimal {
+    match currency {
+        Currency::EUR => Decimal::ONE,
+        Currency::SEK => dec!(11.0304),
+        Currency::USD => dec!(1.08),
+        Currency::NOK => dec!(11.7),
+        Currency::DKK => dec!(7.46),
+        Currency::GBP => dec!(0.85),
+    }
+}
+
+impl Default for ExchangeRates {
+    fn default() -> Self {
+        Self {
+            per_eur: Currency::ALL
+                .into_iter()
+                .filter(|currency| *currency != Currency::EUR)
+                .map(|currency| (currency, built_in_per_eur(currency)))
+                .collect(),
         }
     }
 }
 
 impl fmt::Display for Currency {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Currency::EUR => write!(f, "EUR"),
-            Currency::SEK => write!(f, "SEK"),
-        }
+        write!(f, "{}", self.code())
     }
 }
+
 impl FromStr for Currency {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "SEK" => Ok(Currency::SEK),
-            "EUR" => Ok(Currency::EUR),
-            _ => Err(()),
+        Currency::ALL
+            .into_iter()
+            .find(|currency| currency.code() == s)
+            .ok_or(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_currency_has_a_rate() {
+        let rates = ExchangeRates::default();
+        for currency in Currency::ALL {
+            assert!(
+                rates.per_eur(currency) > Some(Decimal::ZERO),
+                "{}",
+                currency
+            );
+            assert_eq!(currency.code().parse(), Ok(currency));
         }
     }
+
+    #[test]
+    fn test_convert_between_non_eur_currencies() {
+        let rates = ExchangeRates::default()
+            .with_rate(Currency::SEK, dec!(11.0))
+            .with_rate(Currency::USD, dec!(1.1))
+            .with_rate(Currency::EUR, dec!(2.0));
+
+        assert_eq!(
+            rates.convert(dec!(11), Currency::SEK, Currency::EUR),
+            dec!(1)
+        );
+        assert_eq!(
+            rates.convert(dec!(110), Currency::SEK, Currency::USD),
+            dec!(11)
+        );
+        assert_eq!(
+            rates.convert(dec!(5), Currency::USD, Currency::USD),
+            dec!(5)
+        );
+        // EUR is always 1
+        assert_eq!(rates.per_eur(Currency::EUR), Some(Decimal::ONE));
+    }
+
+    #[test]
+    fn test_missing_rate_uses_the_built_in_one() {
+        let rates = ExchangeRates {
+            per_eur: HashMap::from([(Currency::SEK, dec!(10))]),
+        };
+
+        assert_eq!(rates.per_eur(Currency::USD), None);
+        assert_eq!(
+            rates.convert(dec!(10), Currency::SEK, Currency::USD),
+            dec!(1.08)
+        );
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(Currency::EUR.format_amount(dec!(1.5)), "€1.50");
+        assert_eq!(Currency::USD.format_amount(dec!(2)), "$2.00");
+        assert_eq!(Currency::GBP.format_amount(dec!(0.124)), "£0.12");
+        assert_eq!(Currency::SEK.format_amount(dec!(100)), "100.00 SEK");
+        assert_eq!(Currency::NOK.format_amount(dec!(3.333)), "3.33 NOK");
+    }
+
+    #[test]
+    fn test_round_halves_away_from_zero() {
+        assert_eq!(Currency::SEK.round(dec!(10.125)), dec!(10.13));
+        assert_eq!(Currency::SEK.round(dec!(-10.125)), dec!(-10.13));
+        assert_eq!(Currency::EUR.round(dec!(0.124)), dec!(0.12));
+        assert_eq!(Currency::EUR.format_amount(dec!(0.005)), "€0.01");
+    }
 }
@@ -0,0 +1,106 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Language a card is printed in
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    French,
+    Italian,
+    Spanish,
+    Portuguese,
+    Russian,
+    Japanese,
+    Korean,
+    /// Simplified Chinese
+    Chinese,
+    TraditionalChinese,
+}
+
+impl Language {
+    /// Reads the Swedish language tags of the stores, e.g. `tysk` in `Reaper King (tysk)`
+    pub fn from_swedish(tag: &str) -> Option<Self> {
+        match tag.trim().to_lowercase().as_str() {
+            "engelsk" => Some(Language::English),
+            "tysk" => Some(Language::German),
+            "fransk" => Some(Language::French),
+            "italiensk" => Some(Language::Italian),
+            "spansk" => Some(Language::Spanish),
+            "portugisisk" => Some(Language::Portuguese),
+            "rysk" => Some(Language::Russian),
+            "japansk" => Some(Language::Japanese),
+            "koreansk" => Some(Language::Korean),
+            "kinesisk" => Some(Language::Chinese),
+            _ => None,
+        }
+    }
+
+    /// The language as Cardmarket writes it in stock uploads
+    pub fn cardmarket_name(&self) -> String {
+        match self {
+            Language::Chinese => "S-Chinese".to_string(),
+            Language::TraditionalChinese => "T-Chinese".to_string(),
+            language => language.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Language::English => write!(f, "English"),
+            Language::German => write!(f, "German"),
+            Language::French => write!(f, "French"),
+            Language::Italian => write!(f, "Italian"),
+            Language::Spanish => write!(f, "Spanish"),
+            Language::Portuguese => write!(f, "Portuguese"),
+            Language::Russian => write!(f, "Russian"),
+            Language::Japanese => write!(f, "Japanese"),
+            Language::Korean => write!(f, "Korean"),
+            Language::Chinese => write!(f, "Chinese"),
+            Language::TraditionalChinese => write!(f, "Traditional Chinese"),
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    /// Takes the English name or the two letter code, e.g. `German` or `de`
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "en" | "english" => Ok(Language::English),
+            "de" | "german" => Ok(Language::German),
+            "fr" | "french" => Ok(Language::French),
+            "it" | "italian" => Ok(Language::Italian),
+            "es" | "spanish" => Ok(Language::Spanish),
+            "pt" | "portuguese" => Ok(Language::Portuguese),
+            "ru" | "russian" => Ok(Language::Russian),
+            "ja" | "jp" | "japanese" => Ok(Language::Japanese),
+            "ko" | "kr" | "korean" => Ok(Language::Korean),
+            "zh" | "zhs" | "cs" | "chinese" | "simplified chinese" => Ok(Language::Chinese),
+            "zht" | "ct" | "traditional chinese" => Ok(Language::TraditionalChinese),
+            _ => Err(format!("unknown language '{}'", input)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simplified_and_traditional_chinese_are_apart() {
+        assert_eq!("zhs".parse(), Ok(Language::Chinese));
+        assert_eq!(
+            "Traditional Chinese".parse(),
+            Ok(Language::TraditionalChinese)
+        );
+        assert_eq!("zht".parse(), Ok(Language::TraditionalChinese));
+        assert_eq!(Language::Chinese.cardmarket_name(), "S-Chinese");
+        assert_eq!(Language::TraditionalChinese.cardmarket_name(), "T-Chinese");
+    }
+}
@@ -1,10 +1,11 @@
-// use rusqlite::types::FromSql;
-// use rusqlite::types::FromSqlError;
-// use rusqlite::types::FromSqlResult;
-// use rusqlite::types::ValueRef;
+use rusqlite::types::FromSql;
+use rusqlite::types::FromSqlError;
+use rusqlite::types::FromSqlResult;
+use rusqlite::types::ValueRef;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+#[allow(dead_code)]
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Copy)]
 pub enum MagicRarity {
     Common,
@@ -13,17 +14,17 @@ pub enum MagicRarity {
     Mythic,
 }
 
-// impl FromSql for MagicRarity {
-//     fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
-//         match value.as_str()? {
-//             "Common" => Ok(MagicRarity::Common),
-//             "Uncommon" => Ok(MagicRarity::Uncommon),
-//             "Rare" => Ok(MagicRarity::Rare),
-//             "Mythic" => Ok(MagicRarity::Mythic),
-//             _ => Err(FromSqlError::InvalidType),
-//         }
-//     }
-// }
+impl FromSql for MagicRarity {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "Common" => Ok(MagicRarity::Common),
+            "Uncommon" => Ok(MagicRarity::Uncommon),
+            "Rare" => Ok(MagicRarity::Rare),
+            "Mythic" => Ok(MagicRarity::Mythic),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
 
 impl fmt::Display for MagicRarity {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -1,8 +1,10 @@
 pub mod card_parser;
 pub mod cardname;
 pub mod collector_number;
+pub mod condition;
 pub mod compared_card;
 pub mod currency;
+pub mod language;
 pub mod magicrarity;
 pub mod personalcard;
 pub mod price;
@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-use super::{cardname::CardName, colour::Colour, price::Price, rarity::Rarity, setname::SetName};
+use super::{
+    cardname::CardName, colour::Colour, condition::Condition, language::Language, price::Price,
+    rarity::Rarity, setname::SetName,
+};
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct PersonalCard {
@@ -8,7 +11,11 @@ pub struct PersonalCard {
     pub set: SetName,
     pub foil: bool,
     pub price: Price,
-    pub count: i8,
+    pub count: u32,
     pub color: Colour,
     pub rarity: Rarity,
+    #[serde(default)]
+    pub condition: Condition,
+    #[serde(default)]
+    pub language: Language,
 }
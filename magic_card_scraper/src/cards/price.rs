@@ -1,66 +1,76 @@
 use std::cmp::Ordering;
 use std::fmt;
 
-// use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-use super::currency::Currency;
+use super::currency::{Currency, ExchangeRates};
 
+/// An amount of money. Serialized as a decimal string, snapshots with float amounts still load
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Price {
-    pub amount: f64,
+    pub amount: Decimal,
     pub currency: Currency,
 }
 
-// impl FromSql for Currency {
-//     fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
-//         match value.as_str()? {
-//             "EUR" => Ok(Currency::EUR),
-//             "SEK" => Ok(Currency::SEK),
-//             _ => Err(FromSqlError::InvalidType),
-//         }
-//     }
-// }
+/// An amount in SEK, the currency totals are kept in
+pub fn sek(amount: Decimal) -> Price {
+    Price::new(amount, Currency::SEK)
+}
+
+impl FromSql for Currency {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|_| FromSqlError::InvalidType)
+    }
+}
 
 impl Price {
     /// Create a new Price instance
-    pub fn new(amount: f64, currency: Currency) -> Self {
+    pub fn new(amount: Decimal, currency: Currency) -> Self {
         Self { amount, currency }
     }
 
-    /// Convert the price to EUR for comparison
-    fn to_eur(&self) -> f64 {
-        match self.currency {
-            Currency::EUR => self.amount,
-            Currency::SEK => self.amount * self.currency.exchange_rate(),
-        }
+    /// The price rounded to the smallest unit of its currency
+    pub fn rounded(&self) -> Price {
+        Price::new(self.currency.round(self.amount), self.currency)
     }
 
-    /// Convert the price to the specified currency
-    pub fn convert_to(&self, target_currency: Currency) -> f64 {
-        if self.currency == target_currency {
-            self.amount
-        } else {
-            let amount_in_eur = self.to_eur();
-            match target_currency {
-                Currency::EUR => amount_in_eur,
-                Currency::SEK => amount_in_eur * Currency::EUR.exchange_rate(),
-            }
-        }
+    /// Convert the price to the specified currency with the given rates, rounded to that currency
+    pub fn convert_to(&self, target_currency: Currency, rates: &ExchangeRates) -> Price {
+        let amount = rates.convert(self.amount, self.currency, target_currency);
+        Price::new(target_currency.round(amount), target_currency)
     }
-}
 
-// Implement PartialOrd and Ord for Price
-impl PartialOrd for Price {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.to_eur().partial_cmp(&other.to_eur())
+    /// How much more this price is than `other`, in `currency`. Both are rounded to
+    /// `currency` first so the difference is what the two prices would show
+    pub fn difference_with(
+        &self,
+        other: &Price,
+        currency: Currency,
+        rates: &ExchangeRates,
+    ) -> Decimal {
+        self.convert_to(currency, rates).amount - other.convert_to(currency, rates).amount
     }
 }
 
-// Implement PartialEq for Price
+// Prices in different currencies are never equal and have no order, convert them first
 impl PartialEq for Price {
     fn eq(&self, other: &Self) -> bool {
-        self.to_eur() == other.to_eur()
+        self.currency == other.currency && self.amount == other.amount
+    }
+}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.currency == other.currency {
+            self.amount.partial_cmp(&other.amount)
+        } else {
+            None
+        }
     }
 }
 
@@ -108,50 +118,136 @@ impl PartialEq for Price {
 // Implement Display for Price
 impl fmt::Display for Price {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let amount_in_sek = match self.currency {
-            Currency::EUR => self.amount * self.currency.exchange_rate(),
-            Currency::SEK => self.amount,
-        };
-        write!(f, "{:.2} {:?}", amount_in_sek, Currency::SEK)
+        write!(f, "{}", self.currency.format_amount(self.amount))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use rust_decimal_macros::dec;
+
     use super::*;
 
     #[test]
-    fn test_reading_delver_lense_cards() {
-        let price_eur = Price::new(5.0, Currency::EUR);
-        let price_sek = Price::new(60.0, Currency::SEK);
-        let price_sek2 = Price::new(60.0, Currency::SEK);
-
-        assert_eq!(price_eur < price_sek, true);
-        assert_eq!(price_eur > price_sek, false);
-        assert_eq!(price_sek2 == price_sek, true);
+    fn test_only_prices_in_the_same_currency_compare() {
+        let price_eur = Price::new(dec!(5.0), Currency::EUR);
+        let price_sek = Price::new(dec!(60.0), Currency::SEK);
+        let price_sek2 = Price::new(dec!(60.0), Currency::SEK);
+        let cheaper_sek = Price::new(dec!(50.0), Currency::SEK);
+
+        assert!(cheaper_sek < price_sek);
+        assert!(price_sek2 <= price_sek);
+        assert!(price_sek2 == price_sek);
+        assert_eq!(price_eur.partial_cmp(&price_sek), None);
+        assert_ne!(price_eur, price_sek);
     }
 
     #[test]
     fn test_price_conversion() {
-        let price_eur = Price::new(5.0, Currency::EUR);
-        let price_sek = Price::new(60.0, Currency::SEK);
-
-        assert_eq!(price_eur.convert_to(Currency::EUR), 5.0);
-        assert_eq!(price_eur.convert_to(Currency::SEK), 55.152);
-        assert_eq!(price_sek.convert_to(Currency::SEK), 60.0);
-        assert_eq!(price_sek.convert_to(Currency::EUR), 5.43952746);
-    }
-
-    // #[test]
-    // fn test_price_comparison_with_f64() {
-    //     let price_eur = Price::new(5.0, Currency::EUR);
-    //     let price_sek = Price::new(60.0, Currency::SEK);
-
-    //     assert_eq!(price_eur == 5.0, true);
-    //     assert_eq!(price_sek == 5.43952746, true);
-    //     assert!(price_eur < 6.0);
-    //     assert!(price_sek > 5.0);
-    //     assert!(4.0 < price_eur);
-    //     assert!(6.0 > price_sek);
-    // }
+        let rates = ExchangeRates::default();
+        let price_eur = Price::new(dec!(5.0), Currency::EUR);
+        let price_sek = Price::new(dec!(60.0), Currency::SEK);
+
+        assert_eq!(price_eur.convert_to(Currency::EUR, &rates).amount, dec!(5));
+        // 55.152 SEK, rounded to öre
+        assert_eq!(
+            price_eur.convert_to(Currency::SEK, &rates).amount,
+            dec!(55.15)
+        );
+        assert_eq!(price_sek.convert_to(Currency::SEK, &rates).amount, dec!(60));
+        // 5.4395...
+        assert_eq!(
+            price_sek.convert_to(Currency::EUR, &rates).amount,
+            dec!(5.44)
+        );
+        assert_eq!(
+            price_sek.convert_to(Currency::EUR, &rates).currency,
+            Currency::EUR
+        );
+    }
+
+    #[test]
+    fn test_equality_is_exact() {
+        // 0.1 + 0.2 != 0.3 with floats
+        let sum = Price::new(dec!(0.1) + dec!(0.2), Currency::EUR);
+        assert_eq!(sum, Price::new(dec!(0.3), Currency::EUR));
+        assert_eq!(
+            Price::new(dec!(5), Currency::SEK),
+            Price::new(dec!(5.00), Currency::SEK)
+        );
+
+        // Not even when they are worth the same
+        let rates = ExchangeRates::default();
+        let price_eur = Price::new(dec!(5), Currency::EUR);
+        assert_ne!(
+            price_eur,
+            Price::new(
+                rates.convert(dec!(5), Currency::EUR, Currency::SEK),
+                Currency::SEK
+            )
+        );
+    }
+
+    #[test]
+    fn test_difference_is_not_truncated() {
+        let rates = ExchangeRates::from_eur_to_sek(dec!(10));
+        let vendor_price = Price::new(dec!(12), Currency::SEK);
+        let mcm_price = Price::new(dec!(1.15), Currency::EUR);
+
+        assert_eq!(
+            vendor_price.difference_with(&mcm_price, Currency::SEK, &rates),
+            dec!(0.5)
+        );
+        assert_eq!(
+            mcm_price.difference_with(&vendor_price, Currency::SEK, &rates),
+            dec!(-0.5)
+        );
+    }
+
+    #[test]
+    fn test_price_conversion_between_other_currencies() {
+        let rates = ExchangeRates::default()
+            .with_rate(Currency::USD, dec!(1.25))
+            .with_rate(Currency::NOK, dec!(12.5));
+        let price_usd = Price::new(dec!(10.0), Currency::USD);
+
+        assert_eq!(price_usd.convert_to(Currency::EUR, &rates).amount, dec!(8));
+        assert_eq!(
+            price_usd.convert_to(Currency::NOK, &rates).amount,
+            dec!(100)
+        );
+    }
+
+    #[test]
+    fn test_display_follows_currency() {
+        assert_eq!(Price::new(dec!(12.5), Currency::EUR).to_string(), "€12.50");
+        assert_eq!(
+            Price::new(dec!(100), Currency::SEK).to_string(),
+            "100.00 SEK"
+        );
+        assert_eq!(Price::new(dec!(3), Currency::USD).to_string(), "$3.00");
+    }
+
+    #[test]
+    fn test_price_conversion_with_custom_rate() {
+        let rates = ExchangeRates::from_eur_to_sek(dec!(10.0));
+        let price_eur = Price::new(dec!(5.0), Currency::EUR);
+        let price_sek = Price::new(dec!(60.0), Currency::SEK);
+
+        assert_eq!(price_eur.convert_to(Currency::SEK, &rates).amount, dec!(50));
+        assert_eq!(price_sek.convert_to(Currency::EUR, &rates).amount, dec!(6));
+    }
+
+    #[test]
+    fn test_serde_reads_float_amounts() {
+        let price: Price = serde_json::from_str(r#"{"amount": 12.5, "currency": "SEK"}"#).unwrap();
+        assert_eq!(price.amount, dec!(12.5));
+
+        let json = serde_json::to_string(&Price::new(dec!(0.10), Currency::EUR)).unwrap();
+        assert_eq!(json, r#"{"amount":"0.10","currency":"EUR"}"#);
+        assert_eq!(
+            serde_json::from_str::<Price>(&json).unwrap().amount,
+            dec!(0.1)
+        );
+    }
 }
@@ -1,4 +1,4 @@
-use std::fmt;
+use std::{fmt, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
@@ -20,3 +20,18 @@ impl fmt::Display for Rarity {
         }
     }
 }
+
+impl FromStr for Rarity {
+    type Err = String;
+
+    /// Takes the full name or the first letter, e.g. `Mythic` or `M`
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "c" | "common" => Ok(Rarity::Common),
+            "u" | "uncommon" => Ok(Rarity::Uncommon),
+            "r" | "rare" => Ok(Rarity::Rare),
+            "m" | "mythic" | "mythic rare" => Ok(Rarity::Mythic),
+            _ => Err(format!("unknown rarity '{}'", input)),
+        }
+    }
+}
@@ -6,13 +6,21 @@ use serde::{Deserialize, Serialize};
 pub struct ScryfallCard {
     pub name: CardName,
     pub set: SetName,
+    /// Scryfall's code of the set, e.g. `shm`. Snapshots from before it was kept have none
+    #[serde(default)]
+    pub set_code: Option<String>,
     pub image_url: String,
     pub prices: Prices,
     pub collector_number: Option<CollectorNumber>,
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Prices {
     pub eur: Option<Price>,
     pub eur_foil: Option<Price>,
+    /// TCGplayer market prices
+    #[serde(default)]
+    pub usd: Option<Price>,
+    #[serde(default)]
+    pub usd_foil: Option<Price>,
 }
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::error::ParseError;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SetName {
     pub raw: String,
@@ -7,12 +9,15 @@ pub struct SetName {
 }
 
 impl SetName {
-    pub fn new(raw: String) -> Result<Self, String> {
+    pub fn new(raw: String) -> Result<Self, ParseError> {
         let raw = raw.replace("'", "").replace("\"", "");
         let cleaned = Self::clean_set_name(&raw);
 
         if raw.is_empty() || cleaned.is_empty() {
-            return Err("Raw and cleaned names cannot be empty".to_string());
+            return Err(ParseError::SetName {
+                raw,
+                reason: "Raw and cleaned names cannot be empty",
+            });
         }
 
         Ok(SetName { raw, cleaned })
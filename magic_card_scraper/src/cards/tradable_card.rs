@@ -14,7 +14,7 @@ pub struct TradeableCard {
     pub tradeable_vendor: Vendor,
     pub trade_in_price: Price,
     pub mcm_price: Price,
-    pub cards_to_trade: i8,
+    pub cards_to_trade: u32,
     pub card_ammount_requested_by_vendor: i8,
     #[serde(default = "image_url_default")]
     pub image_url: String,
@@ -1,7 +1,7 @@
-// use rusqlite::types::FromSql;
-// use rusqlite::types::FromSqlError;
-// use rusqlite::types::FromSqlResult;
-// use rusqlite::types::ValueRef;
+use rusqlite::types::FromSql;
+use rusqlite::types::FromSqlError;
+use rusqlite::types::FromSqlResult;
+use rusqlite::types::ValueRef;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -10,6 +10,7 @@ pub enum Vendor {
     Dragonslair,
     Alphaspel,
     Cardmarket,
+    Manatorsk,
 }
 
 impl fmt::Display for Vendor {
@@ -18,17 +19,19 @@ impl fmt::Display for Vendor {
             Vendor::Dragonslair => write!(f, "Dragonslair"),
             Vendor::Alphaspel => write!(f, "Alphaspel"),
             Vendor::Cardmarket => write!(f, "Cardmarket"),
+            Vendor::Manatorsk => write!(f, "Manatorsk"),
         }
     }
 }
 
-// impl FromSql for Vendor {
-//     fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
-//         match value.as_str()? {
-//             "Dragonslair" => Ok(Vendor::Dragonslair),
-//             "Alphaspel" => Ok(Vendor::Alphaspel),
-//             "Cardmarket" => Ok(Vendor::Cardmarket),
-//             _ => Err(FromSqlError::InvalidType),
-//         }
-//     }
-// }
+impl FromSql for Vendor {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "Dragonslair" => Ok(Vendor::Dragonslair),
+            "Alphaspel" => Ok(Vendor::Alphaspel),
+            "Cardmarket" => Ok(Vendor::Cardmarket),
+            "Manatorsk" => Ok(Vendor::Manatorsk),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
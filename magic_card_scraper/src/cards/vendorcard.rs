@@ -1,8 +1,8 @@
 use serde::{Deserialize, Serialize};
 
 use super::{
-    cardname::CardName, collector_number::CollectorNumber, price::Price, setname::SetName,
-    vendor::Vendor,
+    cardname::CardName, collector_number::CollectorNumber, condition::Condition,
+    language::Language, price::Price, setname::SetName, vendor::Vendor,
 };
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -20,4 +20,8 @@ pub struct VendorCard {
     pub current_stock: i8,
     pub max_stock: i8,
     pub collector_number: Option<CollectorNumber>,
+    #[serde(default)]
+    pub condition: Condition,
+    #[serde(default)]
+    pub language: Language,
 }
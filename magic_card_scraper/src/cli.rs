@@ -0,0 +1,685 @@
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+use rust_decimal::Decimal;
+
+use magic_card_scraper::cards::{
+    condition::Condition, currency::Currency, language::Language, rarity::Rarity,
+};
+use magic_card_scraper::decklist::ListingFilter;
+use magic_card_scraper::utilities::config::{Config, ConfigError};
+
+/// Scrapes Swedish MTG stores, compares their prices to the MCM trend and renders the results.
+///
+/// Settings are read from the config file, then the DL, AS, MT, SF, EXTERNAL_PRICE_CHECK,
+/// DELVER_LENSE_PATH, CHECK_DL_WHEN_COMPARING and NICE_PRICE_DIFF env vars and last the flags.
+/// Running without a subcommand runs the full pipeline.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// Config file to read. Defaults to mtg-prz.toml in the working directory, if it exists
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
+    /// Directory the card snapshots are read from and written to
+    #[arg(long, global = true)]
+    pub data_dir: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the full scrape → compare → render pipeline
+    Run(RunArgs),
+    /// Scrape a single source and save a timestamped snapshot
+    Scrape(ScrapeArgs),
+    /// Compare vendor snapshots to the Scryfall snapshot and save the compared cards
+    Compare(CompareArgs),
+    /// Render one of the HTML pages from a saved snapshot
+    #[command(subcommand)]
+    Render(RenderCommand),
+    /// Find the cards in a collection export that Dragonslair wants to trade in
+    TradeIn(TradeInArgs),
+    /// Show the recorded prices of a printing, from the price history
+    History(HistoryArgs),
+    /// Show the cards that came in, sold out or were repriced between two snapshots of a vendor
+    Diff(DiffArgs),
+    /// Find the cheapest way to buy a deck list from the newest vendor snapshots
+    Deck(DeckArgs),
+    /// Split a want list into orders from the vendors, counting shipping or pickup per order
+    Basket(BasketArgs),
+    /// Keep track of the cards you own and what they are worth
+    #[command(subcommand)]
+    Collection(CollectionCommand),
+}
+
+#[derive(Args, Debug, Default)]
+pub struct RunArgs {
+    /// Vendors, or scryfall, to load from their newest snapshot instead of scraping
+    #[arg(long, value_delimiter = ',')]
+    pub skip: Vec<String>,
+
+    /// Max price difference in SEK for the nice price page
+    #[arg(long, allow_hyphen_values = true)]
+    pub nice_price_diff: Option<i32>,
+
+    /// Delver Lens csv export to compare with Dragonslair
+    #[arg(long)]
+    pub delver_lense_path: Option<String>,
+
+    /// Don't ask MTGStocks for prices missing from the Scryfall snapshot
+    #[arg(long)]
+    pub no_external_price_check: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ScrapeArgs {
+    /// Vendor to scrape, e.g. dragonslair or alphaspel, or scryfall
+    pub source: String,
+
+    /// Where to save the snapshot. Defaults to a timestamped file in the source's folder
+    #[arg(long, short)]
+    pub output: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    /// Vendor snapshot to compare, as vendor=path. Vendors not given use their newest snapshot
+    #[arg(long = "snapshot", value_name = "VENDOR=PATH", value_parser = parse_vendor_snapshot)]
+    pub snapshots: Vec<(String, String)>,
+
+    /// Scryfall snapshot to compare against. Defaults to the newest one
+    #[arg(long)]
+    pub scryfall: Option<String>,
+
+    /// Where to save the compared cards. Defaults to a timestamped file in the compared cards folder
+    #[arg(long, short)]
+    pub output: Option<String>,
+
+    /// Don't ask MTGStocks for prices missing from the Scryfall snapshot
+    #[arg(long)]
+    pub no_external_price_check: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RenderCommand {
+    /// Render the nice price page from compared cards
+    NicePrice(RenderNicePriceArgs),
+    /// Render the tradable cards page from a trade-in result
+    Tradable(RenderTradableArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct RenderNicePriceArgs {
+    /// Compared cards snapshot to render. Defaults to the newest one
+    #[arg(long, short)]
+    pub input: Option<String>,
+
+    /// Where to write the page. Defaults to the nice price page in the data dir
+    #[arg(long, short)]
+    pub output: Option<String>,
+
+    /// Max price difference in SEK for the most expensive cards. Defaults to the configured one
+    #[arg(long, allow_hyphen_values = true)]
+    pub nice_price_diff: Option<i32>,
+}
+
+#[derive(Args, Debug)]
+pub struct RenderTradableArgs {
+    /// Tradable cards snapshot to render. Defaults to the newest one
+    #[arg(long, short)]
+    pub input: Option<String>,
+
+    /// Where to write the page. Defaults to the tradable cards page in the data dir
+    #[arg(long, short)]
+    pub output: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct TradeInArgs {
+    /// Delver Lens, Moxfield, Deckbox, ManaBox or Dragon Shield CSV export with the cards to
+    /// trade in
+    pub csv: String,
+
+    /// Dragonslair snapshot to compare with. Defaults to the newest one
+    #[arg(long)]
+    pub dragonslair: Option<String>,
+
+    /// Where to save the tradable cards. Defaults to a timestamped file in the tradable cards folder
+    #[arg(long, short)]
+    pub output: Option<String>,
+
+    /// Where to write the tradable cards page. Defaults to the one in the data dir
+    #[arg(long)]
+    pub html_output: Option<String>,
+
+    /// Where to save the Cardmarket stock upload of the cards Dragonslair doesn't take.
+    /// Defaults to a timestamped file in the sell lists folder
+    #[arg(long)]
+    pub cardmarket_output: Option<String>,
+
+    /// Where to save the sell list of the cards Dragonslair doesn't take. Defaults to a
+    /// timestamped file in the sell lists folder
+    #[arg(long)]
+    pub sell_list_output: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    /// Card name, e.g. "Reaper King"
+    pub name: String,
+
+    /// Set name, e.g. Shadowmoor
+    #[arg(long)]
+    pub set: String,
+
+    /// Vendor whose price to show, e.g. dragonslair. Shows the Scryfall MCM trend when left out
+    #[arg(long)]
+    pub vendor: Option<String>,
+
+    /// Show the foil price
+    #[arg(long)]
+    pub foil: bool,
+
+    /// Condition of the vendor's copies to show, NM, EX, GD, PL or DMG
+    #[arg(long, default_value = "NM")]
+    pub condition: Condition,
+
+    /// Language of the vendor's copies to show, name or code, e.g. German or de
+    #[arg(long, default_value = "English")]
+    pub language: Language,
+
+    /// How many days back to show
+    #[arg(long, default_value_t = 90)]
+    pub days: i64,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// Vendor whose snapshots to diff, e.g. dragonslair
+    pub vendor: String,
+
+    /// Older snapshot, as a path or the day it was taken (YYYY-MM-DD). Defaults to the snapshot before --to
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Newer snapshot, as a path or the day it was taken (YYYY-MM-DD). Defaults to the newest one
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Where to save the diff. Defaults to a timestamped file in the snapshot diffs folder
+    #[arg(long, short)]
+    pub output: Option<String>,
+
+    /// Where to write the diff page. Defaults to the json file with an html extension
+    #[arg(long)]
+    pub html_output: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct DeckArgs {
+    /// Deck list, as text (e.g. `4 Counterspell` or an Arena export) or an MTGO .dek file
+    pub decklist: String,
+
+    #[command(flatten)]
+    pub filter: ListingFilterArgs,
+
+    /// Where to save the report. Defaults to a timestamped file in the deck builds folder
+    #[arg(long, short)]
+    pub output: Option<String>,
+}
+
+/// Which vendor copies a deck or want list is bought as
+#[derive(Args, Debug)]
+pub struct ListingFilterArgs {
+    /// Worst condition to buy, NM, EX, GD, PL or DMG
+    #[arg(long, default_value = "EX")]
+    pub min_condition: Condition,
+
+    /// Language to buy, name or code, e.g. German or de
+    #[arg(long, default_value = "English", conflicts_with = "any_language")]
+    pub language: Language,
+
+    /// Buy copies in any language
+    #[arg(long)]
+    pub any_language: bool,
+}
+
+impl ListingFilterArgs {
+    pub fn filter(&self) -> ListingFilter {
+        ListingFilter {
+            min_condition: self.min_condition,
+            language: (!self.any_language).then_some(self.language),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct BasketArgs {
+    /// Want list, in the same formats as a deck list
+    pub wantlist: String,
+
+    #[command(flatten)]
+    pub filter: ListingFilterArgs,
+
+    /// Fixed cost of an order from a vendor in SEK, overrides the config. Can be repeated
+    #[arg(long = "fixed-cost", value_name = "VENDOR=SEK", value_parser = parse_fixed_cost)]
+    pub fixed_costs: Vec<(String, Decimal)>,
+
+    /// Where to save the orders. Defaults to a timestamped file in the baskets folder
+    #[arg(long, short)]
+    pub output: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CollectionCommand {
+    /// Add the cards of a collection export, with the price in the export as what they cost
+    Import(CollectionImportArgs),
+    /// Add copies of a card
+    Add(CollectionAddArgs),
+    /// Remove copies of a card, the oldest ones first
+    Remove(CollectionRemoveArgs),
+    /// Value the collection against the newest Scryfall snapshot
+    Value(CollectionValueArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct CollectionImportArgs {
+    /// Delver Lens, Moxfield, Deckbox, ManaBox or Dragon Shield csv export
+    pub csv: String,
+
+    /// When the cards were acquired, YYYY-MM-DD. Defaults to today
+    #[arg(long)]
+    pub acquired_on: Option<NaiveDate>,
+}
+
+#[derive(Args, Debug)]
+pub struct CollectionAddArgs {
+    /// Card name, e.g. "Reaper King"
+    pub name: String,
+
+    /// Set name, e.g. Shadowmoor
+    #[arg(long)]
+    pub set: String,
+
+    #[arg(long)]
+    pub foil: bool,
+
+    #[arg(long, default_value_t = 1)]
+    pub count: u32,
+
+    /// What a copy cost
+    #[arg(long)]
+    pub price: Option<Decimal>,
+
+    /// Currency of the price
+    #[arg(long, default_value = "SEK", value_parser = parse_currency)]
+    pub currency: Currency,
+
+    /// When the cards were acquired, YYYY-MM-DD. Defaults to today
+    #[arg(long)]
+    pub acquired_on: Option<NaiveDate>,
+
+    /// Colour as Delver Lens writes it, e.g. Blue or Blue/White
+    #[arg(long)]
+    pub colour: Option<String>,
+
+    /// Common, uncommon, rare or mythic
+    #[arg(long, default_value = "common")]
+    pub rarity: Rarity,
+
+    /// NM, EX, GD, PL or DMG
+    #[arg(long, default_value = "NM")]
+    pub condition: Condition,
+
+    /// Language name or code, e.g. German or de
+    #[arg(long, default_value = "English")]
+    pub language: Language,
+}
+
+#[derive(Args, Debug)]
+pub struct CollectionRemoveArgs {
+    /// Card name, e.g. "Reaper King"
+    pub name: String,
+
+    /// Set name, e.g. Shadowmoor
+    #[arg(long)]
+    pub set: String,
+
+    #[arg(long)]
+    pub foil: bool,
+
+    #[arg(long, default_value_t = 1)]
+    pub count: u32,
+
+    /// NM, EX, GD, PL or DMG
+    #[arg(long, default_value = "NM")]
+    pub condition: Condition,
+
+    /// Language name or code, e.g. German or de
+    #[arg(long, default_value = "English")]
+    pub language: Language,
+}
+
+#[derive(Args, Debug)]
+pub struct CollectionValueArgs {
+    /// How many of the biggest gainers and losers to show
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+
+    /// Where to save the valuation. Defaults to a timestamped file in the collection folder
+    #[arg(long, short)]
+    pub output: Option<String>,
+}
+
+fn parse_currency(value: &str) -> Result<Currency, String> {
+    value
+        .to_uppercase()
+        .parse()
+        .map_err(|_| format!("unknown currency '{}'", value))
+}
+
+fn parse_fixed_cost(value: &str) -> Result<(String, Decimal), String> {
+    match value.split_once('=') {
+        Some((vendor, cost)) if !vendor.is_empty() => match cost.parse() {
+            Ok(cost) => Ok((vendor.to_lowercase(), cost)),
+            Err(_) => Err(format!("'{}' is not an amount", cost)),
+        },
+        _ => Err(format!("expected vendor=SEK, got '{}'", value)),
+    }
+}
+
+fn parse_vendor_snapshot(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((vendor, path)) if !vendor.is_empty() && !path.is_empty() => {
+            Ok((vendor.to_lowercase(), path.to_string()))
+        }
+        _ => Err(format!("expected vendor=path, got '{}'", value)),
+    }
+}
+
+impl Cli {
+    /// Overrides the config with the flags that were given
+    pub fn apply_to(&self, config: &mut Config) -> Result<(), ConfigError> {
+        if let Some(data_dir) = &self.data_dir {
+            config.output.data_dir = data_dir.clone();
+        }
+        match &self.command {
+            Some(Command::Run(args)) => {
+                for name in &args.skip {
+                    config.vendors.set_enabled(&name.to_lowercase(), false)?;
+                }
+                if let Some(diff) = args.nice_price_diff {
+                    config.prices.nice_price_diff = diff;
+                }
+                if let Some(path) = &args.delver_lense_path {
+                    config.trade_in.delver_lense_path = path.clone();
+                }
+                if args.no_external_price_check {
+                    config.prices.external_price_check = false;
+                }
+            }
+            Some(Command::Compare(args)) if args.no_external_price_check => {
+                config.prices.external_price_check = false;
+            }
+            Some(Command::Basket(args)) => {
+                for (name, cost) in &args.fixed_costs {
+                    config.basket.set_fixed_cost(name, *cost)?;
+                }
+            }
+            Some(Command::Render(RenderCommand::NicePrice(args))) => {
+                if let Some(diff) = args.nice_price_diff {
+                    config.prices.nice_price_diff = diff;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_subcommand_runs_pipeline() {
+        let cli = Cli::try_parse_from(["mtg-prz"]).unwrap();
+        assert!(cli.command.is_none());
+        assert!(cli.data_dir.is_none());
+    }
+
+    #[test]
+    fn test_parse_scrape_with_output() {
+        let cli = Cli::try_parse_from(["mtg-prz", "scrape", "alphaspel", "-o", "as.json"]).unwrap();
+        match cli.command {
+            Some(Command::Scrape(args)) => {
+                assert_eq!(args.source, "alphaspel");
+                assert_eq!(args.output, Some("as.json".to_string()));
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_render_nice_price_with_negative_diff() {
+        let cli = Cli::try_parse_from([
+            "mtg-prz",
+            "--data-dir",
+            "/tmp/data",
+            "render",
+            "nice-price",
+            "--nice-price-diff",
+            "-10",
+        ])
+        .unwrap();
+        assert_eq!(cli.data_dir.as_deref(), Some("/tmp/data"));
+        match cli.command {
+            Some(Command::Render(RenderCommand::NicePrice(args))) => {
+                assert_eq!(args.nice_price_diff, Some(-10));
+                assert!(args.input.is_none());
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_flags_override_config() {
+        let cli = Cli::try_parse_from([
+            "mtg-prz",
+            "--data-dir",
+            "/tmp/data",
+            "run",
+            "--skip",
+            "dragonslair,scryfall",
+            "--nice-price-diff",
+            "20",
+        ])
+        .unwrap();
+        let mut config = Config::default();
+        config.prices.nice_price_diff = 5;
+        cli.apply_to(&mut config).unwrap();
+
+        assert_eq!(config.output.data_dir, "/tmp/data");
+        assert!(!config.vendors.dragonslair);
+        assert!(config.vendors.alphaspel);
+        assert!(!config.vendors.scryfall);
+        assert_eq!(config.prices.nice_price_diff, 20);
+        assert!(config.prices.external_price_check);
+    }
+
+    #[test]
+    fn test_skip_unknown_vendor_fails() {
+        let cli = Cli::try_parse_from(["mtg-prz", "run", "--skip", "nowhere"]).unwrap();
+        assert!(cli.apply_to(&mut Config::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_compare_snapshots() {
+        let cli = Cli::try_parse_from([
+            "mtg-prz",
+            "compare",
+            "--snapshot",
+            "Dragonslair=dl.json",
+            "--snapshot",
+            "alphaspel=as.json",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Command::Compare(args)) => assert_eq!(
+                args.snapshots,
+                vec![
+                    ("dragonslair".to_string(), "dl.json".to_string()),
+                    ("alphaspel".to_string(), "as.json".to_string())
+                ]
+            ),
+            other => panic!("Unexpected command: {:?}", other),
+        }
+        assert!(Cli::try_parse_from(["mtg-prz", "compare", "--snapshot", "dl.json"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_history() {
+        let cli = Cli::try_parse_from([
+            "mtg-prz",
+            "history",
+            "Reaper King",
+            "--set",
+            "Shadowmoor",
+            "--vendor",
+            "dragonslair",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Command::History(args)) => {
+                assert_eq!(args.name, "Reaper King");
+                assert_eq!(args.set, "Shadowmoor");
+                assert_eq!(args.vendor.as_deref(), Some("dragonslair"));
+                assert!(!args.foil);
+                assert_eq!(args.days, 90);
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+        assert!(Cli::try_parse_from(["mtg-prz", "history", "Reaper King"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_diff() {
+        let cli = Cli::try_parse_from([
+            "mtg-prz",
+            "diff",
+            "dragonslair",
+            "--from",
+            "2025-03-01",
+            "--to",
+            "dl.json",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Command::Diff(args)) => {
+                assert_eq!(args.vendor, "dragonslair");
+                assert_eq!(args.from.as_deref(), Some("2025-03-01"));
+                assert_eq!(args.to.as_deref(), Some("dl.json"));
+                assert!(args.output.is_none());
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+        assert!(Cli::try_parse_from(["mtg-prz", "diff"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_deck() {
+        let cli = Cli::try_parse_from(["mtg-prz", "deck", "burn.dek", "-o", "burn.json"]).unwrap();
+        match cli.command {
+            Some(Command::Deck(args)) => {
+                assert_eq!(args.decklist, "burn.dek");
+                assert_eq!(args.output.as_deref(), Some("burn.json"));
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_basket_fixed_costs_override_config() {
+        let cli = Cli::try_parse_from([
+            "mtg-prz",
+            "basket",
+            "wants.txt",
+            "--fixed-cost",
+            "Alphaspel=0",
+            "--fixed-cost",
+            "dragonslair=79.5",
+        ])
+        .unwrap();
+        let mut config = Config::default();
+
+        cli.apply_to(&mut config).unwrap();
+
+        assert_eq!(config.basket.alphaspel, Decimal::ZERO);
+        assert_eq!(config.basket.dragonslair, Decimal::new(795, 1));
+        assert!(
+            Cli::try_parse_from(["mtg-prz", "basket", "wants.txt", "--fixed-cost", "dl=free"])
+                .is_err()
+        );
+        let unknown_vendor = Cli::try_parse_from([
+            "mtg-prz",
+            "basket",
+            "wants.txt",
+            "--fixed-cost",
+            "nowhere=10",
+        ])
+        .unwrap();
+        assert!(unknown_vendor.apply_to(&mut config).is_err());
+    }
+
+    #[test]
+    fn test_parse_collection_add() {
+        let cli = Cli::try_parse_from([
+            "mtg-prz",
+            "collection",
+            "add",
+            "Reaper King",
+            "--set",
+            "Shadowmoor",
+            "--count",
+            "2",
+            "--price",
+            "12.5",
+            "--currency",
+            "eur",
+            "--acquired-on",
+            "2025-03-01",
+            "--rarity",
+            "R",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(Command::Collection(CollectionCommand::Add(args))) => {
+                assert_eq!(args.name, "Reaper King");
+                assert_eq!(args.count, 2);
+                assert_eq!(args.price, Some(Decimal::new(125, 1)));
+                assert_eq!(args.currency, Currency::EUR);
+                assert_eq!(args.acquired_on, NaiveDate::from_ymd_opt(2025, 3, 1));
+                assert_eq!(args.rarity, Rarity::Rare);
+            }
+            other => panic!("Unexpected command: {:?}", other),
+        }
+        assert!(Cli::try_parse_from([
+            "mtg-prz",
+            "collection",
+            "add",
+            "Reaper King",
+            "--set",
+            "Shadowmoor",
+            "--currency",
+            "gold"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_trade_in_requires_csv() {
+        assert!(Cli::try_parse_from(["mtg-prz", "trade-in"]).is_err());
+    }
+}
@@ -0,0 +1,325 @@
+//! The cards you own, kept in a json file between runs, and what they are worth.
+//!
+//! Cards come in from collection exports or one at a time and every batch is kept as an
+//! acquisition with its count, price and date, so the copies of a printing can be bought at
+//! different prices. Copies in another condition or language are kept apart, they are worth
+//! different amounts. Removing copies takes them from the oldest acquisitions first.
+
+pub mod valuation;
+
+use std::fs;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::cards::{
+    cardname::CardName,
+    condition::Condition,
+    currency::{Currency, ExchangeRates},
+    language::Language,
+    personalcard::PersonalCard,
+    price::Price,
+    setname::SetName,
+};
+use crate::error::StorageError;
+
+/// Copies of a printing that were bought or imported together
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Acquisition {
+    pub count: u32,
+    /// What a copy cost, if known
+    pub price: Option<Price>,
+    pub acquired_on: NaiveDate,
+}
+
+/// Copies of a printing in one condition and language in the collection
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CollectionEntry {
+    /// The printing, its `count` is the number of copies in the collection
+    pub card: PersonalCard,
+    /// Oldest first
+    pub acquisitions: Vec<Acquisition>,
+}
+
+impl CollectionEntry {
+    fn is_printing(
+        &self,
+        name: &CardName,
+        set: &SetName,
+        foil: bool,
+        condition: Condition,
+        language: Language,
+    ) -> bool {
+        self.card.name == *name
+            && self.card.set.cleaned == set.cleaned
+            && self.card.foil == foil
+            && self.card.condition == condition
+            && self.card.language == language
+    }
+
+    /// Average price of a copy over the acquisitions with a price, in SEK with the given rates
+    pub fn average_cost(&self, rates: &ExchangeRates) -> Option<Price> {
+        let priced: Vec<(u32, Price)> = self
+            .acquisitions
+            .iter()
+            .filter_map(|acquisition| acquisition.price.map(|price| (acquisition.count, price)))
+            .filter(|(count, _)| *count > 0)
+            .collect();
+        let count: u32 = priced.iter().map(|(count, _)| count).sum();
+        if count == 0 {
+            return None;
+        }
+        let total: Decimal = priced
+            .iter()
+            .map(|(count, price)| {
+                price.convert_to(Currency::SEK, rates).amount * Decimal::from(*count)
+            })
+            .sum();
+        Some(Price::new(
+            Currency::SEK.round(total / Decimal::from(count)),
+            Currency::SEK,
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Collection {
+    pub entries: Vec<CollectionEntry>,
+}
+
+impl Collection {
+    /// Loads the collection, an empty one if there is no file yet
+    pub fn load(path: &Path) -> Result<Self, StorageError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).map_err(|e| StorageError::io(path, e))?;
+        serde_json::from_str(&content).map_err(|e| StorageError::json(path, e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), StorageError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| StorageError::io(parent, e))?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| StorageError::json(path, e))?;
+        fs::write(path, json).map_err(|e| StorageError::io(path, e))
+    }
+
+    /// Adds `card.count` copies of the card, to the copies of the same printing in the same
+    /// condition and language if there are any
+    pub fn add(&mut self, card: PersonalCard, price: Option<Price>, acquired_on: NaiveDate) {
+        if card.count == 0 {
+            return;
+        }
+        let acquisition = Acquisition {
+            count: card.count,
+            price,
+            acquired_on,
+        };
+        match self.entries.iter_mut().find(|entry| {
+            entry.is_printing(
+                &card.name,
+                &card.set,
+                card.foil,
+                card.condition,
+                card.language,
+            )
+        }) {
+            Some(entry) => {
+                entry.card.count += card.count;
+                entry.acquisitions.push(acquisition);
+            }
+            None => self.entries.push(CollectionEntry {
+                card,
+                acquisitions: vec![acquisition],
+            }),
+        }
+    }
+
+    /// Adds the cards of a collection export, with the price in the export as what a copy cost.
+    /// Returns the number of copies added
+    pub fn import(&mut self, cards: Vec<PersonalCard>, acquired_on: NaiveDate) -> u32 {
+        let mut added = 0;
+        for card in cards {
+            added += card.count;
+            let price = card.price;
+            self.add(card, Some(price), acquired_on);
+        }
+        added
+    }
+
+    /// Removes up to `count` copies of the printing in the condition and language, oldest
+    /// acquisitions first, and returns how many were removed
+    pub fn remove(
+        &mut self,
+        name: &CardName,
+        set: &SetName,
+        foil: bool,
+        condition: Condition,
+        language: Language,
+        count: u32,
+    ) -> Result<u32, StorageError> {
+        let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| entry.is_printing(name, set, foil, condition, language))
+        else {
+            return Err(StorageError::NotInCollection {
+                card: format!(
+                    "{} from {}{} ({}, {})",
+                    name.almost_raw,
+                    set.raw,
+                    if foil { " (Foil)" } else { "" },
+                    condition,
+                    language
+                ),
+            });
+        };
+        let entry = &mut self.entries[index];
+        let mut to_remove = count.min(entry.card.count);
+        let removed = to_remove;
+        for acquisition in &mut entry.acquisitions {
+            let taken = to_remove.min(acquisition.count);
+            acquisition.count -= taken;
+            to_remove -= taken;
+        }
+        entry
+            .acquisitions
+            .retain(|acquisition| acquisition.count > 0);
+        entry.card.count -= removed;
+        if entry.card.count == 0 {
+            self.entries.remove(index);
+        }
+        Ok(removed)
+    }
+
+    /// Number of copies in the collection
+    pub fn count(&self) -> u32 {
+        self.entries.iter().map(|entry| entry.card.count).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::test::helpers::{
+        counterspell_personal_card, reaper_king_personal_card, reaper_king_set_name,
+    };
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    #[test]
+    fn test_import_merges_copies_of_a_printing() {
+        let mut collection = Collection::default();
+        let mut foil_reaper_king = reaper_king_personal_card();
+        foil_reaper_king.foil = true;
+
+        let added = collection.import(
+            vec![reaper_king_personal_card(), counterspell_personal_card()],
+            date(1),
+        );
+        collection.import(vec![reaper_king_personal_card(), foil_reaper_king], date(2));
+        collection.add(
+            reaper_king_personal_card(),
+            Some(Price::new(dec!(50), Currency::SEK)),
+            date(3),
+        );
+
+        assert_eq!(added, 3);
+        assert_eq!(collection.entries.len(), 3);
+        assert_eq!(collection.count(), 6);
+        let reaper_king = &collection.entries[0];
+        assert_eq!(reaper_king.card.count, 3);
+        assert_eq!(
+            reaper_king
+                .acquisitions
+                .iter()
+                .map(|acquisition| acquisition.acquired_on)
+                .collect::<Vec<_>>(),
+            vec![date(1), date(2), date(3)]
+        );
+        // Two copies at 2.06 EUR, 22.72 SEK, and one at 50 SEK
+        assert_eq!(
+            reaper_king.average_cost(&ExchangeRates::default()),
+            Some(Price::new(dec!(31.81), Currency::SEK))
+        );
+    }
+
+    #[test]
+    fn test_import_keeps_conditions_and_languages_apart() {
+        let mut collection = Collection::default();
+        let mut played = reaper_king_personal_card();
+        played.condition = Condition::Played;
+        let mut japanese = reaper_king_personal_card();
+        japanese.language = Language::Japanese;
+
+        collection.import(vec![reaper_king_personal_card()], date(1));
+        collection.import(vec![played, japanese], date(2));
+
+        assert_eq!(
+            collection
+                .entries
+                .iter()
+                .map(|entry| (entry.card.condition, entry.card.language, entry.card.count))
+                .collect::<Vec<_>>(),
+            vec![
+                (Condition::NearMint, Language::English, 1),
+                (Condition::Played, Language::English, 1),
+                (Condition::NearMint, Language::Japanese, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remove_takes_oldest_copies_first() {
+        let mut collection = Collection::default();
+        let mut two_reaper_kings = reaper_king_personal_card();
+        two_reaper_kings.count = 2;
+        collection.add(two_reaper_kings, None, date(1));
+        collection.add(
+            reaper_king_personal_card(),
+            Some(Price::new(dec!(50), Currency::SEK)),
+            date(2),
+        );
+        let name = reaper_king_personal_card().name;
+        let set = reaper_king_set_name();
+        let remove = |collection: &mut Collection, foil, condition, count| {
+            collection.remove(&name, &set, foil, condition, Language::English, count)
+        };
+
+        assert_eq!(
+            remove(&mut collection, false, Condition::NearMint, 2).unwrap(),
+            2
+        );
+        assert_eq!(collection.entries[0].acquisitions.len(), 1);
+        assert_eq!(collection.entries[0].acquisitions[0].acquired_on, date(2));
+        assert!(remove(&mut collection, true, Condition::NearMint, 1).is_err());
+        assert!(remove(&mut collection, false, Condition::Played, 1).is_err());
+        assert_eq!(
+            remove(&mut collection, false, Condition::NearMint, 5).unwrap(),
+            1
+        );
+        assert!(collection.entries.is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("collection").join("collection.json");
+        let mut collection = Collection::load(&path).unwrap();
+        assert!(collection.entries.is_empty());
+        collection.import(vec![counterspell_personal_card()], date(1));
+
+        collection.save(&path).unwrap();
+        collection.save(&path).unwrap();
+
+        assert_eq!(Collection::load(&path).unwrap(), collection);
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+use super::{Collection, CollectionEntry};
+use crate::cards::{
+    cardname::CardName,
+    currency::{Currency, ExchangeRates},
+    personalcard::PersonalCard,
+    price::{sek, Price},
+    scryfallcard::ScryfallCard,
+};
+
+/// What the copies of a printing are worth
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CardValue {
+    pub card: PersonalCard,
+    /// MCM trend of a copy from Scryfall, in SEK
+    pub unit_value: Price,
+    /// MCM trend of all the copies, in SEK
+    pub value: Price,
+    /// What the copies cost, copies without a price counted at the average of the others, in SEK
+    pub cost: Option<Price>,
+    /// Value minus cost
+    pub change: Option<Decimal>,
+    pub change_percent: Option<Decimal>,
+}
+
+/// The copies and value of a set, colour or rarity
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ValueGroup {
+    pub name: String,
+    pub count: u32,
+    /// In SEK
+    pub value: Price,
+}
+
+/// The collection valued against a Scryfall snapshot
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CollectionValuation {
+    pub valued_at: DateTime<Utc>,
+    /// Number of copies in the collection
+    pub cards: u32,
+    /// In SEK
+    pub total_value: Price,
+    /// What the valued cards with a known cost cost, in SEK
+    pub total_cost: Price,
+    /// Value minus cost of the cards with a known cost
+    pub total_change: Decimal,
+    pub top_gainers: Vec<CardValue>,
+    pub top_losers: Vec<CardValue>,
+    /// Highest value first
+    pub by_set: Vec<ValueGroup>,
+    pub by_colour: Vec<ValueGroup>,
+    pub by_rarity: Vec<ValueGroup>,
+    /// Printings without an MCM trend in the snapshot
+    pub unpriced: Vec<PersonalCard>,
+}
+
+/// MCM trend of the printing, the foil price for foils
+fn mcm_price(
+    scryfall_cards: &HashMap<CardName, Vec<ScryfallCard>>,
+    card: &PersonalCard,
+    rates: &ExchangeRates,
+) -> Option<Price> {
+    scryfall_cards
+        .get(&card.name)
+        .into_iter()
+        .flatten()
+        .filter(|scryfall_card| scryfall_card.set.cleaned == card.set.cleaned)
+        .filter_map(|scryfall_card| match card.foil {
+            true => scryfall_card.prices.eur_foil,
+            false => scryfall_card.prices.eur,
+        })
+        .map(|price| price.convert_to(Currency::SEK, rates))
+        .min_by_key(|price| price.amount)
+}
+
+fn card_value(entry: &CollectionEntry, unit_value: Price, rates: &ExchangeRates) -> CardValue {
+    let count = Decimal::from(entry.card.count);
+    let value = unit_value.amount * count;
+    let cost = entry
+        .average_cost(rates)
+        .map(|average_cost| average_cost.amount * count);
+    let change = cost.map(|cost| value - cost);
+    let change_percent = cost
+        .zip(change)
+        .filter(|(cost, _)| *cost != Decimal::ZERO)
+        .map(|(cost, change)| (change / cost * dec!(100)).round_dp(1));
+    CardValue {
+        card: entry.card.clone(),
+        unit_value,
+        value: sek(value),
+        cost: cost.map(sek),
+        change,
+        change_percent,
+    }
+}
+
+fn group_by(values: &[CardValue], group_name: impl Fn(&PersonalCard) -> String) -> Vec<ValueGroup> {
+    let mut groups: Vec<ValueGroup> = Vec::new();
+    for card_value in values {
+        let name = group_name(&card_value.card);
+        match groups.iter_mut().find(|group| group.name == name) {
+            Some(group) => {
+                group.count += card_value.card.count;
+                group.value.amount += card_value.value.amount;
+            }
+            None => groups.push(ValueGroup {
+                name,
+                count: card_value.card.count,
+                value: card_value.value,
+            }),
+        }
+    }
+    groups.sort_by(|a, b| {
+        b.value
+            .amount
+            .cmp(&a.value.amount)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    groups
+}
+
+impl CollectionValuation {
+    /// Values every printing at its MCM trend in SEK with the given rates, with the `top`
+    /// biggest gainers and losers
+    pub fn new(
+        collection: &Collection,
+        scryfall_cards: &HashMap<CardName, Vec<ScryfallCard>>,
+        top: usize,
+        rates: &ExchangeRates,
+    ) -> Self {
+        let mut values = Vec::new();
+        let mut unpriced = Vec::new();
+        for entry in &collection.entries {
+            match mcm_price(scryfall_cards, &entry.card, rates) {
+                Some(unit_value) => values.push(card_value(entry, unit_value, rates)),
+                None => unpriced.push(entry.card.clone()),
+            }
+        }
+
+        let with_cost = values
+            .iter()
+            .filter_map(|value| value.cost.map(|cost| (value.value.amount, cost.amount)));
+        let (valued, total_cost) = with_cost.fold(
+            (Decimal::ZERO, Decimal::ZERO),
+            |(valued, total_cost), (value, cost)| (valued + value, total_cost + cost),
+        );
+
+        let mut gainers: Vec<CardValue> = values
+            .iter()
+            .filter(|value| value.change.is_some_and(|change| change > Decimal::ZERO))
+            .cloned()
+            .collect();
+        gainers.sort_by_key(|value| std::cmp::Reverse(value.change));
+        gainers.truncate(top);
+        let mut losers: Vec<CardValue> = values
+            .iter()
+            .filter(|value| value.change.is_some_and(|change| change < Decimal::ZERO))
+            .cloned()
+            .collect();
+        losers.sort_by_key(|value| value.change);
+        losers.truncate(top);
+
+        Self {
+            valued_at: Utc::now(),
+            cards: collection.count(),
+            total_value: sek(values.iter().map(|value| value.value.amount).sum()),
+            total_cost: sek(total_cost),
+            total_change: valued - total_cost,
+            top_gainers: gainers,
+            top_losers: losers,
+            by_set: group_by(&values, |card| card.set.raw.clone()),
+            by_colour: group_by(&values, |card| card.color.to_string()),
+            by_rarity: group_by(&values, |card| card.rarity.to_string()),
+            unpriced,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::test::helpers::{
+        counterspell_personal_card, reaper_king_personal_card, reaper_king_scryfall_card_cheap,
+        reaper_king_scryfall_card_expensive,
+    };
+
+    #[test]
+    fn test_collection_value_gainers_and_losers() {
+        let acquired_on = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        let mut collection = Collection::default();
+        let mut foil_reaper_king = reaper_king_personal_card();
+        foil_reaper_king.foil = true;
+        foil_reaper_king.count = 2;
+        collection.add(
+            reaper_king_personal_card(),
+            Some(Price::new(dec!(50), Currency::SEK)),
+            acquired_on,
+        );
+        collection.add(
+            foil_reaper_king,
+            Some(Price::new(dec!(10), Currency::SEK)),
+            acquired_on,
+        );
+        collection.add(counterspell_personal_card(), None, acquired_on);
+        let scryfall_cards = HashMap::from([(
+            reaper_king_personal_card().name,
+            vec![
+                reaper_king_scryfall_card_cheap(),
+                reaper_king_scryfall_card_expensive(),
+            ],
+        )]);
+
+        let valuation =
+            CollectionValuation::new(&collection, &scryfall_cards, 5, &ExchangeRates::default());
+
+        // 1 EUR and 2 EUR foil in Shadowmoor: 11.03 + 2 * 22.06
+        assert_eq!(valuation.cards, 5);
+        assert_eq!(valuation.total_value.amount, dec!(55.15));
+        assert_eq!(valuation.total_cost.amount, dec!(70));
+        assert_eq!(valuation.total_change, dec!(-14.85));
+        assert_eq!(valuation.top_gainers.len(), 1);
+        assert!(valuation.top_gainers[0].card.foil);
+        assert_eq!(valuation.top_gainers[0].change, Some(dec!(24.12)));
+        assert_eq!(valuation.top_gainers[0].change_percent, Some(dec!(120.6)));
+        assert_eq!(valuation.top_losers[0].change, Some(dec!(-38.97)));
+        assert_eq!(
+            valuation.by_set,
+            vec![ValueGroup {
+                name: "Shadowmoor".to_string(),
+                count: 3,
+                value: Price::new(dec!(55.15), Currency::SEK),
+            }]
+        );
+        assert_eq!(valuation.by_colour[0].name, "WUBRG");
+        assert_eq!(valuation.by_rarity[0].name, "Rare");
+        assert_eq!(valuation.unpriced, vec![counterspell_personal_card()]);
+    }
+}
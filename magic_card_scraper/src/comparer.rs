@@ -1,28 +1,133 @@
 use std::collections::HashMap;
 
+use chrono::{DateTime, Utc};
 use futures::{stream, StreamExt};
 use log::{debug, error, info};
-use reqwest::Client;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
 
 use crate::{
     cards::{
-        cardname::CardName, compared_card::ComparedCard, currency::Currency, price::Price,
-        scryfallcard::ScryfallCard, vendorcard::VendorCard,
+        cardname::CardName,
+        compared_card::{ComparedCard, MatchQuality},
+        condition::Condition,
+        currency::{Currency, ExchangeRates},
+        language::Language,
+        price::Price,
+        scryfallcard::ScryfallCard,
+        vendorcard::VendorCard,
     },
-    mtg_stock_price_checker::MtgPriceFetcher,
-    utilities::config::CONFIG,
+    http_client::HttpClient,
+    price_source::PriceChain,
 };
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparerSettings {
+    /// Ask MTGStocks for prices missing from the Scryfall cards
+    pub external_price_check: bool,
+    /// Names of the price sources to ask, in order
+    pub price_sources: Vec<String>,
+    /// Max number of cards compared at the same time
+    pub concurrency: usize,
+    pub exchange_rates: ExchangeRates,
+    pub discounts: Discounts,
+}
+
+impl Default for ComparerSettings {
+    fn default() -> Self {
+        Self {
+            external_price_check: true,
+            price_sources: vec!["scryfall".to_string(), "mtgstocks".to_string()],
+            concurrency: 25,
+            exchange_rates: ExchangeRates::default(),
+            discounts: Discounts::default(),
+        }
+    }
+}
+
+/// How many percent less than the MCM trend, which is for near mint english cards, a card in
+/// worse condition or another language is worth
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discounts {
+    pub excellent: Decimal,
+    pub good: Decimal,
+    pub played: Decimal,
+    pub damaged: Decimal,
+    /// For every language but english
+    pub non_english: Decimal,
+}
+
+impl Default for Discounts {
+    fn default() -> Self {
+        Self {
+            excellent: dec!(10),
+            good: dec!(20),
+            played: dec!(30),
+            damaged: dec!(50),
+            non_english: dec!(20),
+        }
+    }
+}
+
+impl Discounts {
+    /// The share of the MCM trend a card in the condition and language is worth
+    pub fn factor(&self, condition: Condition, language: Language) -> Decimal {
+        let condition_discount = match condition {
+            Condition::NearMint => Decimal::ZERO,
+            Condition::Excellent => self.excellent,
+            Condition::Good => self.good,
+            Condition::Played => self.played,
+            Condition::Damaged => self.damaged,
+        };
+        let language_discount = match language {
+            Language::English => Decimal::ZERO,
+            _ => self.non_english,
+        };
+        (Decimal::ONE - condition_discount / dec!(100))
+            * (Decimal::ONE - language_discount / dec!(100))
+    }
+
+    /// The price with the discount of the card's condition and language taken off
+    pub fn apply(&self, price: Price, card: &VendorCard) -> Price {
+        let factor = self.factor(card.condition, card.language);
+        if factor == Decimal::ONE {
+            return price;
+        }
+        Price::new(price.currency.round(price.amount * factor), price.currency)
+    }
+}
+
 pub struct Comparer {
     mcm_cards: HashMap<CardName, Vec<ScryfallCard>>,
-    mtg_stock_url: String, // This struct is empty
+    price_chain: PriceChain,
+    settings: ComparerSettings,
 }
 
 impl Comparer {
-    pub fn new(mcm_cards: HashMap<CardName, Vec<ScryfallCard>>, mtg_stock_url: String) -> Self {
+    /// `scryfall_as_of` is when the Scryfall snapshot of `mcm_cards` was taken. MTGStocks is
+    /// asked through the given client
+    pub fn new(
+        mcm_cards: HashMap<CardName, Vec<ScryfallCard>>,
+        scryfall_as_of: DateTime<Utc>,
+        mtg_stock_url: String,
+        client: &HttpClient,
+        settings: ComparerSettings,
+    ) -> Self {
+        let price_chain =
+            PriceChain::from_settings(&settings, scryfall_as_of, &mtg_stock_url, client);
+        Self::with_price_chain(mcm_cards, price_chain, settings)
+    }
+
+    /// Compares against the prices of the given chain instead of the one in the settings
+    pub fn with_price_chain(
+        mcm_cards: HashMap<CardName, Vec<ScryfallCard>>,
+        price_chain: PriceChain,
+        settings: ComparerSettings,
+    ) -> Self {
         Comparer {
             mcm_cards,
-            mtg_stock_url,
+            price_chain,
+            settings,
         }
     }
 
@@ -57,13 +162,12 @@ impl Comparer {
     ) -> HashMap<CardName, Vec<ComparedCard>> {
         // Separate foil and non-foil cardss
         let (foil_cards, non_foil_cards) = self.separete_foil_and_non_foil_cards(vendor_cards);
-        let price_fetcher = MtgPriceFetcher::new(Client::new(), self.mtg_stock_url.clone());
 
         // Process non-foil cards
         info!("Comparing non-foil cards");
-        let non_foil_results = self.compare(non_foil_cards, price_fetcher.clone()).await;
+        let non_foil_results = self.compare(non_foil_cards).await;
         info!("Comparing foil cards");
-        let foil_results = self.compare(foil_cards, price_fetcher).await;
+        let foil_results = self.compare(foil_cards).await;
 
         // Return combined results
         let compared_cards = [non_foil_results, foil_results].concat();
@@ -72,18 +176,14 @@ impl Comparer {
         for card in compared_cards {
             grouped_cards
                 .entry(card.vendor_card.name.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(card.clone());
         }
 
         grouped_cards
     }
 
-    async fn compare(
-        &self,
-        vendor_cards: HashMap<CardName, Vec<VendorCard>>,
-        price_fetcher: MtgPriceFetcher,
-    ) -> Vec<ComparedCard> {
+    async fn compare(&self, vendor_cards: HashMap<CardName, Vec<VendorCard>>) -> Vec<ComparedCard> {
         stream::iter(vendor_cards.iter())
             .map(|(card_name, vendor_card_list)| {
                 let scryfall_cards = match self.mcm_cards.get(card_name) {
@@ -94,14 +194,12 @@ impl Comparer {
                     }
                 };
 
-                let fetcher = price_fetcher.clone();
-
                 async move {
-                    self.compare_vendorcards_to_mcm_cards(vendor_card_list, scryfall_cards, fetcher)
+                    self.compare_vendorcards_to_mcm_cards(vendor_card_list, scryfall_cards)
                         .await
                 }
             })
-            .buffered(25)
+            .buffered(self.settings.concurrency)
             .collect::<Vec<Vec<ComparedCard>>>()
             .await
             .into_iter()
@@ -114,7 +212,6 @@ impl Comparer {
         &self,
         vendor_cards: &[VendorCard],
         scryfall_cards: Vec<ScryfallCard>,
-        price_fetcher: MtgPriceFetcher,
     ) -> Vec<ComparedCard> {
         if scryfall_cards.is_empty() {
             debug!(
@@ -127,18 +224,13 @@ impl Comparer {
         stream::iter(vendor_cards.iter())
             .map(|vendor_card| {
                 let scryfall_list = scryfall_cards.clone();
-                let fetcher = price_fetcher.clone();
 
                 async move {
-                    self.compare_price_of_specific_vendor_card(
-                        vendor_card,
-                        &scryfall_list,
-                        &fetcher,
-                    )
-                    .await
+                    self.compare_price_of_specific_vendor_card(vendor_card, &scryfall_list)
+                        .await
                 }
             })
-            .buffered(25)
+            .buffered(self.settings.concurrency)
             .filter_map(|result| async move { result })
             .collect()
             .await
@@ -149,12 +241,13 @@ impl Comparer {
         &self,
         vendor_card: &VendorCard,
         scryfall_cards: &[ScryfallCard],
-        price_fetcher: &MtgPriceFetcher,
     ) -> Option<ComparedCard> {
-        // Find matching Scryfall card by set
-        let matching_scryfall_card = scryfall_cards
-            .iter()
-            .find(|card| card.collector_number == vendor_card.collector_number)
+        // Find matching Scryfall card by collector number, then by set
+        let by_collector_number = scryfall_cards.iter().find(|card| {
+            vendor_card.collector_number.is_some()
+                && card.collector_number == vendor_card.collector_number
+        });
+        let matching_scryfall_card = by_collector_number
             .or_else(|| {
                 scryfall_cards
                     .iter()
@@ -169,62 +262,56 @@ impl Comparer {
                 None
             })?;
 
-        let mcm_price = if vendor_card.foil {
-            // Get price data - either from Scryfall or fetch live price
-            match matching_scryfall_card.prices.eur_foil {
-                Some(price) => price,
-                None => self.fetch_live_price(vendor_card, price_fetcher).await,
-            }
-        } else {
-            // Get price data - either from Scryfall or fetch live price
-            match matching_scryfall_card.prices.eur {
-                Some(price) => price,
-                None => self.fetch_live_price(vendor_card, price_fetcher).await,
+        // The trend is for near mint english cards, so played and foreign ones are compared to
+        // what they are worth in their condition and language
+        let quote = self
+            .price_chain
+            .best_quote(&matching_scryfall_card, vendor_card.foil)
+            .await
+            .map(|mut quote| {
+                quote.price = self.settings.discounts.apply(quote.price, vendor_card);
+                quote
+            });
+        let match_quality = match &quote {
+            None => {
+                error!(
+                    "No price found for {} in {}",
+                    vendor_card.name.almost_raw, vendor_card.set.raw
+                );
+                MatchQuality::NoPrice
             }
+            Some(quote) if quote.foil != vendor_card.foil => MatchQuality::OtherFinish,
+            Some(_) if by_collector_number.is_some() => MatchQuality::Exact,
+            Some(_) => MatchQuality::SameSet,
         };
 
         // Create comparison data
+        let rates = &self.settings.exchange_rates;
+        let price_difference = quote
+            .as_ref()
+            .map(|quote| {
+                vendor_card
+                    .price
+                    .difference_with(&quote.price, Currency::SEK, rates)
+            })
+            .unwrap_or_default();
         Some(ComparedCard {
             vendor_card: vendor_card.clone(),
             scryfall_card: matching_scryfall_card,
-            price_difference_to_cheapest_vendor_card: (vendor_card.price.convert_to(Currency::SEK)
-                - mcm_price.convert_to(Currency::SEK))
-                as i32,
+            price_difference_to_cheapest_vendor_card: price_difference,
+            reference_price: quote,
+            match_quality,
         })
     }
-
-    /// Fetch live price for a card
-    async fn fetch_live_price(
-        &self,
-        vendor_card: &VendorCard,
-        price_fetcher: &MtgPriceFetcher,
-    ) -> Price {
-        if !CONFIG.external_price_check {
-            return Price::new(0.0, Currency::EUR);
-        }
-
-        match price_fetcher
-            .get_live_card_price(vendor_card.name.clone(), vendor_card.set.clone())
-            .await
-        {
-            Ok(price) => price,
-            Err(e) => {
-                error!(
-                    "Price fetch failed for {}: {}",
-                    vendor_card.name.almost_raw,
-                    e
-                );
-                Price::new(0.0, Currency::SEK)
-            }
-        }
-    }
 }
 
 #[cfg(test)]
 mod tests {
-
     use std::collections::HashMap;
 
+    use chrono::TimeZone;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
     use tracing_test::traced_test;
 
     fn init() {
@@ -232,13 +319,14 @@ mod tests {
     }
 
     use crate::{
-        cards::currency::Currency,
+        cards::{currency::Currency, price::Price, scryfallcard::Prices},
+        price_source::{PriceQuote, PriceSource, ScryfallPriceSource},
         test::helpers::{
             cardname_sunken_ruins, lifecraft_c_name, lifecraft_c_scryfall_card,
             lifecraft_c_vendor_card, lifecraft_scryfall_card_no_price, reaper_king_card_name,
             reaper_king_scryfall_card_cheap, reaper_king_scryfall_card_expensive,
             reaper_king_vendor_card_cheap, reaper_king_vendor_card_expensive,
-            reaper_king_vendor_card_foil, scryfall_card_sunken_ruins,
+            reaper_king_vendor_card_foil, scryfall_card_sunken_ruins, test_http_client,
             vendor_card_sunken_ruins_foil,
         },
     };
@@ -288,65 +376,99 @@ mod tests {
             (cardname_sunken_ruins(), vec![scryfall_card_sunken_ruins()]),
         ]);
 
-        let comparer = Comparer::new(scryfall_cards, "url".to_string());
+        let as_of = Utc::now();
+        let rates = ExchangeRates::default();
+        let mut price_chain = PriceChain::new();
+        price_chain.push(Box::new(ScryfallPriceSource::new(as_of)));
+        let comparer =
+            Comparer::with_price_chain(scryfall_cards, price_chain, ComparerSettings::default());
+        let scryfall_quote = |price: Option<Price>, foil: bool| {
+            Some(PriceQuote {
+                price: price.unwrap(),
+                source: "scryfall".to_string(),
+                timestamp: as_of,
+                foil,
+            })
+        };
 
         // Initialize the logger for capturing logs during the test
         // let _ = env_logger::builder().is_test(true).try_init();
 
         let result = comparer.compare_vendor_cards(vendor_card_list).await;
 
-        let price_diff = (reaper_king_vendor_card_expensive()
+        let price_diff = reaper_king_vendor_card_expensive()
             .price
-            .convert_to(Currency::SEK)
+            .convert_to(Currency::SEK, &rates)
+            .amount
             - reaper_king_scryfall_card_expensive()
                 .prices
                 .eur
                 .unwrap()
-                .convert_to(Currency::SEK)) as i32;
+                .convert_to(Currency::SEK, &rates)
+                .amount;
         let non_foil_card = ComparedCard {
             vendor_card: reaper_king_vendor_card_expensive(),
             scryfall_card: reaper_king_scryfall_card_expensive(),
             price_difference_to_cheapest_vendor_card: price_diff,
+            reference_price: scryfall_quote(
+                reaper_king_scryfall_card_expensive().prices.eur,
+                false,
+            ),
+            match_quality: MatchQuality::Exact,
         };
 
-        let price_diff_foil = (reaper_king_vendor_card_cheap()
+        let price_diff_foil = reaper_king_vendor_card_cheap()
             .price
-            .convert_to(Currency::SEK)
+            .convert_to(Currency::SEK, &rates)
+            .amount
             - reaper_king_scryfall_card_cheap()
                 .prices
                 .eur
                 .unwrap()
-                .convert_to(Currency::SEK)) as i32;
+                .convert_to(Currency::SEK, &rates)
+                .amount;
         let foil_card = ComparedCard {
             vendor_card: reaper_king_vendor_card_cheap(),
             scryfall_card: reaper_king_scryfall_card_cheap(),
             price_difference_to_cheapest_vendor_card: price_diff_foil,
+            reference_price: scryfall_quote(reaper_king_scryfall_card_cheap().prices.eur, false),
+            match_quality: MatchQuality::SameSet,
         };
 
-        let price_diff_lifecraft = (lifecraft_c_vendor_card().price.convert_to(Currency::SEK)
+        let price_diff_lifecraft = lifecraft_c_vendor_card()
+            .price
+            .convert_to(Currency::SEK, &rates)
+            .amount
             - lifecraft_c_scryfall_card()
                 .prices
                 .eur_foil
                 .unwrap()
-                .convert_to(Currency::SEK)) as i32;
+                .convert_to(Currency::SEK, &rates)
+                .amount;
         let lifecraft = ComparedCard {
             vendor_card: lifecraft_c_vendor_card(),
             scryfall_card: lifecraft_c_scryfall_card(),
             price_difference_to_cheapest_vendor_card: price_diff_lifecraft,
+            reference_price: scryfall_quote(lifecraft_c_scryfall_card().prices.eur_foil, true),
+            match_quality: MatchQuality::Exact,
         };
 
-        let price_diff_sunken_ruins = (vendor_card_sunken_ruins_foil()
+        let price_diff_sunken_ruins = vendor_card_sunken_ruins_foil()
             .price
-            .convert_to(Currency::SEK)
+            .convert_to(Currency::SEK, &rates)
+            .amount
             - scryfall_card_sunken_ruins()
                 .prices
                 .eur_foil
                 .unwrap()
-                .convert_to(Currency::SEK)) as i32;
+                .convert_to(Currency::SEK, &rates)
+                .amount;
         let sunken_ruins_diff = ComparedCard {
             vendor_card: vendor_card_sunken_ruins_foil(),
             scryfall_card: scryfall_card_sunken_ruins(),
             price_difference_to_cheapest_vendor_card: price_diff_sunken_ruins,
+            reference_price: scryfall_quote(scryfall_card_sunken_ruins().prices.eur_foil, true),
+            match_quality: MatchQuality::Exact,
         };
 
         assert_eq!(result.len(), 3);
@@ -380,4 +502,213 @@ mod tests {
         //     Ok(())
         // });
     }
+
+    #[tokio::test]
+    async fn test_compare_with_different_settings() {
+        init();
+        let vendor_cards = HashMap::from([(
+            reaper_king_card_name(),
+            vec![reaper_king_vendor_card_expensive()],
+        )]);
+        let scryfall_cards = HashMap::from([(
+            reaper_king_card_name(),
+            vec![reaper_king_scryfall_card_expensive()],
+        )]);
+        let settings = ComparerSettings {
+            external_price_check: false,
+            price_sources: vec!["scryfall".to_string()],
+            concurrency: 1,
+            exchange_rates: ExchangeRates::from_eur_to_sek(dec!(10.0)),
+            discounts: Discounts::default(),
+        };
+
+        let scryfall_as_of = Utc.with_ymd_and_hms(2025, 3, 10, 17, 33, 0).unwrap();
+        let default_comparer = Comparer::new(
+            scryfall_cards.clone(),
+            scryfall_as_of,
+            "url".to_string(),
+            &test_http_client(),
+            ComparerSettings::default(),
+        );
+        let custom_comparer = Comparer::new(
+            scryfall_cards,
+            scryfall_as_of,
+            "url".to_string(),
+            &test_http_client(),
+            settings.clone(),
+        );
+        let default_result = default_comparer
+            .compare_vendor_cards(vendor_cards.clone())
+            .await;
+        let custom_result = custom_comparer.compare_vendor_cards(vendor_cards).await;
+
+        let diff_with = |rates: &ExchangeRates| {
+            reaper_king_vendor_card_expensive()
+                .price
+                .convert_to(Currency::SEK, rates)
+                .amount
+                - reaper_king_scryfall_card_expensive()
+                    .prices
+                    .eur
+                    .unwrap()
+                    .convert_to(Currency::SEK, rates)
+                    .amount
+        };
+        assert_eq!(
+            default_result[&reaper_king_card_name()][0].price_difference_to_cheapest_vendor_card,
+            diff_with(&ExchangeRates::default())
+        );
+        assert_eq!(
+            custom_result[&reaper_king_card_name()][0].price_difference_to_cheapest_vendor_card,
+            diff_with(&settings.exchange_rates)
+        );
+        assert_eq!(
+            default_result[&reaper_king_card_name()][0]
+                .reference_price
+                .as_ref()
+                .map(|quote| quote.timestamp),
+            Some(scryfall_as_of)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_live_price_when_external_price_check_is_off() {
+        let printing_without_prices = ScryfallCard {
+            prices: Prices {
+                eur: None,
+                eur_foil: None,
+                ..Default::default()
+            },
+            ..lifecraft_c_scryfall_card()
+        };
+        let comparer = Comparer::new(
+            HashMap::from([(lifecraft_c_name(), vec![printing_without_prices])]),
+            Utc::now(),
+            "url".to_string(),
+            &test_http_client(),
+            ComparerSettings {
+                external_price_check: false,
+                ..ComparerSettings::default()
+            },
+        );
+
+        let result = comparer
+            .compare_vendor_cards(HashMap::from([(
+                lifecraft_c_name(),
+                vec![lifecraft_c_vendor_card()],
+            )]))
+            .await;
+
+        let compared = &result[&lifecraft_c_name()][0];
+        assert_eq!(comparer.price_chain.names(), vec!["scryfall"]);
+        assert_eq!(compared.reference_price, None);
+        assert_eq!(compared.match_quality, MatchQuality::NoPrice);
+        assert_eq!(
+            compared.price_difference_to_cheapest_vendor_card,
+            Decimal::ZERO
+        );
+    }
+
+    /// Only knows non-foil prices, like MTGStocks
+    struct NonFoilPriceSource;
+
+    #[async_trait::async_trait]
+    impl PriceSource for NonFoilPriceSource {
+        fn name(&self) -> &'static str {
+            "non-foil"
+        }
+
+        async fn quote(
+            &self,
+            _printing: &ScryfallCard,
+            _foil: bool,
+        ) -> Result<PriceQuote, crate::error::PriceError> {
+            Ok(PriceQuote {
+                price: Price::new(dec!(1.0), Currency::EUR),
+                source: self.name().to_string(),
+                timestamp: Utc::now(),
+                foil: false,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_non_foil_price_for_foil_card_is_other_finish() {
+        let mut price_chain = PriceChain::new();
+        price_chain.push(Box::new(NonFoilPriceSource));
+        let comparer = Comparer::with_price_chain(
+            HashMap::from([(lifecraft_c_name(), vec![lifecraft_c_scryfall_card()])]),
+            price_chain,
+            ComparerSettings::default(),
+        );
+
+        let result = comparer
+            .compare_vendor_cards(HashMap::from([(
+                lifecraft_c_name(),
+                vec![lifecraft_c_vendor_card()],
+            )]))
+            .await;
+
+        let compared = &result[&lifecraft_c_name()][0];
+        assert_eq!(compared.match_quality, MatchQuality::OtherFinish);
+        assert_eq!(
+            compared.reference_price.as_ref().unwrap().source,
+            "non-foil"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_played_and_foreign_cards_are_compared_to_discounted_trend() {
+        let played_german = VendorCard {
+            condition: Condition::Played,
+            language: Language::German,
+            ..reaper_king_vendor_card_expensive()
+        };
+        let damaged = VendorCard {
+            condition: Condition::Damaged,
+            ..reaper_king_vendor_card_expensive()
+        };
+        let mut price_chain = PriceChain::new();
+        price_chain.push(Box::new(NonFoilPriceSource));
+        let comparer = Comparer::with_price_chain(
+            HashMap::from([(
+                reaper_king_card_name(),
+                vec![reaper_king_scryfall_card_expensive()],
+            )]),
+            price_chain,
+            ComparerSettings {
+                exchange_rates: ExchangeRates::from_eur_to_sek(dec!(10.0)),
+                discounts: Discounts {
+                    damaged: dec!(75),
+                    ..Discounts::default()
+                },
+                ..ComparerSettings::default()
+            },
+        );
+
+        let result = comparer
+            .compare_vendor_cards(HashMap::from([(
+                reaper_king_card_name(),
+                vec![reaper_king_vendor_card_expensive(), played_german, damaged],
+            )]))
+            .await;
+
+        // 1 EUR for near mint english, 30 % off for played and 20 % off for german
+        let reference_prices: Vec<Price> = result[&reaper_king_card_name()]
+            .iter()
+            .map(|card| card.reference_price().unwrap())
+            .collect();
+        assert_eq!(
+            reference_prices,
+            vec![
+                Price::new(dec!(1.0), Currency::EUR),
+                Price::new(dec!(0.56), Currency::EUR),
+                Price::new(dec!(0.25), Currency::EUR),
+            ]
+        );
+        assert_eq!(
+            result[&reaper_king_card_name()][1].price_difference_to_cheapest_vendor_card,
+            dec!(94.40)
+        );
+    }
 }
@@ -0,0 +1,362 @@
+//! Splits a want list into orders from the vendors so that the cards and the fixed cost of
+//! every order, shipping or the trip to pick it up, cost as little as possible together.
+//!
+//! Every combination of vendors is tried. For a given set of vendors the cheapest copies in
+//! stock are always the best buy, so each combination only has to be filled once. There are
+//! only a handful of vendors, which keeps the number of combinations small.
+
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::cheapest_build::{in_stock_listings, price_sek, take_copies, MissingCard};
+use super::{Decklist, ListingFilter, SkippedLine};
+use crate::cards::{
+    cardname::CardName,
+    currency::ExchangeRates,
+    price::{sek, Price},
+    vendor::Vendor,
+    vendorcard::VendorCard,
+};
+
+/// Copies of a vendor card in an order
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct OrderLine {
+    pub card: VendorCard,
+    pub count: u32,
+    /// Price of all the copies, in SEK
+    pub total: Price,
+}
+
+/// What to buy from one vendor
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct VendorOrder {
+    pub vendor: Vendor,
+    pub lines: Vec<OrderLine>,
+    /// Price of the cards, in SEK
+    pub cards_total: Price,
+    /// Shipping or pickup cost of the order, in SEK
+    pub fixed_cost: Price,
+    pub total: Price,
+}
+
+/// The cheapest way to buy a want list once every order has its fixed cost
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Basket {
+    pub orders: Vec<VendorOrder>,
+    /// Cards and fixed costs of all the orders, in SEK
+    pub total: Price,
+    /// Buying every card where it is cheapest, fixed costs included, in SEK
+    pub cheapest_per_card_total: Price,
+    pub missing: Vec<MissingCard>,
+    /// Want list lines that weren't bought, e.g. basic lands
+    pub skipped: Vec<SkippedLine>,
+}
+
+/// The cards taken from a set of vendors
+struct Candidate<'a> {
+    taken: Vec<(&'a VendorCard, u32)>,
+    missing: Vec<MissingCard>,
+    total: Decimal,
+    vendors_used: usize,
+}
+
+impl Candidate<'_> {
+    fn cards_missing(&self) -> u32 {
+        self.missing.iter().map(|card| card.count).sum()
+    }
+}
+
+impl Basket {
+    /// Finds the orders that get as many of the wanted cards as the vendors have in stock for
+    /// the least money, in SEK with the given rates, from the copies the filter lets through.
+    /// Vendors without a fixed cost are free to order from
+    pub fn optimise(
+        wants: &Decklist,
+        vendor_cards: &HashMap<CardName, Vec<VendorCard>>,
+        fixed_costs: &HashMap<Vendor, Decimal>,
+        filter: &ListingFilter,
+        rates: &ExchangeRates,
+    ) -> Self {
+        let card_counts = wants.card_counts();
+        let listings: Vec<(&CardName, u32, Vec<&VendorCard>)> = card_counts
+            .iter()
+            .map(|(name, count)| {
+                (
+                    name,
+                    *count,
+                    in_stock_listings(vendor_cards, name, filter, rates),
+                )
+            })
+            .collect();
+
+        let mut vendors: Vec<Vendor> = Vec::new();
+        for card in listings.iter().flat_map(|(_, _, cards)| cards) {
+            if !vendors.contains(&card.vendor) {
+                vendors.push(card.vendor.clone());
+            }
+        }
+
+        let fixed_cost = |vendor: &Vendor| fixed_costs.get(vendor).copied().unwrap_or_default();
+        let fill = |chosen: &[&Vendor]| {
+            let mut taken = Vec::new();
+            let mut missing = Vec::new();
+            for (name, count, cards) in &listings {
+                let cards: Vec<&VendorCard> = cards
+                    .iter()
+                    .filter(|card| chosen.contains(&&card.vendor))
+                    .copied()
+                    .collect();
+                let (copies, still_needed) = take_copies(&cards, *count);
+                taken.extend(copies);
+                if still_needed > 0 {
+                    missing.push(MissingCard {
+                        name: (*name).clone(),
+                        count: still_needed,
+                    });
+                }
+            }
+            let mut used: Vec<&Vendor> = Vec::new();
+            for (card, _) in &taken {
+                if !used.contains(&&card.vendor) {
+                    used.push(&card.vendor);
+                }
+            }
+            let cards_total: Decimal = taken
+                .iter()
+                .map(|(card, copies)| price_sek(card, rates) * Decimal::from(*copies))
+                .sum();
+            let fixed_total: Decimal = used.iter().map(|vendor| fixed_cost(vendor)).sum();
+            Candidate {
+                taken,
+                missing,
+                total: cards_total + fixed_total,
+                vendors_used: used.len(),
+            }
+        };
+
+        let all_vendors: Vec<&Vendor> = vendors.iter().collect();
+        let cheapest_per_card = fill(&all_vendors);
+        let mut best = fill(&[]);
+        for mask in 1..(1u32 << vendors.len()) {
+            let chosen: Vec<&Vendor> = vendors
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, vendor)| vendor)
+                .collect();
+            let candidate = fill(&chosen);
+            let key = |candidate: &Candidate| {
+                (
+                    candidate.cards_missing(),
+                    candidate.total,
+                    candidate.vendors_used,
+                )
+            };
+            if key(&candidate) < key(&best) {
+                best = candidate;
+            }
+        }
+
+        let mut orders: Vec<VendorOrder> = Vec::new();
+        for (card, copies) in best.taken {
+            let line = OrderLine {
+                card: card.clone(),
+                count: copies,
+                total: sek(price_sek(card, rates) * Decimal::from(copies)),
+            };
+            match orders.iter_mut().find(|order| order.vendor == card.vendor) {
+                Some(order) => order.lines.push(line),
+                None => orders.push(VendorOrder {
+                    vendor: card.vendor.clone(),
+                    lines: vec![line],
+                    cards_total: sek(Decimal::ZERO),
+                    fixed_cost: sek(fixed_cost(&card.vendor)),
+                    total: sek(Decimal::ZERO),
+                }),
+            }
+        }
+        for order in &mut orders {
+            let cards_total: Decimal = order.lines.iter().map(|line| line.total.amount).sum();
+            order.cards_total = sek(cards_total);
+            order.total = sek(cards_total + order.fixed_cost.amount);
+        }
+        orders.sort_by_key(|order| order.vendor.to_string());
+
+        Self {
+            orders,
+            total: sek(best.total),
+            cheapest_per_card_total: sek(cheapest_per_card.total),
+            missing: best.missing,
+            skipped: wants.skipped.clone(),
+        }
+    }
+
+    pub fn cards_missing(&self) -> u32 {
+        self.missing.iter().map(|card| card.count).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::cards::{condition::Condition, currency::Currency};
+    use crate::test::helpers::{
+        counterspell_forth_e, reaper_king_vendor_card_cheap, reaper_king_vendor_card_expensive,
+    };
+
+    fn alphaspel_card(card: VendorCard, price: Price) -> VendorCard {
+        let mut card = card;
+        card.vendor = Vendor::Alphaspel;
+        card.price = price;
+        card.current_stock = 1;
+        card
+    }
+
+    fn vendor_cards(cards: Vec<VendorCard>) -> HashMap<CardName, Vec<VendorCard>> {
+        let mut vendor_cards: HashMap<CardName, Vec<VendorCard>> = HashMap::new();
+        for card in cards {
+            vendor_cards
+                .entry(card.name.clone())
+                .or_default()
+                .push(card);
+        }
+        vendor_cards
+    }
+
+    fn bought(basket: &Basket) -> Vec<(Vendor, String, u32)> {
+        basket
+            .orders
+            .iter()
+            .flat_map(|order| &order.lines)
+            .map(|line| {
+                (
+                    line.card.vendor.clone(),
+                    line.card.name.almost_raw.clone(),
+                    line.count,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fixed_cost_keeps_order_with_one_vendor() {
+        let wants = Decklist::from_text("4 Counterspell");
+        let cards = vendor_cards(vec![
+            counterspell_forth_e(),
+            alphaspel_card(counterspell_forth_e(), Price::new(dec!(8), Currency::EUR)),
+        ]);
+        let free = HashMap::from([
+            (Vendor::Dragonslair, Decimal::ZERO),
+            (Vendor::Alphaspel, Decimal::ZERO),
+        ]);
+        let shipping = HashMap::from([
+            (Vendor::Dragonslair, Decimal::ZERO),
+            (Vendor::Alphaspel, dec!(50)),
+        ]);
+
+        let split = Basket::optimise(
+            &wants,
+            &cards,
+            &free,
+            &ListingFilter::default(),
+            &ExchangeRates::default(),
+        );
+        let one_vendor = Basket::optimise(
+            &wants,
+            &cards,
+            &shipping,
+            &ListingFilter::default(),
+            &ExchangeRates::default(),
+        );
+
+        assert_eq!(split.orders.len(), 2);
+        assert_eq!(split.total.amount, dec!(388.24));
+        assert_eq!(
+            bought(&one_vendor),
+            vec![(Vendor::Dragonslair, "Counterspell".to_string(), 4)]
+        );
+        assert_eq!(one_vendor.total.amount, dec!(400.0));
+        assert_eq!(one_vendor.cheapest_per_card_total.amount, dec!(438.24));
+    }
+
+    #[test]
+    fn test_stock_spreads_orders_over_vendors() {
+        let wants = Decklist::from_text("2 Reaper King\n1 Counterspell\n1 Annul");
+        let cards = vendor_cards(vec![
+            counterspell_forth_e(),
+            alphaspel_card(counterspell_forth_e(), Price::new(dec!(8), Currency::EUR)),
+            reaper_king_vendor_card_cheap(),
+            reaper_king_vendor_card_expensive(),
+            alphaspel_card(
+                reaper_king_vendor_card_cheap(),
+                Price::new(dec!(30), Currency::SEK),
+            ),
+        ]);
+        let fixed_costs = HashMap::from([
+            (Vendor::Dragonslair, dec!(49)),
+            (Vendor::Alphaspel, dec!(49)),
+        ]);
+
+        let basket = Basket::optimise(
+            &wants,
+            &cards,
+            &fixed_costs,
+            &ListingFilter::default(),
+            &ExchangeRates::default(),
+        );
+
+        // Dragonslair alone would be 50 + 100 + 100 + 49
+        assert_eq!(
+            bought(&basket),
+            vec![
+                (Vendor::Alphaspel, "Reaper King".to_string(), 1),
+                (Vendor::Alphaspel, "Counterspell".to_string(), 1),
+                (Vendor::Dragonslair, "Reaper King".to_string(), 1),
+            ]
+        );
+        assert_eq!(basket.total.amount, dec!(266.24));
+        assert_eq!(basket.orders[0].cards_total.amount, dec!(118.24));
+        assert_eq!(basket.orders[0].total.amount, dec!(167.24));
+        assert_eq!(basket.cards_missing(), 1);
+    }
+
+    #[test]
+    fn test_only_orders_copies_the_filter_lets_through() {
+        let wants = Decklist::from_text("1 Counterspell");
+        let mut damaged =
+            alphaspel_card(counterspell_forth_e(), Price::new(dec!(1), Currency::SEK));
+        damaged.condition = Condition::Damaged;
+        let cards = vendor_cards(vec![counterspell_forth_e(), damaged]);
+
+        let basket = Basket::optimise(
+            &wants,
+            &cards,
+            &HashMap::new(),
+            &ListingFilter::default(),
+            &ExchangeRates::default(),
+        );
+        let any_condition = Basket::optimise(
+            &wants,
+            &cards,
+            &HashMap::new(),
+            &ListingFilter {
+                min_condition: Condition::Damaged,
+                language: None,
+            },
+            &ExchangeRates::default(),
+        );
+
+        assert_eq!(
+            bought(&basket),
+            vec![(Vendor::Dragonslair, "Counterspell".to_string(), 1)]
+        );
+        assert_eq!(
+            bought(&any_condition),
+            vec![(Vendor::Alphaspel, "Counterspell".to_string(), 1)]
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::{Decklist, ListingFilter, SkippedLine};
+use crate::cards::{
+    cardname::CardName,
+    currency::{Currency, ExchangeRates},
+    price::{sek, Price},
+    scryfallcard::ScryfallCard,
+    vendor::Vendor,
+    vendorcard::VendorCard,
+};
+
+/// Copies of a vendor card to buy
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct BuildLine {
+    pub card: VendorCard,
+    pub count: u32,
+    /// Price of all the copies, in SEK
+    pub total: Price,
+    /// Cheapest MCM trend of any printing of the card, in SEK
+    pub mcm_price: Option<Price>,
+}
+
+/// Copies of a card no vendor has in stock
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct MissingCard {
+    pub name: CardName,
+    pub count: u32,
+}
+
+/// What the deck costs when bought from a single vendor
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct VendorTotal {
+    pub vendor: Vendor,
+    /// Price of the cards the vendor has, in SEK
+    pub total: Price,
+    pub cards_found: u32,
+    pub cards_missing: u32,
+}
+
+/// The cheapest vendor and printing of every card on a deck list, limited by the stock
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CheapestBuild {
+    pub lines: Vec<BuildLine>,
+    /// Price of every card that was found, bought from any vendor, in SEK
+    pub total: Price,
+    pub missing: Vec<MissingCard>,
+    /// Cheapest vendor first, vendors that have more of the deck before ones that have less
+    pub per_vendor: Vec<VendorTotal>,
+    /// MCM trend of the found cards that have one, in SEK
+    pub mcm_total: Price,
+    /// What the found cards with an MCM trend cost over it, in SEK
+    pub premium_over_mcm: Decimal,
+    /// Deck list lines that weren't priced, e.g. basic lands
+    pub skipped: Vec<SkippedLine>,
+}
+
+/// The vendor cards of the name that are in stock and pass the filter, cheapest first
+pub fn in_stock_listings<'a>(
+    vendor_cards: &'a HashMap<CardName, Vec<VendorCard>>,
+    name: &CardName,
+    filter: &ListingFilter,
+    rates: &ExchangeRates,
+) -> Vec<&'a VendorCard> {
+    let mut listings: Vec<&VendorCard> = vendor_cards
+        .get(name)
+        .into_iter()
+        .flatten()
+        .filter(|card| card.current_stock > 0 && filter.accepts(card))
+        .collect();
+    listings.sort_by_key(|card| price_sek(card, rates));
+    listings
+}
+
+/// Price of one copy in SEK
+pub fn price_sek(card: &VendorCard, rates: &ExchangeRates) -> Decimal {
+    card.price.convert_to(Currency::SEK, rates).amount
+}
+
+/// Takes copies from the listings in order until `count` are taken or the stock runs out
+pub(crate) fn take_copies<'a>(
+    listings: &[&'a VendorCard],
+    count: u32,
+) -> (Vec<(&'a VendorCard, u32)>, u32) {
+    let mut taken = Vec::new();
+    let mut needed = count;
+    for card in listings {
+        if needed == 0 {
+            break;
+        }
+        let copies = needed.min(card.current_stock.max(0) as u32);
+        taken.push((*card, copies));
+        needed -= copies;
+    }
+    (taken, needed)
+}
+
+fn mcm_trend(
+    scryfall_cards: &HashMap<CardName, Vec<ScryfallCard>>,
+    name: &CardName,
+    rates: &ExchangeRates,
+) -> Option<Price> {
+    scryfall_cards
+        .get(name)
+        .into_iter()
+        .flatten()
+        .filter_map(|card| card.prices.eur)
+        .map(|price| price.convert_to(Currency::SEK, rates))
+        .min_by_key(|price| price.amount)
+}
+
+impl CheapestBuild {
+    /// Prices the deck in SEK with the given rates, from the copies the filter lets through
+    pub fn new(
+        decklist: &Decklist,
+        vendor_cards: &HashMap<CardName, Vec<VendorCard>>,
+        scryfall_cards: &HashMap<CardName, Vec<ScryfallCard>>,
+        filter: &ListingFilter,
+        rates: &ExchangeRates,
+    ) -> Self {
+        let card_counts = decklist.card_counts();
+
+        let mut lines = Vec::new();
+        let mut missing = Vec::new();
+        for (name, count) in &card_counts {
+            let mcm_price = mcm_trend(scryfall_cards, name, rates);
+            let (taken, still_needed) = take_copies(
+                &in_stock_listings(vendor_cards, name, filter, rates),
+                *count,
+            );
+            for (card, copies) in taken {
+                lines.push(BuildLine {
+                    card: card.clone(),
+                    count: copies,
+                    total: sek(price_sek(card, rates) * Decimal::from(copies)),
+                    mcm_price,
+                });
+            }
+            if still_needed > 0 {
+                missing.push(MissingCard {
+                    name: name.clone(),
+                    count: still_needed,
+                });
+            }
+        }
+
+        let mut vendors: Vec<Vendor> = Vec::new();
+        for card in vendor_cards.values().flatten() {
+            if !vendors.contains(&card.vendor) {
+                vendors.push(card.vendor.clone());
+            }
+        }
+        let mut per_vendor: Vec<VendorTotal> = vendors
+            .into_iter()
+            .map(|vendor| vendor_total(vendor, &card_counts, vendor_cards, filter, rates))
+            .collect();
+        per_vendor.sort_by_key(|total| (total.cards_missing, total.total.amount));
+
+        let with_mcm_price = lines.iter().filter_map(|line| {
+            line.mcm_price
+                .map(|mcm| (line.total.amount, mcm.amount * Decimal::from(line.count)))
+        });
+        let (priced_total, mcm_total) = with_mcm_price.fold(
+            (Decimal::ZERO, Decimal::ZERO),
+            |(priced_total, mcm_total), (total, mcm)| (priced_total + total, mcm_total + mcm),
+        );
+        Self {
+            total: sek(lines.iter().map(|line| line.total.amount).sum()),
+            lines,
+            missing,
+            per_vendor,
+            mcm_total: sek(mcm_total),
+            premium_over_mcm: priced_total - mcm_total,
+            skipped: decklist.skipped.clone(),
+        }
+    }
+
+    pub fn cards_missing(&self) -> u32 {
+        self.missing.iter().map(|card| card.count).sum()
+    }
+}
+
+fn vendor_total(
+    vendor: Vendor,
+    card_counts: &[(CardName, u32)],
+    vendor_cards: &HashMap<CardName, Vec<VendorCard>>,
+    filter: &ListingFilter,
+    rates: &ExchangeRates,
+) -> VendorTotal {
+    let mut total = Decimal::ZERO;
+    let mut cards_found = 0;
+    let mut cards_missing = 0;
+    for (name, count) in card_counts {
+        let listings: Vec<&VendorCard> = in_stock_listings(vendor_cards, name, filter, rates)
+            .into_iter()
+            .filter(|card| card.vendor == vendor)
+            .collect();
+        let (taken, still_needed) = take_copies(&listings, *count);
+        for (card, copies) in taken {
+            total += price_sek(card, rates) * Decimal::from(copies);
+            cards_found += copies;
+        }
+        cards_missing += still_needed;
+    }
+    VendorTotal {
+        vendor,
+        total: sek(total),
+        cards_found,
+        cards_missing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::cards::{condition::Condition, language::Language};
+    use crate::test::helpers::{
+        counterspell_forth_e, counterspell_ice_age, reaper_king_scryfall_card_expensive,
+        reaper_king_vendor_card_cheap, reaper_king_vendor_card_expensive,
+    };
+
+    fn vendor_cards() -> HashMap<CardName, Vec<VendorCard>> {
+        let mut alphaspel_counterspell = counterspell_ice_age();
+        alphaspel_counterspell.vendor = Vendor::Alphaspel;
+        alphaspel_counterspell.price = Price::new(dec!(8), Currency::EUR);
+        alphaspel_counterspell.current_stock = 1;
+        let mut sold_out = counterspell_forth_e();
+        sold_out.price = Price::new(dec!(1), Currency::SEK);
+        sold_out.current_stock = 0;
+        HashMap::from([
+            (
+                counterspell_forth_e().name,
+                vec![
+                    counterspell_forth_e(),
+                    counterspell_ice_age(),
+                    alphaspel_counterspell,
+                    sold_out,
+                ],
+            ),
+            (
+                reaper_king_vendor_card_cheap().name,
+                vec![
+                    reaper_king_vendor_card_expensive(),
+                    reaper_king_vendor_card_cheap(),
+                ],
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_cheapest_build_respects_stock() {
+        let decklist = Decklist::from_text("4 Counterspell\n2 Reaper King\n1 Annul\n4 Island");
+        let scryfall_cards = HashMap::from([(
+            reaper_king_scryfall_card_expensive().name,
+            vec![reaper_king_scryfall_card_expensive()],
+        )]);
+
+        let build = CheapestBuild::new(
+            &decklist,
+            &vendor_cards(),
+            &scryfall_cards,
+            &ListingFilter::default(),
+            &ExchangeRates::default(),
+        );
+
+        let bought: Vec<(Vendor, &str, u32)> = build
+            .lines
+            .iter()
+            .map(|line| {
+                (
+                    line.card.vendor.clone(),
+                    line.card.set.raw.as_str(),
+                    line.count,
+                )
+            })
+            .collect();
+        // Alphaspel's single copy at 8 EUR is cheaper than Dragonslair's at 100 SEK
+        assert_eq!(
+            bought,
+            vec![
+                (Vendor::Alphaspel, "Ice Age", 1),
+                (Vendor::Dragonslair, "Magic 25", 3),
+                (
+                    Vendor::Dragonslair,
+                    "Mystery booster retail edition foils",
+                    1
+                ),
+                (Vendor::Dragonslair, "Shadowmoor", 1),
+            ]
+        );
+        assert_eq!(build.total.amount, dec!(538.24));
+        assert_eq!(
+            build.missing,
+            vec![MissingCard {
+                name: CardName::new("Annul".to_string()).unwrap(),
+                count: 1
+            }]
+        );
+        assert_eq!(build.cards_missing(), 1);
+        assert_eq!(build.skipped.len(), 1);
+        // Both Reaper Kings against the 1 EUR MCM trend
+        assert_eq!(build.mcm_total.amount, dec!(22.06));
+        assert_eq!(build.premium_over_mcm, dec!(127.94));
+    }
+
+    #[test]
+    fn test_totals_per_vendor() {
+        let decklist = Decklist::from_text("4 Counterspell\n2 Reaper King");
+
+        let build = CheapestBuild::new(
+            &decklist,
+            &vendor_cards(),
+            &HashMap::new(),
+            &ListingFilter::default(),
+            &ExchangeRates::default(),
+        );
+
+        assert_eq!(
+            build.per_vendor,
+            vec![
+                VendorTotal {
+                    vendor: Vendor::Dragonslair,
+                    total: Price::new(dec!(550.0), Currency::SEK),
+                    cards_found: 6,
+                    cards_missing: 0,
+                },
+                VendorTotal {
+                    vendor: Vendor::Alphaspel,
+                    total: Price::new(dec!(88.24), Currency::SEK),
+                    cards_found: 1,
+                    cards_missing: 5,
+                },
+            ]
+        );
+        assert_eq!(build.premium_over_mcm, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_only_buys_copies_the_filter_lets_through() {
+        let decklist = Decklist::from_text("1 Reaper King");
+        let mut vendor_cards = vendor_cards();
+        let mut played = reaper_king_vendor_card_cheap();
+        played.price = Price::new(dec!(1), Currency::SEK);
+        played.condition = Condition::Played;
+        let mut german = played.clone();
+        german.condition = Condition::Excellent;
+        german.language = Language::German;
+        vendor_cards
+            .get_mut(&played.name)
+            .unwrap()
+            .extend([played, german]);
+        let bought = |filter: ListingFilter| {
+            let build = CheapestBuild::new(
+                &decklist,
+                &vendor_cards,
+                &HashMap::new(),
+                &filter,
+                &ExchangeRates::default(),
+            );
+            (build.lines[0].card.condition, build.lines[0].card.language)
+        };
+
+        assert_eq!(
+            bought(ListingFilter::default()),
+            (Condition::NearMint, Language::English)
+        );
+        assert_eq!(
+            bought(ListingFilter {
+                language: None,
+                ..ListingFilter::default()
+            }),
+            (Condition::Excellent, Language::German)
+        );
+        assert_eq!(
+            bought(ListingFilter {
+                min_condition: Condition::Played,
+                language: Some(Language::English),
+            }),
+            (Condition::Played, Language::English)
+        );
+    }
+}
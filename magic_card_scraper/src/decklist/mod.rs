@@ -0,0 +1,303 @@
+//! Deck lists in the common text formats and MTGO `.dek` files, and what it costs to buy them
+//! from the vendors.
+//!
+//! Text lists have a card per line, e.g. `4 Counterspell`, `4x Counterspell` or the Arena
+//! export `4 Counterspell (ICE) 64`. `Deck`, `Sideboard`, `Commander` and `Companion` lines
+//! and `SB:` prefixes are understood, and so is the MTGO convention of a blank line before the
+//! sideboard.
+
+pub mod basket;
+pub mod cheapest_build;
+
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::cards::{
+    cardname::CardName, condition::Condition, language::Language, vendorcard::VendorCard,
+};
+use crate::error::StorageError;
+
+/// A card on the deck list
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct DecklistEntry {
+    pub count: u32,
+    pub name: CardName,
+    /// Set code from an Arena export, e.g. `ICE`
+    pub set_code: Option<String>,
+    /// Collector number from an Arena export
+    pub collector_number: Option<String>,
+    pub sideboard: bool,
+}
+
+/// A line that isn't bought, with the reason
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SkippedLine {
+    pub line: String,
+    pub reason: String,
+}
+
+/// Which vendor copies are bought, near mint or excellent english ones by default
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct ListingFilter {
+    /// The worst condition that is bought
+    pub min_condition: Condition,
+    /// The language that is bought, any when None
+    pub language: Option<Language>,
+}
+
+impl Default for ListingFilter {
+    fn default() -> Self {
+        Self {
+            min_condition: Condition::Excellent,
+            language: Some(Language::English),
+        }
+    }
+}
+
+impl ListingFilter {
+    pub fn accepts(&self, card: &VendorCard) -> bool {
+        card.condition.is_at_least(self.min_condition)
+            && self
+                .language
+                .is_none_or(|language| card.language == language)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Decklist {
+    pub entries: Vec<DecklistEntry>,
+    /// Basic lands and lines that couldn't be read
+    pub skipped: Vec<SkippedLine>,
+}
+
+impl Decklist {
+    /// Loads an MTGO `.dek` file or a text deck list
+    pub fn load(path: &str) -> Result<Self, StorageError> {
+        let content = fs::read_to_string(path).map_err(|e| StorageError::io(path, e))?;
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("dek") => Ok(Self::from_dek(&content)),
+            _ => Ok(Self::from_text(&content)),
+        }
+    }
+
+    pub fn from_text(content: &str) -> Self {
+        let line_regex =
+            Regex::new(r"^(?i:(SB:)\s*)?(\d+)x?\s+(.+?)(?:\s+\(([A-Za-z0-9]+)\)(?:\s+(\S+))?)?$")
+                .unwrap();
+        let mut decklist = Self::default();
+        let mut sideboard = false;
+        let mut seen_cards = false;
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() {
+                // MTGO and many sites put the sideboard after a blank line
+                sideboard |= seen_cards;
+                continue;
+            }
+            if line.starts_with("//") || line.starts_with('#') {
+                continue;
+            }
+            match line.to_lowercase().as_str() {
+                "deck" | "main" | "maindeck" | "commander" | "companion" => {
+                    sideboard = false;
+                    continue;
+                }
+                "sideboard" => {
+                    sideboard = true;
+                    continue;
+                }
+                _ => {}
+            }
+            let Some(captures) = line_regex.captures(line) else {
+                decklist.skip(line, "not a count followed by a card name");
+                continue;
+            };
+            let count = captures[2].parse().unwrap_or(0);
+            let name = captures[3].to_string();
+            seen_cards = true;
+            decklist.add(
+                line,
+                count,
+                name,
+                captures.get(4).map(|set| set.as_str().to_uppercase()),
+                captures.get(5).map(|number| number.as_str().to_string()),
+                sideboard || captures.get(1).is_some(),
+            );
+        }
+        decklist
+    }
+
+    /// Reads the `<Cards>` elements of an MTGO `.dek` file
+    pub fn from_dek(content: &str) -> Self {
+        let cards_regex = Regex::new(r"<Cards\s([^>]*?)/?>").unwrap();
+        let attribute_regex = Regex::new(r#"(\w+)="([^"]*)""#).unwrap();
+        let mut decklist = Self::default();
+        for element in cards_regex.captures_iter(content) {
+            let attribute = |name: &str| {
+                attribute_regex
+                    .captures_iter(&element[1])
+                    .find(|attribute| &attribute[1] == name)
+                    .map(|attribute| unescape_xml(&attribute[2]))
+            };
+            let (Some(count), Some(name)) = (
+                attribute("Quantity").and_then(|count| count.parse().ok()),
+                attribute("Name"),
+            ) else {
+                decklist.skip(&element[0], "no quantity or name");
+                continue;
+            };
+            let sideboard = attribute("Sideboard").is_some_and(|sideboard| sideboard == "true");
+            decklist.add(&element[0], count, name, None, None, sideboard);
+        }
+        decklist
+    }
+
+    fn add(
+        &mut self,
+        line: &str,
+        count: u32,
+        name: String,
+        set_code: Option<String>,
+        collector_number: Option<String>,
+        sideboard: bool,
+    ) {
+        match CardName::new(name) {
+            Ok(name) if count > 0 => self.entries.push(DecklistEntry {
+                count,
+                name,
+                set_code,
+                collector_number,
+                sideboard,
+            }),
+            Ok(_) => self.skip(line, "count is 0"),
+            Err(e) => self.skip(line, &e.to_string()),
+        }
+    }
+
+    fn skip(&mut self, line: &str, reason: &str) {
+        self.skipped.push(SkippedLine {
+            line: line.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// How many of each card the deck needs, main deck and sideboard together, in list order
+    pub fn card_counts(&self) -> Vec<(CardName, u32)> {
+        let mut counts: Vec<(CardName, u32)> = Vec::new();
+        for entry in &self.entries {
+            match counts.iter_mut().find(|(name, _)| *name == entry.name) {
+                Some((_, count)) => *count += entry.count,
+                None => counts.push((entry.name.clone(), entry.count)),
+            }
+        }
+        counts
+    }
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(decklist: &Decklist) -> Vec<(&str, u32, bool)> {
+        decklist
+            .entries
+            .iter()
+            .map(|entry| (entry.name.almost_raw.as_str(), entry.count, entry.sideboard))
+            .collect()
+    }
+
+    #[test]
+    fn test_plain_text_list_with_sideboard() {
+        let decklist = Decklist::from_text(
+            "// Mono blue\n\
+             4 Counterspell\n\
+             2x Brainstorm\n\
+             10 Island\n\
+             \n\
+             3 Annul\n\
+             a Brainstorm\n",
+        );
+
+        assert_eq!(
+            names(&decklist),
+            vec![
+                ("Counterspell", 4, false),
+                ("Brainstorm", 2, false),
+                ("Annul", 3, true),
+            ]
+        );
+        let skipped: Vec<&str> = decklist
+            .skipped
+            .iter()
+            .map(|skipped| skipped.line.as_str())
+            .collect();
+        assert_eq!(skipped, vec!["10 Island", "a Brainstorm"]);
+    }
+
+    #[test]
+    fn test_arena_export() {
+        let decklist = Decklist::from_text(
+            "Deck\n\
+             4 Counterspell (ICE) 64\n\
+             1 Fire // Ice (MH2) 290\n\
+             \n\
+             Sideboard\n\
+             SB: 2 Annul (KLD) 46\n",
+        );
+
+        assert_eq!(
+            names(&decklist),
+            vec![
+                ("Counterspell", 4, false),
+                ("Fire // Ice", 1, false),
+                ("Annul", 2, true),
+            ]
+        );
+        assert_eq!(decklist.entries[0].set_code.as_deref(), Some("ICE"));
+        assert_eq!(decklist.entries[0].collector_number.as_deref(), Some("64"));
+        assert!(decklist.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_mtgo_dek_file() {
+        let decklist = Decklist::from_dek(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+            <Deck xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+              <NetDeckID>0</NetDeckID>
+              <Cards CatID="1234" Quantity="4" Sideboard="false" Name="Counterspell" Annotation="0" />
+              <Cards CatID="5678" Quantity="1" Sideboard="true" Name="Jace, the Mind Sculptor" />
+              <Cards CatID="91" Quantity="2" Sideboard="false" Name="Counterspell" />
+              <Cards CatID="92" Sideboard="false" Name="Brainstorm" />
+            </Deck>"#,
+        );
+
+        assert_eq!(
+            names(&decklist),
+            vec![
+                ("Counterspell", 4, false),
+                ("Jace, the Mind Sculptor", 1, true),
+                ("Counterspell", 2, false),
+            ]
+        );
+        assert_eq!(decklist.skipped.len(), 1);
+        assert_eq!(
+            decklist
+                .card_counts()
+                .iter()
+                .map(|(name, count)| (name.almost_raw.as_str(), *count))
+                .collect::<Vec<_>>(),
+            vec![("Counterspell", 6), ("Jace, the Mind Sculptor", 1)]
+        );
+    }
+}
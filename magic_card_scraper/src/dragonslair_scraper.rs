@@ -1,17 +1,25 @@
 use crate::cards::card_parser::fetch_and_parse;
 use crate::cards::cardname::CardName;
+use crate::cards::vendor::Vendor;
 use crate::cards::vendorcard::VendorCard;
+use crate::error::ScrapeError;
+use crate::http_client::HttpClient;
+use crate::scrape_report::ScrapeReport;
+use crate::utilities::constants::{DRAGONSLAIR_CARDS_FOLDER, DRAGONSLAIR_CARDS_PREFIX};
+use crate::vendor_scraper::VendorScraper;
+use async_trait::async_trait;
 use futures::stream::{self, StreamExt};
 use log::{error, info};
-use reqwest::Client;
 use scraper::{Html, Selector};
 use std::collections::HashMap;
-use std::{error::Error, time::Instant};
+use std::time::Instant;
 
 pub struct DragonslairScraper {
     pub url: String,
-    client: Client,
+    client: HttpClient,
     cmcs_available: Vec<u8>,
+    /// Max number of pages fetched at the same time
+    concurrency: usize,
 }
 
 // fn get_page_count(&self, url: &str) -> Result<usize, Box<dyn Error>> {
@@ -29,12 +37,18 @@ pub struct DragonslairScraper {
 // }
 
 impl DragonslairScraper {
-    pub fn new(url: &str, cmcs_available: Option<Vec<u8>>, client: Client) -> Self {
+    pub fn new(
+        url: &str,
+        cmcs_available: Option<Vec<u8>>,
+        client: HttpClient,
+        concurrency: usize,
+    ) -> Self {
         DragonslairScraper {
             url: url.to_string(),
-            client: client,
+            client,
             cmcs_available: cmcs_available
                 .unwrap_or_else(|| vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 15, 16]),
+            concurrency,
         }
     }
 
@@ -42,9 +56,9 @@ impl DragonslairScraper {
     pub async fn get_page_count(&self, url_path: &str) -> Option<u32> {
         let response = self
             .client
-            .get(format!("{}{}", &self.url, url_path))
-            .send()
+            .get(&format!("{}{}", &self.url, url_path))
             .await
+            .and_then(|response| response.error_for_status())
             .ok()?;
         let html_content = response.text().await.ok()?;
 
@@ -60,6 +74,10 @@ impl DragonslairScraper {
         }
     }
 
+    pub fn client(&self) -> &HttpClient {
+        &self.client
+    }
+
     fn generate_card_urls(&self, page_count: u32, cmc: u8) -> Vec<String> {
         (1..=page_count)
             .map(|count| {
@@ -71,31 +89,40 @@ impl DragonslairScraper {
             .collect::<Vec<String>>()
     }
 
-    async fn get_card_urls(&self) -> Result<Vec<String>, Box<dyn Error>> {
+    async fn get_card_urls(&self, report: &mut ScrapeReport) -> Result<Vec<String>, ScrapeError> {
         let start_time = Instant::now();
 
-        let card_urls = stream::iter(&self.cmcs_available)
+        let page_counts = stream::iter(self.cmcs_available.clone())
             .map(|cmc| {
                 let request_url = format!(
                     "/product/magic/card-singles/store:kungsholmstorg/cmc-{}/{}",
                     cmc, 0
                 );
                 async move {
-                    match self.get_page_count(&request_url).await {
-                        Some(page_count) => self.generate_card_urls(page_count, *cmc),
-                        None => {
-                            error!("Failed to get page count on request {:?}", request_url);
-                            Vec::new()
-                        }
-                    }
+                    let page_count = self.get_page_count(&request_url).await;
+                    (cmc, request_url, page_count)
                 }
             })
-            .buffered(20) // Limit to 20 concurrent requests
+            .buffered(self.concurrency)
             .collect::<Vec<_>>()
-            .await
-            .into_iter()
-            .flatten()
-            .collect::<Vec<String>>();
+            .await;
+
+        let mut card_urls = Vec::new();
+        for (cmc, request_url, page_count) in page_counts {
+            match page_count {
+                Some(page_count) => {
+                    report.page_fetched();
+                    card_urls.extend(self.generate_card_urls(page_count, cmc));
+                }
+                None => {
+                    error!("Failed to get page count on request {:?}", request_url);
+                    report.page_failed(
+                        &format!("{}{}", self.url, request_url),
+                        "failed to get page count",
+                    );
+                }
+            }
+        }
 
         info!(
             "Fetching urls to fetch took {:?} sec",
@@ -106,10 +133,12 @@ impl DragonslairScraper {
 
     pub async fn get_available_cards(
         &self,
-    ) -> Result<HashMap<CardName, Vec<VendorCard>>, Box<dyn Error>> {
-        let card_urls = self.get_card_urls().await?;
-        let cards = self.fetch_cards(card_urls).await?;
-        Ok(self.group_cards(&cards))
+    ) -> Result<(HashMap<CardName, Vec<VendorCard>>, ScrapeReport), ScrapeError> {
+        let mut report = ScrapeReport::new(Vendor::Dragonslair);
+        let card_urls = self.get_card_urls(&mut report).await?;
+        let cards = self.fetch_cards(card_urls, &mut report).await?;
+        report.finish();
+        Ok((self.group_cards(&cards), report))
     }
 
     fn group_cards(&self, cards: &Vec<VendorCard>) -> HashMap<CardName, Vec<VendorCard>> {
@@ -117,30 +146,65 @@ impl DragonslairScraper {
         for card in cards {
             grouped_cards
                 .entry(card.name.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(card.clone());
         }
         grouped_cards
     }
 
-    async fn fetch_cards(&self, urls: Vec<String>) -> Result<Vec<VendorCard>, Box<dyn Error>> {
-        let card_urls = stream::iter(urls)
+    async fn fetch_cards(
+        &self,
+        urls: Vec<String>,
+        report: &mut ScrapeReport,
+    ) -> Result<Vec<VendorCard>, ScrapeError> {
+        let pages = stream::iter(urls)
             .map(|url| async move {
-                match fetch_and_parse(&url).await {
-                    Ok(cards) => cards,
-                    Err(e) => {
-                        error!("Error fetching cards from {}: {}", &url, e);
-                        Vec::new()
-                    }
-                }
+                let page = fetch_and_parse(&self.client, &url).await;
+                (url, page)
             })
-            .buffered(20) // Limit to 20 concurrent requests
+            .buffered(self.concurrency)
             .collect::<Vec<_>>()
-            .await
-            .into_iter()
-            .flatten()
-            .collect::<Vec<VendorCard>>();
-        Ok(card_urls)
+            .await;
+
+        let mut cards = Vec::new();
+        for (url, page) in pages {
+            match page {
+                Ok((mut page_cards, page_report)) => {
+                    cards.append(&mut page_cards);
+                    report.merge(page_report);
+                }
+                Err(e) => {
+                    error!("Error fetching cards from {}: {}", &url, e);
+                    report.page_failed(&url, e);
+                }
+            }
+        }
+        Ok(cards)
+    }
+}
+
+#[async_trait]
+impl VendorScraper for DragonslairScraper {
+    fn name(&self) -> &'static str {
+        "dragonslair"
+    }
+
+    fn vendor(&self) -> Vendor {
+        Vendor::Dragonslair
+    }
+
+    fn snapshot_folder(&self) -> &'static str {
+        DRAGONSLAIR_CARDS_FOLDER
+    }
+
+    fn snapshot_prefix(&self) -> &'static str {
+        DRAGONSLAIR_CARDS_PREFIX
+    }
+
+    async fn scrape(
+        &self,
+    ) -> Result<(HashMap<CardName, Vec<VendorCard>>, ScrapeReport), ScrapeError> {
+        self.get_available_cards().await
     }
 }
 
@@ -148,7 +212,7 @@ impl DragonslairScraper {
 mod tests {
 
     use super::*;
-    use tokio;
+    use crate::test::helpers::test_http_client;
 
     #[tokio::test]
     async fn test_get_page_count() {
@@ -168,9 +232,9 @@ mod tests {
             .with_header("x-api-key", "1234")
             .with_body(html_content)
             .create();
-        let url2 = format!("/product/magic/card-singles/store:kungsholmstorg/cmc-0/1",);
+        let url2 = "/product/magic/card-singles/store:kungsholmstorg/cmc-0/1".to_string();
 
-        let res = DragonslairScraper::new(&url, None, reqwest::Client::new())
+        let res = DragonslairScraper::new(&url, None, test_http_client(), 20)
             .get_page_count(&url2)
             .await
             .unwrap();
@@ -196,8 +260,8 @@ mod tests {
             .with_header("x-api-key", "1234")
             .with_body(html_content)
             .create();
-        let url2 = format!("/product/magic/card-singles/store:kungsholmstorg/cmc-15/1",);
-        let res = DragonslairScraper::new(&url, None, reqwest::Client::new())
+        let url2 = "/product/magic/card-singles/store:kungsholmstorg/cmc-15/1".to_string();
+        let res = DragonslairScraper::new(&url, None, test_http_client(), 20)
             .get_page_count(&url2)
             .await
             .unwrap();
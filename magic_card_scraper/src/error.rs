@@ -0,0 +1,500 @@
+//! The errors of the library, by what went wrong: scraping a vendor, parsing a name, page or
+//! file, looking up a price, or reading and writing the data dir.
+//!
+//! Each carries what it was working on, e.g. the url or the path, so the message can be
+//! reported as is. Sending notifications fails with a [`NotifyError`], and the pipeline steps
+//! with a [`PipelineError`] that holds whichever of them, or the [`ConfigError`], went wrong.
+//! Only the CLI boxes them.
+
+use std::fmt;
+use std::io;
+
+use crate::utilities::config::ConfigError;
+
+/// A name, page, API response or file that doesn't say what it should
+#[derive(Debug)]
+pub enum ParseError {
+    /// An empty card name or a basic land
+    CardName {
+        raw: String,
+        reason: &'static str,
+    },
+    SetName {
+        raw: String,
+        reason: &'static str,
+    },
+    CollectorNumber {
+        raw: String,
+    },
+    /// A value that is missing or can't be read, e.g. a price or a count
+    Field {
+        field: &'static str,
+        value: String,
+        reason: String,
+    },
+    Json {
+        what: String,
+        source: serde_json::Error,
+    },
+    Csv {
+        path: String,
+        source: csv::Error,
+    },
+    Toml {
+        what: String,
+        source: toml::de::Error,
+    },
+    /// A file given to the program, e.g. the watchlist, that can't be read
+    Read {
+        path: String,
+        source: io::Error,
+    },
+}
+
+/// A vendor whose pages can't be fetched or read
+#[derive(Debug)]
+pub enum ScrapeError {
+    Request {
+        url: String,
+        source: reqwest::Error,
+    },
+    /// The page was fetched but doesn't look like it should
+    Page {
+        url: String,
+        reason: String,
+    },
+    Parse(ParseError),
+}
+
+/// A price that can't be looked up
+#[derive(Debug)]
+pub enum PriceError {
+    Request {
+        url: String,
+        source: reqwest::Error,
+    },
+    /// The price API answered with something that isn't a price
+    Response {
+        url: String,
+        reason: String,
+    },
+    /// The source has no price for the printing
+    NotFound {
+        source: &'static str,
+        card: String,
+        set: String,
+    },
+    Parse(ParseError),
+    Storage(StorageError),
+}
+
+/// Files and the database in the data dir that can't be read or written
+#[derive(Debug)]
+pub enum StorageError {
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    Json {
+        path: String,
+        source: serde_json::Error,
+    },
+    Database(rusqlite::Error),
+    /// The price history was migrated by a newer version of this program
+    SchemaTooNew {
+        found: usize,
+        supported: usize,
+    },
+    /// No snapshot in the folder matches
+    NoSnapshot {
+        folder: String,
+        reason: String,
+    },
+    /// A stored value that can't be read back
+    Corrupt {
+        what: &'static str,
+        value: String,
+    },
+    Csv {
+        path: String,
+        source: csv::Error,
+    },
+    /// The printing to take copies from isn't in the collection
+    NotInCollection {
+        card: String,
+    },
+}
+
+/// A notifier that can't be set up or can't send the digest
+#[derive(Debug)]
+pub enum NotifyError {
+    Request {
+        url: String,
+        source: reqwest::Error,
+    },
+    Smtp {
+        host: String,
+        source: lettre::transport::smtp::Error,
+    },
+    Address {
+        address: String,
+        source: lettre::address::AddressError,
+    },
+    Message(lettre::error::Error),
+    Storage(StorageError),
+}
+
+/// A step of the pipeline that failed, by what went wrong in it
+#[derive(Debug)]
+pub enum PipelineError {
+    Config(ConfigError),
+    Scrape(ScrapeError),
+    Price(PriceError),
+    Parse(ParseError),
+    Storage(StorageError),
+}
+
+impl ParseError {
+    pub fn field(field: &'static str, value: &str, reason: impl fmt::Display) -> Self {
+        ParseError::Field {
+            field,
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl ScrapeError {
+    pub fn request(url: &str, source: reqwest::Error) -> Self {
+        ScrapeError::Request {
+            url: url.to_string(),
+            source,
+        }
+    }
+
+    pub fn page(url: &str, reason: impl fmt::Display) -> Self {
+        ScrapeError::Page {
+            url: url.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl PriceError {
+    pub fn request(url: &str, source: reqwest::Error) -> Self {
+        PriceError::Request {
+            url: url.to_string(),
+            source,
+        }
+    }
+
+    pub fn response(url: &str, reason: impl fmt::Display) -> Self {
+        PriceError::Response {
+            url: url.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl StorageError {
+    pub fn io(path: impl AsRef<std::path::Path>, source: io::Error) -> Self {
+        StorageError::Io {
+            path: path.as_ref().display().to_string(),
+            source,
+        }
+    }
+
+    pub fn json(path: impl AsRef<std::path::Path>, source: serde_json::Error) -> Self {
+        StorageError::Json {
+            path: path.as_ref().display().to_string(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::CardName { raw, reason } => {
+                write!(f, "Invalid card name '{}': {}", raw, reason)
+            }
+            ParseError::SetName { raw, reason } => {
+                write!(f, "Invalid set name '{}': {}", raw, reason)
+            }
+            ParseError::CollectorNumber { raw } => write!(
+                f,
+                "Invalid collector number '{}': must be 2 to 8 digits long, or 4 to 12 characters long with a dash",
+                raw
+            ),
+            ParseError::Field {
+                field,
+                value,
+                reason,
+            } => write!(f, "Invalid {} '{}': {}", field, value, reason),
+            ParseError::Json { what, source } => write!(f, "Invalid {}: {}", what, source),
+            ParseError::Csv { path, source } => write!(f, "Unable to read {}: {}", path, source),
+            ParseError::Toml { what, source } => write!(f, "Invalid {}: {}", what, source),
+            ParseError::Read { path, source } => write!(f, "Unable to read {}: {}", path, source),
+        }
+    }
+}
+
+impl fmt::Display for ScrapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScrapeError::Request { url, source } => {
+                write!(f, "Failed to fetch {}: {}", url, source)
+            }
+            ScrapeError::Page { url, reason } => write!(f, "Unexpected page {}: {}", url, reason),
+            ScrapeError::Parse(e) => e.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for PriceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PriceError::Request { url, source } => {
+                write!(f, "Failed to fetch prices from {}: {}", url, source)
+            }
+            PriceError::Response { url, reason } => {
+                write!(f, "Unexpected price response from {}: {}", url, reason)
+            }
+            PriceError::NotFound { source, card, set } => {
+                write!(f, "{} has no price for {} in {}", source, card, set)
+            }
+            PriceError::Parse(e) => e.fmt(f),
+            PriceError::Storage(e) => e.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::Io { path, source } => write!(f, "Unable to access {}: {}", path, source),
+            StorageError::Json { path, source } => {
+                write!(f, "Invalid json in {}: {}", path, source)
+            }
+            StorageError::Database(e) => write!(f, "Price history database error: {}", e),
+            StorageError::SchemaTooNew { found, supported } => write!(
+                f,
+                "Price history schema version {} is newer than this program's {}",
+                found, supported
+            ),
+            StorageError::NoSnapshot { folder, reason } => {
+                write!(f, "No snapshot in {}: {}", folder, reason)
+            }
+            StorageError::Corrupt { what, value } => {
+                write!(f, "Stored {} '{}' is invalid", what, value)
+            }
+            StorageError::Csv { path, source } => {
+                write!(f, "Unable to write {}: {}", path, source)
+            }
+            StorageError::NotInCollection { card } => {
+                write!(f, "{} is not in the collection", card)
+            }
+        }
+    }
+}
+
+impl fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NotifyError::Request { url, source } => {
+                write!(f, "Failed to post to {}: {}", url, source)
+            }
+            NotifyError::Smtp { host, source } => {
+                write!(f, "Failed to send mail through {}: {}", host, source)
+            }
+            NotifyError::Address { address, source } => {
+                write!(f, "Invalid email address '{}': {}", address, source)
+            }
+            NotifyError::Message(e) => write!(f, "Unable to build the email: {}", e),
+            NotifyError::Storage(e) => e.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipelineError::Config(e) => e.fmt(f),
+            PipelineError::Scrape(e) => e.fmt(f),
+            PipelineError::Price(e) => e.fmt(f),
+            PipelineError::Parse(e) => e.fmt(f),
+            PipelineError::Storage(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Json { source, .. } => Some(source),
+            ParseError::Csv { source, .. } => Some(source),
+            ParseError::Toml { source, .. } => Some(source),
+            ParseError::Read { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for ScrapeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScrapeError::Request { source, .. } => Some(source),
+            // Displayed as the inner error, so its source is this one's
+            ScrapeError::Parse(e) => e.source(),
+            ScrapeError::Page { .. } => None,
+        }
+    }
+}
+
+impl std::error::Error for PriceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PriceError::Request { source, .. } => Some(source),
+            PriceError::Parse(e) => e.source(),
+            PriceError::Storage(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Io { source, .. } => Some(source),
+            StorageError::Json { source, .. } => Some(source),
+            StorageError::Database(e) => Some(e),
+            StorageError::Csv { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for NotifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NotifyError::Request { source, .. } => Some(source),
+            NotifyError::Smtp { source, .. } => Some(source),
+            NotifyError::Address { source, .. } => Some(source),
+            NotifyError::Message(e) => Some(e),
+            NotifyError::Storage(e) => e.source(),
+        }
+    }
+}
+
+// Displayed as the error they hold, so their sources are that error's
+impl std::error::Error for PipelineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PipelineError::Config(e) => e.source(),
+            PipelineError::Scrape(e) => e.source(),
+            PipelineError::Price(e) => e.source(),
+            PipelineError::Parse(e) => e.source(),
+            PipelineError::Storage(e) => e.source(),
+        }
+    }
+}
+
+impl From<ParseError> for ScrapeError {
+    fn from(e: ParseError) -> Self {
+        ScrapeError::Parse(e)
+    }
+}
+
+impl From<ParseError> for PriceError {
+    fn from(e: ParseError) -> Self {
+        PriceError::Parse(e)
+    }
+}
+
+impl From<StorageError> for PriceError {
+    fn from(e: StorageError) -> Self {
+        PriceError::Storage(e)
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        StorageError::Database(e)
+    }
+}
+
+impl From<StorageError> for NotifyError {
+    fn from(e: StorageError) -> Self {
+        NotifyError::Storage(e)
+    }
+}
+
+impl From<ConfigError> for PipelineError {
+    fn from(e: ConfigError) -> Self {
+        PipelineError::Config(e)
+    }
+}
+
+impl From<ScrapeError> for PipelineError {
+    fn from(e: ScrapeError) -> Self {
+        PipelineError::Scrape(e)
+    }
+}
+
+impl From<PriceError> for PipelineError {
+    fn from(e: PriceError) -> Self {
+        PipelineError::Price(e)
+    }
+}
+
+impl From<ParseError> for PipelineError {
+    fn from(e: ParseError) -> Self {
+        PipelineError::Parse(e)
+    }
+}
+
+impl From<StorageError> for PipelineError {
+    fn from(e: StorageError) -> Self {
+        PipelineError::Storage(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn test_errors_say_what_failed() {
+        let error = ScrapeError::from(ParseError::CardName {
+            raw: "Island".to_string(),
+            reason: "Card cannot be a basic land",
+        });
+        assert_eq!(
+            error.to_string(),
+            "Invalid card name 'Island': Card cannot be a basic land"
+        );
+        // The message is already the parse error's, it shouldn't show up twice in the chain
+        assert!(error.source().is_none());
+
+        let error = PriceError::from(StorageError::io(
+            "data/scryfall_cards/raw.json",
+            io::Error::new(io::ErrorKind::NotFound, "not found"),
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Unable to access data/scryfall_cards/raw.json: not found"
+        );
+        assert_eq!(error.source().unwrap().to_string(), "not found");
+
+        let error = PipelineError::from(StorageError::NotInCollection {
+            card: "Reaper King from Shadowmoor".to_string(),
+        });
+        assert_eq!(
+            error.to_string(),
+            "Reaper King from Shadowmoor is not in the collection"
+        );
+        assert!(error.source().is_none());
+    }
+}
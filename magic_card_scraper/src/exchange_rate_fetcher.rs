@@ -0,0 +1,287 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{Local, NaiveDate};
+use log::{info, warn};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::cards::currency::{Currency, ExchangeRates};
+use crate::error::{ParseError, PriceError, StorageError};
+use crate::http_client::HttpClient;
+use crate::utilities::file_management::load_from_json_file;
+
+/// Response of the Frankfurter (ECB) latest rate API, also the format of the cache file
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct CurrencyRate {
+    pub amount: f64,
+    pub base: String,
+    /// Day the rate was published, `YYYY-MM-DD`. The last business day, so not always today
+    pub date: String,
+    pub rates: Rates,
+    /// Day the rate was fetched, only in the cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fetched_on: Option<NaiveDate>,
+}
+
+/// Caches from before the other currencies were fetched only have SEK
+#[allow(non_snake_case)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct Rates {
+    pub SEK: f64,
+    #[serde(default)]
+    pub USD: Option<f64>,
+    #[serde(default)]
+    pub NOK: Option<f64>,
+    #[serde(default)]
+    pub DKK: Option<f64>,
+    #[serde(default)]
+    pub GBP: Option<f64>,
+}
+
+impl CurrencyRate {
+    /// The built in rates with the ones in the response on top
+    fn exchange_rates(&self) -> Result<ExchangeRates, ParseError> {
+        if self.base != "EUR" {
+            return Err(ParseError::field(
+                "exchange rate base",
+                &self.base,
+                "the rates must be based on EUR",
+            ));
+        }
+        let rates = [
+            (Currency::SEK, Some(self.rates.SEK)),
+            (Currency::USD, self.rates.USD),
+            (Currency::NOK, self.rates.NOK),
+            (Currency::DKK, self.rates.DKK),
+            (Currency::GBP, self.rates.GBP),
+        ];
+        let mut exchange_rates = ExchangeRates::default();
+        for (currency, rate) in rates {
+            if let Some(rate) = rate {
+                let per_eur = Decimal::from_f64(rate / self.amount)
+                    .filter(|per_eur| *per_eur > Decimal::ZERO)
+                    .ok_or_else(|| {
+                        ParseError::field(
+                            "exchange rate",
+                            &format!("{} {} per {} EUR", rate, currency, self.amount),
+                            "not a positive rate",
+                        )
+                    })?;
+                exchange_rates = exchange_rates.with_rate(currency, per_eur);
+            }
+        }
+        Ok(exchange_rates)
+    }
+}
+
+/// Gets the daily EUR rates from Frankfurter, caching them on disk
+pub struct ExchangeRateFetcher {
+    client: HttpClient,
+    base_url: String,
+    cache_path: PathBuf,
+}
+
+impl ExchangeRateFetcher {
+    pub fn new(client: HttpClient, base_url: &str, cache_path: impl Into<PathBuf>) -> Self {
+        Self {
+            client,
+            base_url: base_url.to_string(),
+            cache_path: cache_path.into(),
+        }
+    }
+
+    /// The cached rate if it was fetched today, otherwise a freshly fetched one. Falls back to
+    /// the last cached rate and then the built in rate when the rate can't be fetched
+    pub async fn get_rates(&self) -> ExchangeRates {
+        let cached = self.read_cache();
+        let today = Local::now().date_naive();
+        if let Some(rates) = cached
+            .as_ref()
+            .filter(|rate| rate.fetched_on == Some(today))
+            .and_then(|rate| rate.exchange_rates().ok())
+        {
+            return rates;
+        }
+
+        match self.fetch_and_cache().await {
+            Ok(rates) => rates,
+            Err(e) => {
+                warn!("Failed to fetch the exchange rate: {}", e);
+                match cached.and_then(|rate| Some((rate.exchange_rates().ok()?, rate.date))) {
+                    Some((rates, date)) => {
+                        warn!("Using the cached exchange rate from {}", date);
+                        rates
+                    }
+                    None => {
+                        warn!("No cached exchange rate, using the built in rate");
+                        ExchangeRates::default()
+                    }
+                }
+            }
+        }
+    }
+
+    async fn fetch_and_cache(&self) -> Result<ExchangeRates, PriceError> {
+        let (url, mut rate) = self.fetch_latest().await?;
+        rate.fetched_on = Some(Local::now().date_naive());
+        let rates = rate
+            .exchange_rates()
+            .map_err(|reason| PriceError::response(&url, reason))?;
+        if let Some(parent) = self.cache_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| StorageError::io(parent, e))?;
+        }
+        let json = serde_json::to_string_pretty(&rate)
+            .map_err(|e| StorageError::json(&self.cache_path, e))?;
+        fs::write(&self.cache_path, json).map_err(|e| StorageError::io(&self.cache_path, e))?;
+        Ok(rates)
+    }
+
+    /// The url the rate was fetched from and the rate
+    async fn fetch_latest(&self) -> Result<(String, CurrencyRate), PriceError> {
+        let url = format!("{}/latest?from=EUR&to=SEK,USD,NOK,DKK,GBP", self.base_url);
+        info!("Fetching exchange rate from {}", url);
+        let response = self
+            .client
+            .get(&url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| PriceError::request(&url, e))?;
+        let text = response
+            .text()
+            .await
+            .map_err(|e| PriceError::request(&url, e))?;
+        let rate = serde_json::from_str(&text).map_err(|e| PriceError::response(&url, e))?;
+        Ok((url, rate))
+    }
+
+    fn read_cache(&self) -> Option<CurrencyRate> {
+        if !self.cache_path.is_file() {
+            return None;
+        }
+        load_from_json_file(self.cache_path.to_str()?).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+    use tempfile::tempdir;
+
+    use crate::test::helpers::test_http_client;
+
+    use super::*;
+
+    fn rate_json(date: &str, sek: f64) -> String {
+        format!(
+            r#"{{"amount": 1.0, "base": "EUR", "date": "{}", "rates": {{"SEK": {}}}}}"#,
+            date, sek
+        )
+    }
+
+    fn today() -> String {
+        Local::now().format("%Y-%m-%d").to_string()
+    }
+
+    #[tokio::test]
+    async fn test_fetches_and_caches_rate() {
+        let mut server = std::thread::spawn(|| mockito::Server::new())
+            .join()
+            .unwrap();
+        let mock = server
+            .mock("GET", "/latest?from=EUR&to=SEK,USD,NOK,DKK,GBP")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(format!(
+                r#"{{"amount": 1.0, "base": "EUR", "date": "{}",
+                    "rates": {{"SEK": 11.5, "USD": 1.2, "NOK": 11.9, "DKK": 7.5, "GBP": 0.8}}}}"#,
+                today()
+            ))
+            .expect(1)
+            .create();
+        let dir = tempdir().unwrap();
+        let cache_path = dir.path().join("rates").join("eur_sek.json");
+        let fetcher = ExchangeRateFetcher::new(test_http_client(), &server.url(), &cache_path);
+
+        let rates = fetcher.get_rates().await;
+        // Rates fetched today are read from the cache instead of fetched again
+        let cached_rates = fetcher.get_rates().await;
+
+        mock.assert();
+        assert_eq!(rates.eur_to_sek(), dec!(11.5));
+        assert_eq!(rates.per_eur(Currency::USD), Some(dec!(1.2)));
+        assert_eq!(rates.per_eur(Currency::GBP), Some(dec!(0.8)));
+        assert_eq!(cached_rates, rates);
+        assert_eq!(fetcher.read_cache().unwrap().date, today());
+    }
+
+    #[tokio::test]
+    async fn test_rate_from_an_earlier_day_is_cached_for_the_day_it_was_fetched() {
+        let mut server = std::thread::spawn(|| mockito::Server::new())
+            .join()
+            .unwrap();
+        // Fetched on a weekend, the newest rate is Friday's
+        let mock = server
+            .mock("GET", "/latest?from=EUR&to=SEK,USD,NOK,DKK,GBP")
+            .with_status(200)
+            .with_body(rate_json("2025-03-07", 10.9))
+            .expect(1)
+            .create();
+        let dir = tempdir().unwrap();
+        let cache_path = dir.path().join("eur_sek.json");
+        let fetcher = ExchangeRateFetcher::new(test_http_client(), &server.url(), &cache_path);
+
+        let rates = fetcher.get_rates().await;
+        let cached_rates = fetcher.get_rates().await;
+
+        mock.assert();
+        assert_eq!(rates, ExchangeRates::from_eur_to_sek(dec!(10.9)));
+        assert_eq!(cached_rates, rates);
+        let cached = fetcher.read_cache().unwrap();
+        assert_eq!(cached.date, "2025-03-07");
+        assert_eq!(cached.fetched_on, Some(Local::now().date_naive()));
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_old_cached_rate() {
+        let mut server = std::thread::spawn(|| mockito::Server::new())
+            .join()
+            .unwrap();
+        let mock = server
+            .mock("GET", "/latest?from=EUR&to=SEK,USD,NOK,DKK,GBP")
+            .with_status(500)
+            .expect(4)
+            .create();
+        let dir = tempdir().unwrap();
+        let cache_path = dir.path().join("eur_sek.json");
+        fs::write(&cache_path, rate_json("2024-01-02", 11.2)).unwrap();
+        let fetcher = ExchangeRateFetcher::new(test_http_client(), &server.url(), &cache_path);
+
+        let rates = fetcher.get_rates().await;
+
+        mock.assert();
+        assert_eq!(rates, ExchangeRates::from_eur_to_sek(dec!(11.2)));
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_built_in_rate() {
+        let mut server = std::thread::spawn(|| mockito::Server::new())
+            .join()
+            .unwrap();
+        let mock = server
+            .mock("GET", "/latest?from=EUR&to=SEK,USD,NOK,DKK,GBP")
+            .with_status(200)
+            .with_body(rate_json(&today(), 0.0))
+            .create();
+        let dir = tempdir().unwrap();
+        let cache_path = dir.path().join("eur_sek.json");
+        let fetcher = ExchangeRateFetcher::new(test_http_client(), &server.url(), &cache_path);
+
+        let rates = fetcher.get_rates().await;
+
+        mock.assert();
+        assert_eq!(rates, ExchangeRates::default());
+        assert!(!cache_path.exists());
+    }
+}
@@ -1,33 +1,71 @@
 use std::collections::HashMap;
-use std::error::Error;
 use std::fs;
 
 use crate::cards::cardname::CardName;
 use crate::cards::compared_card::ComparedCard;
-use crate::cards::currency::Currency;
+use crate::cards::condition::Condition;
+use crate::cards::currency::{Currency, ExchangeRates};
+use crate::cards::language::Language;
 use crate::cards::price::Price;
+use crate::error::StorageError;
+use crate::price_history::{PriceTrends, TrendPoint};
 use crate::utilities::string_manipulators::date_time_as_string;
+use crate::watchlist::WatchlistHit;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+/// Which compared cards count as a nice price, all limits in SEK
+#[derive(Debug, Clone, PartialEq)]
+pub struct NicePriceSettings {
+    /// Max price difference for cards above `mid_price_limit`
+    pub nice_price_diff: i32,
+    /// Cards up to this price must be at or below the MCM trend
+    pub cheap_price_limit: Decimal,
+    /// Cards up to this price may cost `mid_price_diff` over the MCM trend
+    pub mid_price_limit: Decimal,
+    pub mid_price_diff: i32,
+    pub exchange_rates: ExchangeRates,
+}
+
+impl Default for NicePriceSettings {
+    fn default() -> Self {
+        Self {
+            nice_price_diff: 0,
+            cheap_price_limit: dec!(10),
+            mid_price_limit: dec!(30),
+            mid_price_diff: 5,
+            exchange_rates: ExchangeRates::default(),
+        }
+    }
+}
 
 // Filter cards based on nice_price_diff
 // A positive diff means that the card is atleast that much cheaper on MCM trend than the cheapest vendor price
 // A negative diff means that the card is at most that much more expensive on MCM trend than the cheapest vendor price
 // This diff is only applied on cards that have a MCM trend price of atleast 15 SEK, anything below must have a positive diff
-pub fn filter_nice_price_cards(
-    cards: &HashMap<CardName, Vec<ComparedCard>>,
-    nice_price_limit: i32,
-) -> Vec<&ComparedCard> {
+// Cards without a reference price are never a nice price
+pub fn filter_nice_price_cards<'a>(
+    cards: &'a HashMap<CardName, Vec<ComparedCard>>,
+    settings: &NicePriceSettings,
+) -> Vec<&'a ComparedCard> {
     cards
         .values()
         .flatten()
+        .filter(|card| card.has_reference_price())
         .filter(|card| {
-            let price_sek = card.vendor_card.price.convert_to(Currency::SEK); // Compare price in SEK
+            let price_sek = card
+                .vendor_card
+                .price
+                .convert_to(Currency::SEK, &settings.exchange_rates)
+                .amount; // Compare price in SEK
             let price_diff = card.price_difference_to_cheapest_vendor_card;
-            if price_sek <= 10.0 {
-                price_diff <= 0
-            } else if price_sek <= 30.0 {
-                price_diff <= 5
+            if price_sek <= settings.cheap_price_limit {
+                price_diff <= Decimal::ZERO
+            } else if price_sek <= settings.mid_price_limit {
+                price_diff <= Decimal::from(settings.mid_price_diff)
             } else {
-                price_sek > 30.0 && price_diff <= nice_price_limit
+                price_diff <= Decimal::from(settings.nice_price_diff)
             }
         })
         .collect()
@@ -37,23 +75,170 @@ pub fn generate_nice_price_page(
     compared_cards: HashMap<CardName, Vec<ComparedCard>>,
     output_dir: &str,
     html_page_name: &str,
-    nice_price_limit: i32,
-) -> Result<(), Box<dyn Error>> {
+    settings: &NicePriceSettings,
+    trends: &PriceTrends,
+    watchlist_hits: &[WatchlistHit],
+) -> Result<(), StorageError> {
     // Filter cards with positive price difference
     let positive_diff_cards: Vec<&ComparedCard> =
-        filter_nice_price_cards(&compared_cards, nice_price_limit);
-
-    let generate_page_content =
-        generate_page_content(positive_diff_cards, &date_time_as_string(None, None));
+        filter_nice_price_cards(&compared_cards, settings);
+
+    let generate_page_content = generate_page_content(
+        positive_diff_cards,
+        &date_time_as_string(None, None),
+        trends,
+        watchlist_hits,
+        &settings.exchange_rates,
+    );
 
-    fs::write(
-        format!("{}/{}", output_dir, html_page_name),
-        generate_page_content,
-    )?;
+    let path = format!("{}/{}", output_dir, html_page_name);
+    fs::write(&path, generate_page_content).map_err(|e| StorageError::io(&path, e))?;
 
     Ok(())
 }
-fn generate_page_content(cards: Vec<&ComparedCard>, current_date: &str) -> String {
+/// The vendor price of the run before the last one, if the last run's price is lower
+fn price_drop(trend: &[TrendPoint]) -> Option<Price> {
+    match trend {
+        [.., previous, last] if last.vendor_price < previous.vendor_price => {
+            Some(previous.vendor_price)
+        }
+        _ => None,
+    }
+}
+
+const SPARKLINE_WIDTH: f64 = 100.0;
+const SPARKLINE_HEIGHT: f64 = 30.0;
+
+/// Inline SVG with the vendor price and the MCM trend of each run, both in SEK so they share
+/// a scale. Empty when there are fewer than two runs to draw a line between
+fn sparkline(trend: &[TrendPoint], rates: &ExchangeRates) -> String {
+    if trend.len() < 2 {
+        return String::new();
+    }
+    let sek = |price: &Price| price.convert_to(Currency::SEK, rates).amount.to_f64();
+    let values: Vec<(Option<f64>, Option<f64>)> = trend
+        .iter()
+        .map(|point| {
+            (
+                sek(&point.vendor_price),
+                point.reference_price.as_ref().and_then(sek),
+            )
+        })
+        .collect();
+    let all = values
+        .iter()
+        .flat_map(|(vendor, mcm)| [vendor, mcm])
+        .flatten();
+    let (min, max) = all.fold((f64::MAX, f64::MIN), |(min, max), value| {
+        (min.min(*value), max.max(*value))
+    });
+    let x = |index: usize| 1.0 + index as f64 * (SPARKLINE_WIDTH - 2.0) / (trend.len() - 1) as f64;
+    let y = |value: f64| {
+        if max > min {
+            SPARKLINE_HEIGHT - 1.0 - (value - min) / (max - min) * (SPARKLINE_HEIGHT - 2.0)
+        } else {
+            SPARKLINE_HEIGHT / 2.0
+        }
+    };
+    let polyline = |class: &str, line: Vec<(usize, f64)>| {
+        if line.len() < 2 {
+            return String::new();
+        }
+        let points: Vec<String> = line
+            .into_iter()
+            .map(|(index, value)| format!("{:.1},{:.1}", x(index), y(value)))
+            .collect();
+        format!(
+            r#"<polyline class="{}" points="{}"/>"#,
+            class,
+            points.join(" ")
+        )
+    };
+    let vendor_line = values
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (vendor, _))| vendor.map(|value| (index, value)))
+        .collect();
+    let mcm_line = values
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (_, mcm))| mcm.map(|value| (index, value)))
+        .collect();
+    format!(
+        r#"<svg class="sparkline" width="{w}" height="{h}" viewBox="0 0 {w} {h}"><title>{from} to {to}</title>{vendor}{mcm}</svg>"#,
+        w = SPARKLINE_WIDTH,
+        h = SPARKLINE_HEIGHT,
+        from = trend[0].timestamp.format("%Y-%m-%d"),
+        to = trend[trend.len() - 1].timestamp.format("%Y-%m-%d"),
+        vendor = polyline("sparkline-vendor", vendor_line),
+        mcm = polyline("sparkline-mcm", mcm_line),
+    )
+}
+
+/// The watched cards that are cheap enough, above the nice price cards. Empty without hits
+fn watchlist_section(hits: &[WatchlistHit], rates: &ExchangeRates) -> String {
+    if hits.is_empty() {
+        return String::new();
+    }
+    let mut section = format!(
+        r#"
+        <h2 class="watchlist-title">Watchlist hits: {}</h2>
+        <table id="watchlist-table">
+            <thead>
+                <tr>
+                    <th>Image</th>
+                    <th>Name/Set</th>
+                    <th data-sort-method="number">Vendor price</th>
+                    <th data-sort-method="number">Max price</th>
+                    <th>Vendor</th>
+                    <th data-sort-method="number">Stock</th>
+                </tr>
+            </thead>
+            <tbody>
+    "#,
+        hits.len()
+    );
+    for hit in hits {
+        let card = &hit.card;
+        let name = &card.name.raw;
+        let set = &card.set.raw;
+        let foil_text = if card.foil { " (Foil)" } else { "" };
+        let image_url = &card.image_url;
+        let price = &card.price;
+        let max_price = &hit.entry.max_price;
+        let price_sek = price.convert_to(Currency::SEK, rates).amount;
+        let max_price_sek = max_price.convert_to(Currency::SEK, rates).amount;
+        let vendor = &card.vendor;
+        let stock = card.current_stock;
+        section.push_str(&format!(
+            r#"
+                <tr>
+                    <td>
+                        <div class="card-image-container">
+                            <img class="card-image" src="{image_url}" alt="{name}">
+                            <img class="enlarged-image" src="{image_url}" alt="{name}">
+                        </div>
+                    </td>
+                    <td>{name}{foil_text}<br>{set}</td>
+                    <td data-sort={price_sek:.2}>{price}</td>
+                    <td data-sort={max_price_sek:.2}>{max_price}</td>
+                    <td>{vendor}</td>
+                    <td>{stock}</td>
+                </tr>
+            "#
+        ));
+    }
+    section.push_str("</tbody></table>");
+    section
+}
+
+fn generate_page_content(
+    cards: Vec<&ComparedCard>,
+    current_date: &str,
+    trends: &PriceTrends,
+    watchlist_hits: &[WatchlistHit],
+    rates: &ExchangeRates,
+) -> String {
     // let mut sorted_cards = cards.to_vec();
     // sorted_cards.sort_by(|a, b| {
     //     a.price_difference_to_cheapest_vendor_card
@@ -84,9 +269,13 @@ fn generate_page_content(cards: Vec<&ComparedCard>, current_date: &str) -> Strin
             </select>
             
             
+            <label for="priceDroppedFilter">Only price drops:</label>
+            <input type="checkbox" id="priceDroppedFilter">
+
             <button onclick="resetFilters()">Reset Filters</button>
         </div>
     </div>
+        {}
         <table id="card-table">
             <thead>
                 <tr>
@@ -96,6 +285,8 @@ fn generate_page_content(cards: Vec<&ComparedCard>, current_date: &str) -> Strin
                     <th data-sort-method="number">MCM price</th>
                     <th data-sort-method="number">Price Difference</th>
                     <th>Vendor</th>
+                    <th>Price source</th>
+                    <th class="no-sort">Trend<br><span class="sparkline-vendor-legend">vendor</span> <span class="sparkline-mcm-legend">MCM</span></th>
                 </tr>
             </thead>
             <tbody>
@@ -104,25 +295,30 @@ fn generate_page_content(cards: Vec<&ComparedCard>, current_date: &str) -> Strin
         include_str!("../../magic_card_scraper/static/nice_price_cards_page/style.css"),
         current_date,
         cards.len(),
+        watchlist_section(watchlist_hits, rates),
     );
 
     for card in cards {
         let vendor = &card.vendor_card.vendor;
         let name = &card.vendor_card.name.raw;
         let set = &card.vendor_card.set.raw;
-        let foil = card.vendor_card.foil;
-        let cheapest_mcm_price = if foil {
-            card.scryfall_card
-                .prices
-                .eur_foil
-                .unwrap_or(Price::new(0.0, Currency::EUR))
-        } else {
-            card.scryfall_card
-                .prices
-                .eur
-                .unwrap_or(Price::new(0.0, Currency::EUR))
+        let cheapest_mcm_price = card
+            .reference_price()
+            .unwrap_or(Price::new(Decimal::ZERO, Currency::EUR));
+        let match_quality = card.match_quality;
+        let (price_source, price_timestamp) = match &card.reference_price {
+            Some(quote) => (
+                quote.source.as_str(),
+                quote.timestamp.format("%Y-%m-%d %H:%M").to_string(),
+            ),
+            None => ("unknown", "".to_string()),
         };
         let cheapest_vendor_price = &card.vendor_card.price;
+        // Sorted in SEK so prices in different currencies sort together
+        let vendor_price_sek = cheapest_vendor_price
+            .convert_to(Currency::SEK, rates)
+            .amount;
+        let mcm_price_sek = cheapest_mcm_price.convert_to(Currency::SEK, rates).amount;
         let price_diff = card.price_difference_to_cheapest_vendor_card;
         let image_url = &card.vendor_card.image_url;
 
@@ -130,20 +326,37 @@ fn generate_page_content(cards: Vec<&ComparedCard>, current_date: &str) -> Strin
         let s_set = &card.scryfall_card.set.raw;
         let s_img = &card.scryfall_card.image_url;
 
+        let trend = trends.get(&card.vendor_card);
+        let price_dropped = price_drop(trend);
+        let price_dropped_badge = match price_dropped {
+            Some(previous) => format!(
+                r#"<br><span class="price-dropped" title="Was {previous} the run before">Price dropped</span>"#
+            ),
+            None => String::new(),
+        };
+        let price_dropped = price_dropped.is_some();
+        let sparkline = sparkline(trend, rates);
+        let condition = match (card.vendor_card.condition, card.vendor_card.language) {
+            (Condition::NearMint, Language::English) => String::new(),
+            (condition, Language::English) => format!("<br>{condition}"),
+            (Condition::NearMint, language) => format!("<br>{language}"),
+            (condition, language) => format!("<br>{condition}, {language}"),
+        };
+
         content.push_str(&format!(
             r#"
-                <tr>
+                <tr data-price-dropped="{price_dropped}">
                     <td>
                         <div class="card-image-container">
                             <img class="card-image" src="{image_url}" alt="{name}">
                             <img class="enlarged-image" src="{image_url}" alt="{name}">
                         </div>
                     </td>
-                    <td>{name}<br>{set}</td>
-                    <td data-sort={cheapest_vendor_price}>{cheapest_vendor_price}</td>
-                    <td data-sort={cheapest_mcm_price:.2}>
+                    <td>{name}<br>{set}{condition}{price_dropped_badge}</td>
+                    <td data-sort={vendor_price_sek:.2}>{cheapest_vendor_price}</td>
+                    <td data-sort={mcm_price_sek:.2}>
                         <div class="hover-container">
-                            <span class="value-text">{cheapest_mcm_price:.2}</span>
+                            <span class="value-text">{cheapest_mcm_price}</span>
                             <div class="hover-content">
                                 <img class="hover-enlarged-image" src="{s_img}" alt="{s_name} / {s_set}">
                                 <span class="hover-text">{s_name} / {s_set}</span>
@@ -152,6 +365,8 @@ fn generate_page_content(cards: Vec<&ComparedCard>, current_date: &str) -> Strin
                     </td>
                     <td data-sort={price_diff:.2}>{price_diff:.2} SEK</td>
                     <td>{vendor}</td>
+                    <td title="{price_timestamp}">{price_source}<br>{match_quality}</td>
+                    <td>{sparkline}</td>
                 </tr>
             "#
         ));
@@ -180,6 +395,12 @@ pub fn generate_html_footer() -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cards::compared_card::MatchQuality;
+    use crate::price_source::PriceQuote;
+    use crate::test::helpers::{
+        reaper_king_scryfall_card_expensive, reaper_king_vendor_card_expensive,
+    };
+    use chrono::Utc;
     use std::path::Path;
     use tempfile::tempdir;
 
@@ -252,7 +473,15 @@ mod tests {
         let temp_dir = ctx.temp_dir.path().to_str().unwrap();
 
         // Call the function
-        generate_nice_price_page(cards, temp_dir, html_page_name, 0).unwrap();
+        generate_nice_price_page(
+            cards,
+            temp_dir,
+            html_page_name,
+            &NicePriceSettings::default(),
+            &PriceTrends::default(),
+            &[],
+        )
+        .unwrap();
 
         // Check that the output directory and index file were created
         // assert!(Path::new(output_dir).exists());
@@ -277,12 +506,201 @@ mod tests {
         // Parse the JSON content directly from the embedded string
         let cards: HashMap<CardName, Vec<ComparedCard>> =
             serde_json::from_str(file_content).unwrap();
-        let nice_price_cards = filter_nice_price_cards(&cards, 0);
+        let nice_price_cards = filter_nice_price_cards(&cards, &NicePriceSettings::default());
         // assert_eq!(cards["Mist-Syndicate Naga"][0].vendor_card.price, 30.0);
         // assert_eq!(cards["Mist-Syndicate Naga"][0].price_difference_to_cheapest_vendor_card, 2);
         assert_eq!(nice_price_cards.len(), 4);
     }
 
+    #[test]
+    fn test_filter_nice_price_cards_with_loose_limits() {
+        let file_content = include_str!("test/test_grouped_compared_cards.json");
+        let cards: HashMap<CardName, Vec<ComparedCard>> =
+            serde_json::from_str(file_content).unwrap();
+        let settings = NicePriceSettings {
+            nice_price_diff: i32::MAX,
+            mid_price_diff: i32::MAX,
+            cheap_price_limit: Decimal::ZERO,
+            ..NicePriceSettings::default()
+        };
+
+        let nice_price_cards = filter_nice_price_cards(&cards, &settings);
+        assert_eq!(nice_price_cards.len(), cards.values().flatten().count());
+    }
+
+    #[test]
+    fn test_cards_without_reference_price_are_not_nice() {
+        let file_content = include_str!("test/test_grouped_compared_cards.json");
+        let mut cards: HashMap<CardName, Vec<ComparedCard>> =
+            serde_json::from_str(file_content).unwrap();
+        let settings = NicePriceSettings {
+            nice_price_diff: i32::MAX,
+            mid_price_diff: i32::MAX,
+            cheap_price_limit: Decimal::ZERO,
+            ..NicePriceSettings::default()
+        };
+        let card_count = cards.values().flatten().count();
+        let unpriced = cards.values_mut().flatten().next().unwrap();
+        unpriced.match_quality = MatchQuality::NoPrice;
+
+        let nice_price_cards = filter_nice_price_cards(&cards, &settings);
+        assert_eq!(nice_price_cards.len(), card_count - 1);
+    }
+
+    #[test]
+    fn test_page_shows_price_source() {
+        let mut card = ComparedCard {
+            vendor_card: reaper_king_vendor_card_expensive(),
+            scryfall_card: reaper_king_scryfall_card_expensive(),
+            price_difference_to_cheapest_vendor_card: dec!(-1),
+            reference_price: Some(PriceQuote {
+                price: Price::new(dec!(12.5), Currency::EUR),
+                source: "mtgstocks".to_string(),
+                timestamp: Utc::now(),
+                foil: false,
+            }),
+            match_quality: MatchQuality::SameSet,
+        };
+
+        let content = generate_page_content(
+            vec![&card],
+            "now",
+            &PriceTrends::default(),
+            &[],
+            &ExchangeRates::default(),
+        );
+        assert!(content.contains(&Price::new(dec!(12.5), Currency::EUR).to_string()));
+        assert!(content.contains("mtgstocks<br>same set"));
+
+        // Snapshots from before the price source was recorded use the Scryfall price
+        card.reference_price = None;
+        card.match_quality = MatchQuality::Unknown;
+        let content = generate_page_content(
+            vec![&card],
+            "now",
+            &PriceTrends::default(),
+            &[],
+            &ExchangeRates::default(),
+        );
+        assert!(content.contains("unknown<br>unknown"));
+        assert!(content.contains(&Price::new(dec!(1.0), Currency::EUR).to_string()));
+    }
+
+    fn trend_point(days_ago: i64, vendor_price: Decimal, mcm_price: Option<Decimal>) -> TrendPoint {
+        TrendPoint {
+            timestamp: Utc::now() - chrono::Duration::days(days_ago),
+            vendor_price: Price::new(vendor_price, Currency::SEK),
+            reference_price: mcm_price.map(|amount| Price::new(amount, Currency::EUR)),
+        }
+    }
+
+    #[test]
+    fn test_price_drop() {
+        let dropped = [
+            trend_point(2, dec!(30), None),
+            trend_point(1, dec!(50), Some(dec!(4))),
+            trend_point(0, dec!(45), Some(dec!(4))),
+        ];
+        let raised = [
+            trend_point(1, dec!(45), None),
+            trend_point(0, dec!(50), None),
+        ];
+
+        assert_eq!(
+            price_drop(&dropped),
+            Some(Price::new(dec!(50), Currency::SEK))
+        );
+        assert_eq!(price_drop(&raised), None);
+        assert_eq!(price_drop(&dropped[2..]), None);
+        assert_eq!(price_drop(&[]), None);
+    }
+
+    #[test]
+    fn test_sparkline() {
+        let trend = [
+            trend_point(2, dec!(50), Some(dec!(4))),
+            trend_point(1, dec!(45), None),
+            trend_point(0, dec!(40), Some(dec!(3))),
+        ];
+
+        let svg = sparkline(&trend, &ExchangeRates::default());
+        assert!(svg.starts_with(r#"<svg class="sparkline""#));
+        assert_eq!(svg.matches(r#"class="sparkline-vendor""#).count(), 1);
+        assert_eq!(svg.matches(r#"class="sparkline-mcm""#).count(), 1);
+        // The highest price is drawn at the top, both lines span the whole width
+        assert!(svg.contains(r#"points="1.0,1.0 50.0,"#));
+        assert!(svg.contains(",29.0\"/>"));
+        assert!(sparkline(&trend[..1], &ExchangeRates::default()).is_empty());
+    }
+
+    #[test]
+    fn test_page_shows_price_drop() {
+        let card = ComparedCard {
+            vendor_card: reaper_king_vendor_card_expensive(),
+            scryfall_card: reaper_king_scryfall_card_expensive(),
+            price_difference_to_cheapest_vendor_card: dec!(-1),
+            reference_price: None,
+            match_quality: MatchQuality::Unknown,
+        };
+        let mut trends = PriceTrends::default();
+        trends.insert(
+            &card.vendor_card,
+            vec![
+                trend_point(1, dec!(120), None),
+                trend_point(0, dec!(100), None),
+            ],
+        );
+
+        let content =
+            generate_page_content(vec![&card], "now", &trends, &[], &ExchangeRates::default());
+        assert!(content.contains(r#"<tr data-price-dropped="true">"#));
+        assert!(content.contains("Was 120.00 SEK the run before"));
+        assert!(content.contains("<svg class=\"sparkline\""));
+
+        let content = generate_page_content(
+            vec![&card],
+            "now",
+            &PriceTrends::default(),
+            &[],
+            &ExchangeRates::default(),
+        );
+        assert!(content.contains(r#"<tr data-price-dropped="false">"#));
+        assert!(!content.contains("Price dropped</span>"));
+    }
+
+    #[test]
+    fn test_page_shows_watchlist_hits() {
+        let hit = WatchlistHit {
+            entry: crate::watchlist::WatchlistEntry {
+                name: reaper_king_vendor_card_expensive().name,
+                set: None,
+                collector_number: None,
+                foil: false,
+                max_price: Price::new(dec!(150), Currency::SEK),
+            },
+            card: reaper_king_vendor_card_expensive(),
+        };
+
+        let content = generate_page_content(
+            vec![],
+            "now",
+            &PriceTrends::default(),
+            &[hit],
+            &ExchangeRates::default(),
+        );
+        assert!(content.contains("Watchlist hits: 1"));
+        assert!(content.contains("<td data-sort=150.00>150.00 SEK</td>"));
+
+        let content = generate_page_content(
+            vec![],
+            "now",
+            &PriceTrends::default(),
+            &[],
+            &ExchangeRates::default(),
+        );
+        assert!(!content.contains(r#"<table id="watchlist-table">"#));
+    }
+
     #[test]
     #[ignore]
     fn test_filter_nice_price_cards_custom_config() {
@@ -295,7 +713,9 @@ mod tests {
             cards,
             "/workspaces/mtg-prz-rust/",
             "test_index_page.html",
-            0,
+            &NicePriceSettings::default(),
+            &PriceTrends::default(),
+            &[],
         )
         .unwrap();
     }
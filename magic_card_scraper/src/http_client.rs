@@ -0,0 +1,292 @@
+//! The HTTP client every scraper, price lookup and webhook goes through. It sends an honest
+//! User-Agent, times out connections and responses that stall, spaces out the requests to each
+//! host and retries the ones that timed out or got a 429 or 5xx with exponential backoff.
+//!
+//! The client is cheap to clone and the clones share the spacing per host.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::warn;
+use reqwest::header::{HeaderMap, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::{Client, Request, Response, StatusCode};
+use tokio::time::{sleep, sleep_until, Instant};
+
+/// Sent when no other User-Agent is configured, so the stores can tell who is asking
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    "magic_card_scraper/",
+    env!("CARGO_PKG_VERSION"),
+    " (MTG price comparison)"
+);
+
+/// The longest `Retry-After` that is waited for, a longer one is cut down to this
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpSettings {
+    pub user_agent: String,
+    /// Longest wait for a connection or for the next bytes of a response. A response that
+    /// keeps arriving, like the Scryfall bulk download, may take as long as it needs
+    pub timeout: Duration,
+    /// Retries of a request that timed out or got a 429 or 5xx
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for every retry after it
+    pub backoff: Duration,
+    /// Least time between the start of two requests to the same host
+    pub min_interval: Duration,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            timeout: Duration::from_secs(30),
+            max_retries: 3,
+            backoff: Duration::from_millis(500),
+            min_interval: Duration::from_millis(100),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    settings: HttpSettings,
+    /// When the next request to each host may start
+    next_request: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl HttpClient {
+    /// Panics like `reqwest::Client::new` if the TLS backend can't be set up
+    pub fn new(settings: HttpSettings) -> Self {
+        let client = Client::builder()
+            .user_agent(settings.user_agent.as_str())
+            .connect_timeout(settings.timeout)
+            .read_timeout(settings.timeout)
+            .build()
+            .expect("Unable to set up the HTTP client");
+        Self {
+            client,
+            settings,
+            next_request: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn settings(&self) -> &HttpSettings {
+        &self.settings
+    }
+
+    pub async fn get(&self, url: &str) -> reqwest::Result<Response> {
+        self.get_with_headers(url, HeaderMap::new()).await
+    }
+
+    /// Gets the url, retrying timeouts, 429s and 5xx. The response after the last retry is
+    /// returned as is, error status or not
+    pub async fn get_with_headers(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+    ) -> reqwest::Result<Response> {
+        let request = self.client.get(url).headers(headers).build()?;
+        self.execute(request).await
+    }
+
+    /// Posts the JSON to the url, retrying like `get`
+    pub async fn post_json(
+        &self,
+        url: &str,
+        body: &serde_json::Value,
+    ) -> reqwest::Result<Response> {
+        let request = self
+            .client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .build()?;
+        self.execute(request).await
+    }
+
+    async fn execute(&self, request: Request) -> reqwest::Result<Response> {
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        let mut retries = 0;
+        loop {
+            self.wait_for_turn(&host).await;
+            // The requests have no body or one made from a string, so they can always be cloned
+            let attempt = request
+                .try_clone()
+                .expect("Requests without a stream can be cloned");
+            let result = self.client.execute(attempt).await;
+            let retry_after = match &result {
+                Ok(response) if is_retryable(response.status()) => retry_after(response.headers()),
+                Err(e) if e.is_timeout() || e.is_connect() => None,
+                _ => return result,
+            };
+            if retries >= self.settings.max_retries {
+                return result;
+            }
+            let backoff = self.settings.backoff * 2u32.saturating_pow(retries);
+            let wait = retry_after.map_or(backoff, |retry_after| retry_after.max(backoff));
+            match &result {
+                Ok(response) => warn!(
+                    "Got {} from {}, retrying in {:?}",
+                    response.status(),
+                    request.url(),
+                    wait
+                ),
+                Err(e) => warn!("Request failed: {}, retrying in {:?}", e, wait),
+            }
+            sleep(wait).await;
+            retries += 1;
+        }
+    }
+
+    /// Waits until `min_interval` has passed since the last request to the host started
+    async fn wait_for_turn(&self, host: &str) {
+        if self.settings.min_interval.is_zero() {
+            return;
+        }
+        let turn = {
+            let mut next_request = self.next_request.lock().unwrap();
+            let now = Instant::now();
+            let turn = next_request
+                .get(host)
+                .copied()
+                .filter(|next| *next > now)
+                .unwrap_or(now);
+            next_request.insert(host.to_string(), turn + self.settings.min_interval);
+            turn
+        };
+        sleep_until(turn).await;
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new(HttpSettings::default())
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// The wait asked for in a `Retry-After` header given in seconds
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::helpers::test_http_client;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retries_server_errors_but_not_client_errors() {
+        let mut server = std::thread::spawn(mockito::Server::new).join().unwrap();
+        let unavailable = server
+            .mock("GET", "/unavailable")
+            .with_status(503)
+            .expect(4)
+            .create();
+        let missing = server
+            .mock("GET", "/missing")
+            .with_status(404)
+            .expect(1)
+            .create();
+        let client = test_http_client();
+
+        let response = client
+            .get(&format!("{}/unavailable", server.url()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let response = client
+            .get(&format!("{}/missing", server.url()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        unavailable.assert();
+        missing.assert();
+    }
+
+    #[tokio::test]
+    async fn test_sends_user_agent_and_spaces_out_requests_to_a_host() {
+        let mut server = std::thread::spawn(mockito::Server::new).join().unwrap();
+        let mock = server
+            .mock("GET", "/")
+            .match_header("user-agent", DEFAULT_USER_AGENT)
+            .expect(3)
+            .create();
+        let client = HttpClient::new(HttpSettings {
+            min_interval: Duration::from_millis(50),
+            ..HttpSettings::default()
+        });
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            client.get(&server.url()).await.unwrap();
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_slow_body_is_not_cut_off_by_the_timeout() {
+        let mut server = std::thread::spawn(mockito::Server::new).join().unwrap();
+        let mock = server
+            .mock("GET", "/bulk")
+            .with_chunked_body(|body| {
+                for _ in 0..5 {
+                    std::thread::sleep(Duration::from_millis(100));
+                    body.write_all(b"chunk")?;
+                }
+                Ok(())
+            })
+            .create();
+        let client = HttpClient::new(HttpSettings {
+            timeout: Duration::from_millis(300),
+            ..HttpSettings::default()
+        });
+
+        let start = Instant::now();
+        let body = client
+            .get(&format!("{}/bulk", server.url()))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        assert!(start.elapsed() > Duration::from_millis(300));
+        assert_eq!(body, "chunk".repeat(5));
+        mock.assert();
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, "2".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(2)));
+        headers.insert(RETRY_AFTER, "3600".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(MAX_RETRY_AFTER));
+    }
+}
@@ -0,0 +1,51 @@
+//! Scrapes Swedish MTG stores, compares their prices to the MCM trend from Scryfall and
+//! renders the cards with a nice price.
+//!
+//! The card domain types live in [`cards`], the vendor scrapers in [`dragonslair_scraper`]
+//! and [`alphaspel_scraper`], which account for every page and product in a
+//! [`scrape_report`], the Scryfall and MTGStocks price lookups in [`scryfall_scraper`]
+//! and [`mtg_stock_price_checker`], chained together by [`price_source`], and the renderers in [`html_generator`] and
+//! [`tradable_cards::html_generator`]. [`pipeline`] ties them together the way the
+//! `magic_card_scraper` binary runs them, recording every run in [`price_history`].
+//! [`snapshot_diff`] shows what changed between two snapshots of a vendor and [`watchlist`]
+//! finds the watched cards that are cheap enough, which [`notifier`] sends out with the new
+//! nice prices. [`decklist`] prices a whole deck list with the vendors' stock and splits
+//! it into orders, and [`collection`] keeps the cards you own and values them.
+//! Every request goes through the rate limited and retrying [`http_client`] and failures are
+//! reported with the typed errors in [`error`].
+
+pub mod alphaspel_scraper;
+pub mod cards;
+pub mod collection;
+pub mod comparer;
+pub mod decklist;
+pub mod dragonslair_scraper;
+pub mod error;
+pub mod exchange_rate_fetcher;
+pub mod html_generator;
+pub mod http_client;
+pub mod manatorsk_scraper;
+pub mod mtg_stock_price_checker;
+pub mod notifier;
+pub mod pipeline;
+pub mod price_history;
+pub mod price_source;
+pub mod scrape_report;
+pub mod scryfall_scraper;
+pub mod snapshot_diff;
+pub mod tradable_cards;
+pub mod utilities;
+pub mod vendor_scraper;
+pub mod watchlist;
+
+#[cfg(test)]
+mod test;
+
+pub use cards::{
+    cardname::CardName, compared_card::ComparedCard, currency::Currency, price::Price,
+    scryfallcard::ScryfallCard, setname::SetName, vendor::Vendor, vendorcard::VendorCard,
+};
+pub use comparer::{Comparer, ComparerSettings};
+pub use html_generator::{generate_nice_price_page, NicePriceSettings};
+pub use price_source::{PriceChain, PriceQuote, PriceSource};
+pub use utilities::config::Config;
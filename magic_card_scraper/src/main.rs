@@ -1,288 +1,680 @@
-mod alphaspel_scraper;
-mod cards;
-mod comparer;
-mod dragonslair_scraper;
-mod html_generator;
-mod mtg_stock_price_checker;
-mod scryfall_scraper;
-mod test;
-mod tradable_cards;
-mod utilities;
+mod cli;
 
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
-
-use crate::cards::cardname::CardName;
-use crate::cards::vendorcard::VendorCard;
-use crate::utilities::{config::CONFIG, string_manipulators::date_time_as_string};
-
-use alphaspel_scraper::AlphaspelScraper;
-use cards::compared_card::ComparedCard;
-use cards::scryfallcard::ScryfallCard;
-
-use comparer::Comparer;
-use dragonslair_scraper::DragonslairScraper;
-
-use html_generator::generate_nice_price_page;
-use log::{error, info};
-use reqwest::Client;
-use scryfall_scraper::ScryfallScraper;
-use tradable_cards::delver_lense_converter::DelverLenseConverter;
-use tradable_cards::html_generator::generate_page_content;
-use tradable_cards::tradable_card_comparer::TradableCardsComparer;
-use utilities::constants::{
-    ALPHASPEL_CARDS_FOLDER, ALPHASPEL_CARDS_PREFIX, ALPHASPEL_URL, COMPARED_CARDS_DIR,
-    COMPARED_FILE_PREFIX, DRAGONSLAIR_CARDS_FOLDER, DRAGONSLAIR_CARDS_PREFIX, DRAGONSLAIR_URL,
-    MTG_STOCKS_BASE_URL, REPOSITORY_ROOT_PATH, SCRYFALL_CARDS_DIR, SCRYFALL_FILE_PREFIX,
+use std::path::Path;
+
+use chrono::{Duration, Local, Utc};
+use clap::Parser;
+use log::info;
+use rust_decimal::Decimal;
+
+use cli::{
+    BasketArgs, Cli, CollectionAddArgs, CollectionCommand, CollectionImportArgs,
+    CollectionRemoveArgs, CollectionValueArgs, Command, CompareArgs, DeckArgs, DiffArgs,
+    HistoryArgs, RenderCommand, RenderNicePriceArgs, RenderTradableArgs, RunArgs, ScrapeArgs,
+    TradeInArgs,
 };
-use utilities::file_management::load_from_json_file;
-use utilities::{file_management::get_newest_file, file_management::save_to_file};
-
-async fn get_alphaspel_cards_and_save_to_file() -> HashMap<CardName, Vec<VendorCard>> {
-    let start_time = chrono::prelude::Local::now();
-    info!("Starting at {}", start_time);
+use magic_card_scraper::cards::{
+    cardname::CardName,
+    colour::Colour,
+    currency::{Currency, ExchangeRates},
+    language::Language,
+    personalcard::PersonalCard,
+    price::Price,
+    setname::SetName,
+    tradable_card::TradeableCard,
+    vendorcard::VendorCard,
+};
+use magic_card_scraper::collection::{
+    valuation::{CardValue, CollectionValuation},
+    Collection,
+};
+use magic_card_scraper::decklist::{basket::Basket, cheapest_build::CheapestBuild, Decklist};
+use magic_card_scraper::http_client::HttpClient;
+use magic_card_scraper::pipeline::{
+    compare_cards_and_save_to_file, compare_delver_lense_cards_to_dl,
+    diff_snapshots_and_save_to_file, get_scryfall_cards_and_save_to_file,
+    given_dated_or_newest_file, given_or_newest_file, load_cards, load_cards_or_empty,
+    load_exchange_rates, load_newest_vendor_cards, load_price_trends, load_watchlist_hits,
+    merge_vendor_cards, open_history, previous_snapshot, record_history, run_pipeline,
+    scrape_vendor_and_save_to_file, scryfall_snapshot_taken_at, snapshot_path, start_history_run,
+    write_nice_price_page, TradeInPaths,
+};
+use magic_card_scraper::price_history::{CopyQuality, PriceHistory};
+use magic_card_scraper::tradable_cards::collection_importer::CollectionImporter;
+use magic_card_scraper::tradable_cards::html_generator::generate_page_content;
+use magic_card_scraper::utilities::config::{Config, ConfigError};
+use magic_card_scraper::utilities::constants::{
+    BASKETS_DIR, BASKET_PREFIX, COLLECTION_VALUATIONS_DIR, COLLECTION_VALUATION_PREFIX,
+    COMPARED_CARDS_DIR, COMPARED_FILE_PREFIX, DECK_BUILDS_DIR, DECK_BUILD_PREFIX,
+    DRAGONSLAIR_CARDS_FOLDER, DRAGONSLAIR_CARDS_PREFIX, SCRYFALL_CARDS_DIR, SCRYFALL_FILE_PREFIX,
+    SNAPSHOT_DIFFS_DIR, TRADABLE_CARDS_DIR, TRADABLE_CARDS_PREFIX,
+};
+use magic_card_scraper::utilities::file_management::{load_from_json_file, save_to_file};
+use magic_card_scraper::vendor_scraper::VendorRegistry;
 
-    let scraper = AlphaspelScraper::new(ALPHASPEL_URL);
-    let alphaspel_cards = scraper.scrape_cards().await.unwrap();
+async fn scrape(
+    config: &Config,
+    client: &HttpClient,
+    args: ScrapeArgs,
+) -> Result<(), Box<dyn Error>> {
+    let data_dir = config.output.data_dir.as_str();
+    let source = args.source.to_lowercase();
+    if source == "scryfall" {
+        let path = args
+            .output
+            .unwrap_or_else(|| snapshot_path(data_dir, SCRYFALL_CARDS_DIR, SCRYFALL_FILE_PREFIX));
+        let (scryfall_cards, _) =
+            get_scryfall_cards_and_save_to_file(config, client, &path).await?;
+        record_history(
+            &mut start_history_run(config),
+            "Scryfall prices",
+            |history, run| history.record_scryfall_prices(run, &scryfall_cards),
+        );
+        return Ok(());
+    }
 
-    let as_cards_path = format!(
-        "{}/{}/{}{}.json",
-        REPOSITORY_ROOT_PATH,
-        ALPHASPEL_CARDS_FOLDER,
-        ALPHASPEL_CARDS_PREFIX,
-        date_time_as_string(None, None)
+    let registry = VendorRegistry::with_default_vendors(config, client);
+    let scraper = registry.get(&source).ok_or_else(|| {
+        format!(
+            "Unknown source '{}', expected scryfall or one of: {}",
+            args.source,
+            registry.names().join(", ")
+        )
+    })?;
+    let path = args.output.unwrap_or_else(|| {
+        snapshot_path(
+            data_dir,
+            scraper.snapshot_folder(),
+            scraper.snapshot_prefix(),
+        )
+    });
+    let cards = scrape_vendor_and_save_to_file(scraper, &path).await?;
+    record_history(
+        &mut start_history_run(config),
+        scraper.name(),
+        |history, run| history.record_vendor_cards(run, &cards),
     );
+    Ok(())
+}
 
-    save_to_file(&as_cards_path, &alphaspel_cards).unwrap();
+async fn compare(
+    config: &Config,
+    client: &HttpClient,
+    rates: &ExchangeRates,
+    args: CompareArgs,
+) -> Result<(), Box<dyn Error>> {
+    let data_dir = config.output.data_dir.as_str();
+    let registry = VendorRegistry::with_default_vendors(config, client);
+    if let Some((vendor, _)) = args
+        .snapshots
+        .iter()
+        .find(|(vendor, _)| registry.get(vendor).is_none())
+    {
+        return Err(format!("Unknown vendor '{}'", vendor).into());
+    }
 
-    let end_time = chrono::prelude::Local::now();
-    info!(
-        "Alphaspel scrape started at: {}. Finished at: {}. Took: {} seconds and with {} cards on as_cards_path: {}",
-        start_time,
-        end_time,
-        (end_time - start_time).num_seconds(),
-        alphaspel_cards.len(),
-        as_cards_path
+    let mut vendor_cards = HashMap::new();
+    for scraper in registry.iter() {
+        let snapshot = args
+            .snapshots
+            .iter()
+            .find(|(vendor, _)| vendor == scraper.name())
+            .map(|(_, path)| path.as_str());
+        let cards = load_cards_or_empty(
+            snapshot,
+            data_dir,
+            scraper.snapshot_folder(),
+            scraper.snapshot_prefix(),
+        );
+        vendor_cards = merge_vendor_cards(vendor_cards, cards);
+    }
+    let scryfall_path = given_or_newest_file(
+        args.scryfall.as_deref(),
+        data_dir,
+        SCRYFALL_CARDS_DIR,
+        SCRYFALL_FILE_PREFIX,
+    )?;
+    let scryfall_cards = load_cards(&scryfall_path)?;
+    let scryfall_as_of = scryfall_snapshot_taken_at(&scryfall_path)?;
+
+    let output = args
+        .output
+        .unwrap_or_else(|| snapshot_path(data_dir, COMPARED_CARDS_DIR, COMPARED_FILE_PREFIX));
+    let compared_cards = compare_cards_and_save_to_file(
+        config,
+        client,
+        rates,
+        scryfall_cards,
+        scryfall_as_of,
+        vendor_cards,
+        &output,
+    )
+    .await?;
+    record_history(
+        &mut start_history_run(config),
+        "compared cards",
+        |history, run| history.record_compared_cards(run, &compared_cards),
     );
-    alphaspel_cards
+    Ok(())
 }
 
-async fn get_dragonslair_cards_and_save_to_file() -> HashMap<CardName, Vec<VendorCard>> {
-    let start_time = chrono::prelude::Local::now();
-    info!("Starting at {}", start_time);
+fn render_nice_price(
+    config: &Config,
+    rates: &ExchangeRates,
+    args: RenderNicePriceArgs,
+) -> Result<(), Box<dyn Error>> {
+    let data_dir = config.output.data_dir.as_str();
+    let input = given_or_newest_file(
+        args.input.as_deref(),
+        data_dir,
+        COMPARED_CARDS_DIR,
+        COMPARED_FILE_PREFIX,
+    )?;
+    let compared_cards = load_cards(&input)?;
+    let output = args
+        .output
+        .unwrap_or_else(|| format!("{}/{}", data_dir, config.output.nice_price_page));
+    let trends = load_price_trends(
+        config,
+        rates,
+        open_history(config).as_ref(),
+        &compared_cards,
+    );
+    write_nice_price_page(
+        compared_cards,
+        &output,
+        &config.nice_price_settings(rates),
+        &trends,
+        &load_watchlist_hits(config),
+    )?;
+    info!("Rendered nice price page from {} to {}", input, output);
+    Ok(())
+}
 
-    let scraper: DragonslairScraper = DragonslairScraper::new(DRAGONSLAIR_URL, None, Client::new());
-    let dragoslair_cards = scraper.get_available_cards().await.unwrap();
+fn render_tradable(
+    config: &Config,
+    rates: &ExchangeRates,
+    args: RenderTradableArgs,
+) -> Result<(), Box<dyn Error>> {
+    let data_dir = config.output.data_dir.as_str();
+    let input = given_or_newest_file(
+        args.input.as_deref(),
+        data_dir,
+        TRADABLE_CARDS_DIR,
+        TRADABLE_CARDS_PREFIX,
+    )?;
+    let tradable_cards = load_from_json_file::<Vec<TradeableCard>>(&input)?;
+    let output = args
+        .output
+        .unwrap_or_else(|| format!("{}/{}", data_dir, config.output.tradable_cards_page));
+    fs::write(&output, generate_page_content(&tradable_cards, rates))?;
+    info!("Rendered tradable cards page from {} to {}", input, output);
+    Ok(())
+}
 
-    let dl_cards_path = format!(
-        "{}/{}/{}{}.json",
-        REPOSITORY_ROOT_PATH,
+async fn trade_in(
+    config: &Config,
+    client: &HttpClient,
+    rates: &ExchangeRates,
+    args: TradeInArgs,
+) -> Result<(), Box<dyn Error>> {
+    let data_dir = config.output.data_dir.as_str();
+    let dl_cards_path = given_or_newest_file(
+        args.dragonslair.as_deref(),
+        data_dir,
         DRAGONSLAIR_CARDS_FOLDER,
         DRAGONSLAIR_CARDS_PREFIX,
-        date_time_as_string(None, None)
-    );
-
-    save_to_file(&dl_cards_path, &dragoslair_cards).unwrap();
-
-    let end_time = chrono::prelude::Local::now();
-    info!(
-        "DL scrape started at: {}. Finished at: {}. Took: {} seconds and with {} cards on dl_cards_path: {}",
-        start_time,
-        end_time,
-        (end_time - start_time).num_seconds(),
-        dragoslair_cards.len(),
-        dl_cards_path
-    );
-    dragoslair_cards
+    )?;
+    let dl_cards = load_cards(&dl_cards_path)?;
+    let defaults = TradeInPaths::new(config);
+    let paths = TradeInPaths {
+        tradable_cards: args.output.unwrap_or(defaults.tradable_cards),
+        html: args.html_output.unwrap_or(defaults.html),
+        cardmarket: args.cardmarket_output.unwrap_or(defaults.cardmarket),
+        sell_list: args.sell_list_output.unwrap_or(defaults.sell_list),
+    };
+    compare_delver_lense_cards_to_dl(config, client, rates, &args.csv, dl_cards, &paths).await?;
+    Ok(())
 }
 
-async fn get_scryfall_cards_and_save_to_file(
-) -> HashMap<CardName, Vec<cards::scryfallcard::ScryfallCard>> {
-    let start_time = chrono::prelude::Local::now();
-    info!("Starting at {}", start_time);
+fn history(config: &Config, client: &HttpClient, args: HistoryArgs) -> Result<(), Box<dyn Error>> {
+    let history = PriceHistory::open(config.history_path())?;
+    let name = CardName::new(args.name.clone())?;
+    let set = SetName::new(args.set.clone())?;
+    let since = Utc::now() - Duration::days(args.days);
+    let (source, points) = match &args.vendor {
+        Some(vendor) => {
+            let registry = VendorRegistry::with_default_vendors(config, client);
+            let scraper = registry.get(&vendor.to_lowercase()).ok_or_else(|| {
+                format!(
+                    "Unknown vendor '{}', expected one of: {}",
+                    vendor,
+                    registry.names().join(", ")
+                )
+            })?;
+            let quality = CopyQuality {
+                condition: args.condition,
+                language: args.language,
+            };
+            let points = history.vendor_price_history(
+                &scraper.vendor(),
+                &name,
+                &set,
+                args.foil,
+                quality,
+                since,
+            )?;
+            let source = format!(
+                "{} ({} {})",
+                scraper.vendor(),
+                args.condition,
+                args.language
+            );
+            (source, points)
+        }
+        None => (
+            "Scryfall".to_string(),
+            history.scryfall_price_history(&name, &set, args.foil, since)?,
+        ),
+    };
 
-    let scryfall_scraper = ScryfallScraper::new(None, reqwest::Client::new(), None);
-    let path_to_raw_scryfall_cards_file = scryfall_scraper
-        .get_raw_scryfall_cards_file()
-        .await
-        .unwrap();
-    let scryfall_cards = scryfall_scraper
-        .convert_raw_to_domain_cards(&path_to_raw_scryfall_cards_file)
-        .unwrap();
+    let foil_text = if args.foil { " (Foil)" } else { "" };
+    println!("{}{} / {} at {}", args.name, foil_text, args.set, source);
+    if points.is_empty() {
+        println!("No prices recorded in the last {} days", args.days);
+    }
+    for point in points {
+        println!(
+            "{}  {}",
+            point.timestamp.format("%Y-%m-%d %H:%M"),
+            point.price
+        );
+    }
+    Ok(())
+}
 
-    let scryfall_cards_path = format!(
-        "{}/{}/{}{}.json",
-        REPOSITORY_ROOT_PATH,
-        SCRYFALL_CARDS_DIR,
-        SCRYFALL_FILE_PREFIX,
-        date_time_as_string(None, None)
+fn diff(
+    config: &Config,
+    client: &HttpClient,
+    rates: &ExchangeRates,
+    args: DiffArgs,
+) -> Result<(), Box<dyn Error>> {
+    let data_dir = config.output.data_dir.as_str();
+    let registry = VendorRegistry::with_default_vendors(config, client);
+    let scraper = registry.get(&args.vendor.to_lowercase()).ok_or_else(|| {
+        format!(
+            "Unknown vendor '{}', expected one of: {}",
+            args.vendor,
+            registry.names().join(", ")
+        )
+    })?;
+    let (folder, prefix) = (scraper.snapshot_folder(), scraper.snapshot_prefix());
+    let to = given_dated_or_newest_file(args.to.as_deref(), data_dir, folder, prefix)?;
+    let from = match args.from.as_deref() {
+        Some(from) => given_dated_or_newest_file(Some(from), data_dir, folder, prefix)?,
+        None => previous_snapshot(&to, data_dir, folder, prefix)?,
+    };
+    let output = args.output.unwrap_or_else(|| {
+        snapshot_path(
+            data_dir,
+            SNAPSHOT_DIFFS_DIR,
+            &format!("{}_diff_", scraper.name()),
+        )
+    });
+    let html_output = args.html_output.unwrap_or_else(|| {
+        Path::new(&output)
+            .with_extension("html")
+            .to_string_lossy()
+            .to_string()
+    });
+    let diff = diff_snapshots_and_save_to_file(&from, &to, &output, &html_output, rates)?;
+
+    println!("{} from {} to {}", scraper.vendor(), diff.from, diff.to);
+    println!(
+        "{} new in stock, {} sold out, {} repriced",
+        diff.new_in_stock.len(),
+        diff.sold_out.len(),
+        diff.repriced.len()
     );
+    println!("Saved to {} and {}", output, html_output);
+    Ok(())
+}
 
-    save_to_file(&scryfall_cards_path, &scryfall_cards).unwrap();
-
-    let end_time = chrono::prelude::Local::now();
-    info!(
-        "Scryfall scrape started at: {}. Finished at: {}. Took: {} seconds and with {} cards on scryfall_cards_path: {}",
-        start_time,
-        end_time,
-        (end_time - start_time).num_seconds(),
-        scryfall_cards.len(),
-        scryfall_cards_path
+fn deck(
+    config: &Config,
+    client: &HttpClient,
+    rates: &ExchangeRates,
+    args: DeckArgs,
+) -> Result<(), Box<dyn Error>> {
+    let data_dir = config.output.data_dir.as_str();
+    let decklist = Decklist::load(&args.decklist)?;
+    let vendor_cards = load_newest_vendor_cards(config, client);
+    let scryfall_cards =
+        load_cards_or_empty(None, data_dir, SCRYFALL_CARDS_DIR, SCRYFALL_FILE_PREFIX);
+    let build = CheapestBuild::new(
+        &decklist,
+        &vendor_cards,
+        &scryfall_cards,
+        &args.filter.filter(),
+        rates,
+    );
+    let output = args
+        .output
+        .unwrap_or_else(|| snapshot_path(data_dir, DECK_BUILDS_DIR, DECK_BUILD_PREFIX));
+    save_to_file(&output, &build)?;
+
+    for line in &build.lines {
+        let foil_text = if line.card.foil { " (Foil)" } else { "" };
+        println!(
+            "{} {}{} / {} {} at {}: {}",
+            line.count,
+            line.card.name.almost_raw,
+            foil_text,
+            line.card.set.raw,
+            condition_text(&line.card),
+            line.card.vendor,
+            line.total
+        );
+    }
+    for card in &build.missing {
+        println!("{} {}: not in stock", card.count, card.name.almost_raw);
+    }
+    for skipped in &build.skipped {
+        println!("Skipped '{}': {}", skipped.line, skipped.reason);
+    }
+    println!();
+    println!(
+        "Mixed: {}, {} cards missing",
+        build.total,
+        build.cards_missing()
+    );
+    for total in &build.per_vendor {
+        println!(
+            "{}: {}, {} cards missing",
+            total.vendor, total.total, total.cards_missing
+        );
+    }
+    println!(
+        "MCM trend of the cards with one: {}, premium over it: {}",
+        build.mcm_total,
+        Currency::SEK.format_amount(build.premium_over_mcm)
     );
-    scryfall_cards
+    println!("Saved to {}", output);
+    Ok(())
 }
 
-async fn compare_cards_and_save_to_file(
-    scryfall_cards: HashMap<CardName, Vec<ScryfallCard>>,
-    vendor_cards: HashMap<CardName, Vec<VendorCard>>,
-) -> HashMap<CardName, Vec<ComparedCard>> {
-    let start_time = chrono::prelude::Local::now();
-    info!("Starting at {}", start_time);
-
-    let comparer = Comparer::new(scryfall_cards, MTG_STOCKS_BASE_URL.to_string());
-    let compared_cards = comparer.compare_vendor_cards(vendor_cards).await;
+/// The condition of the card, and the language when it isn't english
+fn condition_text(card: &VendorCard) -> String {
+    match card.language {
+        Language::English => format!("({})", card.condition),
+        language => format!("({}, {})", card.condition, language),
+    }
+}
 
-    let cards_path = format!(
-        "{}/{}/{}{}.json",
-        REPOSITORY_ROOT_PATH,
-        COMPARED_CARDS_DIR,
-        COMPARED_FILE_PREFIX,
-        date_time_as_string(None, None)
+fn basket(
+    config: &Config,
+    client: &HttpClient,
+    rates: &ExchangeRates,
+    args: BasketArgs,
+) -> Result<(), Box<dyn Error>> {
+    let data_dir = config.output.data_dir.as_str();
+    let wants = Decklist::load(&args.wantlist)?;
+    let vendor_cards = load_newest_vendor_cards(config, client);
+    let basket = Basket::optimise(
+        &wants,
+        &vendor_cards,
+        &config.basket.fixed_costs(),
+        &args.filter.filter(),
+        rates,
     );
-
-    save_to_file(&cards_path, &compared_cards).unwrap();
-
-    let end_time = chrono::prelude::Local::now();
-    info!(
-        "Comparing cards started at: {}. Finished at: {}. Took: {} seconds and with {} cards in dir: {}",
-        start_time,
-        end_time,
-        (end_time - start_time).num_seconds(),
-        compared_cards.len(),
-        cards_path
+    let output = args
+        .output
+        .unwrap_or_else(|| snapshot_path(data_dir, BASKETS_DIR, BASKET_PREFIX));
+    save_to_file(&output, &basket)?;
+
+    for order in &basket.orders {
+        println!(
+            "{}: {} + {} = {}",
+            order.vendor, order.cards_total, order.fixed_cost, order.total
+        );
+        for line in &order.lines {
+            let foil_text = if line.card.foil { " (Foil)" } else { "" };
+            println!(
+                "  {} {}{} / {} {}: {}",
+                line.count,
+                line.card.name.almost_raw,
+                foil_text,
+                line.card.set.raw,
+                condition_text(&line.card),
+                line.total
+            );
+        }
+    }
+    for card in &basket.missing {
+        println!("{} {}: not in stock", card.count, card.name.almost_raw);
+    }
+    for skipped in &basket.skipped {
+        println!("Skipped '{}': {}", skipped.line, skipped.reason);
+    }
+    println!();
+    println!(
+        "Total: {}, every card at its cheapest would be {}",
+        basket.total, basket.cheapest_per_card_total
     );
-    compared_cards
+    println!("Saved to {}", output);
+    Ok(())
 }
 
-fn get_data_from_most_recent_file<T>(
-    folder_name: &str,
-    file_prefix: &str,
-) -> Result<HashMap<CardName, Vec<T>>, Box<dyn std::error::Error>>
-where
-    T: serde::de::DeserializeOwned,
-{
-    let newest_file = get_newest_file(&format!("../{}", folder_name), file_prefix)?;
-    let res = newest_file.to_str().unwrap();
+fn collection_import(config: &Config, args: CollectionImportArgs) -> Result<(), Box<dyn Error>> {
+    let path = config.collection_path();
+    let mut collection = Collection::load(&path)?;
+    let scryfall_cards = load_cards_or_empty(
+        None,
+        &config.output.data_dir,
+        SCRYFALL_CARDS_DIR,
+        SCRYFALL_FILE_PREFIX,
+    );
+    let imported = CollectionImporter::with_scryfall_cards(&scryfall_cards)
+        .get_personal_cards_from_file(&args.csv)?;
+    for skipped in &imported.skipped {
+        println!("Skipped line {}: {}", skipped.line, skipped.reason);
+    }
+    let acquired_on = args
+        .acquired_on
+        .unwrap_or_else(|| Local::now().date_naive());
+    let added = collection.import(imported.cards, acquired_on);
+    collection.save(&path)?;
+    println!(
+        "Imported {} cards from the {} export, the collection has {} cards",
+        added,
+        imported.format,
+        collection.count()
+    );
+    Ok(())
+}
 
-    load_cards(res)
+fn collection_add(config: &Config, args: CollectionAddArgs) -> Result<(), Box<dyn Error>> {
+    let path = config.collection_path();
+    let mut collection = Collection::load(&path)?;
+    let price = args.price.map(|amount| Price::new(amount, args.currency));
+    let card = PersonalCard {
+        name: CardName::new(args.name)?,
+        set: SetName::new(args.set)?,
+        foil: args.foil,
+        price: price.unwrap_or(Price::new(Decimal::ZERO, args.currency)),
+        count: args.count,
+        color: args
+            .colour
+            .as_deref()
+            .and_then(|colour| colour.parse().ok())
+            .unwrap_or(Colour::Unknown),
+        rarity: args.rarity,
+        condition: args.condition,
+        language: args.language,
+    };
+    let acquired_on = args
+        .acquired_on
+        .unwrap_or_else(|| Local::now().date_naive());
+    collection.add(card, price, acquired_on);
+    collection.save(&path)?;
+    println!("The collection has {} cards", collection.count());
+    Ok(())
 }
 
-fn load_cards<T>(path: &str) -> Result<HashMap<CardName, Vec<T>>, Box<dyn std::error::Error>>
-where
-    T: serde::de::DeserializeOwned,
-{
-    load_from_json_file::<HashMap<CardName, Vec<T>>>(path)
-        .map_err(|e| Box::new(e) as Box<dyn Error>)
+fn collection_remove(config: &Config, args: CollectionRemoveArgs) -> Result<(), Box<dyn Error>> {
+    let path = config.collection_path();
+    let mut collection = Collection::load(&path)?;
+    let name = CardName::new(args.name)?;
+    let set = SetName::new(args.set)?;
+    let removed = collection.remove(
+        &name,
+        &set,
+        args.foil,
+        args.condition,
+        args.language,
+        args.count,
+    )?;
+    collection.save(&path)?;
+    println!(
+        "Removed {} cards, the collection has {} cards",
+        removed,
+        collection.count()
+    );
+    Ok(())
 }
 
-async fn compare_delver_lense_cards_to_dl(
-    dl_cards: HashMap<CardName, Vec<VendorCard>>,
+fn collection_value(
+    config: &Config,
+    rates: &ExchangeRates,
+    args: CollectionValueArgs,
 ) -> Result<(), Box<dyn Error>> {
-    let start_time = chrono::prelude::Local::now();
-    info!("Starting delver lense compare at {}", start_time);
-    let delver_lense_converter = DelverLenseConverter::new();
-    let cards = delver_lense_converter
-        .get_delver_lense_cards_from_file(&CONFIG.delver_lense_path)
-        .unwrap();
-
-    let comparer = TradableCardsComparer::new(DragonslairScraper::new(
-        DRAGONSLAIR_URL,
-        None,
-        reqwest::Client::new(),
-    ));
-
-    let tradable_cards = comparer
-        .get_tradable_cards(cards, dl_cards.clone())
-        .await
-        .unwrap();
-    let path = format!(
-        "../tradable_cards/tradable_cards_{}.json",
-        date_time_as_string(None, None)
+    let data_dir = config.output.data_dir.as_str();
+    let collection = Collection::load(&config.collection_path())?;
+    let scryfall_cards =
+        load_cards_or_empty(None, data_dir, SCRYFALL_CARDS_DIR, SCRYFALL_FILE_PREFIX);
+    let valuation = CollectionValuation::new(&collection, &scryfall_cards, args.top, rates);
+    let output = args.output.unwrap_or_else(|| {
+        snapshot_path(
+            data_dir,
+            COLLECTION_VALUATIONS_DIR,
+            COLLECTION_VALUATION_PREFIX,
+        )
+    });
+    save_to_file(&output, &valuation)?;
+
+    println!(
+        "{} cards worth {}, the ones with a known cost cost {} ({})",
+        valuation.cards,
+        valuation.total_value,
+        valuation.total_cost,
+        Currency::SEK.format_amount(valuation.total_change)
     );
-    save_to_file(&path, &tradable_cards)?;
-
-    let html = generate_page_content(&tradable_cards);
+    let print_card_values = |title: &str, values: &[CardValueRow]| {
+        if !values.is_empty() {
+            println!("{}:", title);
+        }
+        for (card, change) in values {
+            println!("  {}: {}", card, change);
+        }
+    };
+    print_card_values("Top gainers", &card_value_rows(&valuation.top_gainers));
+    print_card_values("Top losers", &card_value_rows(&valuation.top_losers));
+    for (title, groups) in [
+        ("By set", &valuation.by_set),
+        ("By colour", &valuation.by_colour),
+        ("By rarity", &valuation.by_rarity),
+    ] {
+        println!("{}:", title);
+        for group in groups {
+            println!("  {}: {} cards, {}", group.name, group.count, group.value);
+        }
+    }
+    if !valuation.unpriced.is_empty() {
+        println!(
+            "{} printings have no MCM trend in the Scryfall snapshot",
+            valuation.unpriced.len()
+        );
+    }
+    println!("Saved to {}", output);
+    Ok(())
+}
 
-    fs::write("../cards.html", html)?;
+type CardValueRow = (String, String);
+
+fn card_value_rows(values: &[CardValue]) -> Vec<CardValueRow> {
+    values
+        .iter()
+        .map(|value| {
+            let foil_text = if value.card.foil { " (Foil)" } else { "" };
+            let change = Currency::SEK.format_amount(value.change.unwrap_or_default());
+            let percent = value
+                .change_percent
+                .map(|percent| format!(" ({}%)", percent))
+                .unwrap_or_default();
+            (
+                format!(
+                    "{} {}{} / {}",
+                    value.card.count, value.card.name.almost_raw, foil_text, value.card.set.raw
+                ),
+                format!("{}{}", change, percent),
+            )
+        })
+        .collect()
+}
 
-    let end_time = chrono::prelude::Local::now();
-    info!(
-        "Comparing delver lense cards to DL cards started at: {}. Finished at: {}. Took: {} seconds and with cards in dir: {}",
-        start_time,
-        end_time,
-        (end_time - start_time).num_seconds(),
-        path
-    );
-    Ok(())
+/// Layers the config file, env vars and flags
+fn load_config(cli: &Cli) -> Result<Config, ConfigError> {
+    let mut config = Config::load(cli.config.as_deref())?;
+    cli.apply_to(&mut config)?;
+    config.validate()?;
+    Ok(config)
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     env_logger::init();
-    info!("Starting");
-
-    let mut dl_cards = if CONFIG.dragonslair {
-        get_dragonslair_cards_and_save_to_file().await
-    } else {
-        match get_data_from_most_recent_file(DRAGONSLAIR_CARDS_FOLDER, DRAGONSLAIR_CARDS_PREFIX) {
-            Ok(cards) => cards,
-            Err(e) => {
-                error!("Failed to load Dragonslair cards: {}", e);
-                HashMap::new()
-            }
+    let cli = Cli::parse();
+    let config = match load_config(&cli) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
         }
     };
-
-    if !CONFIG.delver_lense_path.is_empty() {
-        let _ = compare_delver_lense_cards_to_dl(dl_cards.clone()).await;
-    }
-
-    let alphaspel_cards = if CONFIG.alpha {
-        get_alphaspel_cards_and_save_to_file().await
+    info!("Starting");
+    // Every request of the run goes through this client, so the requests to a host are
+    // spaced out across all the steps
+    let client = HttpClient::new(config.http_settings());
+
+    let command = cli.command.unwrap_or(Command::Run(RunArgs::default()));
+    let rates = if matches!(
+        command,
+        Command::Scrape(_) | Command::History(_) | Command::Diff(_)
+    ) {
+        config.exchange_rates()
     } else {
-        match get_data_from_most_recent_file(ALPHASPEL_CARDS_FOLDER, ALPHASPEL_CARDS_PREFIX) {
-            Ok(cards) => cards,
-            Err(e) => {
-                error!("Failed to load alphaspel cards: {}", e);
-                HashMap::new()
-            }
-        }
+        load_exchange_rates(&config, &client).await
     };
 
-    let scryfall_cards_path = if CONFIG.scryfall {
-        info!("Downloading Scryfall cards...");
-        get_scryfall_cards_and_save_to_file().await
-    } else {
-        match get_data_from_most_recent_file(SCRYFALL_CARDS_DIR, SCRYFALL_FILE_PREFIX) {
-            Ok(cards) => cards,
-            Err(e) => {
-                error!("Failed to load Scryfall cards: {}", e);
-                HashMap::new()
-            }
+    match command {
+        Command::Run(_) => Ok(run_pipeline(&config, &client, &rates).await?),
+        Command::Scrape(args) => scrape(&config, &client, args).await,
+        Command::Compare(args) => compare(&config, &client, &rates, args).await,
+        Command::Render(RenderCommand::NicePrice(args)) => render_nice_price(&config, &rates, args),
+        Command::Render(RenderCommand::Tradable(args)) => render_tradable(&config, &rates, args),
+        Command::TradeIn(args) => trade_in(&config, &client, &rates, args).await,
+        Command::History(args) => history(&config, &client, args),
+        Command::Diff(args) => diff(&config, &client, &rates, args),
+        Command::Deck(args) => deck(&config, &client, &rates, args),
+        Command::Basket(args) => basket(&config, &client, &rates, args),
+        Command::Collection(CollectionCommand::Import(args)) => collection_import(&config, args),
+        Command::Collection(CollectionCommand::Add(args)) => collection_add(&config, args),
+        Command::Collection(CollectionCommand::Remove(args)) => collection_remove(&config, args),
+        Command::Collection(CollectionCommand::Value(args)) => {
+            collection_value(&config, &rates, args)
         }
-    };
-
-    for (name, alpha_cards) in alphaspel_cards {
-        dl_cards
-            .entry(name)
-            .and_modify(|e| e.extend(alpha_cards.clone()))
-            .or_insert(alpha_cards);
     }
-    let compared_cards =
-        compare_cards_and_save_to_file(scryfall_cards_path, dl_cards.clone()).await;
-
-    let _ = generate_nice_price_page(compared_cards, "../", "index.html", CONFIG.nice_price_diff);
-
-    Ok(())
 }
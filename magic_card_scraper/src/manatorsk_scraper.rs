@@ -0,0 +1,476 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use log::{error, info};
+use regex::Regex;
+use rust_decimal::Decimal;
+use scraper::{ElementRef, Html, Selector};
+
+use crate::cards::{
+    cardname::CardName, collector_number::CollectorNumber, condition::Condition,
+    currency::Currency, language::Language, price::Price, setname::SetName, vendor::Vendor,
+    vendorcard::VendorCard,
+};
+use crate::error::{ParseError, ScrapeError};
+use crate::http_client::HttpClient;
+use crate::scrape_report::{ScrapeReport, SkipReason, SkippedProduct};
+use crate::utilities::constants::{MANATORSK_CARDS_FOLDER, MANATORSK_CARDS_PREFIX};
+use crate::vendor_scraper::VendorScraper;
+
+/// Product titles look like `Ragavan, Nimble Pilferer (Modern Horizons 2) #138 [Foil]`
+const TITLE_PATTERN: &str =
+    r"^(?P<name>.+?) \((?P<set>[^()]+)\)(?: #(?P<number>\S+))?(?P<tags>(?: \[[^\]]+\])*)$";
+
+/// Scrapes the Magic singles of the Manatorsk web shop, a WooCommerce store
+#[derive(Debug)]
+pub struct ManatorskScraper {
+    base_url: String,
+    client: HttpClient,
+    title_pattern: Regex,
+    /// Max number of pages fetched at the same time
+    concurrency: usize,
+}
+
+impl ManatorskScraper {
+    pub fn new(base_url: &str, client: HttpClient, concurrency: usize) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            client,
+            title_pattern: Regex::new(TITLE_PATTERN).unwrap(),
+            concurrency,
+        }
+    }
+
+    fn page_url(&self, page: u32) -> String {
+        format!(
+            "{}/produkt-kategori/magic-singles/page/{}/",
+            self.base_url, page
+        )
+    }
+
+    async fn fetch_page(&self, page: u32) -> Result<String, ScrapeError> {
+        let url = self.page_url(page);
+        info!("Fetching cards from {}", url);
+        self.client
+            .get(&url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| ScrapeError::request(&url, e))?
+            .text()
+            .await
+            .map_err(|e| ScrapeError::request(&url, e))
+    }
+
+    /// Highest page number in the pagination, 1 if there is no pagination
+    fn get_page_count(&self, document: &Html) -> u32 {
+        let selector = Selector::parse(".woocommerce-pagination .page-numbers").unwrap();
+        document
+            .select(&selector)
+            .filter_map(|element| element.text().collect::<String>().trim().parse().ok())
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// The cards on a fetched page, with a report of the products that were skipped
+    fn get_cards_from_page(&self, document: &Html) -> (Vec<VendorCard>, ScrapeReport) {
+        let mut report = ScrapeReport::new(Vendor::Manatorsk);
+        report.page_fetched();
+        let selector = Selector::parse("ul.products li.product").unwrap();
+        let cards = document
+            .select(&selector)
+            .filter_map(|product| match self.get_card_from_html(product) {
+                Ok(card) => {
+                    report.product_parsed();
+                    Some(card)
+                }
+                Err(skipped) => {
+                    report.product_skipped(skipped);
+                    None
+                }
+            })
+            .collect();
+        (cards, report)
+    }
+
+    fn get_card_from_html(&self, product: ElementRef) -> Result<VendorCard, SkippedProduct> {
+        let title = product
+            .select(&Selector::parse(".woocommerce-loop-product__title").unwrap())
+            .next()
+            .ok_or_else(|| SkippedProduct::new(SkipReason::BadName, "", "No product title found"))?
+            .text()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let skipped = |reason, message: String| SkippedProduct::new(reason, &title, message);
+
+        let stock_text = product
+            .select(&Selector::parse(".stock").unwrap())
+            .next()
+            .ok_or_else(|| {
+                skipped(
+                    SkipReason::Other,
+                    format!("No stock information found for {}", title),
+                )
+            })?
+            .text()
+            .collect::<String>();
+        if stock_text.contains("Slut i lager") {
+            return Err(skipped(
+                SkipReason::SoldOut,
+                format!("{} is sold out", title),
+            ));
+        }
+        let stock = stock_text
+            .replace("i lager", "")
+            .trim()
+            .parse::<i8>()
+            .map_err(|e| {
+                skipped(
+                    SkipReason::Other,
+                    format!("Failed to parse stock '{}': {}", stock_text.trim(), e),
+                )
+            })?;
+
+        let captures = self.title_pattern.captures(&title).ok_or_else(|| {
+            skipped(
+                SkipReason::UnknownSet,
+                format!("Unable to find the set of {}", title),
+            )
+        })?;
+        let tags: Vec<String> = captures["tags"]
+            .split(']')
+            .map(|tag| tag.trim().trim_start_matches('[').trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+
+        if captures["name"].contains("Token") || captures["set"].contains("Tokens") {
+            return Err(skipped(SkipReason::Token, format!("{} is a token", title)));
+        }
+        let condition = tags
+            .iter()
+            .find_map(|tag| Condition::from_swedish(tag))
+            .unwrap_or_default();
+        let language = tags
+            .iter()
+            .find_map(|tag| Language::from_swedish(tag))
+            .unwrap_or_default();
+
+        // Sale prices have the old price in a del tag and the current one in an ins tag
+        let price_text = product
+            .select(&Selector::parse(".price ins .amount, .price > .amount").unwrap())
+            .next()
+            .ok_or_else(|| {
+                skipped(
+                    SkipReason::BadPrice,
+                    format!("No price found for {}", title),
+                )
+            })?
+            .text()
+            .collect::<String>();
+        let price =
+            parse_price(&price_text).map_err(|e| skipped(SkipReason::BadPrice, e.to_string()))?;
+
+        let image_url = product
+            .select(&Selector::parse("img").unwrap())
+            .next()
+            .and_then(|img| img.value().attr("src"))
+            .unwrap_or_default()
+            .to_string();
+
+        let collector_number = captures
+            .name("number")
+            .and_then(|number| CollectorNumber::new(number.as_str()).ok());
+
+        let name = CardName::new(captures["name"].to_string())
+            .map_err(|e| skipped(SkipReason::BadName, e.to_string()))?;
+        let set = SetName::new(captures["set"].to_string())
+            .map_err(|e| skipped(SkipReason::UnknownSet, e.to_string()))?;
+
+        Ok(VendorCard {
+            name,
+            vendor: Vendor::Manatorsk,
+            foil: tags.iter().any(|tag| tag.contains("foil")),
+            image_url,
+            extended_art: tags.iter().any(|tag| tag == "extended art"),
+            prerelease: tags.iter().any(|tag| tag == "prerelease" || tag == "promo"),
+            showcase: tags.iter().any(|tag| tag == "showcase"),
+            set,
+            price,
+            trade_in_price: 0,
+            current_stock: stock,
+            max_stock: stock,
+            collector_number,
+            condition,
+            language,
+        })
+    }
+
+    pub async fn scrape_cards(
+        &self,
+    ) -> Result<(HashMap<CardName, Vec<VendorCard>>, ScrapeReport), ScrapeError> {
+        let mut report = ScrapeReport::new(Vendor::Manatorsk);
+        // Html is not Send, so it has to be dropped before the next await
+        let (page_count, (mut cards, first_page_report)) = {
+            let first_page = Html::parse_document(&self.fetch_page(1).await?);
+            (
+                self.get_page_count(&first_page),
+                self.get_cards_from_page(&first_page),
+            )
+        };
+        report.merge(first_page_report);
+        info!("Found {} manatorsk pages", page_count);
+
+        let other_pages = stream::iter(2..=page_count)
+            .map(|page| async move {
+                let cards = self
+                    .fetch_page(page)
+                    .await
+                    .map(|html| self.get_cards_from_page(&Html::parse_document(&html)));
+                (page, cards)
+            })
+            .buffered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await;
+        for (page, page_cards) in other_pages {
+            match page_cards {
+                Ok((page_cards, page_report)) => {
+                    cards.extend(page_cards);
+                    report.merge(page_report);
+                }
+                Err(e) => {
+                    error!("Error fetching page {}: {}", self.page_url(page), e);
+                    report.page_failed(&self.page_url(page), e);
+                }
+            }
+        }
+        report.finish();
+
+        let mut grouped_cards: HashMap<CardName, Vec<VendorCard>> = HashMap::new();
+        for card in cards {
+            grouped_cards
+                .entry(card.name.clone())
+                .or_default()
+                .push(card);
+        }
+        Ok((grouped_cards, report))
+    }
+}
+
+/// Parses prices such as `1 049,00 kr`, `1.049,00 kr` or `1.049 kr`. A `.` is a thousands
+/// separator when there is a decimal comma or three digits follow it
+fn parse_price(price: &str) -> Result<Price, ParseError> {
+    let cleaned: String = price
+        .replace("kr", "")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let groups: Vec<&str> = cleaned.split('.').collect();
+    let thousands_separated = cleaned.contains(',')
+        || (groups.len() > 1 && groups[1..].iter().all(|group| group.len() == 3));
+    let cleaned = if thousands_separated {
+        cleaned.replace('.', "")
+    } else {
+        cleaned
+    };
+    let amount = cleaned
+        .replace(',', ".")
+        .parse::<Decimal>()
+        .map_err(|e| ParseError::field("price", price.trim(), e))?;
+    Ok(Price::new(amount, Currency::SEK))
+}
+
+#[async_trait]
+impl VendorScraper for ManatorskScraper {
+    fn name(&self) -> &'static str {
+        "manatorsk"
+    }
+
+    fn vendor(&self) -> Vendor {
+        Vendor::Manatorsk
+    }
+
+    fn snapshot_folder(&self) -> &'static str {
+        MANATORSK_CARDS_FOLDER
+    }
+
+    fn snapshot_prefix(&self) -> &'static str {
+        MANATORSK_CARDS_PREFIX
+    }
+
+    async fn scrape(
+        &self,
+    ) -> Result<(HashMap<CardName, Vec<VendorCard>>, ScrapeReport), ScrapeError> {
+        self.scrape_cards().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scrape_report::SkippedProducts;
+    use crate::test::helpers::test_http_client;
+    use rust_decimal_macros::dec;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn parse_fixture(html: &str) -> (Vec<VendorCard>, ScrapeReport) {
+        let scraper = ManatorskScraper::new("fake", test_http_client(), 10);
+        scraper.get_cards_from_page(&Html::parse_document(html))
+    }
+
+    #[test]
+    fn test_card_parser() {
+        init();
+        let (cards, report) = parse_fixture(include_str!("test/manatorsk_cards_page.html"));
+
+        let first_card = VendorCard {
+            name: CardName::new("Lightning Bolt".to_string()).unwrap(),
+            vendor: Vendor::Manatorsk,
+            foil: false,
+            image_url: "https://manatorsk.se/wp-content/uploads/2024/11/m11-149-300x300.jpg"
+                .to_string(),
+            extended_art: false,
+            prerelease: false,
+            showcase: false,
+            set: SetName::new("Magic 2011".to_string()).unwrap(),
+            price: Price::new(dec!(25.0), Currency::SEK),
+            trade_in_price: 0,
+            current_stock: 4,
+            max_stock: 4,
+            collector_number: Some(CollectorNumber::new("149").unwrap()),
+            condition: Condition::NearMint,
+            language: Language::English,
+        };
+
+        // Sold out and token cards are skipped
+        assert_eq!(cards.len(), 10);
+        assert_eq!(report.parsed, 10);
+        assert_eq!(
+            report.skipped,
+            vec![
+                SkippedProducts {
+                    reason: SkipReason::Token,
+                    count: 1,
+                    samples: vec!["Goblin Token (Core Set 2019 Tokens) #4".to_string()],
+                },
+                SkippedProducts {
+                    reason: SkipReason::SoldOut,
+                    count: 1,
+                    samples: vec!["Thoughtseize (Theros) #107".to_string()],
+                },
+            ]
+        );
+        assert_eq!(cards[0], first_card);
+
+        let ragavan = &cards[1];
+        assert!(ragavan.foil);
+        assert_eq!(ragavan.price, Price::new(dec!(1049.0), Currency::SEK));
+        assert_eq!(ragavan.set.raw, "Modern Horizons 2");
+
+        let one_ring = &cards[2];
+        assert!(one_ring.showcase);
+        assert_eq!(one_ring.price, Price::new(dec!(749.0), Currency::SEK));
+
+        assert_eq!(
+            cards[3].name.almost_raw,
+            "Delver of Secrets // Insectile Aberration"
+        );
+        assert_eq!(cards[3].current_stock, 12);
+        assert_eq!(cards[3].max_stock, 12);
+
+        let sheoldred = &cards[4];
+        assert!(sheoldred.prerelease && sheoldred.foil);
+        assert!(sheoldred.collector_number.is_none());
+
+        assert!(cards[5].extended_art);
+        assert!(cards[6].foil);
+        assert!(cards[7].collector_number.is_none());
+        assert_eq!(cards[8].name.almost_raw, "Brainstorm");
+        assert_eq!(cards[8].language, Language::German);
+        assert_eq!(cards[9].price, Price::new(dec!(19.5), Currency::SEK));
+    }
+
+    #[test]
+    fn test_parse_price() {
+        let amounts: Vec<Decimal> = [
+            "45,00 kr",
+            "1 049,00 kr",
+            "1.200,00 kr",
+            "1.200 kr",
+            "2.345.000 kr",
+            "12.50 kr",
+        ]
+        .iter()
+        .map(|price| parse_price(price).unwrap().amount)
+        .collect();
+
+        assert_eq!(
+            amounts,
+            vec![
+                dec!(45),
+                dec!(1049),
+                dec!(1200),
+                dec!(1200),
+                dec!(2345000),
+                dec!(12.5)
+            ]
+        );
+        assert!(parse_price("Ring oss").is_err());
+    }
+
+    #[test]
+    fn test_get_page_count() {
+        let scraper = ManatorskScraper::new("fake", test_http_client(), 10);
+        let with_pages = Html::parse_document(include_str!("test/manatorsk_cards_page.html"));
+        let without_pages = Html::parse_document(include_str!(
+            "test/manatorsk_cards_page_no_extra_pages.html"
+        ));
+
+        assert_eq!(scraper.get_page_count(&with_pages), 3);
+        assert_eq!(scraper.get_page_count(&without_pages), 1);
+    }
+
+    #[tokio::test]
+    async fn test_scrape_cards() {
+        init();
+        let mut server = std::thread::spawn(|| mockito::Server::new())
+            .join()
+            .unwrap();
+        let url = server.url();
+        let mock = server
+            .mock("GET", "/produkt-kategori/magic-singles/page/1/")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(include_str!("test/manatorsk_cards_page.html"))
+            .create();
+        let other_pages = server
+            .mock(
+                "GET",
+                mockito::Matcher::Regex(r"^/produkt-kategori/magic-singles/page/[23]/$".into()),
+            )
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body(include_str!(
+                "test/manatorsk_cards_page_no_extra_pages.html"
+            ))
+            .expect(2)
+            .create();
+
+        let scraper = ManatorskScraper::new(&url, test_http_client(), 10);
+        let (result, report) = scraper.scrape_cards().await.unwrap();
+
+        mock.assert();
+        other_pages.assert();
+        assert_eq!(result.len(), 10);
+        assert_eq!(report.pages_fetched, 3);
+        assert_eq!(report.pages_failed, 0);
+        assert_eq!(
+            result[&CardName::new("Lightning Bolt".to_string()).unwrap()].len(),
+            3
+        );
+    }
+}
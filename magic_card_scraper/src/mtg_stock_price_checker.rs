@@ -1,6 +1,9 @@
+use async_trait::async_trait;
+use chrono::Utc;
 use log::{debug, info, warn};
 use reqwest::header::{HeaderMap, HeaderValue};
-use reqwest::Client;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -11,18 +14,22 @@ use url::form_urlencoded;
 use crate::cards::cardname::CardName;
 use crate::cards::currency::Currency;
 use crate::cards::price::Price;
+use crate::cards::scryfallcard::ScryfallCard;
 use crate::cards::setname::SetName;
+use crate::error::PriceError;
+use crate::http_client::HttpClient;
+use crate::price_source::{PriceQuote, PriceSource};
 
 #[derive(Clone)]
 pub struct MtgPriceFetcher {
-    client: Client,
+    client: HttpClient,
     base_url: String,
     cache: Arc<Mutex<HashMap<CardName, Vec<MtgStocksCard>>>>,
     // cache: Arc<RwLock<HashMap<CardName, Price>>>,
 }
 
 impl MtgPriceFetcher {
-    pub fn new(client: Client, base_url: String) -> Self {
+    pub fn new(client: HttpClient, base_url: String) -> Self {
         MtgPriceFetcher {
             client,
             base_url,
@@ -34,7 +41,7 @@ impl MtgPriceFetcher {
         &self,
         card_name: CardName,
         card_set: SetName,
-    ) -> Result<Price, Box<dyn std::error::Error>> {
+    ) -> Result<Price, PriceError> {
         info!("Fetching live prices for card: {}", card_name.almost_raw);
 
         // Check cache first
@@ -70,81 +77,100 @@ impl MtgPriceFetcher {
 
         match price {
             Some(card) => Ok(card.price),
-            None => Err("Card not found in the set".into()),
+            None => Err(PriceError::NotFound {
+                source: self.name(),
+                card: card_name.almost_raw,
+                set: card_set.raw,
+            }),
         }
     }
 
-    async fn get_card_search_uri(
-        &self,
-        card_name: &str,
-    ) -> Result<String, Box<dyn std::error::Error>> {
+    async fn get_card_search_uri(&self, card_name: &str) -> Result<String, PriceError> {
         info!("Fetching slug for {}", card_name);
         let card_name_utf8 =
             form_urlencoded::byte_serialize(card_name.as_bytes()).collect::<String>();
-        let url = format!(
-            "https://api.mtgstocks.com/search/autocomplete/{}",
-            card_name_utf8
-        );
+        let url = format!("{}/search/autocomplete/{}", self.base_url, card_name_utf8);
 
         // https://api.mtgstocks.com/search/autocomplete/16455-giant-growth
 
         let response = self
             .client
-            .get(&url)
-            .headers(self.get_headers())
-            .send()
-            .await?;
+            .get_with_headers(&url, self.get_headers())
+            .await
+            .map_err(|e| PriceError::request(&url, e))?;
 
         if response.status().is_success() {
-            let response = response.text().await?;
-            let data: Vec<Value> = serde_json::from_str(&response)?;
-            let token_objects: Vec<&Value> = data
+            let response = response
+                .text()
+                .await
+                .map_err(|e| PriceError::request(&url, e))?;
+            let data: Vec<Value> =
+                serde_json::from_str(&response).map_err(|e| PriceError::response(&url, e))?;
+            let slug = data
                 .iter()
                 .filter(|obj| !obj["name"].as_str().unwrap_or("").contains("Token"))
-                .collect();
+                .find_map(|obj| obj["slug"].as_str());
 
-            if !token_objects.is_empty() {
-                return Ok(token_objects[0]["slug"].as_str().unwrap().to_string());
+            if let Some(slug) = slug {
+                return Ok(slug.to_string());
             }
         }
 
-        Err("Card not found on MTGStocks".into())
+        Err(PriceError::NotFound {
+            source: self.name(),
+            card: card_name.to_string(),
+            set: "any set".to_string(),
+        })
     }
 
     async fn get_list_of_prices_for_card(
         &self,
         slug: &str,
-    ) -> Result<Vec<MtgStocksCard>, Box<dyn std::error::Error>> {
+    ) -> Result<Vec<MtgStocksCard>, PriceError> {
         info!("Fetching prices for {}", slug);
         let slug_utf8 = form_urlencoded::byte_serialize(slug.as_bytes()).collect::<String>();
         let url = format!("{}/prints/{}", self.base_url, slug_utf8);
 
         let response = self
             .client
-            .get(&url)
-            .headers(self.get_headers())
-            .send()
-            .await?;
-
-        if response.status().is_success() {
-            let response = response.text().await?;
-            let data: Value = serde_json::from_str(&response)?;
+            .get_with_headers(&url, self.get_headers())
+            .await
+            .map_err(|e| PriceError::request(&url, e))?;
+
+        let status = response.status();
+        let response = response
+            .text()
+            .await
+            .map_err(|e| PriceError::request(&url, e))?;
+        if status.is_success() {
+            let data: Value =
+                serde_json::from_str(&response).map_err(|e| PriceError::response(&url, e))?;
 
             // Add data for all sets
             let prices: Vec<MtgStocksCard> = data["sets"]
                 .as_array()
-                .unwrap()
+                .ok_or_else(|| PriceError::response(&url, "No sets in the prints"))?
                 .iter()
-                .map(|obj| MtgStocksCard {
-                    set: SetName::new(obj["set_name"].to_string()).unwrap(),
+                .filter_map(|obj| match SetName::new(obj["set_name"].to_string()) {
+                    Ok(set) => Some((set, obj)),
+                    Err(e) => {
+                        warn!("Skipping MTGStocks print of {}: {}", slug, e);
+                        None
+                    }
+                })
+                .map(|(set, obj)| MtgStocksCard {
+                    set,
                     price: Price::new(
-                        match obj["latest_price_mkm"].as_f64() {
+                        match obj["latest_price_mkm"]
+                            .as_f64()
+                            .and_then(Decimal::from_f64)
+                        {
                             Some(price) => price,
                             None => {
                                 warn!(
                                     "latest_price_mkm avg not found for card: {} in set: {}, using default value of 0.0", slug, obj["set_name"]
                                 );
-                                0.0
+                                Decimal::ZERO
                             }
                         },
                         Currency::EUR,
@@ -155,41 +181,47 @@ impl MtgPriceFetcher {
             // prices.extend(cardlist);
             Ok(prices)
         } else {
-            Err(response.text().await?.into())
+            Err(PriceError::response(
+                &url,
+                format!("{}: {}", status, response),
+            ))
         }
     }
 
     fn get_headers(&self) -> HeaderMap {
         let mut headers = HeaderMap::new();
-        headers.insert("accept", HeaderValue::from_static("text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7"));
-        headers.insert(
-            "accept-language",
-            HeaderValue::from_static("en-US,en;q=0.9,sv-SE;q=0.8,sv;q=0.7,nb;q=0.6"),
-        );
-        headers.insert("User-Agent", HeaderValue::from_static("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_10_1) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/39.0.2171.95 Safari/537.36"));
-        headers.insert("cache-control", HeaderValue::from_static("max-age=0"));
-        headers.insert("priority", HeaderValue::from_static("u=0, i"));
         headers.insert(
-            "sec-ch-ua",
-            HeaderValue::from_static(
-                "\"Chromium\";v=\"123\", \"Not;A=Brand\";v=\"24\", \"Google Chrome\";v=\"123\"",
-            ),
+            "accept",
+            HeaderValue::from_static("application/json, */*;q=0.8"),
         );
-        headers.insert("sec-ch-ua-mobile", HeaderValue::from_static("?0"));
         headers.insert(
-            "sec-ch-ua-platform",
-            HeaderValue::from_static("\"Windows\""),
+            "accept-language",
+            HeaderValue::from_static("en-US,en;q=0.9,sv-SE;q=0.8,sv;q=0.7,nb;q=0.6"),
         );
-        headers.insert("sec-fetch-dest", HeaderValue::from_static("document"));
-        headers.insert("sec-fetch-mode", HeaderValue::from_static("navigate"));
-        headers.insert("sec-fetch-site", HeaderValue::from_static("none"));
-        headers.insert("sec-fetch-user", HeaderValue::from_static("?1"));
-        headers.insert("upgrade-insecure-requests", HeaderValue::from_static("1"));
-        headers.insert("credentials", HeaderValue::from_static("omit"));
         headers
     }
 }
 
+/// MTGStocks only has the MCM price of the non-foil printing, which is used for foils too
+#[async_trait]
+impl PriceSource for MtgPriceFetcher {
+    fn name(&self) -> &'static str {
+        "mtgstocks"
+    }
+
+    async fn quote(&self, printing: &ScryfallCard, _foil: bool) -> Result<PriceQuote, PriceError> {
+        let price = self
+            .get_live_card_price(printing.name.clone(), printing.set.clone())
+            .await?;
+        Ok(PriceQuote {
+            price,
+            source: self.name().to_string(),
+            timestamp: Utc::now(),
+            foil: false,
+        })
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct MtgStocksCard {
     set: SetName,
@@ -198,21 +230,78 @@ pub struct MtgStocksCard {
 
 #[cfg(test)]
 mod tests {
-    use crate::utilities::constants::MTG_STOCKS_BASE_URL;
-
     use super::*;
-    use mockito;
-    use tokio;
+    use crate::test::helpers::test_http_client;
+    use rust_decimal_macros::dec;
+
+    const AUTOCOMPLETE_RESPONSE: &str = r#"
+    [
+        {"name": "Giant Growth Token", "slug": "99999-giant-growth-token"},
+        {"name": "Giant Growth", "slug": "16455-giant-growth"}
+    ]"#;
 
     #[tokio::test]
     async fn test_get_card_search_uri() {
-        let client = Client::new();
-        let fetcher = MtgPriceFetcher::new(client, MTG_STOCKS_BASE_URL.to_string());
-        let card_name = "Giant Growth";
-        match fetcher.get_card_search_uri(card_name).await {
-            Ok(slug) => assert!(!slug.is_empty()),
-            Err(err) => panic!("Unexpected error: {:?}", err),
-        }
+        let mut server = std::thread::spawn(|| mockito::Server::new())
+            .join()
+            .unwrap();
+        let mock = server
+            .mock("GET", "/search/autocomplete/Giant+Growth")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(AUTOCOMPLETE_RESPONSE)
+            .create();
+
+        let fetcher = MtgPriceFetcher::new(test_http_client(), server.url());
+        let slug = fetcher.get_card_search_uri("Giant Growth").await.unwrap();
+
+        mock.assert();
+        assert_eq!(slug, "16455-giant-growth");
+    }
+
+    #[tokio::test]
+    async fn test_quote() {
+        let mut server = std::thread::spawn(|| mockito::Server::new())
+            .join()
+            .unwrap();
+        let search = server
+            .mock("GET", "/search/autocomplete/Giant+Growth")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(AUTOCOMPLETE_RESPONSE)
+            .create();
+        let prints = server
+            .mock("GET", "/prints/16455-giant-growth")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"sets": [{"set_name": "Beta", "latest_price_mkm": 11.0}]}"#)
+            .expect(1)
+            .create();
+
+        let fetcher = MtgPriceFetcher::new(test_http_client(), server.url());
+        let printing = ScryfallCard {
+            name: CardName::new("Giant Growth".to_string()).unwrap(),
+            set: SetName::new("Beta".to_string()).unwrap(),
+            set_code: None,
+            image_url: "".to_string(),
+            prices: crate::cards::scryfallcard::Prices {
+                eur: None,
+                eur_foil: None,
+                ..Default::default()
+            },
+            collector_number: None,
+        };
+
+        let quote = fetcher.quote(&printing, true).await.unwrap();
+        // The second quote comes from the cache
+        let cached_quote = fetcher.quote(&printing, false).await.unwrap();
+
+        search.assert();
+        prints.assert();
+        assert_eq!(quote.price, Price::new(dec!(11.0), Currency::EUR));
+        assert_eq!(quote.source, "mtgstocks");
+        assert!(!quote.foil);
+        assert_eq!(cached_quote.price, quote.price);
     }
 
     #[tokio::test]
@@ -241,7 +330,7 @@ mod tests {
             .with_body(mock_response)
             .create();
 
-        let client = reqwest::Client::builder().build().unwrap();
+        let client = test_http_client();
 
         // Override the base URL for testing
         // let test_url = format!("{}/prints", mock_url);
@@ -258,12 +347,12 @@ mod tests {
                 assert!(!prices.is_empty());
                 assert_eq!(prices.len(), 3); // One for Alpha and two from the sets array
                 assert_eq!(prices[0].set, SetName::new("Beta".to_string()).unwrap());
-                assert_eq!(prices[0].price, Price::new(11.0, Currency::EUR));
+                assert_eq!(prices[0].price, Price::new(dec!(11.0), Currency::EUR));
                 assert_eq!(
                     prices[1].set,
                     SetName::new("Unlimited".to_string()).unwrap()
                 );
-                assert_eq!(prices[1].price, Price::new(9.5, Currency::EUR));
+                assert_eq!(prices[1].price, Price::new(dec!(9.5), Currency::EUR));
             }
             Err(err) => panic!("Unexpected error: {:?}", err),
         }
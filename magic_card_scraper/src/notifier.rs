@@ -0,0 +1,703 @@
+//! Sends a digest of the new nice prices and watchlist hits of a run to a webhook, by email
+//! or to a file, leaving out what the previous run already sent.
+
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use log::{error, info};
+use serde_json::json;
+
+use crate::cards::{
+    compared_card::ComparedCard, condition::Condition, language::Language, vendorcard::VendorCard,
+};
+use crate::error::{NotifyError, StorageError};
+use crate::http_client::HttpClient;
+use crate::utilities::config::{Config, SmtpConfig};
+use crate::watchlist::WatchlistHit;
+
+/// Discord rejects webhook messages longer than 2000 characters
+const WEBHOOK_MAX_CHARS: usize = 2000;
+
+/// A line of the digest
+#[derive(Debug, PartialEq, Clone)]
+pub struct DigestItem {
+    /// The card, vendor, condition, language and price, so a card that gets cheaper is sent
+    /// again
+    pub key: String,
+    pub text: String,
+}
+
+/// The nice prices and watchlist hits of a run
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Digest {
+    pub nice_prices: Vec<DigestItem>,
+    pub watchlist_hits: Vec<DigestItem>,
+}
+
+fn card_key(kind: &str, card: &VendorCard) -> String {
+    format!(
+        "{}|{}|{}|{}|{}|{}|{}|{}",
+        kind,
+        card.vendor,
+        card.name.cleaned,
+        card.set.cleaned,
+        card.foil,
+        card.condition,
+        card.language,
+        card.price.amount.normalize()
+    )
+}
+
+/// Copies that aren't near mint and english say what they are
+fn card_title(card: &VendorCard) -> String {
+    let foil_text = if card.foil { " (Foil)" } else { "" };
+    let copy_text = match (card.condition, card.language) {
+        (Condition::NearMint, Language::English) => String::new(),
+        (condition, Language::English) => format!(" ({})", condition),
+        (condition, language) => format!(" ({}, {})", condition, language),
+    };
+    format!(
+        "{}{}{} / {} at {}",
+        card.name.raw, foil_text, copy_text, card.set.raw, card.vendor
+    )
+}
+
+impl Digest {
+    pub fn new(nice_price_cards: &[&ComparedCard], watchlist_hits: &[WatchlistHit]) -> Self {
+        let mut nice_prices: Vec<DigestItem> = nice_price_cards
+            .iter()
+            .map(|card| {
+                let mcm_price = card
+                    .reference_price()
+                    .map(|price| price.to_string())
+                    .unwrap_or_else(|| "unknown".to_string());
+                DigestItem {
+                    key: card_key("nice", &card.vendor_card),
+                    text: format!(
+                        "{}: {}, MCM {} ({:.2} SEK)",
+                        card_title(&card.vendor_card),
+                        card.vendor_card.price,
+                        mcm_price,
+                        card.price_difference_to_cheapest_vendor_card
+                    ),
+                }
+            })
+            .collect();
+        nice_prices.sort_by(|a, b| a.text.cmp(&b.text));
+        let watchlist_hits = watchlist_hits
+            .iter()
+            .map(|hit| DigestItem {
+                key: card_key("watchlist", &hit.card),
+                text: format!(
+                    "{}: {}, max {} ({} in stock)",
+                    card_title(&hit.card),
+                    hit.card.price,
+                    hit.entry.max_price,
+                    hit.card.current_stock
+                ),
+            })
+            .collect();
+        Self {
+            nice_prices,
+            watchlist_hits,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nice_prices.is_empty() && self.watchlist_hits.is_empty()
+    }
+
+    pub fn keys(&self) -> HashSet<String> {
+        self.watchlist_hits
+            .iter()
+            .chain(&self.nice_prices)
+            .map(|item| item.key.clone())
+            .collect()
+    }
+
+    /// The digest without the items whose key was already sent
+    pub fn without(&self, sent: &HashSet<String>) -> Self {
+        let unsent = |items: &[DigestItem]| {
+            items
+                .iter()
+                .filter(|item| !sent.contains(&item.key))
+                .cloned()
+                .collect()
+        };
+        Self {
+            nice_prices: unsent(&self.nice_prices),
+            watchlist_hits: unsent(&self.watchlist_hits),
+        }
+    }
+
+    pub fn subject(&self) -> String {
+        format!(
+            "MTG-prizes: {} watchlist hits and {} new nice prices",
+            self.watchlist_hits.len(),
+            self.nice_prices.len()
+        )
+    }
+
+    /// Plain text body, watchlist hits first
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for (title, items) in [
+            ("Watchlist hits", &self.watchlist_hits),
+            ("New nice prices", &self.nice_prices),
+        ] {
+            if items.is_empty() {
+                continue;
+            }
+            text.push_str(&format!("{}:\n", title));
+            for item in items {
+                text.push_str(&format!("- {}\n", item.text));
+            }
+            text.push('\n');
+        }
+        text.trim_end().to_string()
+    }
+
+    /// The text cut at a line so it fits in `max_chars`, saying how many lines were left out
+    pub fn text_limited(&self, max_chars: usize) -> String {
+        let text = self.text();
+        if text.chars().count() <= max_chars {
+            return text;
+        }
+        let lines: Vec<&str> = text.lines().collect();
+        let mut kept = String::new();
+        for (index, line) in lines.iter().enumerate() {
+            let more = format!("... and {} more lines", lines.len() - index);
+            if kept.chars().count() + line.chars().count() + more.chars().count() + 2 > max_chars {
+                kept.push_str(&more);
+                return kept;
+            }
+            kept.push_str(line);
+            kept.push('\n');
+        }
+        kept
+    }
+}
+
+/// Somewhere the digest can be sent
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Lowercase name used in logs
+    fn name(&self) -> &'static str;
+
+    async fn notify(&self, digest: &Digest) -> Result<(), NotifyError>;
+}
+
+/// Posts the digest as JSON to a Discord or Slack webhook. Discord reads `content`, Slack `text`
+pub struct WebhookNotifier {
+    client: HttpClient,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(client: HttpClient, url: &str) -> Self {
+        Self {
+            client,
+            url: url.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn notify(&self, digest: &Digest) -> Result<(), NotifyError> {
+        let subject = digest.subject();
+        // The bold markers and the newline take 5 characters
+        let body_limit = WEBHOOK_MAX_CHARS - subject.chars().count() - 5;
+        let text = format!("**{}**\n{}", subject, digest.text_limited(body_limit));
+        let body = json!({ "content": text, "text": text });
+        self.client
+            .post_json(&self.url, &body)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|source| NotifyError::Request {
+                url: self.url.clone(),
+                source,
+            })?;
+        Ok(())
+    }
+}
+
+/// Emails the digest
+pub struct SmtpNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    host: String,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl SmtpNotifier {
+    pub fn new(settings: &SmtpConfig) -> Result<Self, NotifyError> {
+        let builder = if settings.starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host).map_err(
+                |source| NotifyError::Smtp {
+                    host: settings.host.clone(),
+                    source,
+                },
+            )?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+        };
+        let builder = builder.port(settings.port);
+        let builder = if settings.username.is_empty() {
+            builder
+        } else {
+            builder.credentials(Credentials::new(
+                settings.username.clone(),
+                settings.password.clone(),
+            ))
+        };
+        let to = settings
+            .to
+            .iter()
+            .map(|address| parse_mailbox(address))
+            .collect::<Result<Vec<Mailbox>, _>>()?;
+        Ok(Self {
+            transport: builder.build(),
+            host: settings.host.clone(),
+            from: parse_mailbox(&settings.from)?,
+            to,
+        })
+    }
+}
+
+fn parse_mailbox(address: &str) -> Result<Mailbox, NotifyError> {
+    address.parse().map_err(|source| NotifyError::Address {
+        address: address.to_string(),
+        source,
+    })
+}
+
+#[async_trait]
+impl Notifier for SmtpNotifier {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    async fn notify(&self, digest: &Digest) -> Result<(), NotifyError> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(digest.subject());
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message.body(digest.text()).map_err(NotifyError::Message)?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|source| NotifyError::Smtp {
+                host: self.host.clone(),
+                source,
+            })?;
+        Ok(())
+    }
+}
+
+/// Appends the digest to a file, or prints it when there is no file
+pub struct FileNotifier {
+    path: Option<PathBuf>,
+}
+
+impl FileNotifier {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    pub fn stdout() -> Self {
+        Self { path: None }
+    }
+}
+
+#[async_trait]
+impl Notifier for FileNotifier {
+    fn name(&self) -> &'static str {
+        match self.path {
+            Some(_) => "file",
+            None => "stdout",
+        }
+    }
+
+    async fn notify(&self, digest: &Digest) -> Result<(), NotifyError> {
+        let content = format!("{}\n\n{}\n\n", digest.subject(), digest.text());
+        match &self.path {
+            Some(path) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| StorageError::io(parent, e))?;
+                }
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| StorageError::io(path, e))?;
+                file.write_all(content.as_bytes())
+                    .map_err(|e| StorageError::io(path, e))?;
+            }
+            None => print!("{}", content),
+        }
+        Ok(())
+    }
+}
+
+/// The notifiers a digest is sent to
+#[derive(Default)]
+pub struct Notifiers {
+    notifiers: Vec<Box<dyn Notifier>>,
+}
+
+impl Notifiers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The notifiers set up in `[notifications]`, none when nothing is configured. The webhook
+    /// is posted to through the given client
+    pub fn from_config(config: &Config, client: &HttpClient) -> Result<Self, NotifyError> {
+        let settings = &config.notifications;
+        let mut notifiers = Self::new();
+        if !settings.webhook_url.is_empty() {
+            notifiers.push(Box::new(WebhookNotifier::new(
+                client.clone(),
+                &settings.webhook_url,
+            )));
+        }
+        if !settings.smtp.host.is_empty() {
+            notifiers.push(Box::new(SmtpNotifier::new(&settings.smtp)?));
+        }
+        match settings.file.as_str() {
+            "" => {}
+            "-" => notifiers.push(Box::new(FileNotifier::stdout())),
+            path => notifiers.push(Box::new(FileNotifier::new(path))),
+        }
+        Ok(notifiers)
+    }
+
+    pub fn push(&mut self, notifier: Box<dyn Notifier>) {
+        self.notifiers.push(notifier);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notifiers.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.notifiers
+            .iter()
+            .map(|notifier| notifier.name())
+            .collect()
+    }
+
+    /// Sends the digest to every notifier, returning how many succeeded. Failures are logged
+    pub async fn notify_all(&self, digest: &Digest) -> usize {
+        let mut sent = 0;
+        for notifier in &self.notifiers {
+            match notifier.notify(digest).await {
+                Ok(()) => {
+                    info!("Sent the digest with {}", notifier.name());
+                    sent += 1;
+                }
+                Err(e) => error!("Failed to send the digest with {}: {}", notifier.name(), e),
+            }
+        }
+        sent
+    }
+}
+
+/// The keys of the previous run's digest, none if there was no previous run
+pub fn load_sent_keys(path: &Path) -> HashSet<String> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_sent_keys(path: &Path, keys: &HashSet<String>) -> Result<(), StorageError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| StorageError::io(parent, e))?;
+    }
+    let mut keys: Vec<&String> = keys.iter().collect();
+    keys.sort();
+    let json = serde_json::to_string_pretty(&keys).map_err(|e| StorageError::json(path, e))?;
+    fs::write(path, json).map_err(|e| StorageError::io(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use rust_decimal_macros::dec;
+    use tempfile::tempdir;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::cards::compared_card::MatchQuality;
+    use crate::cards::{currency::Currency, price::Price};
+    use crate::test::helpers::{
+        reaper_king_scryfall_card_expensive, reaper_king_vendor_card_cheap,
+        reaper_king_vendor_card_expensive, test_http_client,
+    };
+    use crate::watchlist::WatchlistEntry;
+
+    fn compared_card(vendor_card: VendorCard) -> ComparedCard {
+        ComparedCard {
+            vendor_card,
+            scryfall_card: reaper_king_scryfall_card_expensive(),
+            price_difference_to_cheapest_vendor_card: dec!(-12.5),
+            reference_price: None,
+            match_quality: MatchQuality::Unknown,
+        }
+    }
+
+    fn watchlist_hit() -> WatchlistHit {
+        WatchlistHit {
+            entry: WatchlistEntry {
+                name: reaper_king_vendor_card_cheap().name,
+                set: None,
+                collector_number: None,
+                foil: false,
+                max_price: Price::new(dec!(60), Currency::SEK),
+            },
+            card: reaper_king_vendor_card_cheap(),
+        }
+    }
+
+    fn digest() -> Digest {
+        let card = compared_card(reaper_king_vendor_card_expensive());
+        Digest::new(&[&card], &[watchlist_hit()])
+    }
+
+    struct RecordingNotifier {
+        digests: Arc<Mutex<Vec<Digest>>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl Notifier for RecordingNotifier {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        async fn notify(&self, digest: &Digest) -> Result<(), NotifyError> {
+            if self.fail {
+                return Err(
+                    StorageError::io("digests.txt", std::io::Error::other("disk full")).into(),
+                );
+            }
+            self.digests.lock().unwrap().push(digest.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_digest_text() {
+        let digest = digest();
+
+        assert_eq!(
+            digest.subject(),
+            "MTG-prizes: 1 watchlist hits and 1 new nice prices"
+        );
+        assert_eq!(
+            digest.text(),
+            "Watchlist hits:\n\
+             - Reaper King / Mystery booster retail edition foils at Dragonslair: 50.00 SEK, max 60.00 SEK (1 in stock)\n\
+             \n\
+             New nice prices:\n\
+             - Reaper King / Shadowmoor at Dragonslair: 100.00 SEK, MCM €1.00 (-12.50 SEK)"
+        );
+        let limited = digest.text_limited(60);
+        assert!(limited.chars().count() <= 60);
+        assert!(limited.ends_with("... and 4 more lines"));
+    }
+
+    #[test]
+    fn test_digest_leaves_out_what_was_sent() {
+        let previous = digest();
+        let mut cheaper = reaper_king_vendor_card_expensive();
+        cheaper.price = Price::new(dec!(90), Currency::SEK);
+        let cheaper = compared_card(cheaper);
+
+        let same = previous.without(&previous.keys());
+        let repriced = Digest::new(&[&cheaper], &[watchlist_hit()]).without(&previous.keys());
+
+        assert!(same.is_empty());
+        assert!(repriced.watchlist_hits.is_empty());
+        assert_eq!(repriced.nice_prices.len(), 1);
+        assert!(repriced.nice_prices[0].text.contains("90.00 SEK"));
+
+        let mut played_german = watchlist_hit();
+        played_german.card.condition = Condition::Played;
+        played_german.card.language = Language::German;
+        let other_copy = Digest::new(&[], &[played_german]).without(&previous.keys());
+        assert_eq!(other_copy.watchlist_hits.len(), 1);
+        assert!(other_copy.watchlist_hits[0]
+            .text
+            .starts_with("Reaper King (PL, German) / "));
+    }
+
+    #[test]
+    fn test_sent_keys_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notifications").join("sent.json");
+
+        assert!(load_sent_keys(&path).is_empty());
+        save_sent_keys(&path, &digest().keys()).unwrap();
+        assert_eq!(load_sent_keys(&path), digest().keys());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_posts_json() {
+        let mut server = std::thread::spawn(|| mockito::Server::new())
+            .join()
+            .unwrap();
+        let mock = server
+            .mock("POST", "/webhook")
+            .match_header("content-type", "application/json")
+            .match_body(mockito::Matcher::Regex(
+                r#""content":"\*\*MTG-prizes: 1 watchlist hits.*Reaper King"#.to_string(),
+            ))
+            .with_status(204)
+            .create();
+        // Retried 3 times before giving up
+        let failing_mock = server
+            .mock("POST", "/broken")
+            .with_status(500)
+            .expect(4)
+            .create();
+
+        let notifier =
+            WebhookNotifier::new(test_http_client(), &format!("{}/webhook", server.url()));
+        let broken = WebhookNotifier::new(test_http_client(), &format!("{}/broken", server.url()));
+
+        notifier.notify(&digest()).await.unwrap();
+        assert!(broken.notify(&digest()).await.is_err());
+        mock.assert();
+        failing_mock.assert();
+    }
+
+    /// Accepts one SMTP session and returns the commands and the message it got
+    async fn mock_smtp_server() -> (u16, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut received = String::new();
+            let mut in_data = false;
+            writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                received.push_str(&line);
+                received.push('\n');
+                let reply: &[u8] = if in_data {
+                    if line != "." {
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250 localhost\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line == "QUIT" {
+                    writer.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+            received
+        });
+        (port, handle)
+    }
+
+    #[tokio::test]
+    async fn test_smtp_sends_mail() {
+        let (port, server) = mock_smtp_server().await;
+        let notifier = SmtpNotifier::new(&SmtpConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            starttls: false,
+            from: "mtg-prz <prices@example.com>".to_string(),
+            to: vec!["team@example.com".to_string()],
+            ..SmtpConfig::default()
+        })
+        .unwrap();
+
+        notifier.notify(&digest()).await.unwrap();
+        drop(notifier);
+        let received = server.await.unwrap();
+
+        assert!(received.contains("MAIL FROM:<prices@example.com>"));
+        assert!(received.contains("RCPT TO:<team@example.com>"));
+        assert!(received.contains("Subject: MTG-prizes: 1 watchlist hits and 1 new nice prices"));
+        assert!(received.contains("Reaper King / Shadowmoor at Dragonslair"));
+    }
+
+    #[tokio::test]
+    async fn test_file_notifier_appends() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("digests.txt");
+        let notifier = FileNotifier::new(&path);
+
+        notifier.notify(&digest()).await.unwrap();
+        notifier.notify(&digest()).await.unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.matches("New nice prices:").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_notify_all_counts_successes() {
+        let digests = Arc::new(Mutex::new(Vec::new()));
+        let mut notifiers = Notifiers::new();
+        notifiers.push(Box::new(RecordingNotifier {
+            digests: digests.clone(),
+            fail: false,
+        }));
+        notifiers.push(Box::new(RecordingNotifier {
+            digests: digests.clone(),
+            fail: true,
+        }));
+
+        assert_eq!(notifiers.notify_all(&digest()).await, 1);
+        assert_eq!(*digests.lock().unwrap(), vec![digest()]);
+    }
+
+    #[test]
+    fn test_notifiers_from_config() {
+        let mut config = Config::default();
+        let client = test_http_client();
+        assert!(Notifiers::from_config(&config, &client).unwrap().is_empty());
+
+        config.notifications.webhook_url = "http://localhost/webhook".to_string();
+        config.notifications.file = "-".to_string();
+        config.notifications.smtp.host = "localhost".to_string();
+        config.notifications.smtp.from = "prices@example.com".to_string();
+        config.notifications.smtp.to = vec!["team@example.com".to_string()];
+        assert_eq!(
+            Notifiers::from_config(&config, &client).unwrap().names(),
+            vec!["webhook", "smtp", "stdout"]
+        );
+
+        config.notifications.smtp.from = "not an address".to_string();
+        assert!(Notifiers::from_config(&config, &client).is_err());
+    }
+}
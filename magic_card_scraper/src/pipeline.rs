@@ -0,0 +1,798 @@
+//! The steps of the scrape → compare → render pipeline, each saving its result as a snapshot.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use log::{error, info, warn};
+
+use crate::cards::{
+    cardname::CardName, compared_card::ComparedCard, currency::ExchangeRates,
+    scryfallcard::ScryfallCard, vendor::Vendor, vendorcard::VendorCard,
+};
+use crate::comparer::Comparer;
+use crate::dragonslair_scraper::DragonslairScraper;
+use crate::error::{PipelineError, PriceError, StorageError};
+use crate::exchange_rate_fetcher::ExchangeRateFetcher;
+use crate::html_generator::{filter_nice_price_cards, generate_nice_price_page, NicePriceSettings};
+use crate::http_client::HttpClient;
+use crate::notifier::{load_sent_keys, save_sent_keys, Digest, Notifiers};
+use crate::price_history::{PriceHistory, PriceTrends, RunId};
+use crate::scrape_report::scrape_report_path;
+use crate::scryfall_scraper::ScryfallScraper;
+use crate::snapshot_diff::{
+    html_generator::generate_page_content as generate_diff_page, SnapshotDiff,
+};
+use crate::tradable_cards::collection_importer::CollectionImporter;
+use crate::tradable_cards::html_generator::generate_page_content;
+use crate::tradable_cards::sell_list::save_sell_lists;
+use crate::tradable_cards::tradable_card_comparer::TradableCardsComparer;
+use crate::utilities::config::Config;
+use crate::utilities::constants::{
+    CARDMARKET_PREFIX, COMPARED_CARDS_DIR, COMPARED_FILE_PREFIX, DRAGONSLAIR_URL,
+    EXCHANGE_RATE_FILE, FRANKFURTER_URL, MTG_STOCKS_BASE_URL, SCRYFALL_CARDS_DIR,
+    SCRYFALL_FILE_PREFIX, SELL_LISTS_DIR, SELL_LIST_PREFIX, SENT_NOTIFICATIONS_FILE,
+    TRADABLE_CARDS_DIR, TRADABLE_CARDS_PREFIX, WATCHLIST_REPORTS_DIR, WATCHLIST_REPORT_PREFIX,
+};
+use crate::utilities::file_management::{
+    get_file_taken_on, get_newest_file, list_snapshots, load_from_json_file, save_to_file,
+    snapshot_taken_at,
+};
+use crate::utilities::string_manipulators::date_time_as_string;
+use crate::vendor_scraper::{VendorRegistry, VendorScraper};
+use crate::watchlist::{Watchlist, WatchlistHit, WatchlistReport};
+
+/// Path to a new timestamped snapshot file, e.g. `../dragonslair_cards/dl_cards_10_03_2025-17-33.json`
+pub fn snapshot_path(data_dir: &str, folder_name: &str, file_prefix: &str) -> String {
+    format!(
+        "{}/{}/{}{}.json",
+        data_dir,
+        folder_name,
+        file_prefix,
+        date_time_as_string(None, None)
+    )
+}
+
+/// The exchange rates for the rest of the run: the live rates when `exchange_rates.live` is
+/// on, otherwise the built in ones, with the configured SEK rate on top
+pub async fn load_exchange_rates(config: &Config, client: &HttpClient) -> ExchangeRates {
+    let rates = if config.exchange_rates.live {
+        ExchangeRateFetcher::new(
+            client.clone(),
+            FRANKFURTER_URL,
+            format!("{}/{}", config.output.data_dir, EXCHANGE_RATE_FILE),
+        )
+        .get_rates()
+        .await
+    } else {
+        ExchangeRates::default()
+    };
+    let rates = config.with_configured_rates(rates);
+    info!("Using {} SEK per EUR", rates.eur_to_sek());
+    rates
+}
+
+/// Opens the price history. None when the history is turned off or can't be opened, which
+/// is logged but doesn't stop the run
+pub fn open_history(config: &Config) -> Option<PriceHistory> {
+    if !config.history.enabled {
+        return None;
+    }
+    let path = config.history_path();
+    match PriceHistory::open(&path) {
+        Ok(history) => Some(history),
+        Err(e) => {
+            warn!("Not using the price history in {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Opens the price history and adds this run to it, see `open_history`
+pub fn start_history_run(config: &Config) -> Option<(PriceHistory, RunId)> {
+    let history = open_history(config)?;
+    match history.start_run(Utc::now()) {
+        Ok(run) => Some((history, run)),
+        Err(e) => {
+            warn!("Not recording this run in the price history: {}", e);
+            None
+        }
+    }
+}
+
+/// The recorded trends of the cards on the nice price page, none without a price history
+pub fn load_price_trends(
+    config: &Config,
+    rates: &ExchangeRates,
+    history: Option<&PriceHistory>,
+    compared_cards: &HashMap<CardName, Vec<ComparedCard>>,
+) -> PriceTrends {
+    let Some(history) = history else {
+        return PriceTrends::default();
+    };
+    let nice_price_cards =
+        filter_nice_price_cards(compared_cards, &config.nice_price_settings(rates));
+    let since = Utc::now() - Duration::days(config.history.trend_days);
+    match PriceTrends::load(
+        history,
+        nice_price_cards.iter().map(|card| &card.vendor_card),
+        since,
+    ) {
+        Ok(trends) => trends,
+        Err(e) => {
+            error!("Failed to load the price trends: {}", e);
+            PriceTrends::default()
+        }
+    }
+}
+
+/// Records part of the run in the price history, if there is one. Failures are logged
+pub fn record_history<F>(history_run: &mut Option<(PriceHistory, RunId)>, what: &str, record: F)
+where
+    F: FnOnce(&mut PriceHistory, RunId) -> Result<usize, StorageError>,
+{
+    if let Some((history, run)) = history_run {
+        match record(history, *run) {
+            Ok(count) => info!("Recorded {} {} in the price history", count, what),
+            Err(e) => error!("Failed to record {} in the price history: {}", what, e),
+        }
+    }
+}
+
+/// Matches the watchlist, if one is configured, against the vendor cards and saves the
+/// hits as a json report. Failures are logged and give no hits
+pub fn check_watchlist(
+    config: &Config,
+    rates: &ExchangeRates,
+    vendor_cards: &HashMap<CardName, Vec<VendorCard>>,
+) -> Vec<WatchlistHit> {
+    let path = &config.watchlist.path;
+    if path.is_empty() {
+        return Vec::new();
+    }
+    let watchlist = match Watchlist::load(path) {
+        Ok(watchlist) => watchlist,
+        Err(e) => {
+            error!("Failed to load the watchlist {}: {}", path, e);
+            return Vec::new();
+        }
+    };
+    let report = watchlist.report(vendor_cards, &config.watchlist.listing_filter(), rates);
+    info!(
+        "{} hits for the {} cards on the watchlist",
+        report.hits.len(),
+        report.entries
+    );
+    let report_path = snapshot_path(
+        &config.output.data_dir,
+        WATCHLIST_REPORTS_DIR,
+        WATCHLIST_REPORT_PREFIX,
+    );
+    if let Err(e) = save_to_file(&report_path, &report) {
+        error!(
+            "Failed to save the watchlist report to {}: {}",
+            report_path, e
+        );
+    }
+    report.hits
+}
+
+/// The hits in the newest watchlist report, none if no watchlist is configured
+pub fn load_watchlist_hits(config: &Config) -> Vec<WatchlistHit> {
+    if config.watchlist.path.is_empty() {
+        return Vec::new();
+    }
+    match given_or_newest_file(
+        None,
+        &config.output.data_dir,
+        WATCHLIST_REPORTS_DIR,
+        WATCHLIST_REPORT_PREFIX,
+    )
+    .and_then(|path| load_from_json_file::<WatchlistReport>(&path))
+    {
+        Ok(report) => report.hits,
+        Err(e) => {
+            warn!("No watchlist hits to show: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Sends the watchlist hits and nice prices that are new since the run before to the
+/// configured notifiers. What was sent is only remembered when a notifier got it
+pub async fn send_notifications(
+    config: &Config,
+    client: &HttpClient,
+    rates: &ExchangeRates,
+    compared_cards: &HashMap<CardName, Vec<ComparedCard>>,
+    watchlist_hits: &[WatchlistHit],
+) {
+    let notifiers = match Notifiers::from_config(config, client) {
+        Ok(notifiers) if notifiers.is_empty() => return,
+        Ok(notifiers) => notifiers,
+        Err(e) => {
+            error!("Failed to set up the notifiers: {}", e);
+            return;
+        }
+    };
+    let nice_price_cards =
+        filter_nice_price_cards(compared_cards, &config.nice_price_settings(rates));
+    let digest = Digest::new(&nice_price_cards, watchlist_hits);
+    let sent_path = Path::new(&config.output.data_dir).join(SENT_NOTIFICATIONS_FILE);
+    let new_items = digest.without(&load_sent_keys(&sent_path));
+    if new_items.is_empty() {
+        info!("Nothing new to notify about since the run before");
+    } else if notifiers.notify_all(&new_items).await == 0 {
+        return;
+    }
+    if let Err(e) = save_sent_keys(&sent_path, &digest.keys()) {
+        error!(
+            "Failed to save the sent notifications to {}: {}",
+            sent_path.display(),
+            e
+        );
+    }
+}
+
+/// Scrapes the vendor and saves the cards to the given path, and the scrape report to the
+/// `reports` folder next to it
+pub async fn scrape_vendor_and_save_to_file(
+    scraper: &dyn VendorScraper,
+    cards_path: &str,
+) -> Result<HashMap<CardName, Vec<VendorCard>>, PipelineError> {
+    let start_time = chrono::prelude::Local::now();
+    info!("Starting {} scrape at {}", scraper.vendor(), start_time);
+
+    let (vendor_cards, report) = scraper.scrape().await?;
+
+    save_to_file(cards_path, &vendor_cards)?;
+    info!("{}", report);
+    let report_path = scrape_report_path(cards_path);
+    if let Err(e) = report.save(&report_path) {
+        error!(
+            "Failed to save the scrape report to {}: {}",
+            report_path.display(),
+            e
+        );
+    }
+
+    let end_time = chrono::prelude::Local::now();
+    info!(
+        "{} scrape started at: {}. Finished at: {}. Took: {} seconds and with {} cards on cards_path: {}",
+        scraper.vendor(),
+        start_time,
+        end_time,
+        (end_time - start_time).num_seconds(),
+        vendor_cards.len(),
+        cards_path
+    );
+    Ok(vendor_cards)
+}
+
+/// Scrapes the vendor if it is enabled, otherwise loads its newest snapshot
+pub async fn get_vendor_cards(
+    config: &Config,
+    scraper: &dyn VendorScraper,
+) -> Result<HashMap<CardName, Vec<VendorCard>>, PipelineError> {
+    let data_dir = config.output.data_dir.as_str();
+    if config.vendors.is_enabled(scraper.name())? {
+        scrape_vendor_and_save_to_file(
+            scraper,
+            &snapshot_path(
+                data_dir,
+                scraper.snapshot_folder(),
+                scraper.snapshot_prefix(),
+            ),
+        )
+        .await
+    } else {
+        Ok(load_cards_or_empty(
+            None,
+            data_dir,
+            scraper.snapshot_folder(),
+            scraper.snapshot_prefix(),
+        ))
+    }
+}
+
+/// Downloads the Scryfall bulk data and saves the cards to the given path. Returns the cards and
+/// when the bulk data was downloaded
+pub async fn get_scryfall_cards_and_save_to_file(
+    config: &Config,
+    client: &HttpClient,
+    scryfall_cards_path: &str,
+) -> Result<(HashMap<CardName, Vec<ScryfallCard>>, DateTime<Utc>), PriceError> {
+    let start_time = chrono::prelude::Local::now();
+    info!("Starting at {}", start_time);
+
+    let scryfall_scraper = ScryfallScraper::new(
+        None,
+        client.clone(),
+        Some(format!("{}/{}", config.output.data_dir, SCRYFALL_CARDS_DIR)),
+    );
+    let (path_to_raw_scryfall_cards_file, downloaded_at) =
+        scryfall_scraper.get_raw_scryfall_cards_file().await?;
+    let scryfall_cards =
+        scryfall_scraper.convert_raw_to_domain_cards(&path_to_raw_scryfall_cards_file)?;
+
+    save_to_file(scryfall_cards_path, &scryfall_cards)?;
+
+    let end_time = chrono::prelude::Local::now();
+    info!(
+        "Scryfall scrape started at: {}. Finished at: {}. Took: {} seconds and with {} cards on scryfall_cards_path: {}",
+        start_time,
+        end_time,
+        (end_time - start_time).num_seconds(),
+        scryfall_cards.len(),
+        scryfall_cards_path
+    );
+    Ok((scryfall_cards, downloaded_at))
+}
+
+/// Compares the vendor cards to the Scryfall cards, taken at `scryfall_as_of`, and saves the
+/// result to the given path
+pub async fn compare_cards_and_save_to_file(
+    config: &Config,
+    client: &HttpClient,
+    rates: &ExchangeRates,
+    scryfall_cards: HashMap<CardName, Vec<ScryfallCard>>,
+    scryfall_as_of: DateTime<Utc>,
+    vendor_cards: HashMap<CardName, Vec<VendorCard>>,
+    cards_path: &str,
+) -> Result<HashMap<CardName, Vec<ComparedCard>>, StorageError> {
+    let start_time = chrono::prelude::Local::now();
+    info!("Starting at {}", start_time);
+
+    let comparer = Comparer::new(
+        scryfall_cards,
+        scryfall_as_of,
+        MTG_STOCKS_BASE_URL.to_string(),
+        client,
+        config.comparer_settings(rates),
+    );
+    let compared_cards = comparer.compare_vendor_cards(vendor_cards).await;
+
+    save_to_file(cards_path, &compared_cards)?;
+
+    let end_time = chrono::prelude::Local::now();
+    info!(
+        "Comparing cards started at: {}. Finished at: {}. Took: {} seconds and with {} cards in dir: {}",
+        start_time,
+        end_time,
+        (end_time - start_time).num_seconds(),
+        compared_cards.len(),
+        cards_path
+    );
+    Ok(compared_cards)
+}
+
+/// Returns the path if one was given, otherwise the newest snapshot in the folder
+pub fn given_or_newest_file(
+    path: Option<&str>,
+    data_dir: &str,
+    folder_name: &str,
+    file_prefix: &str,
+) -> Result<String, StorageError> {
+    match path {
+        Some(path) => Ok(path.to_string()),
+        None => path_string(&get_newest_file(
+            &format!("{}/{}", data_dir, folder_name),
+            file_prefix,
+        )?),
+    }
+}
+
+fn path_string(path: &Path) -> Result<String, StorageError> {
+    path.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| StorageError::Corrupt {
+            what: "file name",
+            value: path.display().to_string(),
+        })
+}
+
+/// Returns the path if one was given, the newest snapshot taken on the day if a
+/// `YYYY-MM-DD` date was given, otherwise the newest snapshot in the folder
+pub fn given_dated_or_newest_file(
+    path_or_date: Option<&str>,
+    data_dir: &str,
+    folder_name: &str,
+    file_prefix: &str,
+) -> Result<String, StorageError> {
+    match path_or_date.map(|value| (value, NaiveDate::parse_from_str(value, "%Y-%m-%d"))) {
+        Some((_, Ok(date))) => path_string(&get_file_taken_on(
+            &format!("{}/{}", data_dir, folder_name),
+            file_prefix,
+            date,
+        )?),
+        Some((path, Err(_))) => Ok(path.to_string()),
+        None => given_or_newest_file(None, data_dir, folder_name, file_prefix),
+    }
+}
+
+/// The snapshot taken just before the given one, which must be in the folder
+pub fn previous_snapshot(
+    path: &str,
+    data_dir: &str,
+    folder_name: &str,
+    file_prefix: &str,
+) -> Result<String, StorageError> {
+    let folder = format!("{}/{}", data_dir, folder_name);
+    let no_snapshot = |reason: String| StorageError::NoSnapshot {
+        folder: folder.clone(),
+        reason,
+    };
+    let file_name = Path::new(path).file_name();
+    let snapshots = list_snapshots(&folder, file_prefix)?;
+    let index = snapshots
+        .iter()
+        .position(|(snapshot, _)| snapshot.file_name() == file_name)
+        .ok_or_else(|| no_snapshot(format!("{} is not in the folder", path)))?;
+    let (previous, _) = index
+        .checked_sub(1)
+        .map(|index| &snapshots[index])
+        .ok_or_else(|| no_snapshot(format!("none taken before {}", path)))?;
+    path_string(previous)
+}
+
+/// Loads a card snapshot
+pub fn load_cards<T>(path: &str) -> Result<HashMap<CardName, Vec<T>>, StorageError>
+where
+    T: serde::de::DeserializeOwned,
+{
+    load_from_json_file::<HashMap<CardName, Vec<T>>>(path)
+}
+
+/// Loads a card snapshot, logging and returning no cards if it can't be found or read
+pub fn load_cards_or_empty<T>(
+    path: Option<&str>,
+    data_dir: &str,
+    folder_name: &str,
+    file_prefix: &str,
+) -> HashMap<CardName, Vec<T>>
+where
+    T: serde::de::DeserializeOwned,
+{
+    match given_or_newest_file(path, data_dir, folder_name, file_prefix)
+        .and_then(|path| load_cards(&path))
+    {
+        Ok(cards) => cards,
+        Err(e) => {
+            error!("Failed to load cards from {}: {}", folder_name, e);
+            HashMap::new()
+        }
+    }
+}
+
+/// When a Scryfall snapshot was saved, from its name or else from when the file was written
+pub fn scryfall_snapshot_taken_at(path: &str) -> Result<DateTime<Utc>, StorageError> {
+    if let Some(taken_at) = snapshot_taken_at(Path::new(path), SCRYFALL_FILE_PREFIX)
+        .and_then(|taken_at| taken_at.and_local_timezone(Local).earliest())
+    {
+        return Ok(taken_at.with_timezone(&Utc));
+    }
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(DateTime::<Utc>::from)
+        .map_err(|e| StorageError::io(path, e))
+}
+
+/// Loads the newest Scryfall snapshot and when it was saved, logging and returning no cards if
+/// it can't be found or read
+pub fn load_scryfall_cards_or_empty(
+    data_dir: &str,
+) -> (HashMap<CardName, Vec<ScryfallCard>>, DateTime<Utc>) {
+    let snapshot = given_or_newest_file(None, data_dir, SCRYFALL_CARDS_DIR, SCRYFALL_FILE_PREFIX)
+        .and_then(|path| Ok((load_cards(&path)?, scryfall_snapshot_taken_at(&path)?)));
+    match snapshot {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            error!("Failed to load cards from {}: {}", SCRYFALL_CARDS_DIR, e);
+            // Without cards no price is dated
+            (HashMap::new(), Utc::now())
+        }
+    }
+}
+
+/// The newest snapshot of every vendor, merged into one map
+pub fn load_newest_vendor_cards(
+    config: &Config,
+    client: &HttpClient,
+) -> HashMap<CardName, Vec<VendorCard>> {
+    let registry = VendorRegistry::with_default_vendors(config, client);
+    let mut vendor_cards = HashMap::new();
+    for scraper in registry.iter() {
+        let cards = load_cards_or_empty(
+            None,
+            &config.output.data_dir,
+            scraper.snapshot_folder(),
+            scraper.snapshot_prefix(),
+        );
+        vendor_cards = merge_vendor_cards(vendor_cards, cards);
+    }
+    vendor_cards
+}
+
+/// Merges the cards of two vendors into one map
+pub fn merge_vendor_cards(
+    mut cards: HashMap<CardName, Vec<VendorCard>>,
+    other_cards: HashMap<CardName, Vec<VendorCard>>,
+) -> HashMap<CardName, Vec<VendorCard>> {
+    for (name, vendor_cards) in other_cards {
+        cards
+            .entry(name)
+            .and_modify(|e| e.extend(vendor_cards.clone()))
+            .or_insert(vendor_cards);
+    }
+    cards
+}
+
+/// Diffs two vendor snapshots and saves the diff as json and html
+pub fn diff_snapshots_and_save_to_file(
+    old_path: &str,
+    new_path: &str,
+    path: &str,
+    html_path: &str,
+    rates: &ExchangeRates,
+) -> Result<SnapshotDiff, StorageError> {
+    let old_cards = load_cards::<VendorCard>(old_path)?;
+    let new_cards = load_cards::<VendorCard>(new_path)?;
+    let diff = SnapshotDiff::between(old_path, &old_cards, new_path, &new_cards, rates);
+
+    save_to_file(path, &diff)?;
+    if let Some(parent) = Path::new(html_path).parent() {
+        fs::create_dir_all(parent).map_err(|e| StorageError::io(parent, e))?;
+    }
+    fs::write(html_path, generate_diff_page(&diff, rates))
+        .map_err(|e| StorageError::io(html_path, e))?;
+    info!(
+        "Diffed {} against {}: {} new, {} sold out and {} repriced cards, saved to {} and {}",
+        new_path,
+        old_path,
+        diff.new_in_stock.len(),
+        diff.sold_out.len(),
+        diff.repriced.len(),
+        path,
+        html_path
+    );
+    Ok(diff)
+}
+
+/// Writes the nice price page to the given file path
+pub fn write_nice_price_page(
+    compared_cards: HashMap<CardName, Vec<ComparedCard>>,
+    page_path: &str,
+    settings: &NicePriceSettings,
+    trends: &PriceTrends,
+    watchlist_hits: &[WatchlistHit],
+) -> Result<(), StorageError> {
+    let page_path = Path::new(page_path);
+    let output_dir = page_path
+        .parent()
+        .and_then(|dir| dir.to_str())
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or(".");
+    let html_page_name = page_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| {
+            StorageError::io(
+                page_path,
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the page path must end with a file name",
+                ),
+            )
+        })?;
+    generate_nice_price_page(
+        compared_cards,
+        output_dir,
+        html_page_name,
+        settings,
+        trends,
+        watchlist_hits,
+    )
+}
+
+/// Where the trade-in step saves the tradable cards and lists the cards Dragonslair doesn't take
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeInPaths {
+    /// The tradable cards as json
+    pub tradable_cards: String,
+    /// The tradable cards page
+    pub html: String,
+    /// Cardmarket stock upload csv
+    pub cardmarket: String,
+    /// Sell list csv
+    pub sell_list: String,
+}
+
+impl TradeInPaths {
+    /// Paths in the data dir, the snapshots stamped with the current time
+    pub fn new(config: &Config) -> Self {
+        let data_dir = config.output.data_dir.as_str();
+        let csv_path = |prefix| {
+            Path::new(&snapshot_path(data_dir, SELL_LISTS_DIR, prefix))
+                .with_extension("csv")
+                .to_string_lossy()
+                .to_string()
+        };
+        Self {
+            tradable_cards: snapshot_path(data_dir, TRADABLE_CARDS_DIR, TRADABLE_CARDS_PREFIX),
+            html: format!("{}/{}", data_dir, config.output.tradable_cards_page),
+            cardmarket: csv_path(CARDMARKET_PREFIX),
+            sell_list: csv_path(SELL_LIST_PREFIX),
+        }
+    }
+}
+
+/// Finds the cards of a collection export, e.g. from Delver Lens, that Dragonslair wants and
+/// saves them as json and html. The cards Dragonslair doesn't take are listed for sale
+pub async fn compare_delver_lense_cards_to_dl(
+    config: &Config,
+    client: &HttpClient,
+    rates: &ExchangeRates,
+    delver_lense_path: &str,
+    dl_cards: HashMap<CardName, Vec<VendorCard>>,
+    paths: &TradeInPaths,
+) -> Result<(), PipelineError> {
+    let start_time = chrono::prelude::Local::now();
+    info!("Starting delver lense compare at {}", start_time);
+    let scryfall_cards = load_cards_or_empty(
+        None,
+        &config.output.data_dir,
+        SCRYFALL_CARDS_DIR,
+        SCRYFALL_FILE_PREFIX,
+    );
+    let imported = CollectionImporter::with_scryfall_cards(&scryfall_cards)
+        .get_personal_cards_from_file(delver_lense_path)?;
+    info!(
+        "Read {} cards from the {} export, skipped {} rows",
+        imported.cards.len(),
+        imported.format,
+        imported.skipped.len()
+    );
+    let cards = imported.cards;
+
+    let comparer = TradableCardsComparer::new(
+        DragonslairScraper::new(
+            DRAGONSLAIR_URL,
+            None,
+            client.clone(),
+            config.concurrency.dragonslair,
+        ),
+        config.trade_in_settings(),
+    );
+
+    let (tradable_cards, unwanted_cards) = comparer
+        .get_tradable_and_unwanted_cards(cards, dl_cards.clone())
+        .await?;
+    save_to_file(&paths.tradable_cards, &tradable_cards)?;
+
+    save_sell_lists(
+        &unwanted_cards,
+        &scryfall_cards,
+        &config.sell_list_settings(rates),
+        &paths.cardmarket,
+        &paths.sell_list,
+    )?;
+    info!(
+        "Listed {} cards Dragonslair doesn't take for sale in {}",
+        unwanted_cards.len(),
+        paths.sell_list
+    );
+
+    let html = generate_page_content(&tradable_cards, rates);
+
+    fs::write(&paths.html, html).map_err(|e| StorageError::io(&paths.html, e))?;
+
+    let end_time = chrono::prelude::Local::now();
+    info!(
+        "Comparing delver lense cards to DL cards started at: {}. Finished at: {}. Took: {} seconds and with cards in dir: {}",
+        start_time,
+        end_time,
+        (end_time - start_time).num_seconds(),
+        paths.tradable_cards
+    );
+    Ok(())
+}
+
+/// Scrapes or loads every source, compares the cards and renders the nice price page. Every
+/// request of the run goes through the given client, so the requests to a host are spaced out
+/// across all the steps, and every price is converted with the given rates
+pub async fn run_pipeline(
+    config: &Config,
+    client: &HttpClient,
+    rates: &ExchangeRates,
+) -> Result<(), PipelineError> {
+    let data_dir = config.output.data_dir.as_str();
+    let registry = VendorRegistry::with_default_vendors(config, client);
+    let mut history = start_history_run(config);
+
+    let mut vendor_cards = HashMap::new();
+    let mut dl_cards = HashMap::new();
+    for scraper in registry.iter() {
+        let cards = get_vendor_cards(config, scraper).await?;
+        // Snapshots loaded from an earlier run are already in the history
+        if config.vendors.is_enabled(scraper.name())? {
+            record_history(&mut history, scraper.name(), |history, run| {
+                history.record_vendor_cards(run, &cards)
+            });
+        }
+        if scraper.vendor() == Vendor::Dragonslair {
+            dl_cards = cards.clone();
+        }
+        vendor_cards = merge_vendor_cards(vendor_cards, cards);
+    }
+
+    let watchlist_hits = check_watchlist(config, rates, &vendor_cards);
+
+    if !config.trade_in.delver_lense_path.is_empty() {
+        if let Err(e) = compare_delver_lense_cards_to_dl(
+            config,
+            client,
+            rates,
+            &config.trade_in.delver_lense_path,
+            dl_cards,
+            &TradeInPaths::new(config),
+        )
+        .await
+        {
+            error!("Failed to compare the Delver Lens cards: {}", e);
+        }
+    }
+
+    let (scryfall_cards, scryfall_as_of) = if config.vendors.scryfall {
+        info!("Downloading Scryfall cards...");
+        let (scryfall_cards, downloaded_at) = get_scryfall_cards_and_save_to_file(
+            config,
+            client,
+            &snapshot_path(data_dir, SCRYFALL_CARDS_DIR, SCRYFALL_FILE_PREFIX),
+        )
+        .await?;
+        record_history(&mut history, "Scryfall prices", |history, run| {
+            history.record_scryfall_prices(run, &scryfall_cards)
+        });
+        (scryfall_cards, downloaded_at)
+    } else {
+        load_scryfall_cards_or_empty(data_dir)
+    };
+
+    let compared_cards = compare_cards_and_save_to_file(
+        config,
+        client,
+        rates,
+        scryfall_cards,
+        scryfall_as_of,
+        vendor_cards,
+        &snapshot_path(data_dir, COMPARED_CARDS_DIR, COMPARED_FILE_PREFIX),
+    )
+    .await?;
+    record_history(&mut history, "compared cards", |history, run| {
+        history.record_compared_cards(run, &compared_cards)
+    });
+
+    let trends = load_price_trends(
+        config,
+        rates,
+        history.as_ref().map(|(history, _)| history),
+        &compared_cards,
+    );
+
+    send_notifications(config, client, rates, &compared_cards, &watchlist_hits).await;
+
+    if let Err(e) = generate_nice_price_page(
+        compared_cards,
+        data_dir,
+        &config.output.nice_price_page,
+        &config.nice_price_settings(rates),
+        &trends,
+        &watchlist_hits,
+    ) {
+        error!("Failed to generate the nice price page: {}", e);
+    }
+
+    Ok(())
+}
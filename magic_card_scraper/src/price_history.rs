@@ -0,0 +1,810 @@
+//! Every run's vendor cards, Scryfall prices and compared cards, kept in SQLite so prices can
+//! be followed over time instead of grepping the JSON snapshots.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::Decimal;
+
+use crate::cards::{
+    cardname::CardName, compared_card::ComparedCard, condition::Condition, currency::Currency,
+    language::Language, price::Price, scryfallcard::ScryfallCard, setname::SetName, vendor::Vendor,
+    vendorcard::VendorCard,
+};
+use crate::error::StorageError;
+
+/// Schema changes in order. A database at version `n` has had the first `n` applied, the
+/// version is kept in `PRAGMA user_version`. Only ever append to this list
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE runs (
+        id INTEGER PRIMARY KEY,
+        started_at TEXT NOT NULL
+    );
+    CREATE TABLE vendor_cards (
+        id INTEGER PRIMARY KEY,
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        vendor TEXT NOT NULL,
+        name TEXT NOT NULL,
+        name_raw TEXT NOT NULL,
+        set_name TEXT NOT NULL,
+        set_raw TEXT NOT NULL,
+        collector_number TEXT,
+        foil BOOLEAN NOT NULL,
+        price_amount TEXT NOT NULL,
+        price_currency TEXT NOT NULL,
+        trade_in_price INTEGER NOT NULL,
+        current_stock INTEGER NOT NULL,
+        max_stock INTEGER NOT NULL
+    );
+    CREATE INDEX vendor_cards_printing ON vendor_cards (name, set_name, vendor);
+    CREATE TABLE scryfall_prices (
+        id INTEGER PRIMARY KEY,
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        name TEXT NOT NULL,
+        name_raw TEXT NOT NULL,
+        set_name TEXT NOT NULL,
+        set_raw TEXT NOT NULL,
+        collector_number TEXT,
+        eur TEXT,
+        eur_foil TEXT,
+        usd TEXT,
+        usd_foil TEXT
+    );
+    CREATE INDEX scryfall_prices_printing ON scryfall_prices (name, set_name);
+    CREATE TABLE compared_cards (
+        id INTEGER PRIMARY KEY,
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        vendor TEXT NOT NULL,
+        name TEXT NOT NULL,
+        name_raw TEXT NOT NULL,
+        set_name TEXT NOT NULL,
+        set_raw TEXT NOT NULL,
+        foil BOOLEAN NOT NULL,
+        vendor_price_amount TEXT NOT NULL,
+        vendor_price_currency TEXT NOT NULL,
+        reference_price_amount TEXT,
+        reference_price_currency TEXT,
+        price_source TEXT,
+        match_quality TEXT NOT NULL,
+        price_difference_sek TEXT NOT NULL
+    );
+    CREATE INDEX compared_cards_printing ON compared_cards (name, set_name, vendor);
+"#,
+    // The scrapers dropped played, damaged and foreign copies before these were added
+    r#"
+    ALTER TABLE vendor_cards ADD COLUMN condition TEXT NOT NULL DEFAULT 'NM';
+    ALTER TABLE vendor_cards ADD COLUMN language TEXT NOT NULL DEFAULT 'English';
+    ALTER TABLE compared_cards ADD COLUMN condition TEXT NOT NULL DEFAULT 'NM';
+    ALTER TABLE compared_cards ADD COLUMN language TEXT NOT NULL DEFAULT 'English';
+"#,
+];
+
+/// Id of a run in the history
+pub type RunId = i64;
+
+/// A price at the time of a run
+#[derive(Debug, PartialEq, Clone)]
+pub struct PricePoint {
+    pub timestamp: DateTime<Utc>,
+    pub price: Price,
+}
+
+/// A vendor's price of a printing in one run, and the reference price it was compared to
+#[derive(Debug, PartialEq, Clone)]
+pub struct TrendPoint {
+    pub timestamp: DateTime<Utc>,
+    pub vendor_price: Price,
+    pub reference_price: Option<Price>,
+}
+
+/// The condition and language of the copies a vendor price history is of, near mint english
+/// by default
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct CopyQuality {
+    pub condition: Condition,
+    pub language: Language,
+}
+
+impl CopyQuality {
+    pub fn of(card: &VendorCard) -> Self {
+        Self {
+            condition: card.condition,
+            language: card.language,
+        }
+    }
+}
+
+type TrendKey = (String, String, String, bool, CopyQuality);
+
+/// The recorded trends of the cards on a page, by vendor, printing, finish, condition and
+/// language
+#[derive(Debug, Default)]
+pub struct PriceTrends {
+    trends: HashMap<TrendKey, Vec<TrendPoint>>,
+}
+
+impl PriceTrends {
+    /// The trend of each card since `since`
+    pub fn load<'a>(
+        history: &PriceHistory,
+        cards: impl IntoIterator<Item = &'a VendorCard>,
+        since: DateTime<Utc>,
+    ) -> Result<Self, StorageError> {
+        let mut trends = Self::default();
+        for card in cards {
+            if trends.get(card).is_empty() {
+                trends.insert(card, history.compared_card_trend(card, since)?);
+            }
+        }
+        Ok(trends)
+    }
+
+    pub fn insert(&mut self, card: &VendorCard, trend: Vec<TrendPoint>) {
+        self.trends.insert(Self::key(card), trend);
+    }
+
+    /// The card's trend, oldest first, empty when nothing was recorded
+    pub fn get(&self, card: &VendorCard) -> &[TrendPoint] {
+        self.trends
+            .get(&Self::key(card))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn key(card: &VendorCard) -> TrendKey {
+        (
+            card.vendor.to_string(),
+            card.name.cleaned.clone(),
+            card.set.cleaned.clone(),
+            card.foil,
+            CopyQuality::of(card),
+        )
+    }
+}
+
+/// SQLite store of the prices seen in every run
+pub struct PriceHistory {
+    conn: Connection,
+}
+
+impl PriceHistory {
+    /// Opens the database, creating it and any missing parent directory, and migrates it
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| StorageError::io(parent, e))?;
+        }
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, StorageError> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let mut history = Self { conn };
+        history.migrate()?;
+        Ok(history)
+    }
+
+    /// Number of migrations applied to the database
+    pub fn schema_version(&self) -> Result<usize, StorageError> {
+        let version: i64 = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        Ok(version as usize)
+    }
+
+    fn migrate(&mut self) -> Result<(), StorageError> {
+        let version = self.schema_version()?;
+        if version > MIGRATIONS.len() {
+            return Err(StorageError::SchemaTooNew {
+                found: version,
+                supported: MIGRATIONS.len(),
+            });
+        }
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", (index + 1) as i64)?;
+            tx.commit()?;
+            info!("Migrated price history to schema version {}", index + 1);
+        }
+        Ok(())
+    }
+
+    /// Adds a run that the cards of this run are recorded under
+    pub fn start_run(&self, started_at: DateTime<Utc>) -> Result<RunId, StorageError> {
+        self.conn.execute(
+            "INSERT INTO runs (started_at) VALUES (?1)",
+            params![format_timestamp(started_at)],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Records the cards a vendor had in stock, returns how many were recorded
+    pub fn record_vendor_cards(
+        &mut self,
+        run: RunId,
+        cards: &HashMap<CardName, Vec<VendorCard>>,
+    ) -> Result<usize, StorageError> {
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO vendor_cards (run_id, vendor, name, name_raw, set_name, set_raw,
+                    collector_number, foil, price_amount, price_currency, trade_in_price,
+                    current_stock, max_stock, condition, language)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            )?;
+            for card in cards.values().flatten() {
+                insert.execute(params![
+                    run,
+                    card.vendor.to_string(),
+                    card.name.cleaned,
+                    card.name.raw,
+                    card.set.cleaned,
+                    card.set.raw,
+                    card.collector_number.as_ref().map(|number| number.raw()),
+                    card.foil,
+                    card.price.amount.to_string(),
+                    card.price.currency.code(),
+                    card.trade_in_price,
+                    card.current_stock,
+                    card.max_stock,
+                    card.condition.to_string(),
+                    card.language.to_string(),
+                ])?;
+                count += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Records the Scryfall prices of every printing, returns how many were recorded
+    pub fn record_scryfall_prices(
+        &mut self,
+        run: RunId,
+        cards: &HashMap<CardName, Vec<ScryfallCard>>,
+    ) -> Result<usize, StorageError> {
+        let amount = |price: &Option<Price>| price.map(|price| price.amount.to_string());
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO scryfall_prices (run_id, name, name_raw, set_name, set_raw,
+                    collector_number, eur, eur_foil, usd, usd_foil)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )?;
+            for card in cards.values().flatten() {
+                insert.execute(params![
+                    run,
+                    card.name.cleaned,
+                    card.name.raw,
+                    card.set.cleaned,
+                    card.set.raw,
+                    card.collector_number.as_ref().map(|number| number.raw()),
+                    amount(&card.prices.eur),
+                    amount(&card.prices.eur_foil),
+                    amount(&card.prices.usd),
+                    amount(&card.prices.usd_foil),
+                ])?;
+                count += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Records the result of comparing the vendor cards, returns how many were recorded
+    pub fn record_compared_cards(
+        &mut self,
+        run: RunId,
+        cards: &HashMap<CardName, Vec<ComparedCard>>,
+    ) -> Result<usize, StorageError> {
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO compared_cards (run_id, vendor, name, name_raw, set_name, set_raw,
+                    foil, vendor_price_amount, vendor_price_currency, reference_price_amount,
+                    reference_price_currency, price_source, match_quality, price_difference_sek,
+                    condition, language)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            )?;
+            for card in cards.values().flatten() {
+                let vendor_card = &card.vendor_card;
+                let reference_price = card.reference_price();
+                insert.execute(params![
+                    run,
+                    vendor_card.vendor.to_string(),
+                    vendor_card.name.cleaned,
+                    vendor_card.name.raw,
+                    vendor_card.set.cleaned,
+                    vendor_card.set.raw,
+                    vendor_card.foil,
+                    vendor_card.price.amount.to_string(),
+                    vendor_card.price.currency.code(),
+                    reference_price.map(|price| price.amount.to_string()),
+                    reference_price.map(|price| price.currency.code()),
+                    card.reference_price
+                        .as_ref()
+                        .map(|quote| quote.source.as_str()),
+                    card.match_quality.to_string(),
+                    card.price_difference_to_cheapest_vendor_card.to_string(),
+                    vendor_card.condition.to_string(),
+                    vendor_card.language.to_string(),
+                ])?;
+                count += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// The vendor's price of the printing's copies in the given condition and language in every
+    /// run since `since`, oldest first. The cheapest copy is used when the vendor had several
+    /// in the same run
+    pub fn vendor_price_history(
+        &self,
+        vendor: &Vendor,
+        name: &CardName,
+        set: &SetName,
+        foil: bool,
+        quality: CopyQuality,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<PricePoint>, StorageError> {
+        let mut query = self.conn.prepare(
+            "SELECT runs.started_at, vendor_cards.price_amount, vendor_cards.price_currency
+             FROM vendor_cards JOIN runs ON runs.id = vendor_cards.run_id
+             WHERE vendor_cards.vendor = ?1 AND vendor_cards.name = ?2
+                AND vendor_cards.set_name = ?3 AND vendor_cards.foil = ?4
+                AND vendor_cards.condition = ?5 AND vendor_cards.language = ?6
+                AND runs.started_at >= ?7
+             ORDER BY runs.started_at, runs.id",
+        )?;
+        let rows = query.query_map(
+            params![
+                vendor.to_string(),
+                name.cleaned,
+                set.cleaned,
+                foil,
+                quality.condition.to_string(),
+                quality.language.to_string(),
+                format_timestamp(since)
+            ],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        cheapest_per_run(rows)
+    }
+
+    /// The Scryfall EUR price of the printing in every run since `since`, oldest first. The
+    /// cheapest printing is used when the set has several, runs without a price are left out
+    pub fn scryfall_price_history(
+        &self,
+        name: &CardName,
+        set: &SetName,
+        foil: bool,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<PricePoint>, StorageError> {
+        let column = if foil { "eur_foil" } else { "eur" };
+        let mut query = self.conn.prepare(&format!(
+            "SELECT runs.started_at, scryfall_prices.{column}
+             FROM scryfall_prices JOIN runs ON runs.id = scryfall_prices.run_id
+             WHERE scryfall_prices.name = ?1 AND scryfall_prices.set_name = ?2
+                AND scryfall_prices.{column} IS NOT NULL AND runs.started_at >= ?3
+             ORDER BY runs.started_at, runs.id"
+        ))?;
+        let rows = query.query_map(
+            params![name.cleaned, set.cleaned, format_timestamp(since)],
+            |row| Ok((row.get(0)?, row.get(1)?, Currency::EUR)),
+        )?;
+        cheapest_per_run(rows)
+    }
+
+    /// The compared prices of the vendor's copies of the printing in the card's condition and
+    /// language in every run since `since`, oldest first. The cheapest copy is used when the
+    /// vendor had several in the same run
+    pub fn compared_card_trend(
+        &self,
+        card: &VendorCard,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<TrendPoint>, StorageError> {
+        let mut query = self.conn.prepare(
+            "SELECT runs.started_at, compared_cards.vendor_price_amount,
+                compared_cards.vendor_price_currency, compared_cards.reference_price_amount,
+                compared_cards.reference_price_currency
+             FROM compared_cards JOIN runs ON runs.id = compared_cards.run_id
+             WHERE compared_cards.vendor = ?1 AND compared_cards.name = ?2
+                AND compared_cards.set_name = ?3 AND compared_cards.foil = ?4
+                AND compared_cards.condition = ?5 AND compared_cards.language = ?6
+                AND runs.started_at >= ?7
+             ORDER BY runs.started_at, runs.id",
+        )?;
+        let rows = query.query_map(
+            params![
+                card.vendor.to_string(),
+                card.name.cleaned,
+                card.set.cleaned,
+                card.foil,
+                card.condition.to_string(),
+                card.language.to_string(),
+                format_timestamp(since)
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Currency>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<Currency>>(4)?,
+                ))
+            },
+        )?;
+        let mut points: Vec<TrendPoint> = Vec::new();
+        for row in rows {
+            let (started_at, amount, currency, reference_amount, reference_currency) = row?;
+            let reference_price = match (reference_amount, reference_currency) {
+                (Some(amount), Some(currency)) => {
+                    Some(Price::new(parse_amount(&amount)?, currency))
+                }
+                _ => None,
+            };
+            let point = TrendPoint {
+                timestamp: parse_timestamp(&started_at)?,
+                vendor_price: Price::new(parse_amount(&amount)?, currency),
+                reference_price,
+            };
+            match points.last_mut() {
+                Some(last) if last.timestamp == point.timestamp => {
+                    if point.vendor_price < last.vendor_price {
+                        *last = point;
+                    }
+                }
+                _ => points.push(point),
+            }
+        }
+        Ok(points)
+    }
+
+    /// When the latest run started, None if nothing has been recorded
+    pub fn latest_run(&self) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let started_at: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT started_at FROM runs ORDER BY started_at DESC, id DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        started_at.map(|s| parse_timestamp(&s)).transpose()
+    }
+}
+
+/// Turns rows of run start, amount and currency, ordered by run, into one price point per run
+/// with the cheapest price of that run
+fn cheapest_per_run(
+    rows: impl Iterator<Item = rusqlite::Result<(String, String, Currency)>>,
+) -> Result<Vec<PricePoint>, StorageError> {
+    let mut points: Vec<PricePoint> = Vec::new();
+    for row in rows {
+        let (started_at, amount, currency) = row?;
+        let point = PricePoint {
+            timestamp: parse_timestamp(&started_at)?,
+            price: Price::new(parse_amount(&amount)?, currency),
+        };
+        match points.last_mut() {
+            Some(last) if last.timestamp == point.timestamp => {
+                if point.price < last.price {
+                    *last = point;
+                }
+            }
+            _ => points.push(point),
+        }
+    }
+    Ok(points)
+}
+
+/// Fixed width UTC timestamps, so they sort as strings in SQLite
+fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>, StorageError> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|_| StorageError::Corrupt {
+            what: "timestamp",
+            value: timestamp.to_string(),
+        })
+}
+
+fn parse_amount(amount: &str) -> Result<Decimal, StorageError> {
+    amount.parse().map_err(|_| StorageError::Corrupt {
+        what: "amount",
+        value: amount.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use rust_decimal_macros::dec;
+    use tempfile::tempdir;
+
+    use crate::cards::compared_card::MatchQuality;
+    use crate::price_source::PriceQuote;
+    use crate::test::helpers::{
+        reaper_king_card_name, reaper_king_scryfall_card_expensive, reaper_king_set_name,
+        reaper_king_vendor_card_expensive,
+    };
+
+    use super::*;
+
+    fn vendor_cards(price: Decimal) -> HashMap<CardName, Vec<VendorCard>> {
+        let mut card = reaper_king_vendor_card_expensive();
+        card.price = Price::new(price, Currency::SEK);
+        let mut dearer_copy = card.clone();
+        dearer_copy.price = Price::new(price + dec!(10), Currency::SEK);
+        let mut damaged_copy = card.clone();
+        damaged_copy.price = Price::new(dec!(1), Currency::SEK);
+        damaged_copy.condition = Condition::Damaged;
+        HashMap::from([(card.name.clone(), vec![dearer_copy, card, damaged_copy])])
+    }
+
+    #[test]
+    fn test_migrates_new_and_existing_database() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("db").join("history.db");
+
+        let history = PriceHistory::open(&path).unwrap();
+        assert_eq!(history.schema_version().unwrap(), MIGRATIONS.len());
+        history.start_run(Utc::now()).unwrap();
+        drop(history);
+
+        // Reopening keeps the data and doesn't run the migrations again
+        let history = PriceHistory::open(&path).unwrap();
+        assert_eq!(history.schema_version().unwrap(), MIGRATIONS.len());
+        assert!(history.latest_run().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_cards_recorded_before_condition_are_near_mint_english() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("history.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute_batch(
+            "INSERT INTO runs (id, started_at) VALUES (1, '2025-03-01T00:00:00.000000Z');
+             INSERT INTO vendor_cards (run_id, vendor, name, name_raw, set_name, set_raw,
+                foil, price_amount, price_currency, trade_in_price, current_stock, max_stock)
+             VALUES (1, 'Dragonslair', 'reaper king', 'Reaper King', 'shadowmoor',
+                'Shadowmoor', 0, '40', 'SEK', 0, 1, 1);",
+        )
+        .unwrap();
+        drop(conn);
+
+        let history = PriceHistory::open(&path).unwrap();
+        let (condition, language): (String, String) = history
+            .conn
+            .query_row("SELECT condition, language FROM vendor_cards", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(condition.parse(), Ok(Condition::NearMint));
+        assert_eq!(language.parse(), Ok(Language::English));
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("history.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", 99).unwrap();
+        drop(conn);
+
+        assert!(PriceHistory::open(&path).is_err());
+    }
+
+    #[test]
+    fn test_vendor_price_history() {
+        let mut history = PriceHistory::open_in_memory().unwrap();
+        let now = Utc::now();
+        let old_run = history.start_run(now - Duration::days(100)).unwrap();
+        let last_month = history.start_run(now - Duration::days(30)).unwrap();
+        let today = history.start_run(now).unwrap();
+        history
+            .record_vendor_cards(old_run, &vendor_cards(dec!(5)))
+            .unwrap();
+        assert_eq!(
+            history
+                .record_vendor_cards(last_month, &vendor_cards(dec!(40.5)))
+                .unwrap(),
+            3
+        );
+        history
+            .record_vendor_cards(today, &vendor_cards(dec!(35)))
+            .unwrap();
+
+        let points = history
+            .vendor_price_history(
+                &Vendor::Dragonslair,
+                &reaper_king_card_name(),
+                &reaper_king_set_name(),
+                false,
+                CopyQuality::default(),
+                now - Duration::days(90),
+            )
+            .unwrap();
+        let damaged_points = history
+            .vendor_price_history(
+                &Vendor::Dragonslair,
+                &reaper_king_card_name(),
+                &reaper_king_set_name(),
+                false,
+                CopyQuality {
+                    condition: Condition::Damaged,
+                    language: Language::English,
+                },
+                now - Duration::days(90),
+            )
+            .unwrap();
+
+        let prices: Vec<Price> = points.iter().map(|point| point.price).collect();
+        assert_eq!(
+            prices,
+            vec![
+                Price::new(dec!(40.5), Currency::SEK),
+                Price::new(dec!(35), Currency::SEK)
+            ]
+        );
+        assert_eq!(points[1].timestamp.timestamp(), now.timestamp());
+        assert_eq!(damaged_points.len(), 2);
+        assert_eq!(damaged_points[0].price, Price::new(dec!(1), Currency::SEK));
+        assert!(history
+            .vendor_price_history(
+                &Vendor::Alphaspel,
+                &reaper_king_card_name(),
+                &reaper_king_set_name(),
+                false,
+                CopyQuality::default(),
+                now - Duration::days(90),
+            )
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_scryfall_price_history() {
+        let mut history = PriceHistory::open_in_memory().unwrap();
+        let card = reaper_king_scryfall_card_expensive();
+        let run = history.start_run(Utc::now()).unwrap();
+        history
+            .record_scryfall_prices(run, &HashMap::from([(card.name.clone(), vec![card])]))
+            .unwrap();
+
+        let since = Utc::now() - Duration::days(1);
+        let name = reaper_king_card_name();
+        let set = reaper_king_set_name();
+        let points = history
+            .scryfall_price_history(&name, &set, false, since)
+            .unwrap();
+        let foil_points = history
+            .scryfall_price_history(&name, &set, true, since)
+            .unwrap();
+
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].price, Price::new(dec!(1), Currency::EUR));
+        assert_eq!(foil_points[0].price, Price::new(dec!(2), Currency::EUR));
+    }
+
+    #[test]
+    fn test_compared_card_trends() {
+        let mut history = PriceHistory::open_in_memory().unwrap();
+        let now = Utc::now();
+        let compared_copy = |price: Decimal, reference: Option<Decimal>, language: Language| {
+            let mut vendor_card = reaper_king_vendor_card_expensive();
+            vendor_card.price = Price::new(price, Currency::SEK);
+            vendor_card.language = language;
+            ComparedCard {
+                vendor_card,
+                scryfall_card: reaper_king_scryfall_card_expensive(),
+                price_difference_to_cheapest_vendor_card: Decimal::ZERO,
+                reference_price: reference.map(|amount| PriceQuote {
+                    price: Price::new(amount, Currency::EUR),
+                    source: "scryfall".to_string(),
+                    timestamp: now,
+                    foil: false,
+                }),
+                match_quality: MatchQuality::Exact,
+            }
+        };
+        let compared = |price: Decimal, reference: Option<Decimal>| {
+            HashMap::from([(
+                reaper_king_card_name(),
+                vec![
+                    compared_copy(price, reference, Language::English),
+                    compared_copy(dec!(5), reference, Language::Russian),
+                ],
+            )])
+        };
+        let yesterday = history.start_run(now - Duration::days(1)).unwrap();
+        let today = history.start_run(now).unwrap();
+        history
+            .record_compared_cards(yesterday, &compared(dec!(50), Some(dec!(4))))
+            .unwrap();
+        history
+            .record_compared_cards(today, &compared(dec!(45), None))
+            .unwrap();
+
+        let card = reaper_king_vendor_card_expensive();
+        let mut foil_card = card.clone();
+        foil_card.foil = true;
+        let mut russian_card = card.clone();
+        russian_card.language = Language::Russian;
+        let trends = PriceTrends::load(
+            &history,
+            [&card, &foil_card, &russian_card],
+            now - Duration::days(30),
+        )
+        .unwrap();
+
+        let trend = trends.get(&card);
+        assert_eq!(trend.len(), 2);
+        assert_eq!(trend[0].vendor_price, Price::new(dec!(50), Currency::SEK));
+        assert_eq!(
+            trend[0].reference_price,
+            Some(Price::new(dec!(4), Currency::EUR))
+        );
+        assert_eq!(trend[1].vendor_price, Price::new(dec!(45), Currency::SEK));
+        assert_eq!(trend[1].reference_price, None);
+        assert!(trends.get(&foil_card).is_empty());
+        let russian_trend = trends.get(&russian_card);
+        assert_eq!(russian_trend.len(), 2);
+        assert_eq!(
+            russian_trend[1].vendor_price,
+            Price::new(dec!(5), Currency::SEK)
+        );
+    }
+
+    #[test]
+    fn test_record_compared_cards() {
+        let mut history = PriceHistory::open_in_memory().unwrap();
+        let run = history.start_run(Utc::now()).unwrap();
+        let compared = ComparedCard {
+            vendor_card: reaper_king_vendor_card_expensive(),
+            scryfall_card: reaper_king_scryfall_card_expensive(),
+            price_difference_to_cheapest_vendor_card: dec!(-1.25),
+            reference_price: None,
+            match_quality: MatchQuality::NoPrice,
+        };
+
+        let count = history
+            .record_compared_cards(
+                run,
+                &HashMap::from([(reaper_king_card_name(), vec![compared])]),
+            )
+            .unwrap();
+
+        let (diff, quality, reference): (String, String, Option<String>) = history
+            .conn
+            .query_row(
+                "SELECT price_difference_sek, match_quality, reference_price_amount
+                 FROM compared_cards",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(diff, "-1.25");
+        assert_eq!(quality, "no price");
+        assert_eq!(reference, None);
+    }
+}
@@ -0,0 +1,293 @@
+//! Where the MCM trend prices the vendor cards are compared against come from.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::cards::{currency::Currency, price::Price, scryfallcard::ScryfallCard};
+use crate::comparer::ComparerSettings;
+use crate::error::PriceError;
+use crate::http_client::HttpClient;
+use crate::mtg_stock_price_checker::MtgPriceFetcher;
+
+/// Names of the price sources that can be put in the `prices.sources` chain
+pub const KNOWN_PRICE_SOURCES: [&str; 3] = ["scryfall", "mtgstocks", "scryfall_usd"];
+
+/// A price together with where and when it was found
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct PriceQuote {
+    pub price: Price,
+    /// Name of the `PriceSource` that gave the price
+    pub source: String,
+    /// When the source had this price
+    pub timestamp: DateTime<Utc>,
+    /// Whether this is the price of the foil printing
+    pub foil: bool,
+}
+
+impl PriceQuote {
+    pub fn currency(&self) -> Currency {
+        self.price.currency
+    }
+}
+
+/// A provider of MCM trend prices for a printing
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    /// Lowercase name used in the config and in the quotes
+    fn name(&self) -> &'static str;
+
+    /// Price of the printing, the foil one if `foil` is set
+    async fn quote(&self, printing: &ScryfallCard, foil: bool) -> Result<PriceQuote, PriceError>;
+}
+
+/// Prices baked into the Scryfall snapshot, the MCM trend in EUR or the TCGplayer market in USD
+pub struct ScryfallPriceSource {
+    /// When the snapshot was taken
+    as_of: DateTime<Utc>,
+    currency: Currency,
+}
+
+impl ScryfallPriceSource {
+    pub fn new(as_of: DateTime<Utc>) -> Self {
+        Self {
+            as_of,
+            currency: Currency::EUR,
+        }
+    }
+
+    /// The TCGplayer USD prices instead of the MCM EUR ones
+    pub fn usd(as_of: DateTime<Utc>) -> Self {
+        Self {
+            as_of,
+            currency: Currency::USD,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSource for ScryfallPriceSource {
+    fn name(&self) -> &'static str {
+        match self.currency {
+            Currency::USD => "scryfall_usd",
+            _ => "scryfall",
+        }
+    }
+
+    async fn quote(&self, printing: &ScryfallCard, foil: bool) -> Result<PriceQuote, PriceError> {
+        let prices = &printing.prices;
+        let price = match (self.currency, foil) {
+            (Currency::USD, true) => prices.usd_foil,
+            (Currency::USD, false) => prices.usd,
+            (_, true) => prices.eur_foil,
+            (_, false) => prices.eur,
+        };
+        let price = price.ok_or_else(|| PriceError::NotFound {
+            source: self.name(),
+            card: format!(
+                "{} {} ({})",
+                if foil { "foil" } else { "non-foil" },
+                printing.name.almost_raw,
+                self.currency
+            ),
+            set: printing.set.raw.clone(),
+        })?;
+        Ok(PriceQuote {
+            price,
+            source: self.name().to_string(),
+            timestamp: self.as_of,
+            foil,
+        })
+    }
+}
+
+/// Price sources asked in order until one of them has a price
+#[derive(Default)]
+pub struct PriceChain {
+    sources: Vec<Box<dyn PriceSource>>,
+}
+
+impl PriceChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The chain in `settings.price_sources`. The Scryfall prices are dated `scryfall_as_of`,
+    /// when the snapshot was taken. MTGStocks is asked through the given client and left out
+    /// when `external_price_check` is off
+    pub fn from_settings(
+        settings: &ComparerSettings,
+        scryfall_as_of: DateTime<Utc>,
+        mtg_stock_url: &str,
+        client: &HttpClient,
+    ) -> Self {
+        let mut chain = Self::new();
+        for name in &settings.price_sources {
+            match name.as_str() {
+                "scryfall" => chain.push(Box::new(ScryfallPriceSource::new(scryfall_as_of))),
+                "scryfall_usd" => chain.push(Box::new(ScryfallPriceSource::usd(scryfall_as_of))),
+                "mtgstocks" if settings.external_price_check => chain.push(Box::new(
+                    MtgPriceFetcher::new(client.clone(), mtg_stock_url.to_string()),
+                )),
+                "mtgstocks" => debug!("External price check is off, skipping MTGStocks"),
+                _ => warn!("Unknown price source '{}', skipping it", name),
+            }
+        }
+        chain
+    }
+
+    /// Adds a source to the end of the chain
+    pub fn push(&mut self, source: Box<dyn PriceSource>) {
+        self.sources.push(source);
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.sources.iter().map(|source| source.name()).collect()
+    }
+
+    /// Quote from the first source in the chain that has a price for the printing
+    pub async fn best_quote(&self, printing: &ScryfallCard, foil: bool) -> Option<PriceQuote> {
+        for source in &self.sources {
+            match source.quote(printing, foil).await {
+                Ok(quote) => return Some(quote),
+                Err(e) => debug!("No price from {}: {}", source.name(), e),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::helpers::{
+        lifecraft_scryfall_card_no_price, reaper_king_scryfall_card_expensive, test_http_client,
+    };
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    struct FixedPriceSource {
+        price: Price,
+    }
+
+    #[async_trait]
+    impl PriceSource for FixedPriceSource {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        async fn quote(
+            &self,
+            _printing: &ScryfallCard,
+            foil: bool,
+        ) -> Result<PriceQuote, PriceError> {
+            Ok(PriceQuote {
+                price: self.price,
+                source: self.name().to_string(),
+                timestamp: Utc::now(),
+                foil,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scryfall_quote() {
+        let as_of = Utc::now();
+        let source = ScryfallPriceSource::new(as_of);
+        let card = reaper_king_scryfall_card_expensive();
+
+        let quote = source.quote(&card, false).await.unwrap();
+        assert_eq!(
+            quote,
+            PriceQuote {
+                price: Price::new(dec!(1.0), Currency::EUR),
+                source: "scryfall".to_string(),
+                timestamp: as_of,
+                foil: false,
+            }
+        );
+        assert_eq!(quote.currency(), Currency::EUR);
+
+        let foil_quote = source.quote(&card, true).await.unwrap();
+        assert_eq!(foil_quote.price, Price::new(dec!(2.0), Currency::EUR));
+        assert!(foil_quote.foil);
+
+        assert!(source
+            .quote(&lifecraft_scryfall_card_no_price(), false)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scryfall_usd_quote() {
+        let source = ScryfallPriceSource::usd(Utc::now());
+        let mut card = reaper_king_scryfall_card_expensive();
+
+        assert_eq!(source.name(), "scryfall_usd");
+        assert!(source.quote(&card, false).await.is_err());
+
+        card.prices.usd_foil = Some(Price::new(dec!(4.0), Currency::USD));
+        let quote = source.quote(&card, true).await.unwrap();
+        assert_eq!(quote.price, Price::new(dec!(4.0), Currency::USD));
+        assert_eq!(quote.currency(), Currency::USD);
+        assert_eq!(quote.source, "scryfall_usd");
+    }
+
+    #[tokio::test]
+    async fn test_chain_falls_back_to_next_source() {
+        let mut chain = PriceChain::new();
+        chain.push(Box::new(ScryfallPriceSource::new(Utc::now())));
+        chain.push(Box::new(FixedPriceSource {
+            price: Price::new(dec!(3.0), Currency::EUR),
+        }));
+
+        let from_scryfall = chain
+            .best_quote(&reaper_king_scryfall_card_expensive(), false)
+            .await
+            .unwrap();
+        let from_fallback = chain
+            .best_quote(&lifecraft_scryfall_card_no_price(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(from_scryfall.source, "scryfall");
+        assert_eq!(from_fallback.source, "fixed");
+        assert_eq!(from_fallback.price, Price::new(dec!(3.0), Currency::EUR));
+        assert!(PriceChain::new()
+            .best_quote(&reaper_king_scryfall_card_expensive(), false)
+            .await
+            .is_none());
+    }
+
+    #[test]
+    fn test_chain_from_settings() {
+        let settings = ComparerSettings {
+            price_sources: vec![
+                "mtgstocks".to_string(),
+                "scryfall".to_string(),
+                "scryfall_usd".to_string(),
+            ],
+            ..ComparerSettings::default()
+        };
+        let without_external = ComparerSettings {
+            external_price_check: false,
+            ..ComparerSettings::default()
+        };
+        let client = test_http_client();
+
+        assert_eq!(
+            PriceChain::from_settings(&settings, Utc::now(), "url", &client).names(),
+            vec!["mtgstocks", "scryfall", "scryfall_usd"]
+        );
+        assert_eq!(
+            PriceChain::from_settings(&ComparerSettings::default(), Utc::now(), "url", &client)
+                .names(),
+            vec!["scryfall", "mtgstocks"]
+        );
+        assert_eq!(
+            PriceChain::from_settings(&without_external, Utc::now(), "url", &client).names(),
+            vec!["scryfall"]
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! What happened during a vendor scrape: the pages that were fetched or failed and the
+//! products that became cards or were skipped, counted by reason with a few of their names.
+//!
+//! The scrapers fill a report per page, which are merged into one for the whole scrape and
+//! saved next to the snapshot in a `reports` folder.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::cards::vendor::Vendor;
+use crate::error::StorageError;
+use crate::utilities::constants::SCRAPE_REPORTS_DIR;
+
+/// How many product names are kept per skip reason
+pub const SAMPLES_PER_REASON: usize = 5;
+
+/// Why a product on a vendor page didn't become a card
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SkipReason {
+    Token,
+    SoldOut,
+    /// The set is missing or not one the vendor lists
+    UnknownSet,
+    BadPrice,
+    /// The name is missing or a basic land
+    BadName,
+    /// Missing stock or other markup the product should have
+    Other,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SkipReason::Token => write!(f, "token"),
+            SkipReason::SoldOut => write!(f, "sold out"),
+            SkipReason::UnknownSet => write!(f, "unknown set"),
+            SkipReason::BadPrice => write!(f, "bad price"),
+            SkipReason::BadName => write!(f, "bad name"),
+            SkipReason::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// A product that was skipped, with the raw product name as it was on the page
+#[derive(Debug, PartialEq, Clone)]
+pub struct SkippedProduct {
+    pub reason: SkipReason,
+    pub raw_name: String,
+    pub message: String,
+}
+
+impl SkippedProduct {
+    pub fn new(reason: SkipReason, raw_name: &str, message: impl Into<String>) -> Self {
+        Self {
+            reason,
+            raw_name: raw_name.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SkippedProduct {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.reason)
+    }
+}
+
+impl Error for SkippedProduct {}
+
+/// The products skipped for one reason
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SkippedProducts {
+    pub reason: SkipReason,
+    pub count: u32,
+    /// The first few raw product names
+    pub samples: Vec<String>,
+}
+
+/// A page that couldn't be fetched or read
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct FailedPage {
+    pub url: String,
+    pub error: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ScrapeReport {
+    pub vendor: Vendor,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub pages_fetched: u32,
+    pub pages_failed: u32,
+    pub failed_pages: Vec<FailedPage>,
+    /// Products that became cards
+    pub parsed: u32,
+    /// Ordered by reason
+    pub skipped: Vec<SkippedProducts>,
+}
+
+impl ScrapeReport {
+    pub fn new(vendor: Vendor) -> Self {
+        Self {
+            vendor,
+            started_at: Utc::now(),
+            finished_at: None,
+            pages_fetched: 0,
+            pages_failed: 0,
+            failed_pages: Vec::new(),
+            parsed: 0,
+            skipped: Vec::new(),
+        }
+    }
+
+    pub fn page_fetched(&mut self) {
+        self.pages_fetched += 1;
+    }
+
+    pub fn page_failed(&mut self, url: &str, error: impl fmt::Display) {
+        self.pages_failed += 1;
+        self.failed_pages.push(FailedPage {
+            url: url.to_string(),
+            error: error.to_string(),
+        });
+    }
+
+    pub fn product_parsed(&mut self) {
+        self.parsed += 1;
+    }
+
+    pub fn product_skipped(&mut self, product: SkippedProduct) {
+        let samples = if product.raw_name.is_empty() {
+            Vec::new()
+        } else {
+            vec![product.raw_name]
+        };
+        self.add_skipped(SkippedProducts {
+            reason: product.reason,
+            count: 1,
+            samples,
+        });
+    }
+
+    fn add_skipped(&mut self, skipped: SkippedProducts) {
+        match self
+            .skipped
+            .iter_mut()
+            .find(|products| products.reason == skipped.reason)
+        {
+            Some(products) => {
+                products.count += skipped.count;
+                for sample in skipped.samples {
+                    if products.samples.len() < SAMPLES_PER_REASON
+                        && !products.samples.contains(&sample)
+                    {
+                        products.samples.push(sample);
+                    }
+                }
+            }
+            None => {
+                let mut skipped = skipped;
+                skipped.samples.truncate(SAMPLES_PER_REASON);
+                self.skipped.push(skipped);
+                self.skipped.sort_by_key(|products| products.reason);
+            }
+        }
+    }
+
+    /// Adds the counts of a report of part of the scrape, e.g. a page
+    pub fn merge(&mut self, other: ScrapeReport) {
+        self.pages_fetched += other.pages_fetched;
+        self.pages_failed += other.pages_failed;
+        self.failed_pages.extend(other.failed_pages);
+        self.parsed += other.parsed;
+        for skipped in other.skipped {
+            self.add_skipped(skipped);
+        }
+    }
+
+    pub fn skipped_count(&self) -> u32 {
+        self.skipped.iter().map(|products| products.count).sum()
+    }
+
+    pub fn finish(&mut self) {
+        self.finished_at = Some(Utc::now());
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), StorageError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| StorageError::io(parent, e))?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| StorageError::json(path, e))?;
+        fs::write(path, json).map_err(|e| StorageError::io(path, e))
+    }
+}
+
+impl fmt::Display for ScrapeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} pages fetched, {} failed, {} products parsed, {} skipped",
+            self.vendor,
+            self.pages_fetched,
+            self.pages_failed,
+            self.parsed,
+            self.skipped_count()
+        )?;
+        if !self.skipped.is_empty() {
+            let reasons: Vec<String> = self
+                .skipped
+                .iter()
+                .map(|products| format!("{} {}", products.count, products.reason))
+                .collect();
+            write!(f, " ({})", reasons.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the report of the scrape saved to the snapshot path is saved, the `reports` folder
+/// next to the snapshot, with the same file name
+pub fn scrape_report_path(snapshot_path: &str) -> PathBuf {
+    let snapshot_path = Path::new(snapshot_path);
+    let folder = snapshot_path.parent().unwrap_or(Path::new(""));
+    match snapshot_path.file_name() {
+        Some(file_name) => folder.join(SCRAPE_REPORTS_DIR).join(file_name),
+        None => folder.join(SCRAPE_REPORTS_DIR).join("scrape_report.json"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page_report(skipped: &[(SkipReason, &str)]) -> ScrapeReport {
+        let mut report = ScrapeReport::new(Vendor::Alphaspel);
+        report.page_fetched();
+        report.product_parsed();
+        for (reason, name) in skipped {
+            report.product_skipped(SkippedProduct::new(*reason, name, "skipped"));
+        }
+        report
+    }
+
+    #[test]
+    fn test_merge_counts_skipped_products_by_reason() {
+        let mut report = ScrapeReport::new(Vendor::Alphaspel);
+        report.page_failed("https://alphaspel.se/page=3", "timed out");
+        report.merge(page_report(&[
+            (SkipReason::SoldOut, "Reaper King"),
+            (SkipReason::Token, "Goblin Token"),
+        ]));
+        let many_sold_out: Vec<(SkipReason, &str)> = [
+            "Reaper King",
+            "Counterspell",
+            "Brainstorm",
+            "Ponder",
+            "Preordain",
+            "Opt",
+        ]
+        .iter()
+        .map(|name| (SkipReason::SoldOut, *name))
+        .collect();
+        report.merge(page_report(&many_sold_out));
+
+        assert_eq!(report.pages_fetched, 2);
+        assert_eq!(report.pages_failed, 1);
+        assert_eq!(report.parsed, 2);
+        assert_eq!(report.skipped_count(), 8);
+        assert_eq!(report.skipped[0].reason, SkipReason::Token);
+        assert_eq!(report.skipped[1].count, 7);
+        assert_eq!(
+            report.skipped[1].samples,
+            vec![
+                "Reaper King",
+                "Counterspell",
+                "Brainstorm",
+                "Ponder",
+                "Preordain"
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "Alphaspel: 2 pages fetched, 1 failed, 2 products parsed, 8 skipped (1 token, 7 sold out)"
+        );
+    }
+
+    #[test]
+    fn test_report_is_saved_next_to_the_snapshot() {
+        assert_eq!(
+            scrape_report_path("../alphaspel_cards/as_cards_01_03_2025-10-00.json"),
+            PathBuf::from("../alphaspel_cards/reports/as_cards_01_03_2025-10-00.json")
+        );
+    }
+}
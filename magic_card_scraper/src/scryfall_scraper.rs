@@ -5,9 +5,10 @@ use crate::utilities::constants::{
     REPOSITORY_ROOT_PATH, SCRYFALL_API_URL, SCRYFALL_CARDS_DIR, SCRYFALL_RAW_FILE_PREFIX,
 };
 use crate::utilities::string_manipulators::clean_string;
-use chrono::Local;
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
 use log::{self, debug, error, info};
-use reqwest;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fs;
@@ -16,20 +17,21 @@ use crate::cards::cardname::CardName;
 use crate::cards::currency::Currency;
 use crate::cards::price::Price;
 use crate::cards::scryfallcard::ScryfallCard;
-use crate::utilities::file_management::{download_and_save_file, load_from_json_file};
+use crate::error::PriceError;
+use crate::http_client::HttpClient;
+use crate::utilities::file_management::{append_to_file, load_from_json_file};
+
+/// Format of the download time in the names of the raw files
+const RAW_FILE_TIME_FORMAT: &str = "%Y-%m-%d_%H:%M:%S";
 
 pub struct ScryfallScraper {
-    client: reqwest::Client,
+    client: HttpClient,
     base_url: String,
     scryfall_cards_path: String,
 }
 
 impl ScryfallScraper {
-    pub fn new(
-        base_url: Option<&str>,
-        client: reqwest::Client,
-        directory_path: Option<String>,
-    ) -> Self {
+    pub fn new(base_url: Option<&str>, client: HttpClient, directory_path: Option<String>) -> Self {
         ScryfallScraper {
             client,
             base_url: base_url.unwrap_or(SCRYFALL_API_URL).to_string(),
@@ -38,18 +40,9 @@ impl ScryfallScraper {
         }
     }
 
-    fn setup_http_headers() -> reqwest::header::HeaderMap {
-        let mut header_map = reqwest::header::HeaderMap::new();
-        header_map.insert(reqwest::header::ACCEPT, "*/*".parse().unwrap());
-        header_map.insert(
-            reqwest::header::USER_AGENT,
-            "application/json".parse().unwrap(),
-        );
-        header_map
-    }
-
-    /// Returns the path to the file if it exists (The file is in raw format and quite big)
-    fn get_existing_scryfall_file(&self) -> Option<String> {
+    /// Returns the path to the file if it exists (The file is in raw format and quite big) and
+    /// when it was downloaded
+    fn get_existing_scryfall_file(&self) -> Option<(String, DateTime<Utc>)> {
         let current_date = Local::now().format("%Y-%m-%d").to_string();
         fs::read_dir(&self.scryfall_cards_path)
             .ok()?
@@ -57,46 +50,56 @@ impl ScryfallScraper {
                 let entry = entry.ok()?;
                 let file_name = entry.file_name().into_string().ok()?;
                 if file_name.starts_with(&format!("{}_{}", SCRYFALL_RAW_FILE_PREFIX, current_date))
-                    && file_name.ends_with(".json")
                 {
-                    Some(entry.path().to_str()?.to_string())
+                    let downloaded_at = raw_file_downloaded_at(&file_name)?;
+                    Some((entry.path().to_str()?.to_string(), downloaded_at))
                 } else {
                     None
                 }
             })
     }
 
-    pub async fn get_raw_scryfall_cards_file(&self) -> Result<String, Box<dyn std::error::Error>> {
-        if let Some(existing_file) = self.get_existing_scryfall_file() {
+    /// Path to today's raw Scryfall file, downloaded if there is none yet, and when it was
+    /// downloaded, which is as close to when Scryfall had its prices as is known
+    pub async fn get_raw_scryfall_cards_file(&self) -> Result<(String, DateTime<Utc>), PriceError> {
+        if let Some((existing_file, downloaded_at)) = self.get_existing_scryfall_file() {
             info!("Using existing Scryfall price file: {}", existing_file);
-            return Ok(existing_file);
+            return Ok((existing_file, downloaded_at));
         }
 
         let url = format!("{}/bulk-data", self.base_url);
-        let response = self
-            .client
-            .get(url)
-            .headers(Self::setup_http_headers())
-            .send()
-            .await?
-            .text()
-            .await?;
+        let response = self.get_bytes(&url).await?;
 
-        let json: Value = serde_json::from_str(&response)?;
+        let json: Value =
+            serde_json::from_slice(&response).map_err(|e| PriceError::response(&url, e))?;
         let download_uri = json["data"][2]["download_uri"]
             .as_str()
-            .ok_or("Missing download_uri")?;
+            .ok_or_else(|| PriceError::response(&url, "Missing download_uri"))?;
 
-        let current_time = Local::now().format("%Y-%m-%d_%H:%M:%S").to_string();
+        let downloaded_at = Local::now();
+        let current_time = downloaded_at.format(RAW_FILE_TIME_FORMAT).to_string();
         let file_name = format!("{}_{}.json", SCRYFALL_RAW_FILE_PREFIX, &current_time);
         let path = format!("{}/{}", self.scryfall_cards_path, file_name);
 
-        // Create all directories in the path
-        fs::create_dir_all(&self.scryfall_cards_path)?;
-        download_and_save_file(download_uri, &path).await?;
+        let cards = self.get_bytes(download_uri).await?;
+        append_to_file(&path, &cards)?;
 
         info!("Saved raw scryfall price file to: {}", &path);
-        Ok(path)
+        Ok((path, downloaded_at.with_timezone(&Utc)))
+    }
+
+    async fn get_bytes(&self, url: &str) -> Result<Vec<u8>, PriceError> {
+        let response = self
+            .client
+            .get(url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| PriceError::request(url, e))?;
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| PriceError::request(url, e))?;
+        Ok(bytes.to_vec())
     }
 
     fn is_not_artseries(&self, obj: &Value) -> bool {
@@ -116,38 +119,40 @@ impl ScryfallScraper {
     pub fn convert_raw_to_domain_cards(
         &self,
         path: &str,
-    ) -> Result<HashMap<CardName, Vec<ScryfallCard>>, Box<dyn std::error::Error>> {
+    ) -> Result<HashMap<CardName, Vec<ScryfallCard>>, PriceError> {
         let mut scryfall_card_list = Vec::new();
 
-        let cards: serde_json::Value = load_from_json_file(&path)?;
+        let cards: serde_json::Value = load_from_json_file(path)?;
         if let Value::Array(cards_array) = cards {
             for obj in cards_array {
                 if self.is_not_token(&obj)
                     && self.is_not_basic_land(&obj)
                     && self.is_not_artseries(&obj)
                 {
-                    let name = clean_string(obj["name"].as_str().unwrap()).to_string();
-                    let set = clean_string(obj["set_name"].as_str().unwrap()).to_string();
+                    let (Some(name), Some(set)) = (obj["name"].as_str(), obj["set_name"].as_str())
+                    else {
+                        debug!(
+                            "Skipping Scryfall card without a name or set: {}",
+                            obj["id"]
+                        );
+                        continue;
+                    };
+                    let name = clean_string(name).to_string();
+                    let set = clean_string(set).to_string();
                     // let prices = obj["prices"].clone();
-                    let eur: Option<f64> = obj["prices"]["eur"]
-                        .as_str()
-                        .and_then(|s| s.parse().ok())
-                        .or_else(|| obj["prices"]["eur"].as_f64());
-
-                    let eur_foil: Option<f64> = obj["prices"]["eur_foil"]
-                        .as_str()
-                        .and_then(|s| s.parse().ok())
-                        .or_else(|| obj["prices"]["eur_foil"].as_f64());
+                    let price = |key: &str, currency: Currency| {
+                        obj["prices"][key]
+                            .as_str()
+                            .and_then(|s| s.parse().ok())
+                            .or_else(|| obj["prices"][key].as_f64().and_then(Decimal::from_f64))
+                            .map(|amount| Price::new(amount, currency))
+                    };
 
                     let prices = Prices {
-                        eur: match eur {
-                            Some(v) => Some(Price::new(v, Currency::EUR)),
-                            None => Default::default(),
-                        },
-                        eur_foil: match eur_foil {
-                            Some(v) => Some(Price::new(v, Currency::EUR)),
-                            None => Default::default(),
-                        },
+                        eur: price("eur", Currency::EUR),
+                        eur_foil: price("eur_foil", Currency::EUR),
+                        usd: price("usd", Currency::USD),
+                        usd_foil: price("usd_foil", Currency::USD),
                     };
 
                     let image_url = obj["image_uris"]["normal"]
@@ -163,9 +168,10 @@ impl ScryfallScraper {
                         }
                     };
 
-                    let short_set_name = clean_string(obj["set"].as_str().unwrap()).to_string();
+                    let short_set_name =
+                        clean_string(obj["set"].as_str().unwrap_or("")).to_string();
                     let mut number =
-                        clean_string(obj["collector_number"].as_str().unwrap()).to_string();
+                        clean_string(obj["collector_number"].as_str().unwrap_or("")).to_string();
                     while number.len() < 3 {
                         number = format!("0{}", number);
                     }
@@ -182,6 +188,7 @@ impl ScryfallScraper {
                     let card = ScryfallCard {
                         name,
                         set,
+                        set_code: Some(short_set_name).filter(|code| !code.is_empty()),
                         image_url,
                         prices,
                         collector_number,
@@ -196,7 +203,7 @@ impl ScryfallScraper {
         for card in scryfall_card_list {
             grouped_cards
                 .entry(card.name.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(card);
         }
 
@@ -204,13 +211,29 @@ impl ScryfallScraper {
     }
 }
 
+/// When a raw file was downloaded, from its name, e.g.
+/// `scryfall_raw_download_2025-03-10_17:33:00.json`
+fn raw_file_downloaded_at(file_name: &str) -> Option<DateTime<Utc>> {
+    let time = file_name
+        .strip_prefix(SCRYFALL_RAW_FILE_PREFIX)?
+        .strip_prefix('_')?
+        .strip_suffix(".json")?;
+    NaiveDateTime::parse_from_str(time, RAW_FILE_TIME_FORMAT)
+        .ok()?
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|downloaded_at| downloaded_at.with_timezone(&Utc))
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::test::helpers::test_http_client;
     use crate::utilities::constants::SCRYFALL_RAW_FILE_PREFIX;
+    use chrono::TimeZone;
+    use rust_decimal_macros::dec;
 
     use super::*;
-    use env_logger;
-    use mockito;
+
     use serde_json::json;
     use tempfile::{tempdir, TempDir};
 
@@ -230,7 +253,7 @@ mod tests {
 
             let scraper = ScryfallScraper::new(
                 Some(&server.url()),
-                reqwest::Client::new(),
+                test_http_client(),
                 Some(temp_dir.path().to_str().unwrap().to_string()),
             );
 
@@ -249,8 +272,8 @@ mod tests {
 
         fn create_mock_file(&self, temp_dir: &TempDir, content: &str) -> std::path::PathBuf {
             // let prices_dir = self.setup_prices_directory();
-            let current_date = Local::now().format("%Y-%m-%d").to_string();
-            let file_name = format!("{}_{}.json", SCRYFALL_RAW_FILE_PREFIX, current_date);
+            let current_time = Local::now().format(RAW_FILE_TIME_FORMAT).to_string();
+            let file_name = format!("{}_{}.json", SCRYFALL_RAW_FILE_PREFIX, current_time);
             let file_path = temp_dir.path().join(file_name);
             fs::write(&file_path, content).unwrap();
             file_path
@@ -283,7 +306,7 @@ mod tests {
             .create();
 
         // Execute and verify
-        let result = ctx.scraper.get_raw_scryfall_cards_file().await.unwrap();
+        let (result, _) = ctx.scraper.get_raw_scryfall_cards_file().await.unwrap();
         assert!(fs::metadata(&result).is_ok());
         assert!(!fs::read_to_string(&result).unwrap().is_empty());
         mock.assert();
@@ -317,11 +340,30 @@ mod tests {
         let mock = ctx.server.mock("GET", "/bulk-data").expect(0).create();
 
         // Execute and verify
-        let result = ctx.scraper.get_raw_scryfall_cards_file().await.unwrap();
+        let (result, downloaded_at) = ctx.scraper.get_raw_scryfall_cards_file().await.unwrap();
         assert_eq!(result, file_path.to_str().unwrap());
+        assert_eq!(
+            Some(downloaded_at),
+            raw_file_downloaded_at(file_path.file_name().unwrap().to_str().unwrap())
+        );
         mock.assert();
     }
 
+    #[test]
+    fn test_raw_file_downloaded_at() {
+        assert_eq!(
+            raw_file_downloaded_at("scryfall_raw_download_2025-03-10_17:33:00.json"),
+            Local
+                .with_ymd_and_hms(2025, 3, 10, 17, 33, 0)
+                .earliest()
+                .map(|downloaded_at| downloaded_at.with_timezone(&Utc))
+        );
+        assert_eq!(
+            raw_file_downloaded_at("scryfall_raw_download_2025-03-10.json"),
+            None
+        );
+    }
+
     #[tokio::test]
     async fn test_should_create_domain_version_of_scryfall_cards_list() {
         let ctx = TestContext::new();
@@ -347,11 +389,17 @@ mod tests {
             .first()
             .unwrap();
         assert_eq!(kor_card.set.raw, "Zendikar");
-        assert_eq!(kor_card.prices.eur, Some(Price::new(0.19, Currency::EUR)));
+        assert_eq!(kor_card.set_code.as_deref(), Some("zen"));
+        assert_eq!(
+            kor_card.prices.eur,
+            Some(Price::new(dec!(0.19), Currency::EUR))
+        );
         assert_eq!(
             kor_card.prices.eur_foil,
-            Some(Price::new(1.83, Currency::EUR))
+            Some(Price::new(dec!(1.83), Currency::EUR))
         );
+        assert!(kor_card.prices.usd.is_some());
+        assert_eq!(kor_card.prices.usd.unwrap().currency, Currency::USD);
         assert_eq!(kor_card.image_url, "https://cards.scryfall.io/normal/front/0/0/00006596-1166-4a79-8443-ca9f82e6db4e.jpg?1562609251");
     }
 }
@@ -0,0 +1,228 @@
+use crate::cards::{
+    currency::{Currency, ExchangeRates},
+    vendorcard::VendorCard,
+};
+
+use super::{RepricedCard, SnapshotDiff};
+
+pub fn generate_html_header(diff: &SnapshotDiff) -> String {
+    format!(
+        r#"
+    <!DOCTYPE html>
+<html lang="en">
+
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Snapshot diff</title>
+    <script src="https://cdnjs.cloudflare.com/ajax/libs/tablesort/5.2.1/tablesort.min.js"></script>
+    <script src="https://cdnjs.cloudflare.com/ajax/libs/tablesort/5.2.1/sorts/tablesort.number.min.js"></script>
+    <style>
+            {}
+            {}
+    </style>
+</head>
+
+<body>
+    <h1>Snapshot diff</h1>
+    <p class="snapshots">From {} to {}</p>
+    "#,
+        include_str!("../../static/nice_price_cards_page/style.css"),
+        include_str!("../../static/snapshot_diff_page/style.css"),
+        diff.from,
+        diff.to,
+    )
+}
+
+pub fn generate_html_footer() -> String {
+    r#"
+    <script>
+        document.querySelectorAll('table').forEach(table => new Tablesort(table));
+    </script>
+    </body>
+    </html>
+    "#
+    .to_string()
+}
+
+fn image_cell(card: &VendorCard) -> String {
+    let image_url = &card.image_url;
+    let name = &card.name.raw;
+    format!(
+        r#"<td>
+                <div class="card-image-container">
+                    <img class="card-image" src="{image_url}" alt="{name}">
+                    <img class="enlarged-image" src="{image_url}" alt="{name}">
+                </div>
+            </td>"#
+    )
+}
+
+fn name_cell(card: &VendorCard) -> String {
+    let foil_text = if card.foil { " (Foil)" } else { "" };
+    format!(
+        "<td>{}{}<br>{}</td>",
+        card.name.raw, foil_text, card.set.raw
+    )
+}
+
+pub fn generate_stock_table(
+    title: &str,
+    id: &str,
+    cards: &[VendorCard],
+    rates: &ExchangeRates,
+) -> String {
+    let mut table = format!(
+        r#"
+    <h2>{title} ({count})</h2>
+    <table id="{id}">
+        <thead>
+            <tr>
+                <th class="no-sort">Image</th>
+                <th>Name/Set</th>
+                <th data-sort-method="number">Price</th>
+                <th data-sort-method="number">Stock</th>
+                <th>Vendor</th>
+            </tr>
+        </thead>
+        <tbody>
+    "#,
+        count = cards.len()
+    );
+    for card in cards {
+        let price = &card.price;
+        let price_sek = price.convert_to(Currency::SEK, rates).amount;
+        table.push_str(&format!(
+            r#"
+            <tr>
+                {image}
+                {name}
+                <td data-sort={price_sek:.2}>{price}</td>
+                <td>{stock}</td>
+                <td>{vendor}</td>
+            </tr>
+            "#,
+            image = image_cell(card),
+            name = name_cell(card),
+            stock = card.current_stock,
+            vendor = card.vendor,
+        ));
+    }
+    table.push_str("</tbody></table>");
+    table
+}
+
+pub fn generate_repriced_table(cards: &[RepricedCard], rates: &ExchangeRates) -> String {
+    let mut table = format!(
+        r#"
+    <h2>Repriced ({})</h2>
+    <table id="repriced-table">
+        <thead>
+            <tr>
+                <th class="no-sort">Image</th>
+                <th>Name/Set</th>
+                <th data-sort-method="number">Previous price</th>
+                <th data-sort-method="number">Price</th>
+                <th data-sort-method="number">Change</th>
+                <th data-sort-method="number">Change %</th>
+                <th>Vendor</th>
+            </tr>
+        </thead>
+        <tbody>
+    "#,
+        cards.len()
+    );
+    for repriced in cards {
+        let card = &repriced.card;
+        let previous_price = &repriced.previous_price;
+        let price = &card.price;
+        let previous_price_sek = previous_price.convert_to(Currency::SEK, rates).amount;
+        let price_sek = price.convert_to(Currency::SEK, rates).amount;
+        let change = price.currency.format_amount(repriced.change);
+        let change_class = if repriced.change.is_sign_negative() {
+            "price-down"
+        } else {
+            "price-up"
+        };
+        let (change_percent_sort, change_percent) = match repriced.change_percent {
+            Some(percent) => (format!("{:.1}", percent), format!("{:.1}%", percent)),
+            None => ("0".to_string(), "".to_string()),
+        };
+        table.push_str(&format!(
+            r#"
+            <tr>
+                {image}
+                {name}
+                <td data-sort={previous_price_sek:.2}>{previous_price}</td>
+                <td data-sort={price_sek:.2}>{price}</td>
+                <td class="{change_class}" data-sort={change_sort:.2}>{change}</td>
+                <td class="{change_class}" data-sort={change_percent_sort}>{change_percent}</td>
+                <td>{vendor}</td>
+            </tr>
+            "#,
+            image = image_cell(card),
+            name = name_cell(card),
+            change_sort = repriced.change,
+            vendor = card.vendor,
+        ));
+    }
+    table.push_str("</tbody></table>");
+    table
+}
+
+/// The diff page, with the prices sorted in SEK with the given rates
+pub fn generate_page_content(diff: &SnapshotDiff, rates: &ExchangeRates) -> String {
+    let mut content = generate_html_header(diff);
+    content.push_str(&generate_stock_table(
+        "New in stock",
+        "new-table",
+        &diff.new_in_stock,
+        rates,
+    ));
+    content.push_str(&generate_stock_table(
+        "Sold out",
+        "sold-out-table",
+        &diff.sold_out,
+        rates,
+    ));
+    content.push_str(&generate_repriced_table(&diff.repriced, rates));
+    content.push_str(&generate_html_footer());
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::cards::price::Price;
+    use crate::test::helpers::{counterspell_ice_age, reaper_king_vendor_card_expensive};
+
+    #[test]
+    fn test_page_lists_every_change() {
+        let mut cheaper = reaper_king_vendor_card_expensive();
+        cheaper.price = Price::new(dec!(80.0), Currency::SEK);
+        let old = HashMap::from([(
+            cheaper.name.clone(),
+            vec![reaper_king_vendor_card_expensive()],
+        )]);
+        let new = HashMap::from([
+            (cheaper.name.clone(), vec![cheaper]),
+            (counterspell_ice_age().name, vec![counterspell_ice_age()]),
+        ]);
+        let rates = ExchangeRates::default();
+        let diff =
+            SnapshotDiff::between("dl_cards_old.json", &old, "dl_cards_new.json", &new, &rates);
+
+        let page = generate_page_content(&diff, &rates);
+
+        assert!(page.contains("From dl_cards_old.json to dl_cards_new.json"));
+        assert!(page.contains("New in stock (1)"));
+        assert!(page.contains("Sold out (0)"));
+        assert!(page.contains("Repriced (1)"));
+        assert!(page.contains(r#"<td class="price-down" data-sort=-20.00>-20.00 SEK</td>"#));
+        assert!(page.contains(r#"<td class="price-down" data-sort=-20.0>-20.0%</td>"#));
+    }
+}
@@ -0,0 +1,272 @@
+//! What changed in a vendor's stock between two snapshots: the cards that came in, the ones
+//! that sold out and the ones that got a new price.
+
+pub mod html_generator;
+
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+use crate::cards::{
+    cardname::CardName, condition::Condition, currency::ExchangeRates, language::Language,
+    price::Price, vendor::Vendor, vendorcard::VendorCard,
+};
+
+/// Which printing a vendor card is, whatever its price and stock
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct PrintingKey {
+    vendor: Vendor,
+    name: String,
+    set: String,
+    collector_number: Option<String>,
+    foil: bool,
+    extended_art: bool,
+    prerelease: bool,
+    showcase: bool,
+    condition: Condition,
+    language: Language,
+}
+
+impl PrintingKey {
+    fn of(card: &VendorCard) -> Self {
+        Self {
+            vendor: card.vendor.clone(),
+            name: card.name.cleaned.clone(),
+            set: card.set.cleaned.clone(),
+            collector_number: card
+                .collector_number
+                .as_ref()
+                .map(|number| number.raw().to_string()),
+            foil: card.foil,
+            extended_art: card.extended_art,
+            prerelease: card.prerelease,
+            showcase: card.showcase,
+            condition: card.condition,
+            language: card.language,
+        }
+    }
+}
+
+/// A card in stock in both snapshots whose price changed
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct RepricedCard {
+    /// The card as it is in the newer snapshot
+    pub card: VendorCard,
+    pub previous_price: Price,
+    /// New price minus the previous one, in the currency of the new price
+    pub change: Decimal,
+    /// Change relative to the previous price, none if the card used to be free
+    pub change_percent: Option<Decimal>,
+}
+
+/// Cards that came in, sold out or were repriced between two snapshots of a vendor
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    /// The older snapshot
+    pub from: String,
+    /// The newer snapshot
+    pub to: String,
+    /// In stock in the newer snapshot but not in the older one
+    pub new_in_stock: Vec<VendorCard>,
+    /// In stock in the older snapshot but not in the newer one
+    pub sold_out: Vec<VendorCard>,
+    pub repriced: Vec<RepricedCard>,
+}
+
+impl SnapshotDiff {
+    /// Compares the printings in stock in the two snapshots. A printing listed more than once
+    /// is compared by its cheapest listing. A price in another currency than before is
+    /// converted with the given rates
+    pub fn between(
+        from: &str,
+        old_cards: &HashMap<CardName, Vec<VendorCard>>,
+        to: &str,
+        new_cards: &HashMap<CardName, Vec<VendorCard>>,
+        rates: &ExchangeRates,
+    ) -> Self {
+        let old_stock = in_stock(old_cards, rates);
+        let new_stock = in_stock(new_cards, rates);
+
+        let mut new_in_stock = Vec::new();
+        let mut repriced = Vec::new();
+        for (key, card) in &new_stock {
+            match old_stock.get(key) {
+                None => new_in_stock.push((*card).clone()),
+                Some(old_card) => {
+                    if let Some(repriced_card) = repriced_card(old_card, card, rates) {
+                        repriced.push(repriced_card);
+                    }
+                }
+            }
+        }
+        let mut sold_out: Vec<VendorCard> = old_stock
+            .iter()
+            .filter(|(key, _)| !new_stock.contains_key(key))
+            .map(|(_, card)| (*card).clone())
+            .collect();
+
+        new_in_stock.sort_by(compare_cards);
+        sold_out.sort_by(compare_cards);
+        repriced.sort_by(|a, b| compare_cards(&a.card, &b.card));
+        Self {
+            from: from.to_string(),
+            to: to.to_string(),
+            new_in_stock,
+            sold_out,
+            repriced,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.new_in_stock.is_empty() && self.sold_out.is_empty() && self.repriced.is_empty()
+    }
+}
+
+/// The cheapest listing of each printing with at least one card in stock
+fn in_stock<'a>(
+    cards: &'a HashMap<CardName, Vec<VendorCard>>,
+    rates: &ExchangeRates,
+) -> HashMap<PrintingKey, &'a VendorCard> {
+    let mut stock: HashMap<PrintingKey, &VendorCard> = HashMap::new();
+    for card in cards
+        .values()
+        .flatten()
+        .filter(|card| card.current_stock > 0)
+    {
+        stock
+            .entry(PrintingKey::of(card))
+            .and_modify(|cheapest| {
+                if card.price.convert_to(cheapest.price.currency, rates) < cheapest.price {
+                    *cheapest = card;
+                }
+            })
+            .or_insert(card);
+    }
+    stock
+}
+
+fn repriced_card(
+    old_card: &VendorCard,
+    new_card: &VendorCard,
+    rates: &ExchangeRates,
+) -> Option<RepricedCard> {
+    let currency = new_card.price.currency;
+    let previous_price = old_card.price.convert_to(currency, rates);
+    let change = currency.round(new_card.price.amount - previous_price.amount);
+    if change == Decimal::ZERO {
+        return None;
+    }
+    let change_percent = (previous_price.amount != Decimal::ZERO)
+        .then(|| (change / previous_price.amount * dec!(100)).round_dp(1));
+    Some(RepricedCard {
+        card: new_card.clone(),
+        previous_price: old_card.price,
+        change,
+        change_percent,
+    })
+}
+
+fn compare_cards(a: &VendorCard, b: &VendorCard) -> std::cmp::Ordering {
+    (&a.name.raw, &a.set.raw, a.foil).cmp(&(&b.name.raw, &b.set.raw, b.foil))
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::cards::currency::Currency;
+    use crate::test::helpers::{
+        counterspell_forth_e, counterspell_ice_age, reaper_king_vendor_card_expensive,
+        reaper_king_vendor_card_foil,
+    };
+
+    fn snapshot(cards: Vec<VendorCard>) -> HashMap<CardName, Vec<VendorCard>> {
+        let mut snapshot: HashMap<CardName, Vec<VendorCard>> = HashMap::new();
+        for card in cards {
+            snapshot.entry(card.name.clone()).or_default().push(card);
+        }
+        snapshot
+    }
+
+    #[test]
+    fn test_diff_finds_new_sold_out_and_repriced_cards() {
+        let mut sold_out_foil = reaper_king_vendor_card_foil();
+        sold_out_foil.current_stock = 0;
+        let mut cheaper_reaper_king = reaper_king_vendor_card_expensive();
+        cheaper_reaper_king.price = Price::new(dec!(80.0), Currency::SEK);
+        let old = snapshot(vec![
+            reaper_king_vendor_card_expensive(),
+            reaper_king_vendor_card_foil(),
+            counterspell_forth_e(),
+        ]);
+        let new = snapshot(vec![
+            cheaper_reaper_king.clone(),
+            sold_out_foil,
+            counterspell_forth_e(),
+            counterspell_ice_age(),
+        ]);
+
+        let diff = SnapshotDiff::between(
+            "old.json",
+            &old,
+            "new.json",
+            &new,
+            &ExchangeRates::default(),
+        );
+
+        assert_eq!(diff.from, "old.json");
+        assert_eq!(diff.new_in_stock, vec![counterspell_ice_age()]);
+        assert_eq!(diff.sold_out, vec![reaper_king_vendor_card_foil()]);
+        assert_eq!(
+            diff.repriced,
+            vec![RepricedCard {
+                card: cheaper_reaper_king,
+                previous_price: Price::new(dec!(100.0), Currency::SEK),
+                change: dec!(-20.0),
+                change_percent: Some(dec!(-20.0)),
+            }]
+        );
+        assert!(SnapshotDiff::between(
+            "old.json",
+            &old,
+            "old.json",
+            &old,
+            &ExchangeRates::default()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_diff_compares_cheapest_listing() {
+        let mut expensive_listing = reaper_king_vendor_card_expensive();
+        expensive_listing.price = Price::new(dec!(120.0), Currency::SEK);
+        let mut free_listing = reaper_king_vendor_card_expensive();
+        free_listing.price = Price::new(Decimal::ZERO, Currency::SEK);
+        let old = snapshot(vec![
+            expensive_listing.clone(),
+            reaper_king_vendor_card_expensive(),
+        ]);
+
+        let unchanged = SnapshotDiff::between(
+            "old",
+            &old,
+            "new",
+            &snapshot(vec![reaper_king_vendor_card_expensive(), expensive_listing]),
+            &ExchangeRates::default(),
+        );
+        let from_free = SnapshotDiff::between(
+            "old",
+            &snapshot(vec![free_listing]),
+            "new",
+            &old,
+            &ExchangeRates::default(),
+        );
+
+        assert!(unchanged.is_empty());
+        assert_eq!(from_free.repriced[0].change, dec!(100.0));
+        assert_eq!(from_free.repriced[0].change_percent, None);
+    }
+}
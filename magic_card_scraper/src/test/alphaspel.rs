@@ -1,6 +1,6 @@
 #[cfg(test)]
 pub fn alphaspel_page_set_endings() -> Vec<(String, String)> {
-    return vec![
+    vec![
         (
             "/2441-10th-edition/".to_string(),
             "10th Edition".to_string(),
@@ -867,5 +867,5 @@ pub fn alphaspel_page_set_endings() -> Vec<(String, String)> {
             "/3156-zendikar-rising-commander/".to_string(),
             "Zendikar Rising Commander".to_string(),
         ),
-    ];
+    ]
 }
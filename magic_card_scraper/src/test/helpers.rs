@@ -1,13 +1,23 @@
+use std::time::Duration;
+
+use rust_decimal_macros::dec;
+
 use crate::cards::collector_number::CollectorNumber;
 use crate::cards::currency::Currency;
 use crate::cards::price::Price;
 use crate::cards::{
     cardname::CardName,
+    colour::Colour,
+    condition::Condition,
+    language::Language,
+    personalcard::PersonalCard,
+    rarity::Rarity,
     scryfallcard::{Prices, ScryfallCard},
     setname::SetName,
     vendor::Vendor,
     vendorcard::VendorCard,
 };
+use crate::http_client::{HttpClient, HttpSettings};
 
 pub fn reaper_king_card_name() -> CardName {
     CardName::new("Reaper King".to_string()).unwrap()
@@ -31,11 +41,13 @@ pub fn counterspell_forth_e() -> VendorCard {
         prerelease: false,
         showcase: false,
         set: SetName::new("Magic 25".to_string()).unwrap(),
-        price: Price::new(100.0, Currency::SEK),
+        price: Price::new(dec!(100.0), Currency::SEK),
         trade_in_price: 50,
         current_stock: 6,
         max_stock: 4,
         collector_number: Some(CollectorNumber::new("4ED-065").unwrap()),
+        condition: Condition::NearMint,
+        language: Language::English,
     }
 }
 
@@ -49,11 +61,13 @@ pub fn counterspell_ice_age() -> VendorCard {
         prerelease: false,
         showcase: false,
         set: SetName::new("Ice Age".to_string()).unwrap(),
-        price: Price::new(100.0, Currency::SEK),
+        price: Price::new(dec!(100.0), Currency::SEK),
         trade_in_price: 50,
         current_stock: 2,
         max_stock: 4,
         collector_number: Some(CollectorNumber::new("ICE-064").unwrap()),
+        condition: Condition::NearMint,
+        language: Language::English,
     }
 }
 
@@ -67,11 +81,13 @@ pub fn reaper_king_vendor_card_expensive() -> VendorCard {
         prerelease: false,
         showcase: false,
         set: reaper_king_set_name(),
-        price: Price::new(100.0, Currency::SEK),
+        price: Price::new(dec!(100.0), Currency::SEK),
         trade_in_price: 50,
         current_stock: 1,
         max_stock: 2,
         collector_number: Some(CollectorNumber::new("SHM-260").unwrap()),
+        condition: Condition::NearMint,
+        language: Language::English,
     }
 }
 
@@ -85,11 +101,13 @@ pub fn reaper_king_vendor_card_cheap() -> VendorCard {
         prerelease: false,
         showcase: false,
         set: reaper_king_set_name_2(),
-        price: Price::new(50.0, Currency::SEK),
+        price: Price::new(dec!(50.0), Currency::SEK),
         trade_in_price: 40,
         current_stock: 1,
         max_stock: 2,
         collector_number: Some(CollectorNumber::new("FMB1-111").unwrap()),
+        condition: Condition::NearMint,
+        language: Language::English,
     }
 }
 
@@ -103,11 +121,13 @@ pub fn reaper_king_vendor_card_foil() -> VendorCard {
         prerelease: false,
         showcase: false,
         set: reaper_king_set_name(),
-        price: Price::new(200.0, Currency::SEK),
+        price: Price::new(dec!(200.0), Currency::SEK),
         trade_in_price: 100,
         current_stock: 1,
         max_stock: 2,
         collector_number: Some(CollectorNumber::new("SHM-260").unwrap()),
+        condition: Condition::NearMint,
+        language: Language::English,
     }
 }
 
@@ -115,10 +135,12 @@ pub fn reaper_king_scryfall_card_expensive() -> ScryfallCard {
     ScryfallCard {
         name: reaper_king_card_name(),
         set: reaper_king_set_name(),
+        set_code: Some("shm".to_string()),
         image_url: "www.google.com".to_string(),
         prices: Prices {
-            eur: Some(Price::new(1.0, Currency::EUR)),
-            eur_foil: Some(Price::new(2.0, Currency::EUR)),
+            eur: Some(Price::new(dec!(1.0), Currency::EUR)),
+            eur_foil: Some(Price::new(dec!(2.0), Currency::EUR)),
+            ..Default::default()
         },
         collector_number: Some(CollectorNumber::new("SHM-260").unwrap()),
     }
@@ -128,10 +150,12 @@ pub fn reaper_king_scryfall_card_cheap() -> ScryfallCard {
     ScryfallCard {
         name: reaper_king_card_name(),
         set: reaper_king_set_name_2(),
+        set_code: Some("plst".to_string()),
         image_url: "www.google.com".to_string(),
         prices: Prices {
-            eur: Some(Price::new(0.3, Currency::EUR)),
-            eur_foil: Some(Price::new(1.0, Currency::EUR)),
+            eur: Some(Price::new(dec!(0.3), Currency::EUR)),
+            eur_foil: Some(Price::new(dec!(1.0), Currency::EUR)),
+            ..Default::default()
         },
         collector_number: Some(CollectorNumber::new("PLST-260").unwrap()),
     }
@@ -159,11 +183,13 @@ pub fn vendor_card_sunken_ruins_foil() -> VendorCard {
         prerelease: false,
         showcase: false,
         set: setname_sunken_ruins(),
-        price: Price::new(180.0, Currency::SEK),
+        price: Price::new(dec!(180.0), Currency::SEK),
         trade_in_price: 100,
         current_stock: 1,
         max_stock: 1,
         collector_number: Some(CollectorNumber::new("2XM-326").unwrap()),
+        condition: Condition::NearMint,
+        language: Language::English,
     }
 }
 
@@ -171,10 +197,12 @@ pub fn scryfall_card_sunken_ruins() -> ScryfallCard {
     ScryfallCard {
         name: cardname_sunken_ruins(),
         set: setname_sunken_ruins(),
+        set_code: Some("2xm".to_string()),
         image_url: "www.google.com".to_string(),
         prices: Prices {
-            eur: Some(Price::new(17.71, Currency::EUR)),
-            eur_foil: Some(Price::new(20.65, Currency::EUR)),
+            eur: Some(Price::new(dec!(17.71), Currency::EUR)),
+            eur_foil: Some(Price::new(dec!(20.65), Currency::EUR)),
+            ..Default::default()
         },
         collector_number: Some(CollectorNumber::new("2XM-326").unwrap()),
     }
@@ -194,11 +222,13 @@ pub fn lifecraft_c_vendor_card() -> VendorCard {
         prerelease: false,
         showcase: false,
         set: lifecraft_c_set_name(),
-        price: Price::new(100.0, Currency::SEK),
+        price: Price::new(dec!(100.0), Currency::SEK),
         trade_in_price: 50,
         current_stock: 1,
         max_stock: 2,
         collector_number: Some(CollectorNumber::new("AER-113").unwrap()),
+        condition: Condition::NearMint,
+        language: Language::English,
     }
 }
 
@@ -206,10 +236,12 @@ pub fn lifecraft_c_scryfall_card() -> ScryfallCard {
     ScryfallCard {
         name: lifecraft_c_name(),
         set: lifecraft_c_set_name(),
+        set_code: Some("aer".to_string()),
         image_url: "www.google.com".to_string(),
         prices: Prices {
-            eur: Some(Price::new(1.0, Currency::EUR)),
-            eur_foil: Some(Price::new(2.0, Currency::EUR)),
+            eur: Some(Price::new(dec!(1.0), Currency::EUR)),
+            eur_foil: Some(Price::new(dec!(2.0), Currency::EUR)),
+            ..Default::default()
         },
         collector_number: Some(CollectorNumber::new("AER-113").unwrap()),
     }
@@ -219,11 +251,50 @@ pub fn lifecraft_scryfall_card_no_price() -> ScryfallCard {
     ScryfallCard {
         name: lifecraft_c_name(),
         set: SetName::new("random".to_string()).unwrap(),
+        set_code: Some("ras".to_string()),
         image_url: "www.google.com".to_string(),
         prices: Prices {
             eur: None,
             eur_foil: None,
+            ..Default::default()
         },
         collector_number: Some(CollectorNumber::new("ras-113").unwrap()),
     }
 }
+
+pub fn reaper_king_personal_card() -> PersonalCard {
+    PersonalCard {
+        name: reaper_king_card_name(),
+        set: reaper_king_set_name(),
+        foil: false,
+        price: Price::new(dec!(2.06), Currency::EUR),
+        count: 1,
+        color: Colour::WUBRG,
+        rarity: Rarity::Rare,
+        condition: Condition::NearMint,
+        language: Language::English,
+    }
+}
+
+pub fn counterspell_personal_card() -> PersonalCard {
+    PersonalCard {
+        name: counterspell_forth_e().name,
+        set: counterspell_forth_e().set,
+        foil: false,
+        price: Price::new(dec!(0.94), Currency::EUR),
+        count: 2,
+        color: Colour::Blue,
+        rarity: Rarity::Common,
+        condition: Condition::NearMint,
+        language: Language::English,
+    }
+}
+
+/// A client that doesn't space out requests and retries without waiting long
+pub fn test_http_client() -> HttpClient {
+    HttpClient::new(HttpSettings {
+        backoff: Duration::from_millis(1),
+        min_interval: Duration::ZERO,
+        ..HttpSettings::default()
+    })
+}
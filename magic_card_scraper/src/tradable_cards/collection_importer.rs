@@ -0,0 +1,561 @@
+//! Collection csv exports of the common scanner and collection apps, read into `PersonalCard`s.
+//! Which app wrote the file is told from its header row.
+//!
+//! Only Delver Lens exports have colours and only Delver Lens, Deckbox and ManaBox have
+//! rarities, the cards of the other apps get `Colour::Unknown` and `Rarity::Common`. Moxfield
+//! exports only have the set code, e.g. `shm`, which is looked up in the Scryfall cards the
+//! importer is given. Codes it doesn't know are kept as the set name. Delver Lens exports have
+//! no condition or language, their cards are near mint and english.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+
+use csv::StringRecord;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::delver_lense_converter::DelverLenseConverter;
+use crate::cards::{
+    cardname::CardName, colour::Colour, condition::Condition, currency::Currency,
+    delver_lense_card::DelverLenseCard, language::Language, personalcard::PersonalCard,
+    price::Price, rarity::Rarity, scryfallcard::ScryfallCard, setname::SetName,
+};
+use crate::error::ParseError;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum CollectionFormat {
+    DelverLens,
+    Moxfield,
+    Deckbox,
+    ManaBox,
+    DragonShield,
+}
+
+impl fmt::Display for CollectionFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CollectionFormat::DelverLens => write!(f, "Delver Lens"),
+            CollectionFormat::Moxfield => write!(f, "Moxfield"),
+            CollectionFormat::Deckbox => write!(f, "Deckbox"),
+            CollectionFormat::ManaBox => write!(f, "ManaBox"),
+            CollectionFormat::DragonShield => write!(f, "Dragon Shield"),
+        }
+    }
+}
+
+/// The columns a format keeps each value in
+struct Columns {
+    name: &'static str,
+    set: &'static str,
+    /// The set column holds the set code instead of the name
+    set_is_code: bool,
+    quantity: &'static str,
+    foil: &'static str,
+    price: &'static str,
+    /// Column with the currency of the price
+    currency: Option<&'static str>,
+    rarity: Option<&'static str>,
+    condition: Option<&'static str>,
+    language: Option<&'static str>,
+    /// Currency of prices without a currency sign or column
+    default_currency: Currency,
+}
+
+impl CollectionFormat {
+    /// The format whose columns are all in the header row
+    pub fn detect(headers: &StringRecord) -> Option<Self> {
+        let has = |columns: &[&str]| {
+            columns
+                .iter()
+                .all(|column| headers.iter().any(|header| header.trim() == *column))
+        };
+        if has(&[
+            "Name", "Foil", "Edition", "Price", "Quantity", "Color", "Rarity",
+        ]) {
+            Some(CollectionFormat::DelverLens)
+        } else if has(&["Name", "Set code", "Set name", "Quantity"]) {
+            Some(CollectionFormat::ManaBox)
+        } else if has(&["Folder Name", "Card Name", "Set Name", "Printing"]) {
+            Some(CollectionFormat::DragonShield)
+        } else if has(&["Count", "Tradelist Count", "Name", "Edition", "Card Number"]) {
+            Some(CollectionFormat::Deckbox)
+        } else if has(&["Count", "Tradelist Count", "Name", "Edition"]) {
+            Some(CollectionFormat::Moxfield)
+        } else {
+            None
+        }
+    }
+
+    fn columns(&self) -> Columns {
+        match self {
+            CollectionFormat::DelverLens => Columns {
+                name: "Name",
+                set: "Edition",
+                set_is_code: false,
+                quantity: "Quantity",
+                foil: "Foil",
+                price: "Price",
+                currency: None,
+                rarity: Some("Rarity"),
+                condition: None,
+                language: None,
+                default_currency: Currency::EUR,
+            },
+            CollectionFormat::Moxfield => Columns {
+                name: "Name",
+                set: "Edition",
+                set_is_code: true,
+                quantity: "Count",
+                foil: "Foil",
+                price: "Purchase Price",
+                currency: None,
+                rarity: None,
+                condition: Some("Condition"),
+                language: Some("Language"),
+                default_currency: Currency::USD,
+            },
+            CollectionFormat::Deckbox => Columns {
+                name: "Name",
+                set: "Edition",
+                set_is_code: false,
+                quantity: "Count",
+                foil: "Foil",
+                price: "Price",
+                currency: None,
+                rarity: Some("Rarity"),
+                condition: Some("Condition"),
+                language: Some("Language"),
+                default_currency: Currency::USD,
+            },
+            CollectionFormat::ManaBox => Columns {
+                name: "Name",
+                set: "Set name",
+                set_is_code: false,
+                quantity: "Quantity",
+                foil: "Foil",
+                price: "Purchase price",
+                currency: Some("Purchase price currency"),
+                rarity: Some("Rarity"),
+                condition: Some("Condition"),
+                language: Some("Language"),
+                default_currency: Currency::USD,
+            },
+            CollectionFormat::DragonShield => Columns {
+                name: "Card Name",
+                set: "Set Name",
+                set_is_code: false,
+                quantity: "Quantity",
+                foil: "Printing",
+                price: "Price Bought",
+                currency: None,
+                rarity: None,
+                condition: Some("Condition"),
+                language: Some("Language"),
+                default_currency: Currency::USD,
+            },
+        }
+    }
+}
+
+/// A row that couldn't be read, with the reason
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SkippedRow {
+    /// Line in the file, the header is line 1
+    pub line: u64,
+    pub reason: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImportedCards {
+    pub format: CollectionFormat,
+    pub cards: Vec<PersonalCard>,
+    /// Basic lands and rows that couldn't be read
+    pub skipped: Vec<SkippedRow>,
+}
+
+#[derive(Default)]
+pub struct CollectionImporter {
+    /// Set names by lower case set code
+    set_names: HashMap<String, SetName>,
+}
+
+impl CollectionImporter {
+    pub fn new() -> Self {
+        CollectionImporter::default()
+    }
+
+    /// An importer that turns set codes into the names of the sets in the Scryfall cards
+    pub fn with_scryfall_cards(scryfall_cards: &HashMap<CardName, Vec<ScryfallCard>>) -> Self {
+        let set_names = scryfall_cards
+            .values()
+            .flatten()
+            .filter_map(|card| {
+                card.set_code
+                    .as_ref()
+                    .map(|code| (code.to_lowercase(), card.set.clone()))
+            })
+            .collect();
+        CollectionImporter { set_names }
+    }
+
+    /// Reads a collection csv export of any of the known apps
+    pub fn get_personal_cards_from_file(
+        &self,
+        file_path: &str,
+    ) -> Result<ImportedCards, ParseError> {
+        let file = fs::File::open(file_path).map_err(|e| csv_error(file_path, e.into()))?;
+        self.read(file)
+    }
+
+    pub fn read(&self, mut reader: impl Read) -> Result<ImportedCards, ParseError> {
+        let mut content = String::new();
+        reader
+            .read_to_string(&mut content)
+            .map_err(|e| csv_error("collection export", e.into()))?;
+        let content = content.trim_start_matches('\u{feff}');
+        // Dragon Shield starts its exports with an Excel separator hint
+        let (content, skipped_lines) = match content.split_once('\n') {
+            Some((first_line, rest)) if first_line.trim().trim_matches('"') == "sep=," => (rest, 1),
+            _ => (content, 0),
+        };
+
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(content.as_bytes());
+        let headers = csv_reader
+            .headers()
+            .map_err(|e| csv_error("collection export", e))?
+            .clone();
+        let format = CollectionFormat::detect(&headers).ok_or_else(|| {
+            ParseError::field(
+                "collection export header",
+                &headers.iter().collect::<Vec<_>>().join(","),
+                "not the export of a known app",
+            )
+        })?;
+
+        let columns = format.columns();
+        let mut cards = Vec::new();
+        let mut skipped = Vec::new();
+        let delver_lense_converter = DelverLenseConverter::new();
+        for record in csv_reader.records() {
+            let record = record.map_err(|e| csv_error("collection export", e))?;
+            let line = record.position().map_or(0, |position| position.line()) + skipped_lines;
+            let card = if format == CollectionFormat::DelverLens {
+                let delver_lense_card: DelverLenseCard = record
+                    .deserialize(Some(&headers))
+                    .map_err(|e| csv_error("collection export", e))?;
+                delver_lense_converter.convert_delver_lense_card(&delver_lense_card)
+            } else {
+                personal_card(&headers, &record, &columns, &self.set_names)
+            };
+            match card {
+                Ok(card) => cards.push(card),
+                Err(e) => skipped.push(SkippedRow {
+                    line,
+                    reason: e.to_string(),
+                }),
+            }
+        }
+        Ok(ImportedCards {
+            format,
+            cards,
+            skipped,
+        })
+    }
+}
+
+fn csv_error(path: &str, source: csv::Error) -> ParseError {
+    ParseError::Csv {
+        path: path.to_string(),
+        source,
+    }
+}
+
+fn personal_card(
+    headers: &StringRecord,
+    record: &StringRecord,
+    columns: &Columns,
+    set_names: &HashMap<String, SetName>,
+) -> Result<PersonalCard, ParseError> {
+    let get = |column: &str| {
+        headers
+            .iter()
+            .position(|header| header.trim() == column)
+            .and_then(|index| record.get(index))
+            .map(str::trim)
+            .unwrap_or("")
+    };
+
+    let name = CardName::new(get(columns.name).to_string())?;
+    let set = match set_names.get(&get(columns.set).to_lowercase()) {
+        Some(set) if columns.set_is_code => set.clone(),
+        _ => SetName::new(get(columns.set).to_string())?,
+    };
+    let count = get(columns.quantity)
+        .parse()
+        .map_err(|_| ParseError::field("quantity", get(columns.quantity), "not a count"))?;
+    let currency = columns
+        .currency
+        .and_then(|column| get(column).to_uppercase().parse().ok())
+        .unwrap_or(columns.default_currency);
+    let price = parse_price(get(columns.price), currency)?;
+    let rarity = columns
+        .rarity
+        .and_then(|column| get(column).parse().ok())
+        .unwrap_or(Rarity::Common);
+    let condition = match columns.condition.map(get).filter(|value| !value.is_empty()) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| ParseError::field("condition", value, "not a known condition"))?,
+        None => Condition::default(),
+    };
+    let language = match columns.language.map(get).filter(|value| !value.is_empty()) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| ParseError::field("language", value, "not a known language"))?,
+        None => Language::default(),
+    };
+    Ok(PersonalCard {
+        name,
+        set,
+        foil: is_foil(get(columns.foil)),
+        price,
+        count,
+        color: Colour::Unknown,
+        rarity,
+        condition,
+        language,
+    })
+}
+
+fn is_foil(value: &str) -> bool {
+    matches!(
+        value.to_lowercase().as_str(),
+        "foil" | "etched" | "true" | "yes" | "1"
+    )
+}
+
+/// Reads prices like `1.23`, `$1.23` and `1,23 €`. An empty price is 0
+fn parse_price(value: &str, currency: Currency) -> Result<Price, ParseError> {
+    let currency = if value.contains('$') {
+        Currency::USD
+    } else if value.contains('€') {
+        Currency::EUR
+    } else {
+        currency
+    };
+    let amount: String = value
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == ',' || *c == '-')
+        .collect::<String>()
+        .replace(',', ".");
+    if amount.is_empty() {
+        return Ok(Price::new(Decimal::ZERO, currency));
+    }
+    amount
+        .parse()
+        .map(|amount| Price::new(amount, currency))
+        .map_err(|_| ParseError::field("price", value, "not a price"))
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::test::helpers::{
+        reaper_king_card_name, reaper_king_scryfall_card_expensive, reaper_king_set_name,
+    };
+
+    fn names(imported: &ImportedCards) -> Vec<(&str, &str, bool, u32)> {
+        imported
+            .cards
+            .iter()
+            .map(|card| {
+                (
+                    card.name.almost_raw.as_str(),
+                    card.set.raw.as_str(),
+                    card.foil,
+                    card.count,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_delver_lens_export() {
+        let imported = CollectionImporter::new()
+            .get_personal_cards_from_file("src/test/list_of_cards_from_delver_lens.csv")
+            .unwrap();
+
+        assert_eq!(imported.format, CollectionFormat::DelverLens);
+        assert_eq!(
+            imported.cards,
+            DelverLenseConverter::new()
+                .get_delver_lense_cards_from_file("src/test/list_of_cards_from_delver_lens.csv")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_delver_lens_export_skips_unreadable_rows() {
+        let csv = "Name,Foil,Edition,Price,Quantity,Color,Rarity\n\
+                   \"Reaper King\",\"\",\"Shadowmoor\",\"2,06\u{a0}€\",\"1\",\"wuberg\",\"R\"\n\
+                   \"Plains\",\"\",\"Shadowmoor\",\"0,02\u{a0}€\",\"5\",\"w\",\"C\"\n\
+                   \"Counterspell\",\"\",\"Ice Age\",\"1,24\u{a0}€\",\"many\",\"blue\",\"C\"\n\
+                   \"Counterspell\",\"Foil\",\"Fourth Edition\",\"0,94\u{a0}€\",\"2\",\"blue\",\"C\"\n";
+
+        let imported = CollectionImporter::new().read(csv.as_bytes()).unwrap();
+
+        assert_eq!(imported.format, CollectionFormat::DelverLens);
+        assert_eq!(
+            names(&imported),
+            vec![
+                ("Reaper King", "Shadowmoor", false, 1),
+                ("Counterspell", "Fourth Edition", true, 2),
+            ]
+        );
+        assert_eq!(
+            imported
+                .skipped
+                .iter()
+                .map(|row| row.line)
+                .collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+    }
+
+    #[test]
+    fn test_moxfield_export() {
+        let csv = "\"Count\",\"Tradelist Count\",\"Name\",\"Edition\",\"Condition\",\"Language\",\"Foil\",\"Tags\",\"Last Modified\",\"Collector Number\",\"Alter\",\"Proxy\",\"Purchase Price\"\n\
+                   \"2\",\"0\",\"Counterspell\",\"ice\",\"Near Mint\",\"English\",\"\",\"\",\"2025-03-01 10:00:00.000000\",\"64\",\"False\",\"False\",\"1.5\"\n\
+                   \"1\",\"0\",\"Reaper King\",\"shm\",\"Near Mint\",\"English\",\"foil\",\"\",\"2025-03-01 10:00:00.000000\",\"260\",\"False\",\"False\",\"\"\n";
+
+        let imported = CollectionImporter::new().read(csv.as_bytes()).unwrap();
+
+        assert_eq!(imported.format, CollectionFormat::Moxfield);
+        assert_eq!(
+            names(&imported),
+            vec![
+                ("Counterspell", "ice", false, 2),
+                ("Reaper King", "shm", true, 1),
+            ]
+        );
+        assert_eq!(
+            imported.cards[0].price,
+            Price::new(dec!(1.5), Currency::USD)
+        );
+        assert_eq!(imported.cards[1].price.amount, Decimal::ZERO);
+        assert_eq!(imported.cards[1].color, Colour::Unknown);
+    }
+
+    #[test]
+    fn test_moxfield_set_codes_become_set_names() {
+        let csv = "\"Count\",\"Tradelist Count\",\"Name\",\"Edition\",\"Condition\",\"Language\",\"Foil\",\"Tags\",\"Last Modified\",\"Collector Number\",\"Alter\",\"Proxy\",\"Purchase Price\"\n\
+                   \"1\",\"0\",\"Reaper King\",\"shm\",\"Near Mint\",\"English\",\"foil\",\"\",\"2025-03-01 10:00:00.000000\",\"260\",\"False\",\"False\",\"\"\n\
+                   \"1\",\"0\",\"Counterspell\",\"ice\",\"Near Mint\",\"English\",\"\",\"\",\"2025-03-01 10:00:00.000000\",\"64\",\"False\",\"False\",\"\"\n";
+        let scryfall_cards = HashMap::from([(
+            reaper_king_card_name(),
+            vec![reaper_king_scryfall_card_expensive()],
+        )]);
+
+        let imported = CollectionImporter::with_scryfall_cards(&scryfall_cards)
+            .read(csv.as_bytes())
+            .unwrap();
+
+        assert_eq!(imported.cards[0].set, reaper_king_set_name());
+        assert_eq!(imported.cards[0].set.raw, "Shadowmoor");
+        // Not in the Scryfall cards
+        assert_eq!(imported.cards[1].set.raw, "ice");
+    }
+
+    #[test]
+    fn test_deckbox_export() {
+        let csv = "Count,Tradelist Count,Name,Edition,Card Number,Condition,Language,Foil,Signed,Artist Proof,Altered Art,Misprint,Promo,Textless,My Price,Type,Cost,Rarity,Price\n\
+                   1,0,Reaper King,Shadowmoor,260,Near Mint,English,foil,,,,,,,,Artifact Creature,{2/W}{2/U}{2/B}{2/R}{2/G},Rare,$4.20\n\
+                   20,0,Island,Ice Age,,Near Mint,English,,,,,,,,,Basic Land,,Land,$0.10\n";
+
+        let imported = CollectionImporter::new().read(csv.as_bytes()).unwrap();
+
+        assert_eq!(imported.format, CollectionFormat::Deckbox);
+        assert_eq!(
+            names(&imported),
+            vec![("Reaper King", "Shadowmoor", true, 1)]
+        );
+        assert_eq!(imported.cards[0].rarity, Rarity::Rare);
+        assert_eq!(
+            imported.cards[0].price,
+            Price::new(dec!(4.20), Currency::USD)
+        );
+        assert_eq!(imported.skipped.len(), 1);
+        assert_eq!(imported.skipped[0].line, 3);
+    }
+
+    #[test]
+    fn test_manabox_export() {
+        let csv = "Name,Set code,Set name,Collector number,Foil,Rarity,Quantity,ManaBox ID,Scryfall ID,Purchase price,Misprint,Altered,Condition,Language,Purchase price currency\n\
+                   Counterspell,ICE,Ice Age,64,normal,common,3,1,abc,0.80,false,false,near_mint,en,EUR\n\
+                   Reaper King,SHM,Shadowmoor,260,foil,rare,1,2,def,12,false,false,near_mint,en,\n\
+                   Brainstorm,ICE,Ice Age,61,normal,common,many,3,ghi,0.10,false,false,near_mint,en,EUR\n";
+
+        let imported = CollectionImporter::new().read(csv.as_bytes()).unwrap();
+
+        assert_eq!(imported.format, CollectionFormat::ManaBox);
+        assert_eq!(
+            names(&imported),
+            vec![
+                ("Counterspell", "Ice Age", false, 3),
+                ("Reaper King", "Shadowmoor", true, 1),
+            ]
+        );
+        assert_eq!(
+            imported.cards[0].price,
+            Price::new(dec!(0.80), Currency::EUR)
+        );
+        assert_eq!(imported.cards[1].price, Price::new(dec!(12), Currency::USD));
+        assert_eq!(imported.cards[1].rarity, Rarity::Rare);
+        assert_eq!(
+            imported.skipped,
+            vec![SkippedRow {
+                line: 4,
+                reason: "Invalid quantity 'many': not a count".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_dragon_shield_export() {
+        let csv = "\"sep=,\"\n\
+                   Folder Name,Quantity,Trade Quantity,Card Name,Set Code,Set Name,Card Number,Condition,Printing,Language,Price Bought,Date Bought,LOW,MID,MARKET\n\
+                   Binder,2,0,Counterspell,ICE,Ice Age,64,NearMint,Normal,English,0.50,2025-03-01,0.40,0.60,0.55\n\
+                   Binder,1,0,Reaper King,SHM,Shadowmoor,260,Played,Foil,German,,2025-03-01,3.00,4.00,3.50\n\
+                   Binder,1,0,Brainstorm,ICE,Ice Age,61,Mangled,Normal,English,,2025-03-01,0.10,0.20,0.15\n";
+
+        let imported = CollectionImporter::new().read(csv.as_bytes()).unwrap();
+
+        assert_eq!(imported.format, CollectionFormat::DragonShield);
+        assert_eq!(
+            names(&imported),
+            vec![
+                ("Counterspell", "Ice Age", false, 2),
+                ("Reaper King", "Shadowmoor", true, 1),
+            ]
+        );
+        assert_eq!(imported.cards[0].condition, Condition::NearMint);
+        assert_eq!(imported.cards[1].condition, Condition::Played);
+        assert_eq!(imported.cards[1].language, Language::German);
+        assert_eq!(
+            imported.skipped[0].reason,
+            "Invalid condition 'Mangled': not a known condition".to_string()
+        );
+    }
+
+    #[test]
+    fn test_unknown_header_fails() {
+        let csv = "card,amount\nCounterspell,2\n";
+        assert!(CollectionImporter::new().read(csv.as_bytes()).is_err());
+    }
+}
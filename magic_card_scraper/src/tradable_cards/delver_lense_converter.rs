@@ -1,11 +1,14 @@
 use csv::Reader;
-use std::{error::Error, fs::File, num::ParseFloatError};
+use rust_decimal::Decimal;
 
 use crate::cards::{
-    cardname::CardName, currency::Currency, delver_lense_card::DelverLenseCard,
-    personalcard::PersonalCard, price::Price, rarity::Rarity, setname::SetName,
+    cardname::CardName, colour::Colour, condition::Condition, currency::Currency,
+    delver_lense_card::DelverLenseCard, language::Language, personalcard::PersonalCard,
+    price::Price, rarity::Rarity, setname::SetName,
 };
+use crate::error::ParseError;
 
+#[derive(Default)]
 pub struct DelverLenseConverter {}
 
 impl DelverLenseConverter {
@@ -17,11 +20,11 @@ impl DelverLenseConverter {
     pub fn get_delver_lense_cards_from_file(
         &self,
         file_path: &str,
-    ) -> Result<Vec<PersonalCard>, Box<dyn Error>> {
+    ) -> Result<Vec<PersonalCard>, ParseError> {
         // Read the CSV file and parse it into a vector of MyData
         let delver_lense_cards = self.read_csv(file_path)?;
 
-        Ok(self.convert_delver_lense_card_to_personal_card(delver_lense_cards))
+        self.convert_delver_lense_card_to_personal_card(delver_lense_cards)
     }
 
     // problem to soleve. Given that I have several of the same card from delverlense,
@@ -29,50 +32,63 @@ impl DelverLenseConverter {
     pub fn convert_delver_lense_card_to_personal_card(
         &self,
         cards: Vec<DelverLenseCard>,
-    ) -> Vec<PersonalCard> {
+    ) -> Result<Vec<PersonalCard>, ParseError> {
         cards
             .iter()
-            .map(|card| PersonalCard {
-                name: CardName::new(card.Name.clone()).unwrap(),
-                set: SetName::new(card.Edition.clone()).unwrap(),
-                foil: !card.Foil.is_empty(),
-                price: self
-                    .convert_string_price_to_price(card.Price.clone())
-                    .unwrap(),
-                count: card.Quantity.parse().unwrap(),
-                color: card.Color.as_str().parse().unwrap(),
-                rarity: match card.Rarity.as_str() {
-                    "C" => Rarity::Common,
-                    "U" => Rarity::Uncommon,
-                    "R" => Rarity::Rare,
-                    "M" => Rarity::Mythic,
-                    _ => Rarity::Common,
-                },
-            })
-            .collect::<Vec<PersonalCard>>()
+            .map(|card| self.convert_delver_lense_card(card))
+            .collect()
+    }
+
+    pub fn convert_delver_lense_card(
+        &self,
+        card: &DelverLenseCard,
+    ) -> Result<PersonalCard, ParseError> {
+        Ok(PersonalCard {
+            name: CardName::new(card.Name.clone())?,
+            set: SetName::new(card.Edition.clone())?,
+            foil: !card.Foil.is_empty(),
+            price: self
+                .convert_string_price_to_price(card.Price.clone())
+                .map_err(|e| ParseError::field("price", &card.Price, e))?,
+            count: card
+                .Quantity
+                .parse()
+                .map_err(|e| ParseError::field("quantity", &card.Quantity, e))?,
+            color: card.Color.as_str().parse().unwrap_or(Colour::Unknown),
+            rarity: match card.Rarity.as_str() {
+                "C" => Rarity::Common,
+                "U" => Rarity::Uncommon,
+                "R" => Rarity::Rare,
+                "M" => Rarity::Mythic,
+                _ => Rarity::Common,
+            },
+            condition: Condition::default(),
+            language: Language::default(),
+        })
     }
 
     fn convert_string_price_to_price(
         &self,
         price_as_text: String,
-    ) -> Result<Price, ParseFloatError> {
+    ) -> Result<Price, rust_decimal::Error> {
         let res = price_as_text.replace("\u{a0}€", "").replace(",", ".");
-        res.parse::<f64>()
+        res.parse::<Decimal>()
             .map(|amount| Price::new(amount, Currency::EUR))
     }
 
     // Function to read a CSV file and parse it into a vector of MyData
-    fn read_csv(&self, file_path: &str) -> Result<Vec<DelverLenseCard>, Box<dyn Error>> {
-        // Open the CSV file
-        let file = File::open(file_path)?;
-
-        // Create a CSV reader
-        let mut rdr = Reader::from_reader(file);
+    fn read_csv(&self, file_path: &str) -> Result<Vec<DelverLenseCard>, ParseError> {
+        let csv_error = |source| ParseError::Csv {
+            path: file_path.to_string(),
+            source,
+        };
+        // Open the CSV file and create a CSV reader
+        let mut rdr = Reader::from_path(file_path).map_err(csv_error)?;
 
         // Deserialize each record into a MyData object
         let mut results = Vec::new();
         for result in rdr.deserialize() {
-            let record: DelverLenseCard = result?; // Deserialize the row
+            let record: DelverLenseCard = result.map_err(csv_error)?; // Deserialize the row
             results.push(record);
         }
 
@@ -145,8 +161,9 @@ mod tests {
     fn test_reading_delver_lense_cards_and_converting_to_personal_card() {
         let cards = vec![card_1(), card_2(), card_3(), card_4(), card_5()];
         let delver_lense_converter = DelverLenseConverter::new();
-        let expected_cards =
-            delver_lense_converter.convert_delver_lense_card_to_personal_card(cards);
+        let expected_cards = delver_lense_converter
+            .convert_delver_lense_card_to_personal_card(cards)
+            .unwrap();
         let file_path = "src/test/list_of_cards_from_delver_lens.csv";
         let result = delver_lense_converter.get_delver_lense_cards_from_file(file_path);
         assert_eq!(result.unwrap(), expected_cards);
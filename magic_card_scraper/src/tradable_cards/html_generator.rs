@@ -1,4 +1,10 @@
-use crate::cards::{currency::Currency, tradable_card::TradeableCard};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::cards::{
+    currency::{Currency, ExchangeRates},
+    tradable_card::TradeableCard,
+};
 
 pub fn generate_html_header() -> String {
     format!(
@@ -50,7 +56,7 @@ pub fn generate_html_header() -> String {
                 <th class="no-sort">Image</th>
                 <th>Name/Set</th>
                 <th data-sort-method="number">Trade-in price</th>
-                <th data-sort-method="number">MCM price</th>
+                <th data-sort-method="number" title="MCM trend price from the Delver Lens export">MCM price (Delver Lens)</th>
                 <th data-sort-method="number">Vendor requested amnt</th>
                 <th data-sort-method="number">Tradable cards amnt</th>
                 <th data-sort-method="string">Color</th>
@@ -79,25 +85,26 @@ pub fn generate_html_footer() -> String {
     )
 }
 
-pub fn generate_card_row(card: &TradeableCard) -> String {
+pub fn generate_card_row(card: &TradeableCard, rates: &ExchangeRates) -> String {
     let image_url = card.image_url.clone();
     let name = card.name.almost_raw.clone();
     let set_name = card.set.raw.clone();
     let foil_text = if card.foil { " (Foil)" } else { "" };
-    let trade_in_price_sek = card.trade_in_price.convert_to(Currency::SEK);
+    let trade_in_price_sek = card.trade_in_price.convert_to(Currency::SEK, rates).amount;
 
-    let mcm_price_sek = card.mcm_price.convert_to(Currency::SEK);
+    let mcm_price_sek = card.mcm_price.convert_to(Currency::SEK, rates).amount;
 
     let vendor_stock = card.card_ammount_requested_by_vendor;
     let tradable_stock = card.cards_to_trade;
-    let color = card.color.clone();
+    let color = card.color;
     let rarity = card.rarity;
-    let percentual_difference = if mcm_price_sek > 0.0 && trade_in_price_sek > mcm_price_sek {
-        ((trade_in_price_sek - mcm_price_sek) / mcm_price_sek) * 100.0
-    } else {
-        0.0
-    };
-    let is_value_trade = percentual_difference >= 50.0;
+    let percentual_difference =
+        if mcm_price_sek > Decimal::ZERO && trade_in_price_sek > mcm_price_sek {
+            ((trade_in_price_sek - mcm_price_sek) / mcm_price_sek) * dec!(100)
+        } else {
+            Decimal::ZERO
+        };
+    let is_value_trade = percentual_difference >= dec!(50);
     format!(
         r#"
         <tr data-value-trade="{is_value_trade}">
@@ -120,11 +127,12 @@ pub fn generate_card_row(card: &TradeableCard) -> String {
     )
 }
 
-pub fn generate_page_content(cards: &[TradeableCard]) -> String {
+/// The tradable cards page, with the prices in SEK with the given rates
+pub fn generate_page_content(cards: &[TradeableCard], rates: &ExchangeRates) -> String {
     let mut content = generate_html_header();
 
     for card in cards {
-        content.push_str(&generate_card_row(card));
+        content.push_str(&generate_card_row(card, rates));
     }
 
     content.push_str(&generate_html_footer());
@@ -1,3 +1,5 @@
+pub mod collection_importer;
 pub mod delver_lense_converter;
 pub mod html_generator;
+pub mod sell_list;
 pub mod tradable_card_comparer;
@@ -0,0 +1,344 @@
+//! The cards the stores won't take in trade, written as a Cardmarket stock upload and as a
+//! generic sell list, with a price suggested from the Scryfall EUR trend. The trend is for near
+//! mint english cards, so other cards get the discount of their condition and language.
+//!
+//! The Cardmarket file leaves `idProduct` empty, Cardmarket matches the rows on name and
+//! expansion. Cards without any price are left out of it since Cardmarket needs a price, they
+//! are still on the sell list. Every card is listed in the condition and language it has in the
+//! collection export.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::cards::{
+    cardname::CardName,
+    currency::{Currency, ExchangeRates},
+    personalcard::PersonalCard,
+    price::Price,
+    scryfallcard::ScryfallCard,
+};
+use crate::comparer::Discounts;
+use crate::error::StorageError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SellListSettings {
+    /// The suggested price is the EUR trend times this
+    pub price_factor: Decimal,
+    /// Lowest suggested price in EUR
+    pub min_price: Decimal,
+    /// Converts trends in other currencies to EUR and the suggested prices to SEK
+    pub exchange_rates: ExchangeRates,
+    /// Taken off the trend of cards that aren't near mint english
+    pub discounts: Discounts,
+}
+
+impl Default for SellListSettings {
+    fn default() -> Self {
+        Self {
+            price_factor: Decimal::ONE,
+            min_price: Decimal::new(2, 2),
+            exchange_rates: ExchangeRates::default(),
+            discounts: Discounts::default(),
+        }
+    }
+}
+
+/// A card to sell with what it should go for
+#[derive(Debug, PartialEq, Clone)]
+pub struct SellCard {
+    pub card: PersonalCard,
+    /// Scryfall EUR trend of the printing, the price in the collection export if there is none
+    pub trend: Option<Price>,
+    /// In EUR
+    pub suggested_price: Option<Price>,
+}
+
+#[derive(Serialize)]
+struct CardmarketRow<'a> {
+    #[serde(rename = "idProduct")]
+    id_product: &'a str,
+    #[serde(rename = "Count")]
+    count: u32,
+    #[serde(rename = "Name")]
+    name: &'a str,
+    #[serde(rename = "Expansion")]
+    expansion: &'a str,
+    #[serde(rename = "Condition")]
+    condition: &'a str,
+    #[serde(rename = "Language")]
+    language: String,
+    #[serde(rename = "isFoil")]
+    is_foil: &'a str,
+    #[serde(rename = "isSigned")]
+    is_signed: &'a str,
+    #[serde(rename = "isAltered")]
+    is_altered: &'a str,
+    #[serde(rename = "isPlayset")]
+    is_playset: &'a str,
+    #[serde(rename = "Price")]
+    price: String,
+    #[serde(rename = "Comments")]
+    comments: &'a str,
+}
+
+#[derive(Serialize)]
+struct SellListRow<'a> {
+    name: &'a str,
+    set: &'a str,
+    foil: bool,
+    count: u32,
+    condition: String,
+    language: String,
+    trend_eur: Option<String>,
+    suggested_price_eur: Option<String>,
+    suggested_price_sek: Option<String>,
+}
+
+/// The amount with two decimals, e.g. `2.00`
+fn amount_text(price: Price) -> String {
+    format!("{:.2}", price.amount)
+}
+
+/// EUR trend of the printing, the foil trend for foils
+fn scryfall_trend(
+    scryfall_cards: &HashMap<CardName, Vec<ScryfallCard>>,
+    card: &PersonalCard,
+) -> Option<Price> {
+    scryfall_cards
+        .get(&card.name)
+        .into_iter()
+        .flatten()
+        .filter(|scryfall_card| scryfall_card.set.cleaned == card.set.cleaned)
+        .find_map(|scryfall_card| match card.foil {
+            true => scryfall_card.prices.eur_foil,
+            false => scryfall_card.prices.eur,
+        })
+}
+
+/// Prices the cards with the Scryfall trend of their printing, less the discount of their
+/// condition and language. The price in the export is already for the card as it is
+pub fn sell_cards(
+    cards: &[PersonalCard],
+    scryfall_cards: &HashMap<CardName, Vec<ScryfallCard>>,
+    settings: &SellListSettings,
+) -> Vec<SellCard> {
+    cards
+        .iter()
+        .map(|card| {
+            let discount = settings.discounts.factor(card.condition, card.language);
+            let (trend, factor) = match scryfall_trend(scryfall_cards, card) {
+                Some(trend) => (Some(trend), discount),
+                None => (
+                    (card.price.amount > Decimal::ZERO).then_some(card.price),
+                    Decimal::ONE,
+                ),
+            };
+            let suggested_price = trend.map(|trend| {
+                let amount = trend
+                    .convert_to(Currency::EUR, &settings.exchange_rates)
+                    .amount
+                    * factor
+                    * settings.price_factor;
+                Price::new(
+                    Currency::EUR.round(amount).max(settings.min_price),
+                    Currency::EUR,
+                )
+            });
+            SellCard {
+                card: card.clone(),
+                trend,
+                suggested_price,
+            }
+        })
+        .collect()
+}
+
+/// Writes the priced cards as a Cardmarket stock upload csv
+pub fn write_cardmarket_csv(writer: impl Write, cards: &[SellCard]) -> Result<(), csv::Error> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for sell_card in cards {
+        let Some(price) = sell_card.suggested_price else {
+            continue;
+        };
+        csv_writer.serialize(CardmarketRow {
+            id_product: "",
+            count: sell_card.card.count,
+            name: &sell_card.card.name.almost_raw,
+            expansion: &sell_card.card.set.raw,
+            condition: sell_card.card.condition.cardmarket_code(),
+            language: sell_card.card.language.cardmarket_name(),
+            is_foil: if sell_card.card.foil { "X" } else { "" },
+            is_signed: "",
+            is_altered: "",
+            is_playset: "",
+            price: amount_text(price),
+            comments: "",
+        })?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// Writes every card with its trend and suggested price in EUR and SEK
+pub fn write_sell_list_csv(
+    writer: impl Write,
+    cards: &[SellCard],
+    rates: &ExchangeRates,
+) -> Result<(), csv::Error> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for sell_card in cards {
+        csv_writer.serialize(SellListRow {
+            name: &sell_card.card.name.almost_raw,
+            set: &sell_card.card.set.raw,
+            foil: sell_card.card.foil,
+            count: sell_card.card.count,
+            condition: sell_card.card.condition.to_string(),
+            language: sell_card.card.language.to_string(),
+            trend_eur: sell_card
+                .trend
+                .map(|trend| amount_text(trend.convert_to(Currency::EUR, rates))),
+            suggested_price_eur: sell_card.suggested_price.map(amount_text),
+            suggested_price_sek: sell_card
+                .suggested_price
+                .map(|price| amount_text(price.convert_to(Currency::SEK, rates))),
+        })?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// Prices the cards and saves the Cardmarket upload and the sell list
+pub fn save_sell_lists(
+    cards: &[PersonalCard],
+    scryfall_cards: &HashMap<CardName, Vec<ScryfallCard>>,
+    settings: &SellListSettings,
+    cardmarket_path: &str,
+    sell_list_path: &str,
+) -> Result<(), StorageError> {
+    let sell_cards = sell_cards(cards, scryfall_cards, settings);
+    let create = |path: &str| {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent).map_err(|e| StorageError::io(parent, e))?;
+        }
+        fs::File::create(path).map_err(|e| StorageError::io(path, e))
+    };
+    let csv_error = |path: &str, source| StorageError::Csv {
+        path: path.to_string(),
+        source,
+    };
+    write_cardmarket_csv(create(cardmarket_path)?, &sell_cards)
+        .map_err(|e| csv_error(cardmarket_path, e))?;
+    write_sell_list_csv(
+        create(sell_list_path)?,
+        &sell_cards,
+        &settings.exchange_rates,
+    )
+    .map_err(|e| csv_error(sell_list_path, e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::cards::{condition::Condition, language::Language};
+    use crate::test::helpers::{
+        counterspell_personal_card, reaper_king_personal_card, reaper_king_scryfall_card_cheap,
+        reaper_king_scryfall_card_expensive,
+    };
+
+    fn priced_cards(settings: &SellListSettings) -> Vec<SellCard> {
+        let mut foil_reaper_king = reaper_king_personal_card();
+        foil_reaper_king.foil = true;
+        foil_reaper_king.condition = Condition::Played;
+        foil_reaper_king.language = Language::German;
+        let mut unpriced_counterspell = counterspell_personal_card();
+        unpriced_counterspell.price = Price::new(Decimal::ZERO, Currency::EUR);
+        let scryfall_cards = HashMap::from([(
+            reaper_king_personal_card().name,
+            vec![
+                reaper_king_scryfall_card_cheap(),
+                reaper_king_scryfall_card_expensive(),
+            ],
+        )]);
+        sell_cards(
+            &[
+                foil_reaper_king,
+                counterspell_personal_card(),
+                unpriced_counterspell,
+            ],
+            &scryfall_cards,
+            settings,
+        )
+    }
+
+    #[test]
+    fn test_suggested_price_from_trend() {
+        let settings = SellListSettings {
+            price_factor: dec!(0.9),
+            min_price: dec!(0.9),
+            ..SellListSettings::default()
+        };
+
+        let suggested: Vec<Option<Price>> = priced_cards(&settings)
+            .iter()
+            .map(|card| card.suggested_price)
+            .collect();
+
+        // The Shadowmoor foil trend of 2 EUR less 30% for played and 20% for german, and the
+        // 0.94 EUR from the export raised to the lowest price
+        assert_eq!(
+            suggested,
+            vec![
+                Some(Price::new(dec!(1.01), Currency::EUR)),
+                Some(Price::new(dec!(0.9), Currency::EUR)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cardmarket_csv() {
+        let settings = SellListSettings::default();
+        let mut csv = Vec::new();
+
+        write_cardmarket_csv(&mut csv, &priced_cards(&settings)).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "idProduct,Count,Name,Expansion,Condition,Language,isFoil,isSigned,isAltered,isPlayset,Price,Comments\n\
+             ,1,Reaper King,Shadowmoor,PL,German,X,,,,1.12,\n\
+             ,2,Counterspell,Magic 25,NM,English,,,,,0.94,\n"
+        );
+    }
+
+    #[test]
+    fn test_sell_list_csv() {
+        let settings = SellListSettings::default();
+        let mut csv = Vec::new();
+
+        write_sell_list_csv(&mut csv, &priced_cards(&settings), &settings.exchange_rates).unwrap();
+
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "name,set,foil,count,condition,language,trend_eur,suggested_price_eur,suggested_price_sek"
+        );
+        assert_eq!(
+            lines[1],
+            "Reaper King,Shadowmoor,true,1,PL,German,2.00,1.12,12.35"
+        );
+        assert_eq!(
+            lines[2],
+            "Counterspell,Magic 25,false,2,NM,English,0.94,0.94,10.37"
+        );
+        assert_eq!(lines[3], "Counterspell,Magic 25,false,2,NM,English,,,");
+    }
+}
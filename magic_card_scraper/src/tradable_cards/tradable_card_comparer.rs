@@ -1,5 +1,5 @@
-use log::{debug, info};
-use std::{collections::HashMap, error::Error};
+use log::{debug, error, info};
+use std::collections::HashMap;
 use urlencoding::encode;
 
 use crate::{
@@ -9,29 +9,61 @@ use crate::{
         vendorcard::VendorCard,
     },
     dragonslair_scraper::DragonslairScraper,
-    utilities::config::CONFIG,
+    error::ScrapeError,
 };
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeInSettings {
+    /// Search Dragonslair for the cards that are not in the given vendor cards
+    pub check_dl_when_comparing: bool,
+}
+
+impl Default for TradeInSettings {
+    fn default() -> Self {
+        Self {
+            check_dl_when_comparing: true,
+        }
+    }
+}
+
 pub struct TradableCardsComparer {
     dl_scraper: DragonslairScraper,
+    settings: TradeInSettings,
 }
 
 impl TradableCardsComparer {
-    pub fn new(dl_scraper: DragonslairScraper) -> Self {
-        TradableCardsComparer { dl_scraper }
+    pub fn new(dl_scraper: DragonslairScraper, settings: TradeInSettings) -> Self {
+        TradableCardsComparer {
+            dl_scraper,
+            settings,
+        }
     }
 
     pub async fn get_tradable_cards(
         &self,
         personal_cards: Vec<PersonalCard>,
         vendor_cards: HashMap<CardName, Vec<VendorCard>>,
-    ) -> Result<Vec<TradeableCard>, Box<dyn Error>> {
-        let (mut tradable_cards, leftover_personal_cards) =
+    ) -> Result<Vec<TradeableCard>, ScrapeError> {
+        let (tradable_cards, _unwanted_cards) = self
+            .get_tradable_and_unwanted_cards(personal_cards, vendor_cards)
+            .await?;
+        Ok(tradable_cards)
+    }
+
+    /// The tradable cards and the personal cards Dragonslair doesn't take, which are left to
+    /// sell elsewhere. Those are the cards it doesn't have at all, the ones it already has as
+    /// many of as it wants and the copies beyond what it asks for
+    pub async fn get_tradable_and_unwanted_cards(
+        &self,
+        personal_cards: Vec<PersonalCard>,
+        vendor_cards: HashMap<CardName, Vec<VendorCard>>,
+    ) -> Result<(Vec<TradeableCard>, Vec<PersonalCard>), ScrapeError> {
+        let (mut tradable_cards, leftover_personal_cards, mut unwanted_cards) =
             self.get_tradable_and_leftover_cards(personal_cards, vendor_cards);
 
         let mut vendor_cards_with_same_name_as_leftover: Vec<VendorCard> = vec![];
 
-        if CONFIG.check_dl_when_comparing {
+        if self.settings.check_dl_when_comparing {
             for card in &leftover_personal_cards {
                 let card_name_lowercase = card.name.almost_raw.to_lowercase();
                 let card_name_encoded = encode(&card_name_lowercase);
@@ -57,9 +89,13 @@ impl TradableCardsComparer {
 
                 for url in urls {
                     info!("Fetching url: {}", url);
-                    let mut cards = fetch_and_parse(&url).await.unwrap();
-                    info!("Fetched cards: {:?}", &cards);
-                    vendor_cards_with_same_name_as_leftover.append(&mut cards);
+                    match fetch_and_parse(self.dl_scraper.client(), &url).await {
+                        Ok((mut cards, _)) => {
+                            info!("Fetched cards: {:?}", &cards);
+                            vendor_cards_with_same_name_as_leftover.append(&mut cards);
+                        }
+                        Err(e) => error!("Error fetching cards from {}: {}", url, e),
+                    }
                 }
             }
         }
@@ -67,25 +103,31 @@ impl TradableCardsComparer {
         for card in &vendor_cards_with_same_name_as_leftover {
             grouped_vendor_cards
                 .entry(card.name.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(card.clone());
         }
 
-        let (mut more_tradable_cards, _unwanted_cards) =
+        let (mut more_tradable_cards, mut not_found_cards, mut more_unwanted_cards) =
             self.get_tradable_and_leftover_cards(leftover_personal_cards, grouped_vendor_cards);
         tradable_cards.append(&mut more_tradable_cards);
+        unwanted_cards.append(&mut more_unwanted_cards);
+        unwanted_cards.append(&mut not_found_cards);
 
         debug!("tradable cards: {:?}", &tradable_cards);
 
-        return Ok(tradable_cards);
+        Ok((tradable_cards, unwanted_cards))
     }
 
+    /// The tradable cards, the personal cards that are not in the vendor cards and the
+    /// copies the vendor doesn't want, because it has enough of the card or they are more than
+    /// it asks for
     fn get_tradable_and_leftover_cards(
         &self,
         personal_cards: Vec<PersonalCard>,
         vendor_cards: HashMap<CardName, Vec<VendorCard>>,
-    ) -> (Vec<TradeableCard>, Vec<PersonalCard>) {
+    ) -> (Vec<TradeableCard>, Vec<PersonalCard>, Vec<PersonalCard>) {
         let mut leftover_cards = vec![];
+        let mut unwanted_cards = vec![];
 
         let t_cards = personal_cards
             .iter()
@@ -103,6 +145,14 @@ impl TradableCardsComparer {
                 match card {
                     Some(v_card) => {
                         if v_card.current_stock < v_card.max_stock {
+                            let requested = v_card.max_stock - v_card.current_stock;
+                            let cards_to_trade = p_card.count.min(requested as u32);
+                            if p_card.count > cards_to_trade {
+                                unwanted_cards.push(PersonalCard {
+                                    count: p_card.count - cards_to_trade,
+                                    ..p_card.clone()
+                                });
+                            }
                             Some(TradeableCard {
                                 name: v_card.name.clone(),
                                 set: v_card.set.clone(),
@@ -114,14 +164,14 @@ impl TradableCardsComparer {
                                     Currency::SEK,
                                 ),
                                 mcm_price: p_card.price,
-                                cards_to_trade: p_card.count.clone(),
-                                card_ammount_requested_by_vendor: v_card.max_stock
-                                    - v_card.current_stock,
+                                cards_to_trade,
+                                card_ammount_requested_by_vendor: requested,
                                 image_url: v_card.image_url.clone(),
-                                color: p_card.color.clone(),
-                                rarity: p_card.rarity.clone(),
+                                color: p_card.color,
+                                rarity: p_card.rarity,
                             })
                         } else {
+                            unwanted_cards.push(p_card.clone());
                             None
                         }
                     }
@@ -133,23 +183,24 @@ impl TradableCardsComparer {
             })
             .collect();
 
-        (t_cards, leftover_cards)
+        (t_cards, leftover_cards, unwanted_cards)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use rust_decimal_macros::dec;
     use std::collections::HashMap;
 
     use crate::{
         cards::{
-            colour::Colour, currency::Currency, delver_lense_card::DelverLenseCard, price::Price,
-            rarity::Rarity, setname::SetName, tradable_card::TradeableCard, vendor::Vendor,
-            vendorcard::VendorCard,
+            colour::Colour, condition::Condition, currency::Currency,
+            delver_lense_card::DelverLenseCard, language::Language, price::Price, rarity::Rarity,
+            setname::SetName, tradable_card::TradeableCard, vendor::Vendor, vendorcard::VendorCard,
         },
         test::helpers::{
             counterspell_forth_e, counterspell_ice_age, reaper_king_vendor_card_cheap,
-            reaper_king_vendor_card_expensive, reaper_king_vendor_card_foil,
+            reaper_king_vendor_card_expensive, reaper_king_vendor_card_foil, test_http_client,
         },
         tradable_cards::delver_lense_converter::DelverLenseConverter,
     };
@@ -225,15 +276,16 @@ mod tests {
         for card in &vendor_cards {
             vendor_cards_map
                 .entry(card.name.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(card.clone());
         }
 
         // prepare delver cards
         let raw_cards = vec![card_1(), card_2(), card_3(), card_4(), card_5()];
         let delver_lense_converter = DelverLenseConverter::new();
-        let personal_cards =
-            delver_lense_converter.convert_delver_lense_card_to_personal_card(raw_cards);
+        let personal_cards = delver_lense_converter
+            .convert_delver_lense_card_to_personal_card(raw_cards)
+            .unwrap();
 
         // prepare result
         let tradeable_card1 = TradeableCard {
@@ -242,8 +294,8 @@ mod tests {
             foil: true,
             prerelease: false,
             tradeable_vendor: Vendor::Dragonslair,
-            trade_in_price: Price::new(100.0, Currency::SEK),
-            mcm_price: Price::new(200.0, Currency::EUR),
+            trade_in_price: Price::new(dec!(100.0), Currency::SEK),
+            mcm_price: Price::new(dec!(200.0), Currency::EUR),
             cards_to_trade: 1,
             card_ammount_requested_by_vendor: 1,
             image_url: "https://astraeus.dragonslair.se/images/4026/product".to_string(),
@@ -256,8 +308,8 @@ mod tests {
             foil: false,
             prerelease: false,
             tradeable_vendor: Vendor::Dragonslair,
-            trade_in_price: Price::new(50.0, Currency::SEK),
-            mcm_price: Price::new(2.06, Currency::EUR),
+            trade_in_price: Price::new(dec!(50.0), Currency::SEK),
+            mcm_price: Price::new(dec!(2.06), Currency::EUR),
             cards_to_trade: 1,
             card_ammount_requested_by_vendor: 1,
             image_url: "https://astraeus.dragonslair.se/images/4026/product".to_string(),
@@ -271,8 +323,8 @@ mod tests {
             foil: false,
             prerelease: false,
             tradeable_vendor: Vendor::Dragonslair,
-            trade_in_price: Price::new(50.0, Currency::SEK),
-            mcm_price: Price::new(1.24, Currency::EUR),
+            trade_in_price: Price::new(dec!(50.0), Currency::SEK),
+            mcm_price: Price::new(dec!(1.24), Currency::EUR),
             cards_to_trade: 1,
             card_ammount_requested_by_vendor: 2,
             image_url: "https://astraeus.dragonslair.se/images/4026/product".to_string(),
@@ -281,10 +333,11 @@ mod tests {
         };
         let expected_cards = vec![tradeable_card1, tradeable_card2, tradeable_card4];
 
-        let dl_scraper = DragonslairScraper::new("www.test.com", None, reqwest::Client::new());
-        let tradable_cards_comparer = TradableCardsComparer::new(dl_scraper);
+        let dl_scraper = DragonslairScraper::new("www.test.com", None, test_http_client(), 20);
+        let tradable_cards_comparer =
+            TradableCardsComparer::new(dl_scraper, TradeInSettings::default());
 
-        let (result_v_cards, leftover_cards) = tradable_cards_comparer
+        let (result_v_cards, leftover_cards, unwanted_cards) = tradable_cards_comparer
             .get_tradable_and_leftover_cards(personal_cards.clone(), vendor_cards_map);
 
         assert_eq!(result_v_cards, expected_cards);
@@ -292,6 +345,7 @@ mod tests {
             leftover_cards,
             vec![personal_cards[2].clone(), personal_cards[4].clone()]
         );
+        assert!(unwanted_cards.is_empty());
     }
 
     #[test]
@@ -303,14 +357,15 @@ mod tests {
         for card in &vendor_cards {
             vendor_cards_map
                 .entry(card.name.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(card.clone());
         }
 
         let raw_cards = vec![card_1(), card_4()];
         let delver_lense_converter = DelverLenseConverter::new();
-        let personal_cards =
-            delver_lense_converter.convert_delver_lense_card_to_personal_card(raw_cards);
+        let personal_cards = delver_lense_converter
+            .convert_delver_lense_card_to_personal_card(raw_cards)
+            .unwrap();
 
         let _tradeable_card1 = TradeableCard {
             name: CardName::new("Reaper King".to_string()).unwrap(),
@@ -318,8 +373,8 @@ mod tests {
             foil: true,
             prerelease: false,
             tradeable_vendor: Vendor::Dragonslair,
-            trade_in_price: Price::new(100.0, Currency::SEK),
-            mcm_price: Price::new(200.0, Currency::EUR),
+            trade_in_price: Price::new(dec!(100.0), Currency::SEK),
+            mcm_price: Price::new(dec!(200.0), Currency::EUR),
             cards_to_trade: 1,
             card_ammount_requested_by_vendor: 1,
             image_url: "https://astraeus.dragonslair.se/images/4026/product".to_string(),
@@ -333,8 +388,8 @@ mod tests {
             foil: false,
             prerelease: false,
             tradeable_vendor: Vendor::Dragonslair,
-            trade_in_price: Price::new(50.0, Currency::SEK),
-            mcm_price: Price::new(1.24, Currency::EUR),
+            trade_in_price: Price::new(dec!(50.0), Currency::SEK),
+            mcm_price: Price::new(dec!(1.24), Currency::EUR),
             cards_to_trade: 1,
             card_ammount_requested_by_vendor: 2,
             image_url: "https://astraeus.dragonslair.se/images/4026/product".to_string(),
@@ -345,13 +400,53 @@ mod tests {
         let expected_tradable_cards = vec![tradeable_card4];
         let expected_leftover_cards = vec![personal_cards[0].clone()];
 
-        let dl_scraper = DragonslairScraper::new("www.test.com", None, reqwest::Client::new());
-        let tradable_cards_comparer = TradableCardsComparer::new(dl_scraper);
-        let (result_v_cards, leftover_cards) = tradable_cards_comparer
+        let dl_scraper = DragonslairScraper::new("www.test.com", None, test_http_client(), 20);
+        let tradable_cards_comparer =
+            TradableCardsComparer::new(dl_scraper, TradeInSettings::default());
+        let (result_v_cards, leftover_cards, unwanted_cards) = tradable_cards_comparer
             .get_tradable_and_leftover_cards(personal_cards, vendor_cards_map);
 
         assert_eq!(result_v_cards, expected_tradable_cards);
         assert_eq!(leftover_cards, expected_leftover_cards);
+        assert!(unwanted_cards.is_empty());
+    }
+
+    #[test]
+    fn full_stock_cards_and_surplus_copies_are_unwanted() {
+        let mut full_stock_card = reaper_king_vendor_card_expensive();
+        full_stock_card.current_stock = full_stock_card.max_stock;
+        let vendor_cards_map = HashMap::from([
+            (full_stock_card.name.clone(), vec![full_stock_card]),
+            (
+                counterspell_ice_age().name.clone(),
+                vec![counterspell_ice_age()],
+            ),
+        ]);
+
+        let mut four_counterspells = card_4();
+        four_counterspells.Quantity = "4".to_string();
+        let personal_cards = DelverLenseConverter::new()
+            .convert_delver_lense_card_to_personal_card(vec![card_2(), four_counterspells])
+            .unwrap();
+
+        let dl_scraper = DragonslairScraper::new("www.test.com", None, test_http_client(), 20);
+        let tradable_cards_comparer =
+            TradableCardsComparer::new(dl_scraper, TradeInSettings::default());
+        let (result_v_cards, leftover_cards, unwanted_cards) = tradable_cards_comparer
+            .get_tradable_and_leftover_cards(personal_cards.clone(), vendor_cards_map);
+
+        assert_eq!(result_v_cards.len(), 1);
+        assert_eq!(result_v_cards[0].cards_to_trade, 2);
+        assert_eq!(result_v_cards[0].card_ammount_requested_by_vendor, 2);
+        assert!(leftover_cards.is_empty());
+        let surplus_counterspells = PersonalCard {
+            count: 2,
+            ..personal_cards[1].clone()
+        };
+        assert_eq!(
+            unwanted_cards,
+            vec![personal_cards[0].clone(), surplus_counterspells]
+        );
     }
 
     #[tokio::test]
@@ -408,14 +503,18 @@ mod tests {
             name: CardName::new("personalcard".to_string()).unwrap(),
             set: SetName::new("personal card set".to_string()).unwrap(),
             foil: false,
-            price: Price::new(10.0, Currency::SEK),
+            price: Price::new(dec!(10.0), Currency::SEK),
             count: 2,
             color: Colour::Blue,
             rarity: Rarity::Rare,
+            condition: Condition::NearMint,
+            language: Language::English,
         };
 
-        let tradable_cards_comparer =
-            TradableCardsComparer::new(DragonslairScraper::new(&url, None, reqwest::Client::new()));
+        let tradable_cards_comparer = TradableCardsComparer::new(
+            DragonslairScraper::new(&url, None, test_http_client(), 20),
+            TradeInSettings::default(),
+        );
 
         let tradable_cards = tradable_cards_comparer
             .get_tradable_cards([personal_cards].to_vec(), vendor_cards_map)
@@ -427,6 +526,71 @@ mod tests {
         assert!(tradable_cards[0].name.raw == "personalcard");
     }
 
+    #[tokio::test]
+    async fn test_get_tradable_cards_without_checking_dl() {
+        let mut server = std::thread::spawn(|| mockito::Server::new())
+            .join()
+            .unwrap();
+        let url = server.url();
+        let mock = server.mock("GET", mockito::Matcher::Any).expect(0).create();
+
+        let personal_cards = PersonalCard {
+            name: CardName::new("personalcard".to_string()).unwrap(),
+            set: SetName::new("personal card set".to_string()).unwrap(),
+            foil: false,
+            price: Price::new(dec!(10.0), Currency::SEK),
+            count: 2,
+            color: Colour::Blue,
+            rarity: Rarity::Rare,
+            condition: Condition::NearMint,
+            language: Language::English,
+        };
+
+        let tradable_cards_comparer = TradableCardsComparer::new(
+            DragonslairScraper::new(&url, None, test_http_client(), 20),
+            TradeInSettings {
+                check_dl_when_comparing: false,
+            },
+        );
+
+        let tradable_cards = tradable_cards_comparer
+            .get_tradable_cards(vec![personal_cards], HashMap::new())
+            .await
+            .unwrap();
+
+        mock.assert();
+        assert!(tradable_cards.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unwanted_cards_are_returned() {
+        let personal_card = PersonalCard {
+            name: CardName::new("personalcard".to_string()).unwrap(),
+            set: SetName::new("personal card set".to_string()).unwrap(),
+            foil: false,
+            price: Price::new(dec!(10.0), Currency::SEK),
+            count: 2,
+            color: Colour::Blue,
+            rarity: Rarity::Rare,
+            condition: Condition::NearMint,
+            language: Language::English,
+        };
+        let tradable_cards_comparer = TradableCardsComparer::new(
+            DragonslairScraper::new("http://localhost", None, test_http_client(), 20),
+            TradeInSettings {
+                check_dl_when_comparing: false,
+            },
+        );
+
+        let (tradable_cards, unwanted_cards) = tradable_cards_comparer
+            .get_tradable_and_unwanted_cards(vec![personal_card.clone()], HashMap::new())
+            .await
+            .unwrap();
+
+        assert!(tradable_cards.is_empty());
+        assert_eq!(unwanted_cards, vec![personal_card]);
+    }
+
     // #[tokio::test]
     // #[ignore]
     // async fn test_fetch_card() {
@@ -1,71 +1,1015 @@
-use std::env;
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use log::error;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
 
-#[derive(Debug, Clone)]
+use super::constants::{
+    COLLECTION_FILE, NICE_PRICE_PAGE, PRICE_HISTORY_DB, REPOSITORY_ROOT_PATH, TRADABLE_CARDS_PAGE,
+};
+use crate::cards::currency::{Currency, ExchangeRates};
+use crate::cards::{condition::Condition, language::Language, vendor::Vendor};
+use crate::comparer::{ComparerSettings, Discounts};
+use crate::decklist::ListingFilter;
+use crate::html_generator::NicePriceSettings;
+use crate::http_client::{HttpSettings, DEFAULT_USER_AGENT};
+use crate::price_source::KNOWN_PRICE_SOURCES;
+use crate::tradable_cards::sell_list::SellListSettings;
+use crate::tradable_cards::tradable_card_comparer::TradeInSettings;
+
+/// Config file that is read from the working directory if no other file is given
+pub const DEFAULT_CONFIG_FILE: &str = "mtg-prz.toml";
+
+/// Settings for a run. Built from defaults, then the config file, then env vars and
+/// last the command line flags, each layer overriding the one before.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
+    pub vendors: VendorsConfig,
+    pub output: OutputConfig,
+    pub concurrency: ConcurrencyConfig,
+    pub http: HttpConfig,
+    pub prices: PricesConfig,
+    pub exchange_rates: ExchangeRatesConfig,
+    pub trade_in: TradeInConfig,
+    pub history: HistoryConfig,
+    pub watchlist: WatchlistConfig,
+    pub notifications: NotificationsConfig,
+    pub basket: BasketConfig,
+    pub collection: CollectionConfig,
+    pub sell_list: SellListConfig,
+    pub discounts: DiscountsConfig,
+}
+
+/// Which sources to scrape. Sources that are not scraped are loaded from their newest snapshot
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VendorsConfig {
     pub dragonslair: bool,
+    pub alphaspel: bool,
+    /// Off by default, the scraper hasn't been checked against a recorded page of the store yet
+    pub manatorsk: bool,
     pub scryfall: bool,
-    pub alpha: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Directory the card snapshots are read from and written to
+    pub data_dir: String,
+    pub nice_price_page: String,
+    pub tradable_cards_page: String,
+}
+
+/// Max number of concurrent requests
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConcurrencyConfig {
+    pub dragonslair: usize,
+    pub alphaspel: usize,
+    pub manatorsk: usize,
+    pub compare: usize,
+}
+
+/// How the requests to the stores and price APIs are made
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Empty sends `magic_card_scraper/<version> (MTG price comparison)`
+    pub user_agent: String,
+    /// Longest wait for a connection or for the next bytes of a response
+    pub timeout_secs: u64,
+    /// Retries of a request that timed out or got a 429 or 5xx
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for every retry after it
+    pub backoff_ms: u64,
+    /// Max requests per second to each host, 0 for no limit
+    pub requests_per_second: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PricesConfig {
+    /// Max SEK a card above `mid_price_limit` may cost over the MCM trend to be a nice price
     pub nice_price_diff: i32,
+    /// Cards up to this price (SEK) must be at or below the MCM trend
+    pub cheap_price_limit: Decimal,
+    /// Cards up to this price (SEK) may cost `mid_price_diff` SEK over the MCM trend
+    pub mid_price_limit: Decimal,
+    pub mid_price_diff: i32,
+    /// Ask MTGStocks for prices missing from the Scryfall snapshot
     pub external_price_check: bool,
+    /// Price sources to ask for the MCM trend, in order, until one has a price
+    pub sources: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExchangeRatesConfig {
+    /// SEK per EUR, overrides the live or built in SEK rate
+    pub eur_to_sek: Option<Decimal>,
+    /// How much of each currency one EUR buys, overrides the live or built in rates.
+    /// `eur_to_sek` wins over a SEK rate here
+    pub overrides: HashMap<Currency, Decimal>,
+    /// Fetch the daily rates from Frankfurter, falling back to the last cached rates
+    pub live: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TradeInConfig {
+    /// Delver Lens csv export to compare with Dragonslair. Empty skips the trade-in step
     pub delver_lense_path: String,
+    /// Search Dragonslair for cards that are not in the Dragonslair snapshot
     pub check_dl_when_comparing: bool,
 }
 
-impl Default for Config {
+/// How many percent less than the MCM trend a card in worse condition or another language than
+/// near mint english is compared to
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscountsConfig {
+    pub excellent: Decimal,
+    pub good: Decimal,
+    pub played: Decimal,
+    pub damaged: Decimal,
+    pub non_english: Decimal,
+}
+
+/// The SQLite price history every run is recorded in
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    /// Database file, relative to `output.data_dir` unless absolute
+    pub path: String,
+    /// How many days back the trends on the nice price page go
+    pub trend_days: i64,
+}
+
+/// Cards to look out for in every run, see `crate::watchlist`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchlistConfig {
+    /// TOML or CSV watchlist. Empty skips the watchlist
+    pub path: String,
+    /// Worst condition that is a hit
+    pub min_condition: Condition,
+    /// Language that is a hit, unless `any_language` is on
+    pub language: Language,
+    pub any_language: bool,
+}
+
+/// How the trade-in leftovers are listed for sale, see `crate::tradable_cards::sell_list`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SellListConfig {
+    /// The suggested price is the Scryfall EUR trend times this
+    pub price_factor: Decimal,
+    /// Lowest suggested price in EUR
+    pub min_price: Decimal,
+}
+
+/// The cards you own, see `crate::collection`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectionConfig {
+    /// Collection file, relative to `output.data_dir` unless absolute
+    pub path: String,
+}
+
+/// What an order from each vendor costs on top of the cards, in SEK. Shipping, or what the
+/// trip to pick it up is worth
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BasketConfig {
+    pub dragonslair: Decimal,
+    pub alphaspel: Decimal,
+    pub manatorsk: Decimal,
+}
+
+/// Where the digest of new nice prices and watchlist hits is sent after a run. Nothing is
+/// sent when none of them is set
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Discord or Slack compatible webhook
+    pub webhook_url: String,
+    /// File the digest is appended to, `-` prints it
+    pub file: String,
+    pub smtp: SmtpConfig,
+}
+
+/// Mail server the digest is emailed through. The password can also be set with SMTP_PASSWORD
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmtpConfig {
+    /// Empty sends no email
+    pub host: String,
+    pub port: u16,
+    /// Upgrade the connection with STARTTLS, only turn off for a local mail server
+    pub starttls: bool,
+    pub username: String,
+    pub password: String,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+impl Default for VendorsConfig {
     fn default() -> Self {
         Self {
             dragonslair: true,
+            alphaspel: true,
+            manatorsk: false,
             scryfall: true,
-            alpha: true,
+        }
+    }
+}
+
+impl VendorsConfig {
+    /// Whether the vendor or Scryfall, by lowercase name, should be scraped
+    pub fn is_enabled(&self, name: &str) -> Result<bool, ConfigError> {
+        match name {
+            "dragonslair" => Ok(self.dragonslair),
+            "alphaspel" => Ok(self.alphaspel),
+            "manatorsk" => Ok(self.manatorsk),
+            "scryfall" => Ok(self.scryfall),
+            _ => Err(ConfigError::Invalid(format!("unknown vendor '{}'", name))),
+        }
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), ConfigError> {
+        let flag = match name {
+            "dragonslair" => &mut self.dragonslair,
+            "alphaspel" => &mut self.alphaspel,
+            "manatorsk" => &mut self.manatorsk,
+            "scryfall" => &mut self.scryfall,
+            _ => return Err(ConfigError::Invalid(format!("unknown vendor '{}'", name))),
+        };
+        *flag = enabled;
+        Ok(())
+    }
+}
+
+impl BasketConfig {
+    pub fn set_fixed_cost(&mut self, name: &str, cost: Decimal) -> Result<(), ConfigError> {
+        let fixed_cost = match name {
+            "dragonslair" => &mut self.dragonslair,
+            "alphaspel" => &mut self.alphaspel,
+            "manatorsk" => &mut self.manatorsk,
+            _ => return Err(ConfigError::Invalid(format!("unknown vendor '{}'", name))),
+        };
+        *fixed_cost = cost;
+        Ok(())
+    }
+
+    /// The fixed cost of an order from each vendor
+    pub fn fixed_costs(&self) -> HashMap<Vendor, Decimal> {
+        HashMap::from([
+            (Vendor::Dragonslair, self.dragonslair),
+            (Vendor::Alphaspel, self.alphaspel),
+            (Vendor::Manatorsk, self.manatorsk),
+        ])
+    }
+}
+
+impl Default for SellListConfig {
+    fn default() -> Self {
+        Self {
+            price_factor: dec!(1.0),
+            min_price: dec!(0.02),
+        }
+    }
+}
+
+impl Default for CollectionConfig {
+    fn default() -> Self {
+        Self {
+            path: COLLECTION_FILE.to_string(),
+        }
+    }
+}
+
+impl Default for WatchlistConfig {
+    fn default() -> Self {
+        let filter = ListingFilter::default();
+        Self {
+            path: "".to_string(),
+            min_condition: filter.min_condition,
+            language: filter.language.unwrap_or_default(),
+            any_language: filter.language.is_none(),
+        }
+    }
+}
+
+impl WatchlistConfig {
+    pub fn listing_filter(&self) -> ListingFilter {
+        ListingFilter {
+            min_condition: self.min_condition,
+            language: (!self.any_language).then_some(self.language),
+        }
+    }
+}
+
+impl Default for DiscountsConfig {
+    fn default() -> Self {
+        Self {
+            excellent: dec!(10),
+            good: dec!(20),
+            played: dec!(30),
+            damaged: dec!(50),
+            non_english: dec!(20),
+        }
+    }
+}
+
+impl Default for BasketConfig {
+    fn default() -> Self {
+        Self {
+            dragonslair: dec!(49),
+            alphaspel: dec!(49),
+            manatorsk: dec!(49),
+        }
+    }
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: REPOSITORY_ROOT_PATH.to_string(),
+            nice_price_page: NICE_PRICE_PAGE.to_string(),
+            tradable_cards_page: TRADABLE_CARDS_PAGE.to_string(),
+        }
+    }
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            dragonslair: 20,
+            alphaspel: 40,
+            manatorsk: 10,
+            compare: 25,
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            user_agent: "".to_string(),
+            timeout_secs: 30,
+            max_retries: 3,
+            backoff_ms: 500,
+            requests_per_second: 10,
+        }
+    }
+}
+
+impl Default for PricesConfig {
+    fn default() -> Self {
+        Self {
             nice_price_diff: 0,
+            cheap_price_limit: dec!(10),
+            mid_price_limit: dec!(30),
+            mid_price_diff: 5,
             external_price_check: true,
+            sources: vec!["scryfall".to_string(), "mtgstocks".to_string()],
+        }
+    }
+}
+
+impl Default for ExchangeRatesConfig {
+    fn default() -> Self {
+        Self {
+            eur_to_sek: None,
+            overrides: HashMap::new(),
+            live: true,
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: PRICE_HISTORY_DB.to_string(),
+            trend_days: 30,
+        }
+    }
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        Self {
+            host: "".to_string(),
+            port: 587,
+            starttls: true,
+            username: "".to_string(),
+            password: "".to_string(),
+            from: "".to_string(),
+            to: Vec::new(),
+        }
+    }
+}
+
+impl Default for TradeInConfig {
+    fn default() -> Self {
+        Self {
             delver_lense_path: "".to_string(),
             check_dl_when_comparing: true,
         }
     }
 }
 
+#[derive(Debug)]
+pub enum ConfigError {
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    Parse {
+        path: String,
+        source: toml::de::Error,
+    },
+    InvalidEnv {
+        name: String,
+        value: String,
+        expected: &'static str,
+    },
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, source } => {
+                write!(f, "Unable to read config file {}: {}", path, source)
+            }
+            ConfigError::Parse { path, source } => {
+                write!(f, "Invalid config file {}: {}", path, source)
+            }
+            ConfigError::InvalidEnv {
+                name,
+                value,
+                expected,
+            } => write!(
+                f,
+                "Invalid value '{}' for env var {}, expected {}",
+                value, name, expected
+            ),
+            ConfigError::Invalid(reason) => write!(f, "Invalid config: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Read { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
 impl Config {
-    pub fn new() -> Self {
-        let mut config = Config::default();
-        config.update_from_env();
-        config
+    /// Loads the defaults, the config file and the env vars. The given file must exist,
+    /// while `mtg-prz.toml` in the working directory is only read if it is there.
+    pub fn load(config_path: Option<&str>) -> Result<Self, ConfigError> {
+        let mut config = match config_path {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).is_file() => {
+                Self::from_file(DEFAULT_CONFIG_FILE)?
+            }
+            None => Config::default(),
+        };
+        config.update_from_env(|name| env::var(name).ok())?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, ConfigError> {
+        let content = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_string(),
+            source,
+        })?;
+        Self::from_toml(&content).map_err(|source| ConfigError::Parse {
+            path: path.to_string(),
+            source,
+        })
     }
 
-    fn update_from_env(&mut self) {
-        if let Ok(dragonslair) = env::var("DL") {
-            self.dragonslair = dragonslair == "1";
+    pub fn from_toml(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    /// Overrides the settings with the env vars that are set
+    pub fn update_from_env<F>(&mut self, get_var: F) -> Result<(), ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(value) = get_var("DL") {
+            self.vendors.dragonslair = parse_env_flag("DL", &value)?;
+        }
+        if let Some(value) = get_var("SF") {
+            self.vendors.scryfall = parse_env_flag("SF", &value)?;
+        }
+        if let Some(value) = get_var("AS") {
+            self.vendors.alphaspel = parse_env_flag("AS", &value)?;
+        }
+        if let Some(value) = get_var("MT") {
+            self.vendors.manatorsk = parse_env_flag("MT", &value)?;
+        }
+        if let Some(value) = get_var("NICE_PRICE_DIFF") {
+            self.prices.nice_price_diff =
+                value.trim().parse().map_err(|_| ConfigError::InvalidEnv {
+                    name: "NICE_PRICE_DIFF".to_string(),
+                    value: value.clone(),
+                    expected: "a whole number of SEK",
+                })?;
+        }
+        if let Some(value) = get_var("EXTERNAL_PRICE_CHECK") {
+            self.prices.external_price_check = parse_env_flag("EXTERNAL_PRICE_CHECK", &value)?;
+        }
+        if let Some(value) = get_var("DELVER_LENSE_PATH") {
+            self.trade_in.delver_lense_path = value;
         }
-        if let Ok(scryfall) = env::var("SF") {
-            self.scryfall = scryfall == "1";
+        if let Some(value) = get_var("CHECK_DL_WHEN_COMPARING") {
+            self.trade_in.check_dl_when_comparing =
+                parse_env_flag("CHECK_DL_WHEN_COMPARING", &value)?;
         }
-        if let Ok(alpha) = env::var("AS") {
-            self.alpha = alpha == "1";
+        if let Some(value) = get_var("SMTP_PASSWORD") {
+            self.notifications.smtp.password = value;
         }
-        if let Ok(nice_price_diff) = env::var("NICE_PRICE_DIFF") {
-            self.nice_price_diff = nice_price_diff.parse().unwrap_or(0);
+        Ok(())
+    }
+
+    /// Checks the values that can't be checked while parsing
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let concurrency = &self.concurrency;
+        if concurrency.dragonslair == 0
+            || concurrency.alphaspel == 0
+            || concurrency.manatorsk == 0
+            || concurrency.compare == 0
+        {
+            return Err(ConfigError::Invalid(
+                "concurrency limits must be at least 1".to_string(),
+            ));
         }
-        if let Ok(external_price_check) = env::var("EXTERNAL_PRICE_CHECK") {
-            self.external_price_check = external_price_check == "1";
+        if self.http.timeout_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "http.timeout_secs must be at least 1".to_string(),
+            ));
         }
-        if let Ok(delver_lense_check) = env::var("DELVER_LENSE_PATH") {
-            if std::path::Path::new(&delver_lense_check).is_file()
-                && delver_lense_check.ends_with(".csv")
-            {
-                self.delver_lense_path = delver_lense_check;
-            } else if !&delver_lense_check.is_empty() {
-                error!("Supplied incorrect path to delver lense card file");
-                self.delver_lense_path = "".to_string();
+        if let Some(rate) = self.exchange_rates.eur_to_sek {
+            if rate <= Decimal::ZERO {
+                return Err(ConfigError::Invalid(format!(
+                    "exchange_rates.eur_to_sek must be a positive number, got {}",
+                    rate
+                )));
             }
         }
-        if let Ok(check_dl_when_comparing) = env::var("CHECK_DL_WHEN_COMPARING") {
-            self.check_dl_when_comparing = check_dl_when_comparing == "1";
+        if self.exchange_rates.overrides.contains_key(&Currency::EUR) {
+            return Err(ConfigError::Invalid(
+                "exchange_rates.overrides can't set EUR, the rates are per EUR".to_string(),
+            ));
+        }
+        if let Some((currency, rate)) = self
+            .exchange_rates
+            .overrides
+            .iter()
+            .find(|(_, rate)| **rate <= Decimal::ZERO)
+        {
+            return Err(ConfigError::Invalid(format!(
+                "exchange_rates.overrides.{} must be a positive number, got {}",
+                currency, rate
+            )));
+        }
+        if self.prices.cheap_price_limit > self.prices.mid_price_limit {
+            return Err(ConfigError::Invalid(format!(
+                "prices.cheap_price_limit ({}) can't be above prices.mid_price_limit ({})",
+                self.prices.cheap_price_limit, self.prices.mid_price_limit
+            )));
+        }
+        if self.history.trend_days < 1 {
+            return Err(ConfigError::Invalid(format!(
+                "history.trend_days must be at least 1, got {}",
+                self.history.trend_days
+            )));
+        }
+        if self.prices.sources.is_empty() {
+            return Err(ConfigError::Invalid(
+                "prices.sources must name at least one price source".to_string(),
+            ));
+        }
+        if let Some(source) = self
+            .prices
+            .sources
+            .iter()
+            .find(|source| !KNOWN_PRICE_SOURCES.contains(&source.as_str()))
+        {
+            return Err(ConfigError::Invalid(format!(
+                "unknown price source '{}' in prices.sources, known sources are {}",
+                source,
+                KNOWN_PRICE_SOURCES.join(", ")
+            )));
+        }
+        let delver_lense_path = &self.trade_in.delver_lense_path;
+        let is_csv_file =
+            Path::new(delver_lense_path).is_file() && delver_lense_path.ends_with(".csv");
+        if !delver_lense_path.is_empty() && !is_csv_file {
+            return Err(ConfigError::Invalid(format!(
+                "delver lense path {} is not a csv file",
+                delver_lense_path
+            )));
+        }
+        let smtp = &self.notifications.smtp;
+        if !smtp.host.is_empty() && (smtp.from.is_empty() || smtp.to.is_empty()) {
+            return Err(ConfigError::Invalid(
+                "notifications.smtp needs a from address and at least one to address".to_string(),
+            ));
+        }
+        let basket = &self.basket;
+        if basket.dragonslair < Decimal::ZERO
+            || basket.alphaspel < Decimal::ZERO
+            || basket.manatorsk < Decimal::ZERO
+        {
+            return Err(ConfigError::Invalid(
+                "basket fixed costs can't be negative".to_string(),
+            ));
+        }
+        let discounts = &self.discounts;
+        let percentages = [
+            discounts.excellent,
+            discounts.good,
+            discounts.played,
+            discounts.damaged,
+            discounts.non_english,
+        ];
+        if percentages
+            .iter()
+            .any(|percent| *percent < Decimal::ZERO || *percent > dec!(100))
+        {
+            return Err(ConfigError::Invalid(
+                "discounts must be between 0 and 100 percent".to_string(),
+            ));
+        }
+        let sell_list = &self.sell_list;
+        if sell_list.price_factor <= Decimal::ZERO || sell_list.min_price < Decimal::ZERO {
+            return Err(ConfigError::Invalid(
+                "sell_list.price_factor must be positive and sell_list.min_price can't be negative"
+                    .to_string(),
+            ));
+        }
+        let watchlist_path = &self.watchlist.path;
+        let is_watchlist_file = Path::new(watchlist_path).is_file()
+            && (watchlist_path.ends_with(".toml") || watchlist_path.ends_with(".csv"));
+        if !watchlist_path.is_empty() && !is_watchlist_file {
+            return Err(ConfigError::Invalid(format!(
+                "watchlist path {} is not a toml or csv file",
+                watchlist_path
+            )));
+        }
+        Ok(())
+    }
+
+    /// The built in rates with the configured ones on top, for when the live rates aren't
+    /// loaded
+    pub fn exchange_rates(&self) -> ExchangeRates {
+        self.with_configured_rates(ExchangeRates::default())
+    }
+
+    /// The rates with the configured ones on top
+    pub fn with_configured_rates(&self, rates: ExchangeRates) -> ExchangeRates {
+        let rates = self
+            .exchange_rates
+            .overrides
+            .iter()
+            .fold(rates, |rates, (currency, rate)| {
+                rates.with_rate(*currency, *rate)
+            });
+        match self.exchange_rates.eur_to_sek {
+            Some(eur_to_sek) => rates.with_rate(Currency::SEK, eur_to_sek),
+            None => rates,
+        }
+    }
+
+    /// Where the price history database is
+    pub fn history_path(&self) -> PathBuf {
+        Path::new(&self.output.data_dir).join(&self.history.path)
+    }
+
+    /// Where the collection is kept
+    pub fn collection_path(&self) -> PathBuf {
+        Path::new(&self.output.data_dir).join(&self.collection.path)
+    }
+
+    pub fn http_settings(&self) -> HttpSettings {
+        let http = &self.http;
+        let user_agent = match http.user_agent.trim() {
+            "" => DEFAULT_USER_AGENT,
+            user_agent => user_agent,
+        };
+        let min_interval = match http.requests_per_second {
+            0 => Duration::ZERO,
+            requests_per_second => Duration::from_secs(1) / requests_per_second,
+        };
+        HttpSettings {
+            user_agent: user_agent.to_string(),
+            timeout: Duration::from_secs(http.timeout_secs),
+            max_retries: http.max_retries,
+            backoff: Duration::from_millis(http.backoff_ms),
+            min_interval,
+        }
+    }
+
+    pub fn comparer_settings(&self, rates: &ExchangeRates) -> ComparerSettings {
+        ComparerSettings {
+            external_price_check: self.prices.external_price_check,
+            price_sources: self.prices.sources.clone(),
+            concurrency: self.concurrency.compare,
+            exchange_rates: rates.clone(),
+            discounts: self.discounts(),
+        }
+    }
+
+    pub fn discounts(&self) -> Discounts {
+        Discounts {
+            excellent: self.discounts.excellent,
+            good: self.discounts.good,
+            played: self.discounts.played,
+            damaged: self.discounts.damaged,
+            non_english: self.discounts.non_english,
+        }
+    }
+
+    pub fn nice_price_settings(&self, rates: &ExchangeRates) -> NicePriceSettings {
+        NicePriceSettings {
+            nice_price_diff: self.prices.nice_price_diff,
+            cheap_price_limit: self.prices.cheap_price_limit,
+            mid_price_limit: self.prices.mid_price_limit,
+            mid_price_diff: self.prices.mid_price_diff,
+            exchange_rates: rates.clone(),
+        }
+    }
+
+    pub fn sell_list_settings(&self, rates: &ExchangeRates) -> SellListSettings {
+        SellListSettings {
+            price_factor: self.sell_list.price_factor,
+            min_price: self.sell_list.min_price,
+            exchange_rates: rates.clone(),
+            discounts: self.discounts(),
+        }
+    }
+
+    pub fn trade_in_settings(&self) -> TradeInSettings {
+        TradeInSettings {
+            check_dl_when_comparing: self.trade_in.check_dl_when_comparing,
         }
     }
 }
 
-lazy_static::lazy_static! {
-    pub static ref CONFIG: Config = Config::new();
+fn parse_env_flag(name: &str, value: &str) -> Result<bool, ConfigError> {
+    match value.trim() {
+        "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        _ => Err(ConfigError::InvalidEnv {
+            name: name.to_string(),
+            value: value.to_string(),
+            expected: "1 or 0",
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn env_vars(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_partial_config_file_keeps_defaults() {
+        let config = Config::from_toml(
+            r#"
+            [vendors]
+            alphaspel = false
+
+            [prices]
+            nice_price_diff = -10
+
+            [exchange_rates]
+            eur_to_sek = 11.5
+
+            [watchlist]
+            min_condition = "Played"
+            any_language = true
+            "#,
+        )
+        .unwrap();
+
+        assert!(!config.vendors.alphaspel);
+        assert!(config.vendors.dragonslair);
+        assert_eq!(config.prices.nice_price_diff, -10);
+        assert_eq!(config.prices.mid_price_limit, dec!(30));
+        assert_eq!(config.exchange_rates.eur_to_sek, Some(dec!(11.5)));
+        assert_eq!(config.concurrency, ConcurrencyConfig::default());
+        assert_eq!(
+            config.watchlist.listing_filter(),
+            ListingFilter {
+                min_condition: Condition::Played,
+                language: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_exchange_rate_overrides() {
+        let config = Config::from_toml(
+            r#"
+            [exchange_rates]
+            eur_to_sek = 11.5
+
+            [exchange_rates.overrides]
+            USD = 1.2
+            SEK = 10
+            "#,
+        )
+        .unwrap();
+
+        let rates = config.with_configured_rates(ExchangeRates::default());
+        assert_eq!(rates.per_eur(Currency::USD), Some(dec!(1.2)));
+        assert_eq!(rates.eur_to_sek(), dec!(11.5));
+        assert_eq!(rates.per_eur(Currency::GBP), Some(dec!(0.85)));
+        assert!(Config::from_toml("[exchange_rates.overrides]\nXYZ = 1").is_err());
+    }
+
+    #[test]
+    fn test_example_file_matches_defaults() {
+        let config = Config::from_file("mtg-prz.example.toml").unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_history_path_is_relative_to_data_dir() {
+        let mut config = Config::from_toml("[output]\ndata_dir = \"/tmp/data\"").unwrap();
+        assert_eq!(
+            config.history_path(),
+            PathBuf::from("/tmp/data/db/price_history.db")
+        );
+        assert_eq!(
+            config.collection_path(),
+            PathBuf::from("/tmp/data/collection/collection.json")
+        );
+        config.history.path = "/var/db/history.db".to_string();
+        assert_eq!(config.history_path(), PathBuf::from("/var/db/history.db"));
+    }
+
+    #[test]
+    fn test_missing_config_file_fails() {
+        let err = Config::load(Some("does_not_exist.toml")).unwrap_err();
+        assert!(matches!(err, ConfigError::Read { .. }));
+    }
+
+    #[test]
+    fn test_unknown_and_mistyped_keys_are_rejected() {
+        assert!(Config::from_toml("[vendors]\nnowhere = true").is_err());
+        assert!(Config::from_toml("[prices]\nnice_price_diff = \"ten\"").is_err());
+    }
+
+    #[test]
+    fn test_env_vars_override_file() {
+        let mut config = Config::from_toml("[vendors]\ndragonslair = false").unwrap();
+        config
+            .update_from_env(env_vars(&[
+                ("DL", "1"),
+                ("SF", "0"),
+                ("NICE_PRICE_DIFF", "15"),
+            ]))
+            .unwrap();
+
+        assert!(config.vendors.dragonslair);
+        assert!(!config.vendors.scryfall);
+        assert_eq!(config.prices.nice_price_diff, 15);
+    }
+
+    #[test]
+    fn test_invalid_env_vars_fail() {
+        let mut config = Config::default();
+        let err = config
+            .update_from_env(env_vars(&[("NICE_PRICE_DIFF", "lots")]))
+            .unwrap_err();
+        assert!(err.to_string().contains("NICE_PRICE_DIFF"));
+
+        assert!(config.update_from_env(env_vars(&[("DL", "yes")])).is_err());
+    }
+
+    #[test]
+    fn test_default_settings_match_component_defaults() {
+        let config = Config::default();
+        let rates = config.exchange_rates();
+        assert_eq!(
+            config.comparer_settings(&rates),
+            ComparerSettings::default()
+        );
+        assert_eq!(
+            config.nice_price_settings(&rates),
+            NicePriceSettings::default()
+        );
+        assert_eq!(config.trade_in_settings(), TradeInSettings::default());
+        assert_eq!(
+            config.sell_list_settings(&rates),
+            SellListSettings::default()
+        );
+        assert_eq!(config.http_settings(), HttpSettings::default());
+        assert_eq!(config.watchlist.listing_filter(), ListingFilter::default());
+    }
+
+    #[test]
+    fn test_http_settings() {
+        let config = Config::from_toml(
+            r#"
+            [http]
+            user_agent = "mtg-prz (me@example.com)"
+            requests_per_second = 4
+            "#,
+        )
+        .unwrap();
+        let settings = config.http_settings();
+        assert_eq!(settings.user_agent, "mtg-prz (me@example.com)");
+        assert_eq!(settings.min_interval, Duration::from_millis(250));
+        assert_eq!(settings.max_retries, 3);
+
+        let config = Config::from_toml("[http]\nrequests_per_second = 0").unwrap();
+        assert_eq!(config.http_settings().min_interval, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Config::default().validate().is_ok());
+
+        let mut config = Config::default();
+        config.concurrency.compare = 0;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.http.timeout_secs = 0;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.exchange_rates.eur_to_sek = Some(dec!(-1));
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.exchange_rates.overrides = HashMap::from([(Currency::USD, dec!(0))]);
+        assert!(config.validate().is_err());
+        config.exchange_rates.overrides = HashMap::from([(Currency::EUR, dec!(1))]);
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.trade_in.delver_lense_path = "not_a_file.csv".to_string();
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.prices.sources = vec!["scryfall".to_string(), "cardkingdom".to_string()];
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.prices.sources.clear();
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.watchlist.path = "Cargo.toml".to_string();
+        assert!(config.validate().is_ok());
+        config.watchlist.path = "Cargo.lock".to_string();
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.notifications.smtp.host = "localhost".to_string();
+        assert!(config.validate().is_err());
+        config.notifications.smtp.from = "prices@example.com".to_string();
+        config.notifications.smtp.to = vec!["team@example.com".to_string()];
+        assert!(config.validate().is_ok());
+
+        let mut config = Config::default();
+        config.basket.alphaspel = dec!(-10);
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.discounts.played = dec!(120);
+        assert!(config.validate().is_err());
+        assert!(config.basket.set_fixed_cost("nowhere", dec!(0)).is_err());
+    }
 }
@@ -4,6 +4,10 @@ pub const DRAGONSLAIR_URL: &str = "https://astraeus.dragonslair.se";
 pub const ALPHASPEL_CARDS_FOLDER: &str = "alphaspel_cards";
 pub const ALPHASPEL_CARDS_PREFIX: &str = "as_cards_";
 pub const ALPHASPEL_URL: &str = "https://alphaspel.se";
+pub const MANATORSK_CARDS_FOLDER: &str = "manatorsk_cards";
+pub const MANATORSK_CARDS_PREFIX: &str = "mt_cards_";
+pub const MANATORSK_URL: &str = "https://manatorsk.se";
+pub const SCRAPE_REPORTS_DIR: &str = "reports";
 
 pub const SCRYFALL_CARDS_DIR: &str = "scryfall_cards";
 
@@ -18,3 +22,33 @@ pub const SCRYFALL_API_URL: &str = "https://api.scryfall.com";
 pub const REPOSITORY_ROOT_PATH: &str = "..";
 
 pub const MTG_STOCKS_BASE_URL: &str = "https://api.mtgstocks.com";
+
+pub const FRANKFURTER_URL: &str = "https://api.frankfurter.app";
+pub const EXCHANGE_RATE_FILE: &str = "exchange_rate.json";
+
+pub const TRADABLE_CARDS_DIR: &str = "tradable_cards";
+pub const TRADABLE_CARDS_PREFIX: &str = "tradable_cards_";
+pub const SELL_LISTS_DIR: &str = "sell_lists";
+pub const CARDMARKET_PREFIX: &str = "cardmarket_";
+pub const SELL_LIST_PREFIX: &str = "sell_list_";
+
+pub const SNAPSHOT_DIFFS_DIR: &str = "snapshot_diffs";
+
+pub const WATCHLIST_REPORTS_DIR: &str = "watchlist_reports";
+pub const WATCHLIST_REPORT_PREFIX: &str = "watchlist_";
+
+pub const DECK_BUILDS_DIR: &str = "deck_builds";
+pub const DECK_BUILD_PREFIX: &str = "deck_build_";
+pub const COLLECTION_FILE: &str = "collection/collection.json";
+pub const COLLECTION_VALUATIONS_DIR: &str = "collection/valuations";
+pub const COLLECTION_VALUATION_PREFIX: &str = "valuation_";
+
+pub const BASKETS_DIR: &str = "baskets";
+pub const BASKET_PREFIX: &str = "basket_";
+
+pub const SENT_NOTIFICATIONS_FILE: &str = "notifications/sent.json";
+
+pub const PRICE_HISTORY_DB: &str = "db/price_history.db";
+
+pub const NICE_PRICE_PAGE: &str = "index.html";
+pub const TRADABLE_CARDS_PAGE: &str = "cards.html";
@@ -1,111 +1,181 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use log::{error, info};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fs::File;
 use std::io::BufReader;
-use std::io::{self, BufWriter, Write};
+use std::io::{BufWriter, Write};
 use std::path::PathBuf;
 use std::{
     fs::{self, OpenOptions},
     path::Path,
 };
 
-pub fn load_from_json_file<T: DeserializeOwned>(filename: &str) -> io::Result<T> {
+use crate::error::StorageError;
+
+pub fn load_from_json_file<T: DeserializeOwned>(filename: &str) -> Result<T, StorageError> {
     info!("Loading from file: {}", filename);
     let file = File::open(filename).map_err(|e| {
         error!("Failed to open file: {}", e);
-        e
+        StorageError::io(filename, e)
     })?;
     let reader = BufReader::new(file);
     let data = serde_json::from_reader(reader).map_err(|e| {
         error!("Failed to read from file: {}", e);
-        e
+        StorageError::json(filename, e)
     })?;
     Ok(data)
 }
 
-pub async fn download_and_save_file(
-    url: &str,
-    path: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    let response = client.get(url).send().await?.bytes().await?;
+/// Appends the bytes to the file, creating it and its folders if needed
+pub fn append_to_file(path: &str, bytes: &[u8]) -> Result<(), StorageError> {
     if let Some(parent) = Path::new(path).parent() {
-        fs::create_dir_all(parent)?;
+        fs::create_dir_all(parent).map_err(|e| StorageError::io(parent, e))?;
     }
     let file = OpenOptions::new()
-        .write(true)
         .create(true)
         .append(true)
-        .open(path)?;
+        .open(path)
+        .map_err(|e| StorageError::io(path, e))?;
     let mut writer = BufWriter::new(file);
-    writer.write_all(&response)?;
-    Ok(())
+    writer
+        .write_all(bytes)
+        .and_then(|_| writer.flush())
+        .map_err(|e| StorageError::io(path, e))
 }
 
-pub fn save_to_file<T: Serialize>(path: &str, data: &T) -> io::Result<()> {
+pub fn save_to_file<T: Serialize>(path: &str, data: &T) -> Result<(), StorageError> {
     info!(
         "Saving data type: {} to file: {} with",
         std::any::type_name::<T>(),
         path
     );
     if let Some(parent) = Path::new(path).parent() {
-        fs::create_dir_all(parent)?;
+        fs::create_dir_all(parent).map_err(|e| StorageError::io(parent, e))?;
     }
 
     // Open the file in write mode, creating it if it doesn't exist
     let file = OpenOptions::new()
-        .write(true)
         .create(true)
         .append(true)
-        .open(path)?;
+        .open(path)
+        .map_err(|e| StorageError::io(path, e))?;
     // let file = File::create(filename)?;
 
     let writer = BufWriter::new(file);
-    serde_json::to_writer_pretty(writer, data)?;
+    serde_json::to_writer_pretty(writer, data).map_err(|e| StorageError::json(path, e))?;
     Ok(())
 }
 
-pub fn get_newest_file(
+pub fn get_newest_file(folder_path: &str, prefix: &str) -> Result<PathBuf, StorageError> {
+    list_snapshots(folder_path, prefix)?
+        .pop()
+        .map(|(file_path, _)| file_path)
+        .ok_or_else(|| StorageError::NoSnapshot {
+            folder: folder_path.to_string(),
+            reason: format!("no files starting with {}", prefix),
+        })
+}
+
+/// The newest snapshot in the folder that was taken on the given day
+pub fn get_file_taken_on(
+    folder_path: &str,
+    prefix: &str,
+    date: NaiveDate,
+) -> Result<PathBuf, StorageError> {
+    list_snapshots(folder_path, prefix)?
+        .into_iter()
+        .rfind(|(_, date_time)| date_time.date() == date)
+        .map(|(file_path, _)| file_path)
+        .ok_or_else(|| StorageError::NoSnapshot {
+            folder: folder_path.to_string(),
+            reason: format!("none taken on {}", date),
+        })
+}
+
+/// When a snapshot was taken, from its name, e.g. `dl_cards_10_03_2025-17-33.json`
+pub fn snapshot_taken_at(file_path: &Path, prefix: &str) -> Option<NaiveDateTime> {
+    let name = file_path.file_name()?.to_str()?;
+    let date_time_str = name.split(prefix).nth(1)?;
+    NaiveDateTime::parse_from_str(&date_time_str.replace(".json", ""), "%d_%m_%Y-%H-%M").ok()
+}
+
+/// The timestamped snapshots in the folder, oldest first
+pub fn list_snapshots(
     folder_path: &str,
     prefix: &str,
-) -> Result<PathBuf, Box<dyn std::error::Error>> {
+) -> Result<Vec<(PathBuf, NaiveDateTime)>, StorageError> {
     let path = Path::new(folder_path);
 
     if !path.is_dir() {
-        return Err(format!("{} is not a directory", folder_path).into());
+        return Err(StorageError::NoSnapshot {
+            folder: folder_path.to_string(),
+            reason: "it is not a directory".to_string(),
+        });
     }
 
-    let newest_file = fs::read_dir(path)?
+    let mut snapshots: Vec<(PathBuf, NaiveDateTime)> = fs::read_dir(path)
+        .map_err(|e| StorageError::io(path, e))?
         .filter_map(|entry| {
             let entry = entry.ok()?;
             let file_path = entry.path();
 
             if file_path.is_file() {
                 info!("Found file: {}", file_path.display());
-                file_path
-                    .file_name()
-                    .and_then(|name| name.to_str())
-                    .and_then(|name| {
-                        name.split(prefix)
-                            .nth(1)
-                            .and_then(|date_time_str| {
-                                NaiveDateTime::parse_from_str(
-                                    &date_time_str.replace(".json", ""),
-                                    "%d_%m_%Y-%H-%M",
-                                )
-                                .ok()
-                            })
-                            .map(|date_time| (file_path.clone(), date_time))
-                    })
+                snapshot_taken_at(&file_path, prefix)
+                    .map(|date_time| (file_path.clone(), date_time))
             } else {
                 error!("Failed to read file: {}", file_path.display());
                 None
             }
         })
-        .max_by_key(|&(_, date_time)| date_time)
-        .map(|(file_path, _)| file_path);
+        .collect();
+    snapshots.sort_by_key(|&(_, date_time)| date_time);
+    Ok(snapshots)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
 
-    newest_file.ok_or_else(|| "No valid files found".into())
+    use super::*;
+
+    #[test]
+    fn test_snapshots_are_found_by_date() {
+        let dir = tempdir().unwrap();
+        for name in [
+            "dl_cards_02_03_2025-07-00.json",
+            "dl_cards_01_03_2025-23-59.json",
+            "dl_cards_02_03_2025-18-30.json",
+            "as_cards_03_03_2025-07-00.json",
+            "dl_cards_notes.json",
+        ] {
+            fs::write(dir.path().join(name), "{}").unwrap();
+        }
+        let folder = dir.path().to_str().unwrap();
+        let file_name = |path: PathBuf| path.file_name().unwrap().to_str().unwrap().to_string();
+
+        let snapshots = list_snapshots(folder, "dl_cards_").unwrap();
+        let newest = get_newest_file(folder, "dl_cards_").unwrap();
+        let taken_on = get_file_taken_on(
+            folder,
+            "dl_cards_",
+            NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(
+            file_name(snapshots[0].0.clone()),
+            "dl_cards_01_03_2025-23-59.json"
+        );
+        assert_eq!(file_name(newest), "dl_cards_02_03_2025-18-30.json");
+        assert_eq!(file_name(taken_on), "dl_cards_01_03_2025-23-59.json");
+        assert!(get_file_taken_on(
+            folder,
+            "dl_cards_",
+            NaiveDate::from_ymd_opt(2025, 3, 3).unwrap()
+        )
+        .is_err());
+    }
 }
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use crate::{
+    alphaspel_scraper::AlphaspelScraper,
+    cards::{cardname::CardName, vendor::Vendor, vendorcard::VendorCard},
+    dragonslair_scraper::DragonslairScraper,
+    error::ScrapeError,
+    http_client::HttpClient,
+    manatorsk_scraper::ManatorskScraper,
+    scrape_report::ScrapeReport,
+    utilities::{
+        config::Config,
+        constants::{ALPHASPEL_URL, DRAGONSLAIR_URL, MANATORSK_URL},
+    },
+};
+
+/// A store whose singles can be scraped into `VendorCard`s
+#[async_trait]
+pub trait VendorScraper: Send + Sync {
+    /// Lowercase name used in the config, on the command line and in logs
+    fn name(&self) -> &'static str;
+
+    fn vendor(&self) -> Vendor;
+
+    /// Folder in the data dir the snapshots are saved in
+    fn snapshot_folder(&self) -> &'static str;
+
+    /// Start of the snapshot file names, followed by the date
+    fn snapshot_prefix(&self) -> &'static str;
+
+    /// Scrapes all singles in stock, grouped by card name, with a report of the pages fetched
+    /// and the products that were skipped
+    async fn scrape(
+        &self,
+    ) -> Result<(HashMap<CardName, Vec<VendorCard>>, ScrapeReport), ScrapeError>;
+}
+
+/// The vendor scrapers that the pipeline knows about
+#[derive(Default)]
+pub struct VendorRegistry {
+    scrapers: Vec<Box<dyn VendorScraper>>,
+}
+
+impl VendorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All the stores this crate can scrape, set up from the config. They share the given
+    /// client
+    pub fn with_default_vendors(config: &Config, client: &HttpClient) -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(DragonslairScraper::new(
+            DRAGONSLAIR_URL,
+            None,
+            client.clone(),
+            config.concurrency.dragonslair,
+        )));
+        registry.register(Box::new(AlphaspelScraper::new(
+            ALPHASPEL_URL,
+            client.clone(),
+            config.concurrency.alphaspel,
+        )));
+        registry.register(Box::new(ManatorskScraper::new(
+            MANATORSK_URL,
+            client.clone(),
+            config.concurrency.manatorsk,
+        )));
+        registry
+    }
+
+    /// Adds a scraper, replacing any scraper with the same name
+    pub fn register(&mut self, scraper: Box<dyn VendorScraper>) {
+        self.scrapers.retain(|s| s.name() != scraper.name());
+        self.scrapers.push(scraper);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn VendorScraper> {
+        self.scrapers
+            .iter()
+            .find(|scraper| scraper.name() == name)
+            .map(|scraper| scraper.as_ref())
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.scrapers.iter().map(|scraper| scraper.name()).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn VendorScraper> {
+        self.scrapers.iter().map(|scraper| scraper.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeScraper {
+        name: &'static str,
+        cards: HashMap<CardName, Vec<VendorCard>>,
+    }
+
+    #[async_trait]
+    impl VendorScraper for FakeScraper {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn vendor(&self) -> Vendor {
+            Vendor::Cardmarket
+        }
+
+        fn snapshot_folder(&self) -> &'static str {
+            "fake_cards"
+        }
+
+        fn snapshot_prefix(&self) -> &'static str {
+            "fake_cards_"
+        }
+
+        async fn scrape(
+            &self,
+        ) -> Result<(HashMap<CardName, Vec<VendorCard>>, ScrapeReport), ScrapeError> {
+            Ok((self.cards.clone(), ScrapeReport::new(Vendor::Cardmarket)))
+        }
+    }
+
+    #[test]
+    fn test_default_vendors() {
+        let registry =
+            VendorRegistry::with_default_vendors(&Config::default(), &HttpClient::default());
+        assert_eq!(
+            registry.names(),
+            vec!["dragonslair", "alphaspel", "manatorsk"]
+        );
+        assert_eq!(
+            registry.get("alphaspel").unwrap().vendor(),
+            Vendor::Alphaspel
+        );
+        assert!(registry.get("nowhere").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_register_replaces_scraper_with_same_name() {
+        let mut registry = VendorRegistry::new();
+        registry.register(Box::new(FakeScraper {
+            name: "fake",
+            cards: HashMap::new(),
+        }));
+        let cards = HashMap::from([(
+            crate::test::helpers::reaper_king_card_name(),
+            vec![crate::test::helpers::reaper_king_vendor_card_cheap()],
+        )]);
+        registry.register(Box::new(FakeScraper {
+            name: "fake",
+            cards: cards.clone(),
+        }));
+
+        assert_eq!(registry.names(), vec!["fake"]);
+        assert_eq!(
+            registry.get("fake").unwrap().scrape().await.unwrap().0,
+            cards
+        );
+    }
+}
@@ -0,0 +1,419 @@
+//! Cards to look out for, each with the most it may cost, and the vendor cards that are
+//! cheap enough.
+//!
+//! The watchlist is a TOML file with a `[[cards]]` table per card or a CSV file with the
+//! columns `name,set,collector_number,foil,max_price,currency`. Only the name and max price
+//! are needed, the max price is in SEK unless another currency is given. Only the copies the
+//! listing filter lets through are hits, like the copies a deck is bought as.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::cards::{
+    cardname::CardName,
+    collector_number::CollectorNumber,
+    currency::{Currency, ExchangeRates},
+    price::Price,
+    setname::SetName,
+    vendorcard::VendorCard,
+};
+use crate::decklist::ListingFilter;
+use crate::error::ParseError;
+
+/// A card on the watchlist
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct WatchlistEntry {
+    pub name: CardName,
+    /// Only this set, any set if not given
+    pub set: Option<SetName>,
+    /// Only this printing, any printing if not given
+    pub collector_number: Option<CollectorNumber>,
+    pub foil: bool,
+    /// The most the card may cost to be a hit
+    pub max_price: Price,
+}
+
+/// A line of the watchlist file as it is written
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WatchlistRow {
+    name: String,
+    set: Option<String>,
+    collector_number: Option<String>,
+    foil: Option<bool>,
+    max_price: Decimal,
+    currency: Option<Currency>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WatchlistFile {
+    #[serde(default)]
+    cards: Vec<WatchlistRow>,
+}
+
+impl TryFrom<WatchlistRow> for WatchlistEntry {
+    type Error = ParseError;
+
+    fn try_from(row: WatchlistRow) -> Result<Self, Self::Error> {
+        let name = CardName::new(row.name.clone())?;
+        let set = row
+            .set
+            .filter(|set| !set.trim().is_empty())
+            .map(SetName::new)
+            .transpose()?;
+        let collector_number = row
+            .collector_number
+            .filter(|number| !number.trim().is_empty())
+            .map(|number| CollectorNumber::new(&number))
+            .transpose()?;
+        if row.max_price < Decimal::ZERO {
+            return Err(ParseError::field(
+                "max price",
+                &row.max_price.to_string(),
+                format!("the max price of {} can't be negative", row.name),
+            ));
+        }
+        Ok(WatchlistEntry {
+            name,
+            set,
+            collector_number,
+            foil: row.foil.unwrap_or(false),
+            max_price: Price::new(row.max_price, row.currency.unwrap_or(Currency::SEK)),
+        })
+    }
+}
+
+impl WatchlistEntry {
+    /// Whether the vendor card is this printing, in stock, let through by the filter and at or
+    /// below the max price. A price in another currency than the max price is converted with
+    /// the given rates
+    pub fn matches(
+        &self,
+        card: &VendorCard,
+        filter: &ListingFilter,
+        rates: &ExchangeRates,
+    ) -> bool {
+        let set_matches = self
+            .set
+            .as_ref()
+            .is_none_or(|set| set.cleaned == card.set.cleaned);
+        let collector_number_matches = self
+            .collector_number
+            .as_ref()
+            .is_none_or(|number| card.collector_number.as_ref() == Some(number));
+        card.name == self.name
+            && set_matches
+            && collector_number_matches
+            && card.foil == self.foil
+            && card.current_stock > 0
+            && filter.accepts(card)
+            && card.price.convert_to(self.max_price.currency, rates).amount <= self.max_price.amount
+    }
+}
+
+/// A vendor card that is cheap enough for a watchlist entry
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct WatchlistHit {
+    pub entry: WatchlistEntry,
+    pub card: VendorCard,
+}
+
+/// The hits of a run, saved as json
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct WatchlistReport {
+    pub generated_at: DateTime<Utc>,
+    /// Number of cards on the watchlist
+    pub entries: usize,
+    pub hits: Vec<WatchlistHit>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Watchlist {
+    pub entries: Vec<WatchlistEntry>,
+}
+
+impl Watchlist {
+    /// Loads a `.toml` or `.csv` watchlist
+    pub fn load(path: &str) -> Result<Self, ParseError> {
+        let read_error = |source| ParseError::Read {
+            path: path.to_string(),
+            source,
+        };
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&fs::read_to_string(path).map_err(read_error)?),
+            Some("csv") => Self::from_csv(fs::File::open(path).map_err(read_error)?),
+            _ => Err(ParseError::field(
+                "watchlist path",
+                path,
+                "must be a .toml or .csv file",
+            )),
+        }
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, ParseError> {
+        let file: WatchlistFile = toml::from_str(content).map_err(|source| ParseError::Toml {
+            what: "watchlist".to_string(),
+            source,
+        })?;
+        Self::from_rows(file.cards)
+    }
+
+    pub fn from_csv(reader: impl Read) -> Result<Self, ParseError> {
+        let rows = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(reader)
+            .deserialize()
+            .collect::<Result<Vec<WatchlistRow>, _>>()
+            .map_err(|source| ParseError::Csv {
+                path: "watchlist".to_string(),
+                source,
+            })?;
+        Self::from_rows(rows)
+    }
+
+    fn from_rows(rows: Vec<WatchlistRow>) -> Result<Self, ParseError> {
+        let entries = rows
+            .into_iter()
+            .map(WatchlistEntry::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { entries })
+    }
+
+    /// The vendor cards that are cheap enough for an entry, cheapest first for each entry
+    pub fn find_hits(
+        &self,
+        vendor_cards: &HashMap<CardName, Vec<VendorCard>>,
+        filter: &ListingFilter,
+        rates: &ExchangeRates,
+    ) -> Vec<WatchlistHit> {
+        let mut hits = Vec::new();
+        for entry in &self.entries {
+            let mut cards: Vec<&VendorCard> = vendor_cards
+                .get(&entry.name)
+                .into_iter()
+                .flatten()
+                .filter(|card| entry.matches(card, filter, rates))
+                .collect();
+            cards.sort_by_key(|card| {
+                card.price
+                    .convert_to(entry.max_price.currency, rates)
+                    .amount
+            });
+            hits.extend(cards.into_iter().map(|card| WatchlistHit {
+                entry: entry.clone(),
+                card: card.clone(),
+            }));
+        }
+        hits
+    }
+
+    /// The hits as a report stamped with the current time
+    pub fn report(
+        &self,
+        vendor_cards: &HashMap<CardName, Vec<VendorCard>>,
+        filter: &ListingFilter,
+        rates: &ExchangeRates,
+    ) -> WatchlistReport {
+        WatchlistReport {
+            generated_at: Utc::now(),
+            entries: self.entries.len(),
+            hits: self.find_hits(vendor_cards, filter, rates),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::cards::{condition::Condition, language::Language};
+    use crate::test::helpers::{
+        counterspell_forth_e, reaper_king_card_name, reaper_king_set_name,
+        reaper_king_vendor_card_cheap, reaper_king_vendor_card_expensive,
+        reaper_king_vendor_card_foil,
+    };
+
+    fn vendor_cards() -> HashMap<CardName, Vec<VendorCard>> {
+        HashMap::from([
+            (
+                reaper_king_card_name(),
+                vec![
+                    reaper_king_vendor_card_expensive(),
+                    reaper_king_vendor_card_cheap(),
+                    reaper_king_vendor_card_foil(),
+                ],
+            ),
+            (counterspell_forth_e().name, vec![counterspell_forth_e()]),
+        ])
+    }
+
+    #[test]
+    fn test_load_toml_watchlist() {
+        let watchlist = Watchlist::from_toml(
+            r#"
+            [[cards]]
+            name = "Reaper King"
+            set = "Shadowmoor"
+            max_price = 150
+
+            [[cards]]
+            name = "Counterspell"
+            collector_number = "4ED-065"
+            foil = true
+            max_price = 2.5
+            currency = "EUR"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            watchlist.entries,
+            vec![
+                WatchlistEntry {
+                    name: reaper_king_card_name(),
+                    set: Some(reaper_king_set_name()),
+                    collector_number: None,
+                    foil: false,
+                    max_price: Price::new(dec!(150), Currency::SEK),
+                },
+                WatchlistEntry {
+                    name: counterspell_forth_e().name,
+                    set: None,
+                    collector_number: Some(CollectorNumber::new("4ED-065").unwrap()),
+                    foil: true,
+                    max_price: Price::new(dec!(2.5), Currency::EUR),
+                },
+            ]
+        );
+        assert!(Watchlist::from_toml("[[cards]]\nname = \"Reaper King\"").is_err());
+    }
+
+    #[test]
+    fn test_load_csv_watchlist() {
+        let csv = "name,set,collector_number,foil,max_price,currency\n\
+                   Reaper King,Shadowmoor,,false,150,\n\
+                   Counterspell,,4ED-065,true,2.5,EUR\n";
+
+        let watchlist = Watchlist::from_csv(csv.as_bytes()).unwrap();
+
+        assert_eq!(
+            watchlist,
+            Watchlist::from_toml(
+                r#"
+                [[cards]]
+                name = "Reaper King"
+                set = "Shadowmoor"
+                max_price = 150
+                [[cards]]
+                name = "Counterspell"
+                collector_number = "4ED-065"
+                foil = true
+                max_price = 2.5
+                currency = "EUR"
+                "#
+            )
+            .unwrap()
+        );
+        assert!(Watchlist::from_csv("name,max_price\nReaper King,lots\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_find_hits() {
+        let watchlist = Watchlist::from_toml(
+            r#"
+            [[cards]]
+            name = "reaper king"
+            max_price = 150
+            [[cards]]
+            name = "Counterspell"
+            max_price = 50
+            [[cards]]
+            name = "Reaper King"
+            foil = true
+            max_price = 20
+            currency = "EUR"
+            "#,
+        )
+        .unwrap();
+
+        let hits = watchlist.find_hits(
+            &vendor_cards(),
+            &ListingFilter::default(),
+            &ExchangeRates::default(),
+        );
+        let hit_prices: Vec<Price> = hits.iter().map(|hit| hit.card.price).collect();
+
+        assert_eq!(
+            hit_prices,
+            vec![
+                reaper_king_vendor_card_cheap().price,
+                reaper_king_vendor_card_expensive().price,
+                reaper_king_vendor_card_foil().price,
+            ]
+        );
+        assert!(hits
+            .iter()
+            .all(|hit| hit.card.name == reaper_king_card_name()));
+        assert_eq!(
+            watchlist
+                .report(
+                    &vendor_cards(),
+                    &ListingFilter::default(),
+                    &ExchangeRates::default()
+                )
+                .entries,
+            3
+        );
+    }
+
+    #[test]
+    fn test_entry_needs_matching_printing_in_stock() {
+        let mut entry = WatchlistEntry {
+            name: reaper_king_card_name(),
+            set: Some(reaper_king_set_name()),
+            collector_number: Some(CollectorNumber::new("SHM-260").unwrap()),
+            foil: false,
+            max_price: Price::new(dec!(100), Currency::SEK),
+        };
+        let mut sold_out = reaper_king_vendor_card_expensive();
+        sold_out.current_stock = 0;
+
+        let filter = ListingFilter::default();
+        let rates = ExchangeRates::default();
+
+        assert!(entry.matches(&reaper_king_vendor_card_expensive(), &filter, &rates));
+        assert!(!entry.matches(&sold_out, &filter, &rates));
+        entry.collector_number = Some(CollectorNumber::new("SHM-261").unwrap());
+        assert!(!entry.matches(&reaper_king_vendor_card_expensive(), &filter, &rates));
+    }
+
+    #[test]
+    fn test_entry_needs_copy_the_filter_lets_through() {
+        let entry = WatchlistEntry {
+            name: reaper_king_card_name(),
+            set: None,
+            collector_number: None,
+            foil: false,
+            max_price: Price::new(dec!(100), Currency::SEK),
+        };
+        let mut damaged_german = reaper_king_vendor_card_cheap();
+        damaged_german.condition = Condition::Damaged;
+        damaged_german.language = Language::German;
+        let any_copy = ListingFilter {
+            min_condition: Condition::Damaged,
+            language: None,
+        };
+        let rates = ExchangeRates::default();
+
+        assert!(!entry.matches(&damaged_german, &ListingFilter::default(), &rates));
+        assert!(entry.matches(&damaged_german, &any_copy, &rates));
+    }
+}
@@ -25,14 +25,16 @@ function populateFilters() {
 // Filter function
 function applyFilters() {
     const vendorFilter = document.getElementById('vendorFilter').value;
+    const onlyPriceDrops = document.getElementById('priceDroppedFilter').checked;
     const rows = document.querySelectorAll('#card-table tbody tr');
 
     rows.forEach(row => {
         const vendor = row.querySelector('td:nth-child(6)').textContent.trim();
 
         const vendorMatch = vendorFilter === 'all' || vendor === vendorFilter;
+        const priceDropMatch = !onlyPriceDrops || row.dataset.priceDropped === 'true';
 
-        if (vendorMatch) {
+        if (vendorMatch && priceDropMatch) {
             row.classList.remove('hidden');
         } else {
             row.classList.add('hidden');
@@ -43,6 +45,7 @@ function applyFilters() {
 // Reset filters
 function resetFilters() {
     document.getElementById('vendorFilter').value = 'all';
+    document.getElementById('priceDroppedFilter').checked = false;
     const rows = document.querySelectorAll('#card-table tbody tr');
     rows.forEach(row => row.classList.remove('hidden'));
 }
@@ -51,4 +54,5 @@ function resetFilters() {
 populateFilters();
 
 // Add event listeners to filters
-document.getElementById('vendorFilter').addEventListener('change', applyFilters);
\ No newline at end of file
+document.getElementById('vendorFilter').addEventListener('change', applyFilters);
+document.getElementById('priceDroppedFilter').addEventListener('change', applyFilters);
\ No newline at end of file